@@ -0,0 +1,101 @@
+//! Fixtures shared by the benchmarks and the performance tests
+
+#![allow(dead_code)]
+
+use assert_fs::{prelude::*, TempDir};
+use dofigen_lib::{DofigenPatch, Resource};
+
+pub const BUILDERS: usize = 100;
+pub const EXTEND_DEPTH: usize = 10;
+
+/// Generates a Dofigen file with many builders used by the runtime stage
+pub fn large_dofigen() -> String {
+    let mut yaml = String::from("builders:\n");
+    for builder in 0..BUILDERS {
+        yaml.push_str(&format!(
+            r#"  builder-{builder}:
+    fromImage:
+      path: rust
+      tag: "1.80"
+    workdir: /app
+    env:
+      CARGO_TARGET_DIR: /app/target
+    copy:
+      - paths: ["crates/{builder}"]
+    run:
+      - cargo build --release -p crate-{builder}
+    cache:
+      - target: /usr/local/cargo/registry
+"#
+        ));
+    }
+    yaml.push_str("fromImage:\n  path: alpine\ncopy:\n");
+    for builder in 0..BUILDERS {
+        yaml.push_str(&format!(
+            "  - fromBuilder: builder-{builder}\n    paths: [\"/app/target/release/crate-{builder}\"]\n    target: /bin/\n"
+        ));
+    }
+    yaml
+}
+
+/// Writes a chain of files, each extending the previous one by its absolute path
+pub fn extend_chain(temp: &TempDir) -> Resource {
+    let base = temp.child("layer-0.yml");
+    base.write_str(&large_dofigen()).unwrap();
+    for layer in 1..EXTEND_DEPTH {
+        temp.child(format!("layer-{layer}.yml"))
+            .write_str(&format!(
+                r#"extend: ['{previous}']
+env:
+  LAYER: "{layer}"
+run:
+  "+":
+    - echo layer {layer}
+"#,
+                previous = temp
+                    .child(format!("layer-{}.yml", layer - 1))
+                    .path()
+                    .display()
+            ))
+            .unwrap();
+    }
+    Resource::File(
+        temp.child(format!("layer-{}.yml", EXTEND_DEPTH - 1))
+            .path()
+            .into(),
+    )
+}
+
+pub const LAYERS: usize = 10;
+
+/// Generates a Dofigen patch overriding all the builders of a workspace
+pub fn layer(index: usize) -> DofigenPatch {
+    let mut yaml = String::from("builders:\n");
+    for builder in 0..BUILDERS {
+        yaml.push_str(&format!(
+            r#"  builder-{builder}:
+    fromImage:
+      path: alpine
+      tag: "3.{index}"
+    workdir: /app
+    env:
+      LAYER: "{index}"
+    copy:
+      "+":
+        - paths: ["src/{builder}"]
+    run:
+      "+":
+        - echo layer {index}
+"#
+        ));
+    }
+    yaml.push_str(&format!(
+        r#"fromBuilder: builder-0
+copy:
+  "+":
+    - fromBuilder: builder-{index}
+      paths: ["/app"]
+"#
+    ));
+    serde_yaml::from_str(&yaml).unwrap()
+}
@@ -0,0 +1,24 @@
+use common::{layer, LAYERS};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use dofigen_lib::{Dofigen, DofigenPatch};
+use struct_patch::Merge;
+
+mod common;
+
+fn merge_layers(c: &mut Criterion) {
+    let layers: Vec<DofigenPatch> = (0..LAYERS).map(layer).collect();
+
+    c.bench_function("merge 10 layers of 100 builders", |b| {
+        b.iter_batched(
+            || layers.clone(),
+            |layers| {
+                let merged = layers.into_iter().reduce(|a, b| a.merge(b)).unwrap();
+                black_box(Dofigen::from(merged))
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, merge_layers);
+criterion_main!(benches);
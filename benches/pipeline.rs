@@ -0,0 +1,47 @@
+use assert_fs::TempDir;
+use common::{extend_chain, large_dofigen};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dofigen_lib::{Dofigen, DofigenContext, GenerationContext};
+
+mod common;
+
+fn parse(c: &mut Criterion) {
+    let yaml = large_dofigen();
+
+    c.bench_function("parse 100 builders", |b| {
+        b.iter(|| {
+            let mut context = DofigenContext::new();
+            context.offline = true;
+            black_box(context.parse_from_string(black_box(&yaml)).unwrap())
+        })
+    });
+}
+
+fn extend(c: &mut Criterion) {
+    let temp = TempDir::new().unwrap();
+    let resource = extend_chain(&temp);
+
+    c.bench_function("extend chain of 10 files", |b| {
+        b.iter(|| {
+            let mut context = DofigenContext::new();
+            context.offline = true;
+            black_box(context.parse_from_resource(resource.clone()).unwrap())
+        })
+    });
+}
+
+fn generate(c: &mut Criterion) {
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(&large_dofigen())
+        .unwrap();
+
+    c.bench_function("generate 100 builders", |b| {
+        b.iter(|| {
+            let mut context = GenerationContext::from(dofigen.clone());
+            black_box(context.generate_dockerfile().unwrap())
+        })
+    });
+}
+
+criterion_group!(benches, parse, extend, generate);
+criterion_main!(benches);
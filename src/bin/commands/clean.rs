@@ -0,0 +1,101 @@
+//! # clean
+//!
+//! The clean subcommand removes the files generated by Dofigen, the vendored resources and optionally the cache of the URL resources.
+
+use super::generate::DEFAULT_DOCKERFILE;
+use crate::CliCommand;
+use clap::Args;
+use colored::{Color, Colorize};
+use dofigen_lib::{Error, HttpCache, Result};
+use std::{fs, path::Path};
+
+/// The generated files start with this comment, after the syntax line of the Dockerfiles
+const GENERATED_HEADER: &str = "# This file is generated by Dofigen";
+
+#[derive(Args, Debug, Clone)]
+pub struct Clean {
+    /// The generated Dockerfile. Its .dockerignore file is also removed
+    #[clap(short, long, default_value = DEFAULT_DOCKERFILE)]
+    output: String,
+
+    /// The directory of the vendored URL resources
+    #[clap(long, default_value = ".dofigen/vendor")]
+    directory: String,
+
+    /// Also remove the cache of the URL resources, shared by all the projects of the user
+    #[clap(long, action)]
+    cache: bool,
+
+    /// Only display the removed files
+    #[clap(long, action)]
+    dry_run: bool,
+}
+
+impl Clean {
+    fn remove(&self, path: &Path) -> Result<()> {
+        println!(
+            "{:>20} {}",
+            "Remove".color(Color::Red).bold(),
+            path.display()
+        );
+        if self.dry_run {
+            return Ok(());
+        }
+        if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        }
+        .map_err(|err| Error::Custom(format!("Unable to remove {:?}: {}", path, err)))
+    }
+
+    /// Removes a file only if it was generated by Dofigen
+    fn remove_generated(&self, path: &Path) -> Result<()> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Ok(());
+        };
+        if content
+            .lines()
+            .take(2)
+            .any(|line| line.starts_with(GENERATED_HEADER))
+        {
+            self.remove(path)
+        } else {
+            println!(
+                "{:>20} {} isn't generated by Dofigen",
+                "Keep".color(Color::Yellow).bold(),
+                path.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+impl CliCommand for Clean {
+    fn run(self) -> Result<()> {
+        let dockerfile = Path::new(&self.output);
+        let filename = dockerfile
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(DEFAULT_DOCKERFILE);
+        let ignorefile = if filename == DEFAULT_DOCKERFILE {
+            dockerfile.with_file_name(".dockerignore")
+        } else {
+            dockerfile.with_file_name(format!("{}.dockerignore", filename))
+        };
+        self.remove_generated(dockerfile)?;
+        self.remove_generated(&ignorefile)?;
+
+        let vendor = Path::new(&self.directory);
+        if vendor.exists() {
+            self.remove(vendor)?;
+        }
+
+        if self.cache {
+            if let Some(cache) = HttpCache::default_dir().filter(|dir| dir.exists()) {
+                self.remove(&cache)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! # complete
+//!
+//! The complete subcommand exports the completions available at a position of a Dofigen file.
+
+use crate::CliCommand;
+use clap::Args;
+use dofigen_lib::{completion_model, Error, Result};
+
+#[derive(Args, Debug, Clone)]
+pub struct Complete {
+    /// The position to complete, formatted as file:line:column.
+    /// The line and the column start at 1
+    #[clap(long)]
+    position: String,
+}
+
+impl CliCommand for Complete {
+    fn run(self) -> Result<()> {
+        let (file, line, column) = parse_position(&self.position)?;
+        let content = std::fs::read_to_string(file)
+            .map_err(|err| Error::Custom(format!("Unable to read the file {}: {}", file, err)))?;
+        let model = completion_model(&content, line, column)?;
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&model).map_err(Error::display)?
+        );
+        Ok(())
+    }
+}
+
+fn parse_position(position: &str) -> Result<(&str, usize, usize)> {
+    let error = || {
+        Error::Custom(format!(
+            "The position '{}' must be formatted as file:line:column",
+            position
+        ))
+    };
+    let mut parts = position.rsplitn(3, ':');
+    let column = parts
+        .next()
+        .and_then(|c| c.parse().ok())
+        .ok_or_else(error)?;
+    let line = parts
+        .next()
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(error)?;
+    let file = parts.next().filter(|f| !f.is_empty()).ok_or_else(error)?;
+    Ok((file, line, column))
+}
@@ -0,0 +1,237 @@
+//! # compose
+//!
+//! The compose subcommand generates the Dockerfiles of the services of a Compose file.
+//! A service is generated from the Dofigen file of its `x-dofigen` extension or from the Dofigen file of its build context.
+
+use super::generate::Generate;
+use crate::{CliCommand, GlobalOptions};
+use clap::Args;
+use colored::{Color, Colorize};
+use dofigen_lib::{Error, Result};
+use serde_yaml::{Mapping, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const COMPOSE_FILES: [&str; 4] = [
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+const DOFIGEN_FILES: [&str; 3] = ["dofigen.yml", "dofigen.yaml", "dofigen.json"];
+const DOFIGEN_EXTENSION: &str = "x-dofigen";
+const DEFAULT_DOCKERFILE: &str = "Dockerfile";
+
+#[derive(Args, Debug, Clone)]
+pub struct Compose {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The Compose file. Default search for the next files: compose.yaml, compose.yml, docker-compose.yaml, docker-compose.yml
+    #[clap(short, long)]
+    compose_file: Option<String>,
+
+    /// Locked version of the dofigen definitions
+    #[clap(short, long, action)]
+    locked: bool,
+
+    /// Set the build.dockerfile entries of the services to the generated Dockerfiles.
+    /// The comments of the Compose file are not kept
+    #[clap(short, long, action)]
+    write: bool,
+}
+
+/// A service of the Compose file built from a Dofigen file
+struct DofigenService {
+    name: String,
+    /// The Dofigen file, relative to the current directory
+    file: PathBuf,
+    /// The generated Dockerfile, relative to the current directory
+    dockerfile: PathBuf,
+    /// The generated Dockerfile, relative to the build context, to write in the Compose file
+    build_dockerfile: String,
+}
+
+impl Compose {
+    fn compose_file(&self) -> Result<PathBuf> {
+        if let Some(file) = &self.compose_file {
+            return Ok(file.into());
+        }
+        COMPOSE_FILES
+            .iter()
+            .map(PathBuf::from)
+            .find(|file| file.exists())
+            .ok_or(Error::Custom("No Compose file found".into()))
+    }
+
+    /// Finds the services built from a Dofigen file
+    fn dofigen_services(compose_dir: &Path, compose: &Value) -> Result<Vec<DofigenService>> {
+        let Some(services) = compose.get("services").and_then(Value::as_mapping) else {
+            return Ok(vec![]);
+        };
+        let mut dofigen_services = vec![];
+        for (name, service) in services {
+            let name = name.as_str().unwrap_or_default().to_string();
+            let (context, dockerfile) = match service.get("build") {
+                Some(Value::String(context)) => (context.as_str(), None),
+                Some(Value::Mapping(build)) => (
+                    build.get("context").and_then(Value::as_str).unwrap_or("."),
+                    build.get("dockerfile").and_then(Value::as_str),
+                ),
+                _ => continue,
+            };
+            let context = compose_dir.join(context);
+
+            let file = match service.get(DOFIGEN_EXTENSION) {
+                Some(Value::String(file)) => {
+                    let file = PathBuf::from(file);
+                    if !context.join(&file).exists() {
+                        return Err(Error::Custom(format!(
+                            "The Dofigen file {:?} of the service '{}' doesn't exist",
+                            context.join(&file),
+                            name
+                        )));
+                    }
+                    file
+                }
+                Some(_) => {
+                    return Err(Error::Custom(format!(
+                        "The {} extension of the service '{}' must be the path of a Dofigen file",
+                        DOFIGEN_EXTENSION, name
+                    )))
+                }
+                None => match DOFIGEN_FILES
+                    .iter()
+                    .map(PathBuf::from)
+                    .find(|file| context.join(file).exists())
+                {
+                    Some(file) => file,
+                    None => continue,
+                },
+            };
+
+            // The Dockerfile is generated next to the Dofigen file unless the service defines it
+            let build_dockerfile = match dockerfile {
+                Some(dockerfile) => dockerfile.to_string(),
+                None => file
+                    .with_file_name(dockerfile_name(&file))
+                    .to_string_lossy()
+                    .to_string(),
+            };
+            dofigen_services.push(DofigenService {
+                name,
+                file: context.join(file),
+                dockerfile: context.join(&build_dockerfile),
+                build_dockerfile,
+            });
+        }
+        Ok(dofigen_services)
+    }
+
+    /// Sets the build.dockerfile entries of the services
+    fn write_compose_file(
+        path: &Path,
+        mut compose: Value,
+        services: &[DofigenService],
+    ) -> Result<()> {
+        for service in services {
+            let Some(definition) = compose
+                .get_mut("services")
+                .and_then(|services| services.get_mut(service.name.as_str()))
+            else {
+                continue;
+            };
+            let build = definition
+                .get_mut("build")
+                .expect("The service is built from a Dofigen file");
+            if let Value::String(context) = build {
+                let mut mapping = Mapping::new();
+                mapping.insert("context".into(), Value::String(context.clone()));
+                *build = Value::Mapping(mapping);
+            }
+            if let Value::Mapping(build) = build {
+                build.insert(
+                    "dockerfile".into(),
+                    Value::String(service.build_dockerfile.clone()),
+                );
+            }
+        }
+        let content = serde_yaml::to_string(&compose).map_err(Error::from)?;
+        fs::write(path, content)
+            .map_err(|err| Error::Custom(format!("Unable to write the Compose file: {}", err)))
+    }
+}
+
+/// The name of the Dockerfile generated from a Dofigen file.
+/// The default Dofigen files generate a Dockerfile and the other ones a file named after them
+fn dockerfile_name(file: &Path) -> String {
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    if DOFIGEN_FILES.contains(&name.as_ref()) {
+        return DEFAULT_DOCKERFILE.into();
+    }
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    format!("{}.{}", stem, DEFAULT_DOCKERFILE)
+}
+
+impl CliCommand for Compose {
+    fn run(self) -> Result<()> {
+        if self.options.file.is_some() {
+            return Err(Error::Custom(
+                "The compose command finds the Dofigen files from the Compose services. Use --compose-file to define the Compose file".into(),
+            ));
+        }
+        let path = self.compose_file()?;
+        let content = fs::read_to_string(&path).map_err(|err| {
+            Error::Custom(format!(
+                "Unable to read the Compose file {:?}: {}",
+                path, err
+            ))
+        })?;
+        let compose: Value = serde_yaml::from_str(&content).map_err(Error::from)?;
+        let compose_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        let services = Self::dofigen_services(&compose_dir, &compose)?;
+        if services.is_empty() {
+            return Err(Error::Custom(format!(
+                "No service of {:?} is built from a Dofigen file",
+                path
+            )));
+        }
+
+        for service in services.iter() {
+            Generate {
+                options: GlobalOptions {
+                    file: Some(service.file.to_string_lossy().to_string()),
+                    ..self.options.clone()
+                },
+                output: service.dockerfile.to_string_lossy().to_string(),
+                locked: self.locked,
+                from_metadata: false,
+                oci_config: None,
+                prune_unused: false,
+                lock_labels: false,
+                matrix: false,
+                matrix_key: None,
+                variant: None,
+                debug_packages: vec![],
+                debug_root: false,
+                verify_checksums: false,
+                watch: false,
+            }
+            .run()?;
+            println!(
+                "{:>20} {} {}",
+                "Generate service".color(Color::Blue).bold(),
+                service.name,
+                service.dockerfile.display()
+            );
+        }
+
+        if self.write {
+            Self::write_compose_file(&path, compose, &services)?;
+        }
+        Ok(())
+    }
+}
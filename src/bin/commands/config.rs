@@ -0,0 +1,185 @@
+//! # config
+//!
+//! The config subcommand reads and writes the user configuration, holding the default options of all the projects.
+
+use crate::CliCommand;
+use clap::{Args, Subcommand};
+use dofigen_lib::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf};
+
+const CONFIG_DIR_ENV: &str = "DOFIGEN_CONFIG_DIR";
+const CONFIG_FILE: &str = "config.yml";
+const KEYS: [&str; 4] = ["registryMirror", "offline", "color", "profiles"];
+
+/// The user configuration, applied under the command line options
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UserConfig {
+    /// The registry serving the Docker Hub images instead of Docker Hub
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry_mirror: Option<String>,
+
+    /// Run the commands offline
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offline: Option<bool>,
+
+    /// Colorize the output of the commands
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<bool>,
+
+    /// The profiles activated when the Dofigen files declare them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+}
+
+impl UserConfig {
+    /// The configuration directory: the DOFIGEN_CONFIG_DIR environment variable or the user configuration directory
+    pub fn default_dir() -> Option<PathBuf> {
+        if let Some(dir) = env::var_os(CONFIG_DIR_ENV) {
+            return Some(dir.into());
+        }
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+            .map(|dir| dir.join("dofigen"))
+    }
+
+    fn path() -> Result<PathBuf> {
+        Self::default_dir()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .ok_or(Error::Custom(
+                "No user configuration directory found, set the DOFIGEN_CONFIG_DIR environment variable".into(),
+            ))
+    }
+
+    /// Loads the user configuration. It is empty when the file doesn't exist
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::default_dir().map(|dir| dir.join(CONFIG_FILE)) else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|err| Error::Custom(format!("Could not read file {:?}: {}", path, err)))?;
+        serde_yaml::from_str(&content).map_err(|err| {
+            Error::Custom(format!(
+                "The user configuration {:?} is invalid: {}",
+                path, err
+            ))
+        })
+    }
+
+    fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| {
+                Error::Custom(format!("Could not create directory {:?}: {}", dir, err))
+            })?;
+        }
+        fs::write(&path, serde_yaml::to_string(self).map_err(Error::from)?)
+            .map_err(|err| Error::Custom(format!("Could not write file {:?}: {}", path, err)))
+    }
+
+    /// Returns the value of a key, the profiles being separated by commas
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(match check_key(key)? {
+            "registryMirror" => self.registry_mirror.clone(),
+            "offline" => self.offline.map(|offline| offline.to_string()),
+            "color" => self.color.map(|color| color.to_string()),
+            _ => (!self.profiles.is_empty()).then(|| self.profiles.join(",")),
+        })
+    }
+
+    /// Sets the value of a key, an empty value removing it
+    fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = Some(value).filter(|value| !value.is_empty());
+        match check_key(key)? {
+            "registryMirror" => self.registry_mirror = value.map(str::to_string),
+            "offline" => self.offline = value.map(|value| parse_bool(key, value)).transpose()?,
+            "color" => self.color = value.map(|value| parse_bool(key, value)).transpose()?,
+            _ => {
+                self.profiles = value
+                    .map(|value| {
+                        value
+                            .split(',')
+                            .map(|profile| profile.trim().to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_key(key: &str) -> Result<&str> {
+    KEYS.into_iter().find(|k| *k == key).ok_or_else(|| {
+        Error::Custom(format!(
+            "The key '{}' isn't a user configuration key, the keys are: {}",
+            key,
+            KEYS.join(", ")
+        ))
+    })
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    value.parse().map_err(|_| {
+        Error::Custom(format!(
+            "The value of '{}' must be true or false, not '{}'",
+            key, value
+        ))
+    })
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct Config {
+    /// The operation on the user configuration
+    #[clap(subcommand)]
+    pub command: ConfigCommand,
+}
+
+/// The operations on the user configuration
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Display the value of a key of the user configuration
+    Get(ConfigGet),
+
+    /// Set the value of a key of the user configuration. An empty value removes it
+    Set(ConfigSet),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ConfigGet {
+    /// The key: registryMirror, offline, color or profiles
+    key: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ConfigSet {
+    /// The key: registryMirror, offline, color or profiles
+    key: String,
+
+    /// The value. The profiles are separated by commas
+    value: String,
+}
+
+impl CliCommand for Config {
+    fn run(self) -> Result<()> {
+        match self.command {
+            ConfigCommand::Get(get) => {
+                if let Some(value) = UserConfig::load()?.get(&get.key)? {
+                    println!("{}", value);
+                }
+                Ok(())
+            }
+            ConfigCommand::Set(set) => {
+                let mut config = UserConfig::load()?;
+                config.set(&set.key, &set.value)?;
+                config.save()
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! # diff
+//!
+//! The diff subcommand compares the effective configuration and the generated Dockerfile of two Dofigen files,
+//! or the Dockerfile generated from a Dofigen file with the existing one.
+
+use crate::*;
+use clap::Args;
+use commands::{
+    display_lint_messages, display_timings, generate::DEFAULT_DOCKERFILE, get_file_path,
+    get_image_from_path, get_lockfile_path, load_lockfile,
+};
+use dofigen_lib::{
+    diff_lines, lock::Lock, DiffHunk, Dofigen, DofigenContext, Error, FieldChange,
+    GenerationContext, LineTag, Result, TimingPhase,
+};
+use std::time::Instant;
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct Diff {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The Dofigen file compared with the one of the --file option.
+    /// Without it, the Dockerfile generated from the Dofigen file is compared with the existing one
+    pub base: Option<String>,
+
+    /// The existing Dockerfile compared with the generated one when no base Dofigen file is given
+    #[clap(long, default_value = DEFAULT_DOCKERFILE)]
+    pub dockerfile: String,
+
+    /// Exit with an error when there are differences
+    #[clap(long, action)]
+    pub exit_code: bool,
+}
+
+impl Diff {
+    /// Loads a Dofigen file with its lock file, replacing the images tags with their digest like the generate command
+    fn load(&self, path: String) -> Result<(Dofigen, String)> {
+        let start = Instant::now();
+        let lockfile = load_lockfile(get_lockfile_path(path.clone()));
+        let mut context = lockfile
+            .as_ref()
+            .map(|l| l.to_context())
+            .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        context.update_file_resources = true;
+
+        let dofigen = get_image_from_path(path, &mut context)?;
+        let locked = dofigen.lock(&mut context)?;
+        display_lint_messages(&context.get_lint_messages());
+
+        let start = Instant::now();
+        let dockerfile = GenerationContext::from(locked).generate_dockerfile()?;
+        context
+            .timings
+            .add(TimingPhase::Generation, start.elapsed());
+        display_timings(self.options.timings, &context.timings)?;
+
+        Ok((dofigen, dockerfile))
+    }
+}
+
+impl CliCommand for Diff {
+    fn run(self) -> Result<()> {
+        let path = get_file_path(&self.options.file)?;
+        let (dofigen, dockerfile) = self.load(path.clone())?;
+
+        let (fields, hunks, base_name) = match &self.base {
+            Some(base) => {
+                let (base_dofigen, base_dockerfile) = self.load(base.clone())?;
+                (
+                    base_dofigen.diff_fields(&dofigen)?,
+                    diff_lines(&base_dockerfile, &dockerfile),
+                    base.clone(),
+                )
+            }
+            None => {
+                let existing = std::fs::read_to_string(&self.dockerfile).map_err(|err| {
+                    Error::Custom(format!(
+                        "Unable to read the file {}: {}",
+                        self.dockerfile, err
+                    ))
+                })?;
+                (
+                    vec![],
+                    diff_lines(&existing, &dockerfile),
+                    self.dockerfile.clone(),
+                )
+            }
+        };
+
+        if !fields.is_empty() {
+            println!("{}", "Effective configuration".bold());
+            fields.iter().for_each(display_field_change);
+        }
+        if !hunks.is_empty() {
+            if !fields.is_empty() {
+                println!();
+            }
+            println!("{}", format!("--- {}", base_name).bold());
+            println!("{}", format!("+++ {}", path).bold());
+            hunks.iter().for_each(display_hunk);
+        }
+
+        if fields.is_empty() && hunks.is_empty() {
+            println!("No differences");
+        } else if self.exit_code {
+            return Err(Error::Custom("The files differ".into()));
+        }
+        Ok(())
+    }
+}
+
+fn display_field_change(change: &FieldChange) {
+    let path = change.path.join(".");
+    match (&change.old, &change.new) {
+        (Some(old), Some(new)) => println!(
+            "{} {}: {} -> {}",
+            "~".color(Color::Yellow).bold(),
+            path.color(Color::Blue).bold(),
+            old.color(Color::Red),
+            new.color(Color::Green)
+        ),
+        (None, Some(new)) => println!(
+            "{} {}: {}",
+            "+".color(Color::Green).bold(),
+            path.color(Color::Blue).bold(),
+            new.color(Color::Green)
+        ),
+        (Some(old), None) => println!(
+            "{} {}: {}",
+            "-".color(Color::Red).bold(),
+            path.color(Color::Blue).bold(),
+            old.color(Color::Red)
+        ),
+        (None, None) => {}
+    }
+}
+
+fn display_hunk(hunk: &DiffHunk) {
+    println!("{}", hunk.header().color(Color::Cyan));
+    hunk.lines.iter().for_each(|(tag, line)| match tag {
+        LineTag::Equal => println!(" {}", line),
+        LineTag::Delete => println!("{}", format!("-{}", line).color(Color::Red)),
+        LineTag::Insert => println!("{}", format!("+{}", line).color(Color::Green)),
+    });
+}
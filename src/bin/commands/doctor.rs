@@ -0,0 +1,335 @@
+//! # doctor
+//!
+//! The doctor subcommand checks the environment used to build the generated Dockerfiles and the state of the Dofigen files, and displays how to fix the problems.
+
+use super::generate::DEFAULT_DOCKERFILE;
+use crate::*;
+use clap::Args;
+use colored::{Color, Colorize};
+use commands::{get_file_path, get_image_from_path, get_lockfile_path, load_lockfile};
+use dofigen_lib::{
+    lock::{Lock, LockFile},
+    Dofigen, DofigenContext, Error, NetworkFixtures, Resource, Result,
+};
+use std::{collections::HashMap, fs, process};
+
+/// The generated files start with this comment followed by the Dofigen version
+const GENERATED_HEADER: &str = "# This file is generated by Dofigen v";
+
+/// The first Docker version using BuildKit as default builder
+const BUILDKIT_DEFAULT_DOCKER_VERSION: u32 = 23;
+
+#[derive(Args, Debug, Clone)]
+pub struct Doctor {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The generated Dockerfile
+    #[clap(short, long, default_value = DEFAULT_DOCKERFILE)]
+    output: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CheckStatus {
+    Ok,
+    Warn,
+    Error,
+}
+
+/// The result of a check with the way to fix it
+struct Check {
+    status: CheckStatus,
+    message: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(message: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Ok,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Warn,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn error(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Error,
+            message: message.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn display(&self) {
+        let status = match self.status {
+            CheckStatus::Ok => "Ok".color(Color::Green).bold(),
+            CheckStatus::Warn => "Warning".color(Color::Yellow).bold(),
+            CheckStatus::Error => "Error".color(Color::Red).bold(),
+        };
+        println!("{:>20} {}", status, self.message);
+        if let Some(fix) = &self.fix {
+            println!("{:>20} {}", "", fix.color(Color::Cyan));
+        }
+    }
+}
+
+/// Runs a command and returns its trimmed output when it succeeds
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = process::Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(stdout.lines().next().unwrap_or_default().trim().to_string())
+}
+
+/// Extracts the major version from the output of `docker --version`, like "Docker version 27.3.1, build ce12230"
+fn docker_major_version(version: &str) -> Option<u32> {
+    version
+        .split_whitespace()
+        .find(|part| part.starts_with(|c: char| c.is_ascii_digit()))?
+        .split('.')
+        .next()?
+        .parse()
+        .ok()
+}
+
+impl Doctor {
+    fn check_docker(&self, checks: &mut Vec<Check>) {
+        let Some(version) = command_output("docker", &["--version"]) else {
+            checks.push(Check::warn(
+                "Docker isn't installed or isn't in the PATH",
+                "Install Docker to build the generated Dockerfiles: https://docs.docker.com/get-docker/",
+            ));
+            return;
+        };
+        checks.push(Check::ok(version.clone()));
+
+        match command_output("docker", &["buildx", "version"]) {
+            Some(buildx) => checks.push(Check::ok(buildx)),
+            None => checks.push(Check::warn(
+                "The Docker buildx plugin isn't installed",
+                "Install it to use the cache and the platforms of the generated Dockerfiles: https://github.com/docker/buildx#installing",
+            )),
+        }
+
+        let buildkit = std::env::var("DOCKER_BUILDKIT").ok();
+        let major = docker_major_version(&version);
+        checks.push(match buildkit.as_deref() {
+            Some("0") => Check::warn(
+                "BuildKit is disabled by the DOCKER_BUILDKIT environment variable",
+                "Unset DOCKER_BUILDKIT: the generated Dockerfiles use the BuildKit mounts",
+            ),
+            Some("1") => Check::ok("BuildKit is enabled"),
+            _ if major.is_some_and(|major| major < BUILDKIT_DEFAULT_DOCKER_VERSION) => Check::warn(
+                "BuildKit isn't the default builder of this Docker version",
+                format!(
+                    "Set DOCKER_BUILDKIT=1 or upgrade Docker to version {} or later",
+                    BUILDKIT_DEFAULT_DOCKER_VERSION
+                ),
+            ),
+            _ => Check::ok("BuildKit is the default builder"),
+        });
+    }
+
+    /// Parses the Dofigen file and returns it with the context used to load it
+    fn check_file(
+        &self,
+        path: &str,
+        lockfile: Option<&LockFile>,
+        fixtures: &Option<NetworkFixtures>,
+        variables: &HashMap<String, String>,
+        checks: &mut Vec<Check>,
+    ) -> Option<(Dofigen, DofigenContext)> {
+        let mut context = lockfile
+            .map(|lockfile| lockfile.to_context())
+            .unwrap_or(DofigenContext::new());
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = fixtures.clone();
+        context.variables = variables.clone();
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        context.update_file_resources = true;
+
+        match get_image_from_path(path.to_string(), &mut context) {
+            Ok(dofigen) => {
+                checks.push(Check::ok(format!("The Dofigen file {} is valid", path)));
+                Some((dofigen, context))
+            }
+            Err(err) => {
+                checks.push(Check::error(
+                    format!("The Dofigen file {} can't be loaded: {}", path, err),
+                    "Fix the Dofigen file, its JSON Schema is available at https://github.com/lenra-io/dofigen/blob/main/docs/dofigen.schema.json",
+                ));
+                None
+            }
+        }
+    }
+
+    fn check_lockfile(
+        &self,
+        lockfile: Option<&LockFile>,
+        dofigen: &Dofigen,
+        context: &mut DofigenContext,
+        checks: &mut Vec<Check>,
+    ) {
+        let Some(lockfile) = lockfile else {
+            checks.push(Check::warn(
+                "There is no lock file",
+                "Run `dofigen update` to lock the images and the extended resources",
+            ));
+            return;
+        };
+        let effective = dofigen.lock(context).and_then(|locked| {
+            context.clean_unused();
+            LockFile::from_context(&locked, context)
+        });
+        match effective {
+            Ok(effective) if effective.effective == lockfile.effective => {
+                checks.push(Check::ok("The lock file is up to date"))
+            }
+            Ok(_) => checks.push(Check::warn(
+                "The lock file doesn't match the Dofigen file",
+                "Run `dofigen update` or `dofigen gen` to update it",
+            )),
+            Err(err) => checks.push(Check::warn(
+                format!("The lock file can't be checked: {}", err),
+                "Run `dofigen update` to update it",
+            )),
+        }
+    }
+
+    fn check_dockerfile(&self, checks: &mut Vec<Check>) {
+        let Ok(content) = fs::read_to_string(&self.output) else {
+            checks.push(Check::warn(
+                format!("The file {} isn't generated", self.output),
+                "Run `dofigen gen` to generate it",
+            ));
+            return;
+        };
+        let Some(version) = content
+            .lines()
+            .take_while(|line| line.starts_with('#'))
+            .find_map(|line| line.strip_prefix(GENERATED_HEADER))
+        else {
+            checks.push(Check::warn(
+                format!("The file {} isn't generated by Dofigen", self.output),
+                "Run `dofigen gen` to generate it",
+            ));
+            return;
+        };
+        if version == env!("CARGO_PKG_VERSION") {
+            checks.push(Check::ok(format!(
+                "The file {} is generated by this Dofigen version",
+                self.output
+            )));
+        } else {
+            checks.push(Check::warn(
+                format!(
+                    "The file {} is generated by Dofigen v{} instead of v{}",
+                    self.output,
+                    version,
+                    env!("CARGO_PKG_VERSION")
+                ),
+                "Run `dofigen gen` to generate it again",
+            ));
+        }
+    }
+
+    /// Checks that the URL resources extended by the Dofigen file can be loaded again
+    fn check_remote_extends(
+        &self,
+        context: &DofigenContext,
+        fixtures: &Option<NetworkFixtures>,
+        checks: &mut Vec<Check>,
+    ) {
+        let Ok(used) = LockFile::from_context(&Dofigen::default(), context) else {
+            return;
+        };
+        let mut resources = used
+            .resources
+            .keys()
+            .filter_map(|resource| match resource.parse() {
+                Ok(Resource::Url(url)) => Some(url),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        resources.sort();
+
+        for url in resources {
+            if let Some(path) = used.vendor.get(url.as_str()) {
+                checks.push(Check::ok(format!("{} is vendored in {}", url, path)));
+                continue;
+            }
+            if self.options.offline {
+                checks.push(Check::warn(
+                    format!("{} can't be loaded offline", url),
+                    "Run `dofigen vendor` to load it from a local directory",
+                ));
+                continue;
+            }
+            let mut url_context = DofigenContext::new();
+            url_context.update_url_resources = true;
+            url_context.fixtures = fixtures.clone();
+            url_context.http_cache = self.options.http_cache();
+            match url_context.parse_from_resource(Resource::Url(url.clone())) {
+                Ok(_) => checks.push(Check::ok(format!("{} is reachable", url))),
+                Err(err) => checks.push(Check::error(
+                    format!("{} can't be loaded: {}", url, err),
+                    "Check the network access or run `dofigen vendor` from another machine to load it from a local directory",
+                )),
+            }
+        }
+    }
+}
+
+impl CliCommand for Doctor {
+    fn run(self) -> Result<()> {
+        let fixtures = self.options.network_fixtures()?;
+        let variables = self.options.variables()?;
+        let mut checks = vec![];
+        self.check_docker(&mut checks);
+
+        match get_file_path(&self.options.file) {
+            Ok(path) => {
+                let lockfile = load_lockfile(get_lockfile_path(path.clone()));
+                if let Some((dofigen, mut context)) =
+                    self.check_file(&path, lockfile.as_ref(), &fixtures, &variables, &mut checks)
+                {
+                    self.check_remote_extends(&context, &fixtures, &mut checks);
+                    self.check_lockfile(lockfile.as_ref(), &dofigen, &mut context, &mut checks);
+                }
+                self.check_dockerfile(&mut checks);
+            }
+            Err(err) => checks.push(Check::error(
+                err.to_string(),
+                "Create a dofigen.yml file or use the --file option",
+            )),
+        }
+
+        checks.iter().for_each(Check::display);
+
+        let errors = checks
+            .iter()
+            .filter(|check| check.status == CheckStatus::Error)
+            .count();
+        if errors > 0 {
+            return Err(Error::Custom(format!(
+                "Found {} problem{}",
+                errors,
+                if errors > 1 { "s" } else { "" }
+            )));
+        }
+        Ok(())
+    }
+}
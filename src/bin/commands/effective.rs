@@ -4,8 +4,14 @@
 
 use crate::*;
 pub use clap::Args;
-use commands::{get_file_path, get_image_from_path, get_lockfile_path, load_lockfile};
-use dofigen_lib::{generate_effective_content, lock::Lock, DofigenContext, Error, Result};
+use commands::{
+    display_lint_messages, display_timings, get_file_path, get_image_from_path, get_lockfile_path,
+    load_lockfile,
+};
+use dofigen_lib::{
+    generate_effective_content, lock::Lock, DofigenContext, Error, Result, TimingPhase,
+};
+use std::time::Instant;
 
 use crate::CliCommand;
 
@@ -17,17 +23,28 @@ pub struct Effective {
     /// Locked version of the dofigen definition
     #[clap(short, long, action)]
     locked: bool,
+
+    /// Replace the images tags with their digests, the default behavior.
+    /// The effective content of the lock file has its images already resolved
+    #[clap(long, action, conflicts_with = "keep_tags")]
+    resolve_images: bool,
+
+    /// Keep the images tags instead of replacing them with their digests
+    #[clap(long, action, conflicts_with = "locked")]
+    keep_tags: bool,
 }
 
 impl CliCommand for Effective {
     fn run(self) -> Result<()> {
         let path = get_file_path(&self.options.file)?;
         let lockfile_path = get_lockfile_path(path.clone());
+        let start = Instant::now();
         let lockfile = load_lockfile(lockfile_path.clone());
         let mut context = lockfile
             .as_ref()
             .map(|l| l.to_context())
             .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
 
         let dofigen = if self.locked {
             if path == "-" {
@@ -39,15 +56,27 @@ impl CliCommand for Effective {
             context.parse_from_string(lockfile.effective.as_str())?
         } else {
             context.offline = self.options.offline;
+            context.registry_mirror = self.options.registry_mirror.clone();
+            context.fixtures = self.options.network_fixtures()?;
+            context.variables = self.options.variables()?;
+            context.profiles = self.options.profiles.clone();
+            context.default_profiles = self.options.default_profiles.clone();
+            context.http_cache = self.options.http_cache();
             context.update_file_resources = true;
-            context.display_updates = false;
 
             let dofigen = get_image_from_path(path, &mut context)?;
 
-            dofigen.lock(&mut context)?
+            if self.keep_tags {
+                dofigen
+            } else {
+                dofigen.lock(&mut context)?
+            }
         };
 
+        display_lint_messages(&context.get_lint_messages());
+
         println!("{}", generate_effective_content(&dofigen)?);
-        Ok(())
+
+        display_timings(self.options.timings, &context.timings)
     }
 }
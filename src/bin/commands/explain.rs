@@ -0,0 +1,132 @@
+//! # explain
+//!
+//! The explain subcommand displays the effective content annotated with the file of the extend chain and the patch operation that produced each value.
+
+use crate::*;
+pub use clap::Args;
+use commands::{
+    display_lint_messages, display_timings, get_file_path, get_image_from_path, get_lockfile_path,
+    load_lockfile,
+};
+use dofigen_lib::{generate_effective_content, DofigenContext, Error, Result, TimingPhase};
+use serde_yaml::Value;
+use std::time::Instant;
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct Explain {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+}
+
+/// Writes the effective content with a comment on each value giving its origin
+struct Explainer<'a> {
+    context: &'a DofigenContext,
+    file: String,
+    lines: Vec<String>,
+}
+
+impl Explainer<'_> {
+    fn origin(&self, path: &[String]) -> String {
+        format!(
+            "# {} ({})",
+            self.context
+                .field_origin(path)
+                .map(|resource| resource.to_string())
+                .unwrap_or(self.file.clone()),
+            self.context.field_operation(path)
+        )
+    }
+
+    fn explain_value(&mut self, prefix: String, value: &Value, path: &mut Vec<String>) {
+        let indent = " ".repeat(path.len() * 2);
+        match value {
+            Value::Mapping(mapping) if !mapping.is_empty() => {
+                if !path.is_empty() {
+                    self.lines
+                        .push(format!("{}  {}", prefix, self.origin(path)));
+                }
+                for (key, child) in mapping {
+                    let key = scalar(key);
+                    path.push(key.clone());
+                    self.explain_value(format!("{}{}:", indent, key), child, path);
+                    path.pop();
+                }
+            }
+            Value::Sequence(sequence) if !sequence.is_empty() => {
+                self.lines
+                    .push(format!("{}  {}", prefix, self.origin(path)));
+                for (position, child) in sequence.iter().enumerate() {
+                    path.push(position.to_string());
+                    self.explain_value(format!("{}-", indent), child, path);
+                    path.pop();
+                }
+            }
+            value => {
+                self.lines.push(format!(
+                    "{} {}  {}",
+                    prefix,
+                    scalar(value),
+                    self.origin(path)
+                ));
+            }
+        }
+    }
+}
+
+/// Formats a scalar on a single line, quoting the strings that would be read differently
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Mapping(_) => "{}".into(),
+        Value::Sequence(_) => "[]".into(),
+        Value::String(string) => {
+            let yaml = serde_yaml::to_string(string).unwrap_or_default();
+            let yaml = yaml.trim_end();
+            if yaml.contains(['\n', '#']) {
+                serde_json::to_string(string).unwrap_or_default()
+            } else {
+                yaml.to_string()
+            }
+        }
+        value => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+    }
+}
+
+impl CliCommand for Explain {
+    fn run(self) -> Result<()> {
+        let path = get_file_path(&self.options.file)?;
+        let start = Instant::now();
+        let lockfile = load_lockfile(get_lockfile_path(path.clone()));
+        let mut context = lockfile
+            .as_ref()
+            .map(|l| l.to_context())
+            .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        context.update_file_resources = true;
+
+        let dofigen = get_image_from_path(path.clone(), &mut context)?;
+
+        display_lint_messages(&context.get_lint_messages());
+
+        let effective: Value =
+            serde_yaml::from_str(&generate_effective_content(&dofigen)?).map_err(Error::from)?;
+        let mut explainer = Explainer {
+            context: &context,
+            file: if path == "-" { "stdin".into() } else { path },
+            lines: vec![],
+        };
+        explainer.explain_value(String::new(), &effective, &mut vec![]);
+        println!("{}", explainer.lines.join("\n"));
+
+        display_timings(self.options.timings, &context.timings)
+    }
+}
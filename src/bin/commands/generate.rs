@@ -2,17 +2,27 @@
 //!
 //! The generate subcommand generates a Dockerfile and a .dockerignore file from a Dofigen file.
 
-use super::{get_file_path, get_image_from_path, get_lockfile_path, load_lockfile};
+use super::{
+    display_lint_messages, display_timings, get_file_path, get_image_from_path, get_lockfile_path,
+    get_metadata_content, load_lockfile, METADATA_LOCKFILE,
+};
 use crate::{CliCommand, GlobalOptions};
-use clap::Args;
+use clap::{Args, ValueEnum};
 use colored::{Color, Colorize};
 use dofigen_lib::{
     lock::{Lock, LockFile},
-    DofigenContext, Error, GenerationContext, MessageLevel, Result,
+    DebugVariant, DofigenContext, Error, GenerationContext, MessageLevel, OciImageConfig, Result,
+    TimingPhase,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime},
 };
-use std::{fs, path::PathBuf};
 
-const DEFAULT_DOCKERFILE: &str = "Dockerfile";
+pub(crate) const DEFAULT_DOCKERFILE: &str = "Dockerfile";
 
 #[derive(Args, Debug, Default, Clone)]
 pub struct Generate {
@@ -22,16 +32,85 @@ pub struct Generate {
     /// The output Dockerfile file
     /// Define to - to write to stdout
     #[clap(short, long, default_value = DEFAULT_DOCKERFILE)]
-    output: String,
+    pub output: String,
 
     /// Locked version of the dofigen definition
     #[clap(short, long, action)]
-    locked: bool,
+    pub locked: bool,
+
+    /// Read the Dofigen configuration from the [package.metadata.dofigen] table of Cargo.toml
+    /// or the "dofigen" key of package.json. The lock file is then dofigen.lock
+    #[clap(long, action, conflicts_with = "file")]
+    pub from_metadata: bool,
+
+    /// Also write the OCI image configuration of the runtime stage, as JSON, in the given file
+    #[clap(long)]
+    pub oci_config: Option<String>,
+
+    /// Omit the builders the runtime stage doesn't depend on from the generated Dockerfile
+    #[clap(long, action)]
+    pub prune_unused: bool,
+
+    /// Add labels with the lock file digest and the Dofigen version to the image, to check it with the verify-image command
+    #[clap(long, action)]
+    pub lock_labels: bool,
+
+    /// Generate a Dockerfile, suffixed by the combination values, for each combination of the matrix values.
+    /// Each combination also has its own lock file
+    #[clap(long, action, conflicts_with = "locked")]
+    pub matrix: bool,
+
+    /// The key of the matrix combination being generated, suffixing the lock file
+    #[clap(skip)]
+    pub matrix_key: Option<String>,
+
+    /// Also generate a variant of the Dockerfile, suffixed by its name, applying a built-in overlay
+    #[clap(long, value_enum)]
+    pub variant: Option<GenerateVariant>,
+
+    /// The packages installed in the debug variant. Defaults to curl and strace
+    #[clap(long = "debug-package", value_name = "PACKAGE")]
+    pub debug_packages: Vec<String>,
+
+    /// Run the debug variant as root
+    #[clap(long, action)]
+    pub debug_root: bool,
+
+    /// Check the checksums of the ADD instructions whose files are local
+    #[clap(long, action)]
+    pub verify_checksums: bool,
+
+    /// Generate the files again each time the Dofigen file or one of its extended local files changes
+    #[clap(long, action, conflicts_with_all = ["locked", "matrix", "from_metadata"])]
+    pub watch: bool,
+}
+
+/// The interval between the checks of the watched files
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The variants generated alongside the Dockerfile
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum GenerateVariant {
+    /// Installs debugging tools in the runtime stage
+    Debug,
+}
+
+impl GenerateVariant {
+    fn name(&self) -> &'static str {
+        match self {
+            GenerateVariant::Debug => "debug",
+        }
+    }
 }
 
 impl Generate {
-    fn write_dockerfile(&self, dockerfile_content: &str, ignore_content: &str) -> Result<()> {
-        let dockerfile = PathBuf::from(&self.output);
+    fn write_dockerfile(
+        &self,
+        output: &str,
+        dockerfile_content: &str,
+        ignore_content: &str,
+    ) -> Result<()> {
+        let dockerfile = PathBuf::from(output);
         fs::write(&dockerfile, dockerfile_content).expect("Unable to write the Dockerfile");
 
         let filename = dockerfile.file_name().unwrap().to_str().unwrap();
@@ -44,38 +123,177 @@ impl Generate {
 
         Ok(())
     }
+
+    fn run_matrix(self) -> Result<()> {
+        if self.output == "-" || self.options.file.as_deref() == Some("-") {
+            return Err(Error::Custom(
+                "The '--matrix' option can't be used with stdin or stdout".into(),
+            ));
+        }
+        let mut context = DofigenContext::new();
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        let dofigen = if self.from_metadata {
+            context.parse_from_string(&get_metadata_content()?)?
+        } else {
+            get_image_from_path(get_file_path(&self.options.file)?, &mut context)?
+        };
+
+        let combinations = dofigen.matrix_combinations()?;
+        if combinations.is_empty() {
+            return Err(Error::Custom(
+                "The '--matrix' option requires a matrix in the Dofigen file".into(),
+            ));
+        }
+        for combination in combinations {
+            let mut variables = self.options.variables.clone();
+            let mut names = combination.variables.keys().collect::<Vec<_>>();
+            names.sort();
+            variables.extend(
+                names
+                    .into_iter()
+                    .map(|name| format!("{}={}", name, combination.variables[name])),
+            );
+            Generate {
+                options: GlobalOptions {
+                    variables,
+                    ..self.options.clone()
+                },
+                output: format!("{}.{}", self.output, combination.key),
+                oci_config: self
+                    .oci_config
+                    .as_ref()
+                    .map(|path| format!("{}.{}", path, combination.key)),
+                matrix: false,
+                matrix_key: Some(combination.key),
+                ..self.clone()
+            }
+            .run()?;
+        }
+        Ok(())
+    }
 }
 
 impl CliCommand for Generate {
     fn run(self) -> Result<()> {
+        if self.matrix {
+            return self.run_matrix();
+        }
+        if self.watch {
+            return self.run_watch();
+        }
+        self.generate(&mut vec![])
+    }
+}
+
+impl Generate {
+    /// Generates the files each time the watched files change, displaying the errors instead of stopping
+    fn run_watch(self) -> Result<()> {
         let path = get_file_path(&self.options.file)?;
-        let lockfile_path = get_lockfile_path(path.clone());
+        if path == "-" || self.output == "-" {
+            return Err(Error::Custom(
+                "The '--watch' option can't be used with stdin or stdout".into(),
+            ));
+        }
+        let mut files = vec![PathBuf::from(path)];
+        loop {
+            if let Err(err) = self.generate(&mut files) {
+                eprintln!("{}: {}", "error".color(Color::Red).bold(), err);
+            }
+            // Taken before the message so that the changes made once it's displayed are seen
+            let times = modification_times(&files);
+            eprintln!(
+                "Watching {} for changes",
+                files
+                    .iter()
+                    .map(|file| file.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            wait_for_change(&files, times);
+        }
+    }
+
+    /// Generates the files, updating the watched files with the local files loaded once the Dofigen file is parsed
+    fn generate(&self, watched_files: &mut Vec<PathBuf>) -> Result<()> {
+        if self.variant.is_some() && self.output == "-" {
+            return Err(Error::Custom(
+                "The '--variant' option can't be used with stdout".into(),
+            ));
+        }
+        let path = if self.from_metadata {
+            None
+        } else {
+            Some(get_file_path(&self.options.file)?)
+        };
+        let lockfile_path = match &path {
+            Some(path) => get_lockfile_path(path.clone()),
+            None => Some(PathBuf::from(METADATA_LOCKFILE)),
+        }
+        .map(|lockfile_path| match &self.matrix_key {
+            Some(key) => lockfile_path.with_extension(format!("{}.lock", key)),
+            None => lockfile_path,
+        });
+        let start = Instant::now();
         let lockfile = load_lockfile(lockfile_path.clone());
         let mut context = lockfile
             .as_ref()
             .map(|l| l.to_context())
             .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
 
-        let dofigen = if self.locked {
-            if path == "-" {
+        let mut labels = BTreeMap::new();
+        let mut dofigen = if self.locked {
+            if path.as_deref() == Some("-") {
                 return Err(Error::Custom(
                     "The '--locked' option can't be used with stdin".into(),
                 ));
             }
             let lockfile = lockfile.ok_or(Error::Custom("No lock file found".into()))?;
+            if self.lock_labels {
+                labels = lockfile.labels()?;
+            }
             context.parse_from_string(lockfile.effective.as_str())?
         } else {
             context.offline = self.options.offline;
+            context.registry_mirror = self.options.registry_mirror.clone();
+            context.fixtures = self.options.network_fixtures()?;
+            context.variables = self.options.variables()?;
+            context.profiles = self.options.profiles.clone();
+            context.default_profiles = self.options.default_profiles.clone();
+            context.http_cache = self.options.http_cache();
             context.update_file_resources = true;
+            let previous = context.clone();
 
-            let dofigen = get_image_from_path(path, &mut context)?;
+            let dofigen = match &path {
+                Some(path) => get_image_from_path(path.clone(), &mut context)?,
+                None => context.parse_from_string(&get_metadata_content()?)?,
+            };
+            *watched_files = context.used_files();
 
             // Replace images tags with the digest
             let locked_image = dofigen.lock(&mut context)?;
             context.clean_unused();
+            let report = context.update_report(&previous);
+            if !report.is_empty() {
+                if self.output == "-" {
+                    eprintln!("{}", report.to_text());
+                } else {
+                    println!("{}", report.to_text());
+                }
+            }
             let new_lockfile = LockFile::from_context(&locked_image, &mut context)?;
+            if self.lock_labels {
+                labels = new_lockfile.labels()?;
+            }
 
             if let Some(lockfile_path) = lockfile_path {
+                let start = Instant::now();
                 serde_yaml::to_writer(
                     std::fs::File::create(lockfile_path).map_err(|err| {
                         Error::Custom(format!("Unable to create the lock file: {}", err))
@@ -83,28 +301,55 @@ impl CliCommand for Generate {
                     &new_lockfile,
                 )
                 .map_err(Error::from)?;
+                context.timings.add(TimingPhase::LockIo, start.elapsed());
             };
 
             locked_image
         };
 
+        if self.verify_checksums {
+            let dir = path
+                .as_deref()
+                .filter(|path| *path != "-")
+                .and_then(|path| Path::new(path).parent())
+                .unwrap_or(Path::new(""));
+            dofigen.verify_checksums(dir)?;
+        }
+        if self.prune_unused {
+            dofigen.prune_unused_builders();
+        }
+        let variant = match self.variant {
+            Some(GenerateVariant::Debug) => Some(dofigen.debug_variant(&DebugVariant {
+                packages: if self.debug_packages.is_empty() {
+                    DebugVariant::default().packages
+                } else {
+                    self.debug_packages.clone()
+                },
+                root: self.debug_root,
+            })?),
+            None => None,
+        };
+
+        let validation_messages = context.with_field_origins(dofigen.validate());
+
+        let start = Instant::now();
+        let oci_config = OciImageConfig::from(&dofigen);
         let mut generation_context = GenerationContext::from(dofigen);
+        generation_context.add_labels(labels.clone());
 
         let dockerfile_content = generation_context.generate_dockerfile()?;
+        context
+            .timings
+            .add(TimingPhase::Generation, start.elapsed());
 
-        let messages = generation_context.get_lint_messages().clone();
+        let messages = [
+            context.get_lint_messages(),
+            validation_messages,
+            context.with_field_origins(generation_context.get_lint_messages()),
+        ]
+        .concat();
 
-        messages.iter().for_each(|message| {
-            eprintln!(
-                "{}[path={}]: {}",
-                match message.level {
-                    MessageLevel::Error => "error".color(Color::Red).bold(),
-                    MessageLevel::Warn => "warning".color(Color::Yellow).bold(),
-                },
-                message.path.join(".").color(Color::Blue).bold(),
-                message.message
-            );
-        });
+        display_lint_messages(&messages);
 
         let errors = messages
             .iter()
@@ -122,11 +367,66 @@ impl CliCommand for Generate {
         if self.output == "-" {
             print!("{}", dockerfile_content);
         } else {
+            let start = Instant::now();
+            let dockerignore_content = generation_context.generate_dockerignore()?;
+            context
+                .timings
+                .add(TimingPhase::Generation, start.elapsed());
             self.write_dockerfile(
+                &self.output,
                 dockerfile_content.as_str(),
-                generation_context.generate_dockerignore()?.as_str(),
+                dockerignore_content.as_str(),
             )?;
         };
-        Ok(())
+
+        if let (Some(variant), Some(variant_dofigen)) = (self.variant, variant) {
+            let start = Instant::now();
+            let mut variant_context = GenerationContext::from(variant_dofigen);
+            variant_context.add_labels(labels);
+            let dockerfile_content = variant_context.generate_dockerfile()?;
+            let dockerignore_content = variant_context.generate_dockerignore()?;
+            context
+                .timings
+                .add(TimingPhase::Generation, start.elapsed());
+            self.write_dockerfile(
+                &format!("{}.{}", self.output, variant.name()),
+                dockerfile_content.as_str(),
+                dockerignore_content.as_str(),
+            )?;
+        }
+
+        if let Some(path) = &self.oci_config {
+            let content = serde_json::to_string_pretty(&oci_config).map_err(Error::display)?;
+            fs::write(path, content).map_err(|err| {
+                Error::Custom(format!(
+                    "Unable to write the OCI image configuration: {}",
+                    err
+                ))
+            })?;
+        }
+
+        display_timings(self.options.timings, &context.timings)
+    }
+}
+
+/// Returns the modification times of the files, None for the ones that can't be read
+fn modification_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// Waits until one of the files is modified, created or removed since the given modification times
+fn wait_for_change(files: &[PathBuf], times: Vec<Option<SystemTime>>) {
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        if modification_times(files) != times {
+            return;
+        }
     }
 }
@@ -0,0 +1,69 @@
+//! # graph
+//!
+//! The graph subcommand displays the dependencies between the stages, the images and the contexts of a Dofigen file.
+
+use crate::*;
+use clap::Args;
+use commands::{
+    display_lint_messages, display_timings, get_file_path, get_image_from_path, get_lockfile_path,
+    load_lockfile,
+};
+use dofigen_lib::{DofigenContext, Error, Result, TimingPhase};
+use std::time::Instant;
+
+/// The output format of the graph
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+    Json,
+}
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct Graph {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The output format of the graph
+    #[clap(long, value_enum, default_value = "dot")]
+    format: GraphFormat,
+}
+
+impl CliCommand for Graph {
+    fn run(self) -> Result<()> {
+        let path = get_file_path(&self.options.file)?;
+        let start = Instant::now();
+        let lockfile = load_lockfile(get_lockfile_path(path.clone()));
+        let mut context = lockfile
+            .as_ref()
+            .map(|l| l.to_context())
+            .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        context.update_file_resources = true;
+
+        let dofigen = get_image_from_path(path, &mut context)?;
+
+        display_lint_messages(&context.get_lint_messages());
+
+        let graph = dofigen.stage_graph();
+        println!(
+            "{}",
+            match self.format {
+                GraphFormat::Dot => graph.to_dot(),
+                GraphFormat::Mermaid => graph.to_mermaid(),
+                GraphFormat::Json =>
+                    serde_json::to_string_pretty(&graph).map_err(Error::display)?,
+            }
+        );
+
+        display_timings(self.options.timings, &context.timings)
+    }
+}
@@ -0,0 +1,31 @@
+//! # inspect
+//!
+//! The inspect subcommand displays the description of an image from its registry.
+
+use crate::CliCommand;
+use clap::Args;
+use dofigen_lib::{Error, ImageName, RegistryClient, Result};
+
+#[derive(Args, Debug, Clone)]
+pub struct Inspect {
+    /// The image to inspect, like alpine:3.20 or ghcr.io/owner/image@sha256:...
+    image: String,
+
+    /// The registry serving the Docker Hub images instead of Docker Hub, like mirror.gcr.io
+    #[clap(long, value_name = "HOST")]
+    pub registry_mirror: Option<String>,
+}
+
+impl CliCommand for Inspect {
+    fn run(self) -> Result<()> {
+        let image: ImageName = self.image.parse()?;
+        let inspection = RegistryClient::new()
+            .with_mirror(self.registry_mirror.clone())
+            .inspect(&image)?;
+        print!(
+            "{}",
+            serde_yaml::to_string(&inspection).map_err(Error::from)?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! # lint
+//!
+//! The lint subcommand checks a Dofigen file against the best practice rules and the generation checks.
+
+use crate::*;
+use clap::Args;
+use commands::{
+    display_timings, get_file_path, get_image_from_path, get_lockfile_path, load_lockfile,
+};
+use dofigen_lib::{
+    read_lint_config, DofigenContext, Error, GenerationContext, LintMessage, MessageLevel, Result,
+    TimingPhase, LINT_CONFIG_FILE, LINT_RULES,
+};
+use serde_json::{json, Value};
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+/// The rule of the messages of the generation checks
+const DOFIGEN_RULE: &str = "dofigen";
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The output format of the lint messages
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum LintFormat {
+    #[default]
+    Text,
+    Json,
+    Sarif,
+}
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct Lint {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The output format of the lint messages
+    #[clap(long, value_enum, default_value = "text")]
+    format: LintFormat,
+
+    /// The lint configuration file giving the levels of the rules, overriding the lint field of the Dofigen file.
+    /// Default to the .dofigenlint.yml file next to the Dofigen file when it exists
+    #[clap(long)]
+    config: Option<String>,
+}
+
+impl CliCommand for Lint {
+    fn run(self) -> Result<()> {
+        let path = get_file_path(&self.options.file)?;
+        let start = Instant::now();
+        let lockfile = load_lockfile(get_lockfile_path(path.clone()));
+        let mut context = lockfile
+            .as_ref()
+            .map(|l| l.to_context())
+            .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        context.update_file_resources = true;
+        if let Some(config) = self.config_path(&path) {
+            context.lint_levels = read_lint_config(config)?;
+        }
+
+        let dofigen = get_image_from_path(path.clone(), &mut context)?;
+
+        let checks = [
+            context.get_lint_messages(),
+            dofigen.validate(),
+            GenerationContext::from(dofigen.clone()).get_lint_messages(),
+        ]
+        .concat();
+        let messages = context
+            .with_field_origins(checks)
+            .into_iter()
+            .map(|message| (DOFIGEN_RULE, message))
+            .chain(context.lint(&dofigen))
+            .collect::<Vec<_>>();
+
+        match self.format {
+            LintFormat::Text => messages.iter().for_each(|(rule, message)| {
+                println!(
+                    "{}[{}][path={}]: {}",
+                    level_name(&message.level)
+                        .color(level_color(&message.level))
+                        .bold(),
+                    rule,
+                    message.path.join(".").color(Color::Blue).bold(),
+                    message.message
+                )
+            }),
+            LintFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(
+                    &messages
+                        .iter()
+                        .map(|(rule, message)| json!({
+                            "rule": rule,
+                            "level": level_name(&message.level),
+                            "path": message.path.join("."),
+                            "message": message.message,
+                        }))
+                        .collect::<Vec<_>>()
+                )
+                .map_err(Error::display)?
+            ),
+            LintFormat::Sarif => println!(
+                "{}",
+                serde_json::to_string_pretty(&sarif_report(&path, &messages))
+                    .map_err(Error::display)?
+            ),
+        }
+
+        display_timings(self.options.timings, &context.timings)?;
+
+        let errors = messages
+            .iter()
+            .filter(|(_, message)| message.level == MessageLevel::Error)
+            .count();
+        if errors > 0 {
+            return Err(Error::Custom(format!(
+                "The lint found {} error{}",
+                errors,
+                if errors > 1 { "s" } else { "" }
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Lint {
+    /// Returns the path of the lint configuration file, given by the option or next to the Dofigen file
+    fn config_path(&self, path: &str) -> Option<PathBuf> {
+        if let Some(config) = &self.config {
+            return Some(PathBuf::from(config));
+        }
+        let dir = if path == "-" {
+            Path::new(".")
+        } else {
+            Path::new(path).parent().unwrap_or(Path::new("."))
+        };
+        Some(dir.join(LINT_CONFIG_FILE)).filter(|config| config.exists())
+    }
+}
+
+fn level_name(level: &MessageLevel) -> &'static str {
+    match level {
+        MessageLevel::Error => "error",
+        MessageLevel::Warn => "warning",
+    }
+}
+
+fn level_color(level: &MessageLevel) -> Color {
+    match level {
+        MessageLevel::Error => Color::Red,
+        MessageLevel::Warn => Color::Yellow,
+    }
+}
+
+/// Returns the messages as a SARIF log, for the code scanning tools
+/// See https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+fn sarif_report(path: &str, messages: &[(&str, LintMessage)]) -> Value {
+    let rules = [json!({
+        "id": DOFIGEN_RULE,
+        "shortDescription": { "text": "The checks of the Dofigen structure made before generating the Dockerfile" },
+    })]
+    .into_iter()
+    .chain(LINT_RULES.iter().map(|rule| {
+        json!({
+            "id": rule.id,
+            "shortDescription": { "text": rule.description },
+            "defaultConfiguration": { "level": level_name(&rule.level) },
+        })
+    }))
+    .collect::<Vec<_>>();
+    let results = messages
+        .iter()
+        .map(|(rule, message)| {
+            json!({
+                "ruleId": rule,
+                "level": level_name(&message.level),
+                "message": { "text": message.message },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": path } },
+                    "logicalLocations": [{ "fullyQualifiedName": message.path.join(".") }],
+                }],
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "dofigen",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
@@ -1,11 +1,36 @@
-use dofigen_lib::{lock::LockFile, Dofigen, DofigenContext, Error, Resource, Result};
-use std::path::PathBuf;
+use crate::TimingsFormat;
+use colored::{Color, Colorize};
+use dofigen_lib::{
+    lock::LockFile, Dofigen, DofigenContext, Error, LintMessage, MessageLevel, Result, Timings,
+};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
+pub mod clean;
+#[cfg(feature = "json_schema")]
+pub mod complete;
+pub mod compose;
+pub mod config;
+pub mod diff;
+pub mod doctor;
 pub mod effective;
+pub mod explain;
 pub mod generate;
+pub mod graph;
+pub mod inspect;
+pub mod lint;
+pub mod plugin;
+pub mod refactor;
+pub mod sbom;
 #[cfg(feature = "json_schema")]
 pub mod schema;
+pub mod self_update;
+pub mod tags;
 pub mod update;
+pub mod vendor;
+pub mod verify_image;
 
 pub(crate) fn get_file_path(path: &Option<String>) -> Result<String> {
     if let Some(path) = path {
@@ -20,6 +45,40 @@ pub(crate) fn get_file_path(path: &Option<String>) -> Result<String> {
     }
 }
 
+/// The lock file used when the Dofigen configuration is read from the project metadata
+pub(crate) const METADATA_LOCKFILE: &str = "dofigen.lock";
+
+/// Returns the Dofigen configuration embedded in the project metadata as JSON content.
+/// Search for the `[package.metadata.dofigen]` table of Cargo.toml and then the `dofigen` key of package.json
+pub(crate) fn get_metadata_content() -> Result<String> {
+    if Path::new("Cargo.toml").exists() {
+        let manifest: toml::Table =
+            toml::from_str(&read_metadata_file("Cargo.toml")?).map_err(Error::display)?;
+        let metadata = manifest
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("dofigen"));
+        if let Some(metadata) = metadata {
+            return serde_json::to_string(metadata).map_err(Error::display);
+        }
+    }
+    if Path::new("package.json").exists() {
+        let package: serde_json::Value =
+            serde_json::from_str(&read_metadata_file("package.json")?).map_err(Error::display)?;
+        if let Some(metadata) = package.get("dofigen") {
+            return serde_json::to_string(metadata).map_err(Error::display);
+        }
+    }
+    Err(Error::Custom(
+        "No Dofigen metadata found in Cargo.toml or package.json".into(),
+    ))
+}
+
+fn read_metadata_file(path: &str) -> Result<String> {
+    std::fs::read_to_string(path)
+        .map_err(|err| Error::Custom(format!("Unable to read the file {}: {}", path, err)))
+}
+
 pub(crate) fn get_lockfile_path(path: String) -> Option<PathBuf> {
     if path == "-" {
         None
@@ -37,14 +96,47 @@ pub(crate) fn get_image_from_path(path: String, context: &mut DofigenContext) ->
 }
 
 pub(crate) fn load_lockfile(path: Option<PathBuf>) -> Option<LockFile> {
-    path.map(|path| {
-        if path.exists() {
-            let mut context = DofigenContext::new();
-            context.display_updates = false;
-            Resource::File(path).load(&mut context).ok()
-        } else {
-            None
+    path.filter(|path| path.exists())
+        .and_then(|path| LockFile::read(path).ok())
+}
+
+pub(crate) fn display_lint_messages(messages: &[LintMessage]) {
+    messages.iter().for_each(|message| {
+        eprintln!(
+            "{}[path={}]: {}",
+            match message.level {
+                MessageLevel::Error => "error".color(Color::Red).bold(),
+                MessageLevel::Warn => "warning".color(Color::Yellow).bold(),
+            },
+            message.path.join(".").color(Color::Blue).bold(),
+            message.message
+        );
+    });
+}
+
+pub(crate) fn display_timings(format: Option<TimingsFormat>, timings: &Timings) -> Result<()> {
+    match format {
+        Some(TimingsFormat::Text) => {
+            timings.durations().iter().for_each(|(phase, duration)| {
+                eprintln!(
+                    "{:>20} {:.3}ms",
+                    phase.as_str().color(Color::Cyan).bold(),
+                    duration.as_secs_f64() * 1000.0
+                );
+            });
         }
-    })
-    .flatten()
+        Some(TimingsFormat::Json) => {
+            let durations: BTreeMap<_, _> = timings
+                .durations()
+                .iter()
+                .map(|(phase, duration)| (phase, duration.as_secs_f64() * 1000.0))
+                .collect();
+            eprintln!(
+                "{}",
+                serde_json::to_string(&durations).map_err(Error::display)?
+            );
+        }
+        None => {}
+    }
+    Ok(())
 }
@@ -0,0 +1,117 @@
+//! # plugin
+//!
+//! The unknown subcommands run the `dofigen-<name>` executable found in the PATH, like the git ones,
+//! with the effective configuration of the Dofigen file as JSON on its standard input.
+
+use crate::*;
+use commands::{
+    config::UserConfig, display_lint_messages, get_file_path, get_image_from_path,
+    get_lockfile_path, load_lockfile,
+};
+use dofigen_lib::{DofigenContext, Error, Result};
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// The prefix of the plugins executables
+const PLUGIN_PREFIX: &str = "dofigen-";
+
+/// The environment variable giving the Dofigen file to the plugins
+const PLUGIN_FILE_ENV: &str = "DOFIGEN_FILE";
+
+/// The environment variable giving the Dofigen version to the plugins
+const PLUGIN_VERSION_ENV: &str = "DOFIGEN_VERSION";
+
+/// Runs the plugin of an external subcommand, the first argument being the subcommand name.
+/// The process exits with the plugin exit code when it fails
+pub(crate) fn run_plugin(args: Vec<String>) -> Result<()> {
+    let (name, args) = args
+        .split_first()
+        .ok_or(Error::Custom("No subcommand given".into()))?;
+    let program = format!("{}{}", PLUGIN_PREFIX, name);
+
+    let file = file_option(args).map(str::to_string);
+    let path = match &file {
+        Some(file) => Some(file.clone()),
+        None => get_file_path(&None).ok(),
+    };
+    let effective = path.as_ref().map(|path| effective_json(path)).transpose()?;
+
+    let mut command = Command::new(&program);
+    command
+        .args(args)
+        .env(PLUGIN_VERSION_ENV, env!("CARGO_PKG_VERSION"))
+        .stdin(if effective.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        });
+    if let Some(path) = &path {
+        command.env(PLUGIN_FILE_ENV, path);
+    }
+    let mut child = command.spawn().map_err(|err| match err.kind() {
+        ErrorKind::NotFound => Error::Custom(format!(
+            "The subcommand '{}' doesn't exist and no {} plugin is found in the PATH",
+            name, program
+        )),
+        _ => Error::Custom(format!("Unable to run {}: {}", program, err)),
+    })?;
+
+    // Written from another thread since the plugin can exit without reading it
+    let writer = child
+        .stdin
+        .take()
+        .zip(effective)
+        .map(|(mut stdin, effective)| {
+            thread::spawn(move || {
+                let _ = stdin.write_all(effective.as_bytes());
+            })
+        });
+    let status = child
+        .wait()
+        .map_err(|err| Error::Custom(format!("Unable to run {}: {}", program, err)))?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Returns the value of the file option of the plugin arguments, if any
+fn file_option(args: &[String]) -> Option<&str> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "-f" || arg == "--file" {
+            return args.next().map(String::as_str);
+        }
+        if let Some(file) = arg.strip_prefix("--file=") {
+            return Some(file);
+        }
+    }
+    None
+}
+
+/// Returns the effective configuration of the Dofigen file as JSON, with the resources of its lock file
+fn effective_json(path: &str) -> Result<String> {
+    let lockfile = load_lockfile(get_lockfile_path(path.into()));
+    let mut context = lockfile
+        .as_ref()
+        .map(|l| l.to_context())
+        .unwrap_or(DofigenContext::new());
+    let mut options = GlobalOptions::default();
+    options.apply_user_config(&UserConfig::load()?);
+    context.offline = options.offline;
+    context.registry_mirror = options.registry_mirror.clone();
+    context.default_profiles = options.default_profiles.clone();
+    context.http_cache = options.http_cache();
+    context.update_file_resources = true;
+
+    let dofigen = get_image_from_path(path.into(), &mut context)?;
+    display_lint_messages(&context.get_lint_messages());
+    serde_json::to_string(&dofigen).map_err(Error::display)
+}
@@ -0,0 +1,430 @@
+//! # refactor
+//!
+//! The refactor subcommand rewrites Dofigen files across their extend layers without changing the effective configuration.
+
+use super::{get_file_path, get_lockfile_path, load_lockfile};
+use crate::{CliCommand, GlobalOptions};
+use clap::{Args, Subcommand};
+use dofigen_lib::{Dofigen, DofigenContext, DofigenPatch, Error, Extend, Resource, Result};
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+use struct_patch::Merge;
+
+const EXTEND_KEYS: [&str; 2] = ["extend", "extends"];
+const BUILDERS_KEY: &str = "builders";
+const PROFILES_KEY: &str = "profiles";
+
+#[derive(Args, Debug, Clone)]
+pub struct Refactor {
+    /// The refactoring to apply
+    #[clap(subcommand)]
+    pub command: RefactorCommand,
+}
+
+/// The refactorings
+#[derive(Subcommand, Debug, Clone)]
+pub enum RefactorCommand {
+    /// Move a builder to an extended file and extend it from the current file.
+    /// The rewritten files are reformatted and lose their comments
+    ExtractBuilder(ExtractBuilder),
+
+    /// Merge an extended file into the current file and stop extending it.
+    /// The rewritten file is reformatted and loses its comments
+    Inline(Inline),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExtractBuilder {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The name of the builder to extract
+    name: String,
+
+    /// The file to move the builder to, relative to the Dofigen file.
+    /// It is created if it doesn't exist
+    #[clap(long)]
+    to: String,
+
+    /// Rewrite the files even if they have comments, that are lost
+    #[clap(long, action)]
+    force: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct Inline {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The extended file or URL to inline, as written in the extend field
+    layer: String,
+
+    /// Rewrite the file even if it has comments, that are lost
+    #[clap(long, action)]
+    force: bool,
+}
+
+impl Refactor {
+    /// Returns the options of the refactoring
+    pub fn options_mut(&mut self) -> &mut GlobalOptions {
+        match &mut self.command {
+            RefactorCommand::ExtractBuilder(e) => &mut e.options,
+            RefactorCommand::Inline(i) => &mut i.options,
+        }
+    }
+}
+
+impl CliCommand for Refactor {
+    fn run(self) -> Result<()> {
+        match self.command {
+            RefactorCommand::ExtractBuilder(e) => e.run(),
+            RefactorCommand::Inline(i) => i.run(),
+        }
+    }
+}
+
+impl CliCommand for ExtractBuilder {
+    fn run(self) -> Result<()> {
+        let path = get_refactored_file_path(&self.options)?;
+        let target_path = sibling_path(&path, &self.to);
+        let content = read_file(&path)?;
+        check_comments(&path, &content, self.force)?;
+
+        let mut document = parse_document(&content)?;
+        let mapping = as_mapping(&mut document, &path)?;
+
+        let builder = as_mapping(
+            mapping
+                .entry(BUILDERS_KEY.into())
+                .or_insert_with(|| Value::Mapping(Mapping::new())),
+            &path,
+        )?
+        .shift_remove(self.name.as_str())
+        .ok_or(Error::Custom(format!(
+            "The builder '{}' doesn't exist in {}",
+            self.name,
+            path.display()
+        )))?;
+        if mapping
+            .get(BUILDERS_KEY)
+            .and_then(Value::as_mapping)
+            .is_some_and(Mapping::is_empty)
+        {
+            mapping.shift_remove(BUILDERS_KEY);
+        }
+
+        // The builder keeps the same merge order if the target is the last extended layer
+        let mut extend = extend_entries(mapping)?;
+        let target_extended = extend.contains(&self.to);
+        if target_extended && extend.last() != Some(&self.to) {
+            return Err(Error::Custom(format!(
+                "The file {} must be the last extended file to keep the same configuration",
+                self.to
+            )));
+        }
+        if !target_extended && target_path.exists() {
+            return Err(Error::Custom(format!(
+                "The file {} already exists and isn't extended by {}",
+                self.to,
+                path.display()
+            )));
+        }
+
+        let target_content = if target_path.exists() {
+            let target_content = read_file(&target_path)?;
+            check_comments(&target_path, &target_content, self.force)?;
+            Some(target_content)
+        } else {
+            None
+        };
+        let mut target = match &target_content {
+            Some(target_content) => parse_document(target_content)?,
+            None => Value::Mapping(Mapping::new()),
+        };
+        let target_builders = as_mapping(
+            as_mapping(&mut target, &target_path)?
+                .entry(BUILDERS_KEY.into())
+                .or_insert_with(|| Value::Mapping(Mapping::new())),
+            &target_path,
+        )?;
+        if target_builders.contains_key(self.name.as_str()) {
+            return Err(Error::Custom(format!(
+                "The builder '{}' already exists in {}",
+                self.name, self.to
+            )));
+        }
+        target_builders.insert(self.name.clone().into(), builder);
+
+        if !target_extended {
+            extend.push(self.to.clone());
+        }
+        set_extend_entries(mapping, extend);
+
+        let effective = refactor_context(&path, &self.options)?
+            .parse_from_resource(Resource::File(path.clone()))?;
+        write_document(&target_path, &target)?;
+        write_document(&path, &document)?;
+
+        // The builder could be merged differently, for example when its fields use patches operators
+        let extracted_effective = refactor_context(&path, &self.options)?
+            .parse_from_resource(Resource::File(path.clone()));
+        if extracted_effective.ok().as_ref() != Some(&effective) {
+            write_file(&path, &content)?;
+            match target_content {
+                Some(target_content) => write_file(&target_path, &target_content)?,
+                None => std::fs::remove_file(&target_path).map_err(|err| {
+                    Error::Custom(format!(
+                        "Unable to remove the file {}: {}",
+                        target_path.display(),
+                        err
+                    ))
+                })?,
+            }
+            return Err(Error::Custom(format!(
+                "The builder '{}' can't be extracted to {} without changing the effective configuration",
+                self.name, self.to
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl CliCommand for Inline {
+    fn run(self) -> Result<()> {
+        let path = get_refactored_file_path(&self.options)?;
+        let content = read_file(&path)?;
+        check_comments(&path, &content, self.force)?;
+
+        let mut document = parse_document(&content)?;
+        let mut extend = extend_entries(as_mapping(&mut document, &path)?)?;
+        let position =
+            extend
+                .iter()
+                .position(|entry| entry == &self.layer)
+                .ok_or(Error::Custom(format!(
+                    "The file {} doesn't extend {}",
+                    path.display(),
+                    self.layer
+                )))?;
+        extend.remove(position);
+
+        let mut context = refactor_context(&path, &self.options)?;
+        let effective = context.parse_from_resource(Resource::File(path.clone()))?;
+
+        let layer_resource = match self.layer.parse()? {
+            Resource::File(_) => Resource::File(sibling_path(&path, &self.layer)),
+            url => url,
+        };
+        let layer = layer_resource.load::<Extend<DofigenPatch>>(&mut context)?;
+        if !layer.profiles.is_empty() {
+            return Err(Error::Custom(format!(
+                "The file {} can't be inlined since it declares profiles",
+                self.layer
+            )));
+        }
+        if !layer.params.is_empty() {
+            return Err(Error::Custom(format!(
+                "The file {} can't be inlined since it declares parameters",
+                self.layer
+            )));
+        }
+        let layer: DofigenPatch = layer.merge(&mut context)?;
+        let layer_fields =
+            serde_yaml::to_value(Dofigen::from(layer.clone())).map_err(Error::from)?;
+        let local_fields = as_mapping(&mut document, &path)?.clone();
+        let profiles = local_fields.get(PROFILES_KEY).cloned();
+        let local = serde_yaml::from_value::<Extend<DofigenPatch>>(document)
+            .map_err(Error::from)?
+            .value;
+
+        let mut inlined =
+            serde_yaml::to_value(Dofigen::from(layer.merge(local))).map_err(Error::from)?;
+        let inlined_mapping = as_mapping(&mut inlined, &path)?;
+        // The fields the layer doesn't set keep their patches and shorthands of the file
+        for (key, value) in inlined_mapping.iter_mut() {
+            let layer_field = layer_fields.as_mapping().and_then(|layer| layer.get(&*key));
+            if let (None, Some(local_value)) = (layer_field, local_fields.get(&*key)) {
+                *value = local_value.clone();
+            }
+        }
+        set_extend_entries(inlined_mapping, extend);
+        if let Some(profiles) = profiles {
+            inlined_mapping.insert(PROFILES_KEY.into(), profiles);
+        }
+        write_document(&path, &inlined)?;
+
+        // The patches operators of the file are resolved with the inlined layer,
+        // so the remaining extended files could be merged differently
+        let inlined_effective = refactor_context(&path, &self.options)?
+            .parse_from_resource(Resource::File(path.clone()));
+        if inlined_effective.ok().as_ref() != Some(&effective) {
+            write_file(&path, &content)?;
+            return Err(Error::Custom(format!(
+                "The file {} can't be inlined without changing the effective configuration",
+                self.layer
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the context used to load the Dofigen files, with the resources of the lock file
+fn refactor_context(path: &Path, options: &GlobalOptions) -> Result<DofigenContext> {
+    let mut context = load_lockfile(get_lockfile_path(path.to_string_lossy().to_string()))
+        .map(|lockfile| lockfile.to_context())
+        .unwrap_or(DofigenContext::new());
+    context.offline = options.offline;
+    context.registry_mirror = options.registry_mirror.clone();
+    context.fixtures = options.network_fixtures()?;
+    context.variables = options.variables()?;
+    context.profiles = options.profiles.clone();
+    context.default_profiles = options.default_profiles.clone();
+    context.http_cache = options.http_cache();
+    context.update_file_resources = true;
+    Ok(context)
+}
+
+/// Returns the path of the Dofigen file to refactor
+fn get_refactored_file_path(options: &GlobalOptions) -> Result<PathBuf> {
+    let path = get_file_path(&options.file)?;
+    if path == "-" {
+        return Err(Error::Custom(
+            "Refactor command can't be used with stdin".into(),
+        ));
+    }
+    Ok(PathBuf::from(path))
+}
+
+/// Resolves a path relatively to the directory of the given file, like the extended files
+pub(super) fn sibling_path(file: &Path, path: &str) -> PathBuf {
+    file.parent()
+        .map(|parent| parent.join(path))
+        .unwrap_or_else(|| PathBuf::from(path))
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    std::fs::read_to_string(path).map_err(|err| {
+        Error::Custom(format!(
+            "Unable to read the file {}: {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    std::fs::write(path, content).map_err(|err| {
+        Error::Custom(format!(
+            "Unable to write the file {}: {}",
+            path.display(),
+            err
+        ))
+    })
+}
+
+pub(super) fn load_document(path: &Path) -> Result<Value> {
+    parse_document(&read_file(path)?)
+}
+
+fn parse_document(content: &str) -> Result<Value> {
+    let document: Value = serde_yaml::from_str(content).map_err(Error::from)?;
+    Ok(if document.is_null() {
+        Value::Mapping(Mapping::new())
+    } else {
+        document
+    })
+}
+
+pub(super) fn write_document(path: &Path, document: &Value) -> Result<()> {
+    let content = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::to_string_pretty(document).map_err(Error::display)?
+    } else {
+        serde_yaml::to_string(document).map_err(Error::from)?
+    };
+    write_file(path, &content)
+}
+
+/// Fails when the YAML content has comments, that the rewrite would lose, unless forced
+fn check_comments(path: &Path, content: &str, force: bool) -> Result<()> {
+    if force || path.extension().is_some_and(|ext| ext == "json") || !has_comments(content) {
+        return Ok(());
+    }
+    Err(Error::Custom(format!(
+        "The file {} has comments that the refactoring would remove, use --force to rewrite it anyway",
+        path.display()
+    )))
+}
+
+/// Checks if a line has a comment, a # out of the quoted scalars and after a space or at the start of the line.
+/// The # of the block scalars are also considered as comments
+fn has_comments(content: &str) -> bool {
+    content.lines().any(|line| {
+        let mut quote = None;
+        let mut previous = ' ';
+        for c in line.chars() {
+            match (quote, c) {
+                (None, '#') if previous.is_whitespace() => return true,
+                (None, '\'' | '"') if previous.is_whitespace() || "[{,".contains(previous) => {
+                    quote = Some(c)
+                }
+                (Some(opening), _) if opening == c => quote = None,
+                _ => {}
+            }
+            previous = c;
+        }
+        false
+    })
+}
+
+fn as_mapping<'a>(value: &'a mut Value, path: &Path) -> Result<&'a mut Mapping> {
+    value.as_mapping_mut().ok_or(Error::Custom(format!(
+        "Unexpected structure in {}: a mapping was expected",
+        path.display()
+    )))
+}
+
+/// Returns the extended files of the document, whatever the form of the extend field
+pub(super) fn extend_entries(mapping: &Mapping) -> Result<Vec<String>> {
+    match EXTEND_KEYS.iter().find_map(|key| mapping.get(*key)) {
+        None | Some(Value::Null) => Ok(vec![]),
+        Some(Value::String(entry)) => Ok(vec![entry.clone()]),
+        Some(Value::Sequence(entries)) => entries
+            .iter()
+            .map(|entry| {
+                entry
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or(Error::Custom("The extend entries must be strings".into()))
+            })
+            .collect(),
+        Some(_) => Err(Error::Custom(
+            "The extend field must be a string or a list".into(),
+        )),
+    }
+}
+
+/// Replaces the extend field of the document, removing it when there is no entry.
+/// The field keeps its position or is added at the top of the document
+fn set_extend_entries(mapping: &mut Mapping, entries: Vec<String>) {
+    let value = Value::Sequence(entries.into_iter().map(Value::String).collect());
+    let empty = value.as_sequence().is_some_and(Vec::is_empty);
+    let mut replaced = false;
+    let mut rewritten = Mapping::new();
+    if !empty && !EXTEND_KEYS.iter().any(|key| mapping.contains_key(*key)) {
+        rewritten.insert(EXTEND_KEYS[0].into(), value.clone());
+        replaced = true;
+    }
+    std::mem::take(mapping)
+        .into_iter()
+        .for_each(|(key, field)| {
+            if EXTEND_KEYS.iter().any(|extend_key| key == *extend_key) {
+                if !empty && !replaced {
+                    rewritten.insert(key, value.clone());
+                    replaced = true;
+                }
+            } else {
+                rewritten.insert(key, field);
+            }
+        });
+    *mapping = rewritten;
+}
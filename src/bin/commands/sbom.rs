@@ -0,0 +1,60 @@
+//! # sbom
+//!
+//! The sbom subcommand exports a CycloneDX software bill of materials of the inputs declared in a Dofigen file.
+
+use crate::*;
+use clap::Args;
+use commands::{
+    display_lint_messages, display_timings, get_file_path, get_image_from_path, get_lockfile_path,
+    load_lockfile,
+};
+use dofigen_lib::{DofigenContext, Error, Result, TimingPhase};
+use std::time::Instant;
+
+#[derive(Args, Debug, Default, Clone)]
+pub struct Sbom {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// Build the bill of materials from the declared inputs, the base images and the packages of the stages,
+    /// without building the image
+    #[clap(long = "static", action)]
+    pub static_inputs: bool,
+}
+
+impl CliCommand for Sbom {
+    fn run(self) -> Result<()> {
+        if !self.static_inputs {
+            return Err(Error::Custom(
+                "Only the bill of materials of the declared inputs is supported, use the '--static' option".into(),
+            ));
+        }
+        let path = get_file_path(&self.options.file)?;
+        let start = Instant::now();
+        let lockfile = load_lockfile(get_lockfile_path(path.clone()));
+        let mut context = lockfile
+            .as_ref()
+            .map(|l| l.to_context())
+            .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        context.update_file_resources = true;
+
+        let dofigen = get_image_from_path(path, &mut context)?;
+
+        display_lint_messages(&context.get_lint_messages());
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&dofigen.static_sbom()).map_err(Error::display)?
+        );
+
+        display_timings(self.options.timings, &context.timings)
+    }
+}
@@ -0,0 +1,234 @@
+//! # self
+//!
+//! The self subcommand checks that a newer version of Dofigen is released on GitHub and replaces the running binary with it.
+
+use crate::CliCommand;
+use clap::{Args, Subcommand};
+use colored::{Color, Colorize};
+use dofigen_lib::{ChecksumAlgorithm, Error, Result, TagVersion};
+use reqwest::{blocking::Client, header};
+use serde::Deserialize;
+use std::{env, fs, path::Path};
+
+/// The GitHub API giving the latest release of Dofigen
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/lenra-io/dofigen/releases/latest";
+
+/// The environment variable overriding the URL of the latest release, for the mirrors
+const RELEASES_URL_ENV: &str = "DOFIGEN_RELEASES_URL";
+
+#[derive(Args, Debug, Clone)]
+pub struct SelfCommand {
+    /// The action on the Dofigen binary
+    #[clap(subcommand)]
+    pub command: SelfSubcommand,
+}
+
+/// The actions on the Dofigen binary
+#[derive(Subcommand, Debug, Clone)]
+pub enum SelfSubcommand {
+    /// Check if a newer version of Dofigen is released
+    CheckUpdate(CheckUpdate),
+
+    /// Replace the Dofigen binary with the latest released version
+    Update(SelfUpdate),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CheckUpdate {
+    /// The command won't check the releases
+    #[clap(long, action)]
+    pub offline: bool,
+
+    /// Check the releases even when the user configuration sets offline
+    #[clap(long, action, conflicts_with = "offline")]
+    pub online: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SelfUpdate {
+    /// Replace the binary even if it's already the latest version
+    #[clap(long, action)]
+    pub force: bool,
+}
+
+/// A GitHub release
+#[derive(Deserialize, Debug, Clone)]
+struct Release {
+    tag_name: String,
+    html_url: Option<String>,
+    #[serde(default)]
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+impl Release {
+    fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    fn is_newer(&self) -> bool {
+        match (
+            TagVersion::parse(self.version()),
+            TagVersion::parse(env!("CARGO_PKG_VERSION")),
+        ) {
+            (Some(latest), Some(current)) => latest > current,
+            _ => false,
+        }
+    }
+
+    fn asset(&self, name: &str) -> Result<&ReleaseAsset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or(Error::Custom(format!(
+                "No {} binary found in the release {}",
+                name, self.tag_name
+            )))
+    }
+}
+
+impl CliCommand for SelfCommand {
+    fn run(self) -> Result<()> {
+        match self.command {
+            SelfSubcommand::CheckUpdate(c) => c.run(),
+            SelfSubcommand::Update(u) => u.run(),
+        }
+    }
+}
+
+impl CliCommand for CheckUpdate {
+    fn run(self) -> Result<()> {
+        if self.offline {
+            println!("The update check is disabled in offline mode");
+            return Ok(());
+        }
+        let client = client()?;
+        let release = latest_release(&client)?;
+        if release.is_newer() {
+            println!(
+                "A new version of Dofigen is available: {} -> {}",
+                env!("CARGO_PKG_VERSION").color(Color::Red),
+                release.version().color(Color::Green).bold()
+            );
+            if let Some(url) = &release.html_url {
+                println!("Release notes: {}", url);
+            }
+            println!(
+                "Run {} to install it",
+                "dofigen self update".color(Color::Cyan)
+            );
+        } else {
+            println!(
+                "Dofigen {} is the latest version",
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+        Ok(())
+    }
+}
+
+impl CliCommand for SelfUpdate {
+    fn run(self) -> Result<()> {
+        let client = client()?;
+        let release = latest_release(&client)?;
+        if !self.force && !release.is_newer() {
+            println!(
+                "Dofigen {} is the latest version",
+                env!("CARGO_PKG_VERSION")
+            );
+            return Ok(());
+        }
+
+        let name = binary_name();
+        let binary = download(&client, release.asset(&name)?)?;
+        let expected = String::from_utf8_lossy(&download(
+            &client,
+            release.asset(&format!("{}.sha256", name))?,
+        )?)
+        .trim()
+        .to_string();
+        let checksum = ChecksumAlgorithm::Sha256.checksum(&binary);
+        if checksum != format!("sha256:{}", expected) {
+            return Err(Error::Custom(format!(
+                "The checksum of the downloaded {} binary doesn't match the released one",
+                name
+            )));
+        }
+
+        let current = env::current_exe()
+            .map_err(|err| Error::Custom(format!("Unable to find the Dofigen binary: {}", err)))?;
+        replace_binary(&current, &binary)?;
+        println!(
+            "Dofigen updated from {} to {}",
+            env!("CARGO_PKG_VERSION"),
+            release.version().color(Color::Green).bold()
+        );
+        Ok(())
+    }
+}
+
+/// Returns the HTTP client, using the proxies of the HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment variables
+fn client() -> Result<Client> {
+    Client::builder()
+        .user_agent(concat!("dofigen/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(Error::from)
+}
+
+fn latest_release(client: &Client) -> Result<Release> {
+    let url = env::var(RELEASES_URL_ENV).unwrap_or(LATEST_RELEASE_URL.into());
+    client
+        .get(&url)
+        .header(header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.json())
+        .map_err(Error::from)
+}
+
+fn download(client: &Client, asset: &ReleaseAsset) -> Result<Vec<u8>> {
+    client
+        .get(&asset.browser_download_url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .map(|bytes| bytes.to_vec())
+        .map_err(Error::from)
+}
+
+/// Returns the name of the released binary of the current platform, like dofigen-linux-x86_64
+fn binary_name() -> String {
+    format!(
+        "dofigen-{}-{}{}",
+        env::consts::OS,
+        env::consts::ARCH,
+        env::consts::EXE_SUFFIX
+    )
+}
+
+/// Replaces the binary with the downloaded one, through a file in the same directory to keep the replacement atomic.
+/// The running binary of Windows can't be overwritten but can be renamed
+fn replace_binary(current: &Path, binary: &[u8]) -> Result<()> {
+    let io_error = |err: std::io::Error| {
+        Error::Custom(format!("Unable to replace the Dofigen binary: {}", err))
+    };
+    let new = current.with_extension("new");
+    fs::write(&new, binary).map_err(io_error)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755)).map_err(io_error)?;
+    }
+    #[cfg(windows)]
+    {
+        let old = current.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(current, &old).map_err(io_error)?;
+    }
+    fs::rename(&new, current).map_err(io_error)
+}
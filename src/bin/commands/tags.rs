@@ -0,0 +1,43 @@
+//! # tags
+//!
+//! The tags subcommand lists the tags of an image from its registry, the highest versions first.
+
+use crate::CliCommand;
+use clap::Args;
+use dofigen_lib::{sort_tags, Error, ImageName, RegistryClient, Result};
+use regex::Regex;
+
+#[derive(Args, Debug, Clone)]
+pub struct Tags {
+    /// The image to list the tags of, like alpine or ghcr.io/owner/image
+    image: String,
+
+    /// The registry serving the Docker Hub images instead of Docker Hub, like mirror.gcr.io
+    #[clap(long, value_name = "HOST")]
+    pub registry_mirror: Option<String>,
+
+    /// A regular expression the listed tags must match, like '^3\.\d+$'
+    #[clap(long)]
+    filter: Option<String>,
+}
+
+impl CliCommand for Tags {
+    fn run(self) -> Result<()> {
+        let filter = self
+            .filter
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(Error::display)?;
+        let image: ImageName = self.image.parse()?;
+        let mut tags = RegistryClient::new()
+            .with_mirror(self.registry_mirror.clone())
+            .list_tags(&image)?;
+        if let Some(filter) = filter {
+            tags.retain(|tag| filter.is_match(tag));
+        }
+        sort_tags(&mut tags);
+        tags.iter().for_each(|tag| println!("{}", tag));
+        Ok(())
+    }
+}
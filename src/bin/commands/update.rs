@@ -1,15 +1,28 @@
-//! # generate
+//! # update
 //!
-//! The generate subcommand generates a Dockerfile and a .dockerignore file from a Dofigen file.
+//! The update subcommand updates the lock file and reports the changes of its images and resources.
 
-use super::{get_file_path, get_image_from_path, get_lockfile_path, load_lockfile};
+use super::{
+    display_timings,
+    generate::{Generate, DEFAULT_DOCKERFILE},
+    get_file_path, get_image_from_path, get_lockfile_path, load_lockfile,
+    refactor::{extend_entries, load_document, sibling_path, write_document},
+};
 use crate::{CliCommand, GlobalOptions};
-use clap::Args;
+use clap::{Args, ValueEnum};
 use dofigen_lib::{
     lock::{Lock, LockFile},
-    Error, Result,
+    Error, ImageName, ImageVersion, Resource, Result, TimingPhase, UpdateReport,
+};
+use serde_yaml::Value;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Instant,
 };
 
+const FROM_IMAGE_KEY: &str = "fromImage";
+
 #[derive(Args, Debug, Default, Clone)]
 pub struct Update {
     #[command(flatten)]
@@ -18,6 +31,215 @@ pub struct Update {
     /// Don't actually write the lockfile
     #[clap(long, action)]
     dry_run: bool,
+
+    /// Regenerate the Dockerfile and .dockerignore files from the updated lock file
+    #[clap(
+        long,
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_DOCKERFILE,
+        value_name = "OUTPUT",
+        conflicts_with = "dry_run"
+    )]
+    sync_dockerfile: Option<String>,
+
+    /// Set the tag of an image in the Dofigen file and its local extended files before updating, like rust=1.85.
+    /// Can be repeated
+    #[clap(long = "image", value_name = "IMAGE=TAG", conflicts_with = "dry_run")]
+    images: Vec<String>,
+
+    /// Write the images used by the Dofigen file, with their tags and locked digests, as JSON in the given file
+    #[clap(long)]
+    manifest: Option<String>,
+
+    /// Write the report of the changes of the lock file in the given file, like report.md for a pull request description
+    #[clap(long)]
+    output: Option<String>,
+
+    /// The format of the report. Defaults to the format of the output file extension, or text
+    #[clap(long, value_enum)]
+    report_format: Option<ReportFormat>,
+}
+
+/// The format of the update report
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+impl Update {
+    /// Sets the tags of the updated images in the Dofigen file and its local extended files
+    fn set_image_tags(&self, path: &Path) -> Result<()> {
+        let updates = self
+            .images
+            .iter()
+            .map(|update| {
+                let (image, tag) = update.split_once('=').ok_or(Error::Custom(format!(
+                    "The image update '{}' must be formatted as IMAGE=TAG",
+                    update
+                )))?;
+                Ok((image.parse::<ImageName>()?, tag.to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut updated = HashSet::new();
+        for file in local_layers(path)? {
+            let mut document = load_document(&file)?;
+            let mut changed = false;
+            for (position, (image, tag)) in updates.iter().enumerate() {
+                if set_image_tag(&mut document, image, tag) {
+                    updated.insert(position);
+                    changed = true;
+                }
+            }
+            if changed {
+                write_document(&file, &document)?;
+            }
+        }
+
+        if let Some((image, _)) = updates
+            .iter()
+            .enumerate()
+            .find(|(position, _)| !updated.contains(position))
+            .map(|(_, update)| update)
+        {
+            return Err(Error::Custom(format!(
+                "The image '{}' isn't used by the Dofigen file or its local extended files",
+                image.to_string()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the Dofigen file followed by its local extended files
+fn local_layers(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut layers = vec![path.to_path_buf()];
+    let mut position = 0;
+    while position < layers.len() {
+        let file = layers[position].clone();
+        let document = load_document(&file)?;
+        if let Some(mapping) = document.as_mapping() {
+            for entry in extend_entries(mapping)? {
+                if let Resource::File(_) = entry.parse::<Resource>()? {
+                    let layer = sibling_path(&file, &entry);
+                    if !layers.contains(&layer) {
+                        layers.push(layer);
+                    }
+                }
+            }
+        }
+        position += 1;
+    }
+    Ok(layers)
+}
+
+/// Sets the tag of the fromImage fields using the image, in both their string and structure forms
+fn set_image_tag(value: &mut Value, image: &ImageName, tag: &str) -> bool {
+    let mut changed = false;
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, field) in mapping.iter_mut() {
+                if key == FROM_IMAGE_KEY {
+                    changed |= set_from_image_tag(field, image, tag);
+                } else {
+                    changed |= set_image_tag(field, image, tag);
+                }
+            }
+        }
+        Value::Sequence(values) => {
+            for value in values {
+                changed |= set_image_tag(value, image, tag);
+            }
+        }
+        _ => {}
+    }
+    changed
+}
+
+fn set_from_image_tag(value: &mut Value, image: &ImageName, tag: &str) -> bool {
+    match value {
+        Value::String(name) => match name.parse::<ImageName>() {
+            Ok(mut name_image) if same_repository(&name_image, image) => {
+                name_image.version = Some(ImageVersion::Tag(tag.to_string()));
+                *value = Value::String(name_image.to_string());
+                true
+            }
+            _ => false,
+        },
+        Value::Mapping(mapping) => {
+            let Some(path) = mapping.get("path").and_then(Value::as_str) else {
+                return false;
+            };
+            let name_image = ImageName {
+                host: mapping
+                    .get("host")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                port: mapping
+                    .get("port")
+                    .and_then(Value::as_u64)
+                    .and_then(|port| port.try_into().ok()),
+                path: path.to_string(),
+                version: None,
+            };
+            if !same_repository(&name_image, image) {
+                return false;
+            }
+            mapping.shift_remove("digest");
+            mapping.insert("tag".into(), Value::String(tag.to_string()));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Checks if two image names target the same repository, whatever their versions
+fn same_repository(a: &ImageName, b: &ImageName) -> bool {
+    let (a, b) = (a.fill(), b.fill());
+    a.host == b.host && a.port == b.port && a.path == b.path
+}
+
+impl Update {
+    /// Displays the report of the changes of the lock file and writes it in the output file
+    fn report(&self, report: &UpdateReport) -> Result<()> {
+        let extension = self
+            .output
+            .as_ref()
+            .and_then(|output| Path::new(output).extension())
+            .and_then(|extension| extension.to_str());
+        let format = self.report_format.unwrap_or(match extension {
+            Some("md" | "markdown") => ReportFormat::Markdown,
+            Some("json") => ReportFormat::Json,
+            _ => ReportFormat::Text,
+        });
+
+        if !report.is_empty() && (self.output.is_some() || format == ReportFormat::Text) {
+            println!("{}", report.to_text());
+        }
+        if self.output.is_none() && format == ReportFormat::Text {
+            return Ok(());
+        }
+
+        if format == ReportFormat::Text {
+            colored::control::set_override(false);
+        }
+        let content = match format {
+            ReportFormat::Text => report.to_text(),
+            ReportFormat::Markdown => report.to_markdown(),
+            ReportFormat::Json => serde_json::to_string_pretty(report).map_err(Error::display)?,
+        };
+        match &self.output {
+            Some(output) => std::fs::write(output, content).map_err(|err| {
+                Error::Custom(format!("Unable to write the update report: {}", err))
+            }),
+            None => {
+                println!("{}", content);
+                Ok(())
+            }
+        }
+    }
 }
 
 impl CliCommand for Update {
@@ -29,29 +251,53 @@ impl CliCommand for Update {
                 "Update command can't be used with stdin".into(),
             ));
         }
+        if !self.images.is_empty() {
+            self.set_image_tags(Path::new(&path))?;
+        }
         let lockfile_path = get_lockfile_path(path.clone());
+        let start = Instant::now();
         let lockfile = load_lockfile(lockfile_path.clone()).ok_or(Error::Custom(
             "The update command needs a lock file to update".into(),
         ))?;
 
         let lockfile_path = lockfile_path.unwrap();
         let mut context = lockfile.to_context();
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
 
         context.offline = self.options.offline;
+
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
         context.update_docker_tags = !self.options.offline;
         context.update_file_resources = true;
         context.update_url_resources = !self.options.offline;
+        context.update_add_checksums = !self.options.offline;
 
+        let previous = context.clone();
         let dofigen = get_image_from_path(path, &mut context)?;
 
         // Replace images tags with the digest
         let locked_image = dofigen.lock(&mut context)?;
         context.clean_unused();
+        self.report(&context.update_report(&previous))?;
+
+        if let Some(manifest) = &self.manifest {
+            let content = serde_json::to_string_pretty(&dofigen.image_dependencies(&context))
+                .map_err(Error::display)?;
+            std::fs::write(manifest, content).map_err(|err| {
+                Error::Custom(format!("Unable to write the dependency manifest: {}", err))
+            })?;
+        }
 
         if self.dry_run {
-            return Ok(());
+            return display_timings(self.options.timings, &context.timings);
         }
 
+        let start = Instant::now();
         let new_lockfile = LockFile::from_context(&locked_image, &context)?;
 
         serde_yaml::to_writer(
@@ -60,7 +306,31 @@ impl CliCommand for Update {
             &new_lockfile,
         )
         .map_err(Error::from)?;
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
 
-        Ok(())
+        if let Some(output) = self.sync_dockerfile {
+            Generate {
+                options: GlobalOptions {
+                    timings: None,
+                    ..self.options.clone()
+                },
+                output,
+                locked: true,
+                from_metadata: false,
+                oci_config: None,
+                prune_unused: false,
+                lock_labels: false,
+                matrix: false,
+                matrix_key: None,
+                variant: None,
+                debug_packages: vec![],
+                debug_root: false,
+                verify_checksums: false,
+                watch: false,
+            }
+            .run()?;
+        }
+
+        display_timings(self.options.timings, &context.timings)
     }
 }
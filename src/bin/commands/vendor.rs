@@ -0,0 +1,80 @@
+//! # vendor
+//!
+//! The vendor subcommand writes the URL resources extended by a Dofigen file in a local directory.
+//! The lock file then loads them from this directory instead of the URLs.
+
+use super::{
+    display_timings, get_file_path, get_image_from_path, get_lockfile_path, load_lockfile,
+};
+use crate::{CliCommand, GlobalOptions};
+use clap::Args;
+use colored::{Color, Colorize};
+use dofigen_lib::{
+    lock::{Lock, LockFile},
+    DofigenContext, Error, Result, TimingPhase,
+};
+use std::time::Instant;
+
+#[derive(Args, Debug, Clone)]
+pub struct Vendor {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The directory where the URL resources are written
+    #[clap(long, default_value = ".dofigen/vendor")]
+    directory: String,
+}
+
+impl CliCommand for Vendor {
+    fn run(self) -> Result<()> {
+        let path = get_file_path(&self.options.file)?;
+        if path == "-" {
+            return Err(Error::Custom(
+                "Vendor command can't be used with stdin".into(),
+            ));
+        }
+        let lockfile_path = get_lockfile_path(path.clone()).unwrap();
+        let start = Instant::now();
+        let mut context = load_lockfile(Some(lockfile_path.clone()))
+            .map(|lockfile| lockfile.to_context())
+            .unwrap_or(DofigenContext::new());
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
+
+        context.offline = self.options.offline;
+
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        context.variables = self.options.variables()?;
+        context.profiles = self.options.profiles.clone();
+        context.default_profiles = self.options.default_profiles.clone();
+        context.http_cache = self.options.http_cache();
+        context.update_file_resources = true;
+
+        let dofigen = get_image_from_path(path, &mut context)?;
+
+        // Replace images tags with the digest
+        let locked_image = dofigen.lock(&mut context)?;
+        context.clean_unused();
+
+        for (url, path) in context.vendor_resources(&self.directory)? {
+            println!(
+                "{:>20} {} {}",
+                "Vendor resource".color(Color::Blue).bold(),
+                url,
+                path.display()
+            );
+        }
+
+        let start = Instant::now();
+        let lockfile = LockFile::from_context(&locked_image, &context)?;
+        serde_yaml::to_writer(
+            std::fs::File::create(lockfile_path)
+                .map_err(|err| Error::Custom(format!("Unable to create the lock file: {}", err)))?,
+            &lockfile,
+        )
+        .map_err(Error::from)?;
+        context.timings.add(TimingPhase::LockIo, start.elapsed());
+
+        display_timings(self.options.timings, &context.timings)
+    }
+}
@@ -0,0 +1,68 @@
+//! # verify-image
+//!
+//! The verify-image subcommand checks that the labels of an image, added by the `--lock-labels` option of the generate subcommand, match the local lock file.
+
+use crate::*;
+use clap::Args;
+use colored::{Color, Colorize};
+use commands::{get_file_path, get_lockfile_path, load_lockfile};
+use dofigen_lib::{
+    lock::{LOCK_LABEL, VERSION_LABEL},
+    DofigenContext, Error, ImageName, Result,
+};
+
+#[derive(Args, Debug, Clone)]
+pub struct VerifyImage {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// The image to verify, like ghcr.io/owner/image:1.0.0
+    image: String,
+}
+
+impl CliCommand for VerifyImage {
+    fn run(self) -> Result<()> {
+        let path = get_file_path(&self.options.file)?;
+        let lockfile = load_lockfile(get_lockfile_path(path))
+            .ok_or(Error::Custom("No lock file found".into()))?;
+        let expected = lockfile.labels()?;
+
+        let image: ImageName = self.image.parse()?;
+        let mut context = DofigenContext::new();
+        context.offline = self.options.offline;
+        context.registry_mirror = self.options.registry_mirror.clone();
+        context.fixtures = self.options.network_fixtures()?;
+        let labels = context.resolve_image(&image)?.labels;
+
+        let Some(lock) = labels.get(LOCK_LABEL) else {
+            return Err(Error::Custom(format!(
+                "The image {} has no {} label, generate its Dockerfile with the --lock-labels option",
+                self.image, LOCK_LABEL
+            )));
+        };
+
+        if let Some(version) = labels.get(VERSION_LABEL) {
+            if *version != expected[VERSION_LABEL] {
+                println!(
+                    "{:>20} The image is generated by Dofigen v{} instead of v{}",
+                    "Warning".color(Color::Yellow).bold(),
+                    version,
+                    expected[VERSION_LABEL]
+                );
+            }
+        }
+
+        if *lock != expected[LOCK_LABEL] {
+            return Err(Error::Custom(format!(
+                "The image {} is generated from another lock file: {} instead of {}",
+                self.image, lock, expected[LOCK_LABEL]
+            )));
+        }
+        println!(
+            "{:>20} The image {} is generated from the local lock file",
+            "Ok".color(Color::Green).bold(),
+            self.image
+        );
+        Ok(())
+    }
+}
@@ -1,9 +1,30 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::{Color, Colorize};
+use commands::{
+    clean::Clean,
+    compose::Compose,
+    config::{Config, UserConfig},
+    diff::Diff,
+    doctor::Doctor,
+    effective::Effective,
+    explain::Explain,
+    generate::Generate,
+    graph::Graph,
+    inspect::Inspect,
+    lint::Lint,
+    plugin::run_plugin,
+    refactor::Refactor,
+    sbom::Sbom,
+    self_update::{SelfCommand, SelfSubcommand},
+    tags::Tags,
+    update::Update,
+    vendor::Vendor,
+    verify_image::VerifyImage,
+};
 #[cfg(feature = "json_schema")]
-use commands::schema::Schema;
-use commands::{effective::Effective, generate::Generate, update::Update};
-use dofigen_lib::Result;
+use commands::{complete::Complete, schema::Schema};
+use dofigen_lib::{Error, HttpCache, NetworkFixtures, Result};
+use std::collections::HashMap;
 
 mod commands;
 
@@ -28,6 +49,104 @@ pub struct GlobalOptions {
     /// This disables extending file from URL and loading image tag
     #[clap(long, action)]
     pub offline: bool,
+
+    /// Load the data from the URLs even when the user configuration sets offline
+    #[clap(long, action, conflicts_with = "offline")]
+    pub online: bool,
+
+    /// Serve the image tags and URL resources from a fixtures file instead of the network.
+    /// Useful to write deterministic tests
+    #[clap(long)]
+    pub fixtures: Option<String>,
+
+    /// Load the image tags and URL resources from the network and record them in the fixtures file
+    #[clap(long, action, requires = "fixtures")]
+    pub record: bool,
+
+    /// Download the URL resources again instead of revalidating the cached ones
+    #[clap(long, action)]
+    pub refresh: bool,
+
+    /// Display the duration of each phase of the command
+    #[clap(long, value_enum, num_args = 0..=1, default_missing_value = "text")]
+    pub timings: Option<TimingsFormat>,
+
+    /// Set a variable of the Dofigen files, overriding its declared value. Can be used several times
+    #[clap(long = "set", value_name = "NAME=VALUE")]
+    pub variables: Vec<String>,
+
+    /// Activate a profile of the Dofigen files, applying its patches. Can be used several times
+    #[clap(long = "profile", value_name = "NAME")]
+    pub profiles: Vec<String>,
+
+    /// The registry serving the Docker Hub images instead of Docker Hub, like mirror.gcr.io
+    #[clap(long, value_name = "HOST")]
+    pub registry_mirror: Option<String>,
+
+    /// The profiles of the user configuration, activated when the Dofigen files declare them
+    #[clap(skip)]
+    pub default_profiles: Vec<String>,
+}
+
+impl GlobalOptions {
+    /// Applies the user configuration under the options
+    pub fn apply_user_config(&mut self, config: &UserConfig) {
+        if !self.online {
+            self.offline |= config.offline.unwrap_or_default();
+        }
+        if self.registry_mirror.is_none() {
+            self.registry_mirror = config.registry_mirror.clone();
+        }
+        if self.profiles.is_empty() {
+            self.default_profiles = config.profiles.clone();
+        }
+    }
+
+    /// Returns the network fixtures defined by the options
+    pub fn network_fixtures(&self) -> Result<Option<NetworkFixtures>> {
+        self.fixtures
+            .as_ref()
+            .map(|path| {
+                if self.record {
+                    NetworkFixtures::record(path)
+                } else {
+                    NetworkFixtures::replay(path)
+                }
+            })
+            .transpose()
+    }
+
+    /// Returns the variables set by the options
+    pub fn variables(&self) -> Result<HashMap<String, String>> {
+        self.variables
+            .iter()
+            .map(|variable| {
+                variable
+                    .split_once('=')
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .ok_or(Error::Custom(format!(
+                        "The variable '{}' must be set as NAME=VALUE",
+                        variable
+                    )))
+            })
+            .collect()
+    }
+
+    /// Returns the cache of the URL resources
+    pub fn http_cache(&self) -> Option<HttpCache> {
+        HttpCache::default_dir().map(|dir| {
+            let mut http_cache = HttpCache::new(dir);
+            http_cache.refresh = self.refresh;
+            http_cache
+        })
+    }
+}
+
+/// The output format of the timings
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum TimingsFormat {
+    Text,
+    Json,
 }
 
 pub trait CliCommand {
@@ -44,22 +163,149 @@ pub enum Command {
     /// Generate the effective Dofigen configuration once the extends are resolved
     Effective(Effective),
 
+    /// Display the effective Dofigen configuration with the extended file and the patch operation that produced each value
+    Explain(Explain),
+
+    /// Display the dependency graph of the stages, the images and the contexts
+    Graph(Graph),
+
+    /// Compare the effective configuration and the Dockerfile of two Dofigen files, or the generated Dockerfile with the existing one
+    Diff(Diff),
+
+    /// Check the Dofigen file against the best practice rules, as text, JSON or SARIF
+    Lint(Lint),
+
+    /// Export a CycloneDX software bill of materials of the base images and packages declared in the stages
+    Sbom(Sbom),
+
     /// Updates the lock file
     Update(Update),
 
+    /// Refactor the Dofigen file and its extended files
+    Refactor(Refactor),
+
+    /// Write the extended URL resources in a local directory loaded through the lock file
+    Vendor(Vendor),
+
+    /// Generate the Dockerfiles of the Compose services built from a Dofigen file
+    Compose(Compose),
+
+    /// Remove the generated files, the vendored resources and optionally the cache of the URL resources
+    Clean(Clean),
+
+    /// Check the Docker environment and the state of the Dofigen files, and display how to fix the problems
+    Doctor(Doctor),
+
+    /// Display the digest, platforms, configuration and tags of an image from its registry
+    Inspect(Inspect),
+
+    /// List the tags of an image from its registry, the highest versions first
+    Tags(Tags),
+
+    /// Check that the labels of an image generated with the --lock-labels option match the local lock file
+    VerifyImage(VerifyImage),
+
+    /// Check the releases of Dofigen and update its binary
+    #[clap(name = "self")]
+    SelfCommand(SelfCommand),
+
+    /// Read and write the user configuration, holding the default options of all the projects
+    Config(Config),
+
     /// Generate the JSON Schema for the Dofigen structure
     #[cfg(feature = "json_schema")]
     Schema(Schema),
+
+    /// Export the completions available at a position of a Dofigen file, as JSON
+    #[cfg(feature = "json_schema")]
+    Complete(Complete),
+
+    /// Run the dofigen-<name> plugin found in the PATH
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
 
 impl Command {
-    fn run(self) -> Result<()> {
+    /// Returns the options common to the subcommands, when the subcommand has them
+    fn options_mut(&mut self) -> Option<&mut GlobalOptions> {
+        match self {
+            Command::Generate(g) => Some(&mut g.options),
+            Command::Effective(e) => Some(&mut e.options),
+            Command::Explain(e) => Some(&mut e.options),
+            Command::Graph(g) => Some(&mut g.options),
+            Command::Diff(d) => Some(&mut d.options),
+            Command::Lint(l) => Some(&mut l.options),
+            Command::Sbom(s) => Some(&mut s.options),
+            Command::Update(u) => Some(&mut u.options),
+            Command::Refactor(r) => Some(r.options_mut()),
+            Command::Vendor(v) => Some(&mut v.options),
+            Command::Compose(c) => Some(&mut c.options),
+            Command::Doctor(d) => Some(&mut d.options),
+            Command::VerifyImage(v) => Some(&mut v.options),
+            _ => None,
+        }
+    }
+
+    /// Applies the user configuration under the options of the subcommand
+    fn apply_user_config(&mut self, config: &UserConfig) {
+        if let Some(color) = config.color {
+            colored::control::set_override(color);
+        }
+        match self {
+            Command::Inspect(Inspect {
+                registry_mirror, ..
+            })
+            | Command::Tags(Tags {
+                registry_mirror, ..
+            }) => {
+                if registry_mirror.is_none() {
+                    *registry_mirror = config.registry_mirror.clone();
+                }
+            }
+            Command::SelfCommand(SelfCommand {
+                command: SelfSubcommand::CheckUpdate(check),
+            }) => {
+                if !check.online {
+                    check.offline |= config.offline.unwrap_or_default();
+                }
+            }
+            _ => {
+                if let Some(options) = self.options_mut() {
+                    options.apply_user_config(config);
+                }
+            }
+        }
+    }
+
+    fn run(mut self) -> Result<()> {
+        // The user configuration is ignored by the config subcommand, to be able to fix it
+        if !matches!(self, Command::Config(_)) {
+            self.apply_user_config(&UserConfig::load()?);
+        }
         match self {
             Command::Generate(g) => g.run(),
             Command::Effective(e) => e.run(),
+            Command::Explain(e) => e.run(),
+            Command::Graph(g) => g.run(),
+            Command::Diff(d) => d.run(),
+            Command::Lint(l) => l.run(),
+            Command::Sbom(s) => s.run(),
             Command::Update(u) => u.run(),
+            Command::Refactor(r) => r.run(),
+            Command::Vendor(v) => v.run(),
+            Command::Compose(c) => c.run(),
+            Command::Clean(c) => c.run(),
+            Command::Doctor(d) => d.run(),
+            Command::Inspect(i) => i.run(),
+            Command::Tags(t) => t.run(),
+            Command::VerifyImage(v) => v.run(),
+            Command::SelfCommand(s) => s.run(),
+            Command::Config(c) => c.run(),
             #[cfg(feature = "json_schema")]
             Command::Schema(s) => s.run(),
+            #[cfg(feature = "json_schema")]
+            Command::Complete(c) => c.run(),
+            Command::External(args) => run_plugin(args),
         }
     }
 }
@@ -80,4 +326,27 @@ mod tests {
     fn verify_cmd() {
         <Cli as CommandFactory>::command().debug_assert();
     }
+
+    #[test]
+    fn online_overrides_user_config() {
+        let config = UserConfig {
+            offline: Some(true),
+            ..Default::default()
+        };
+        let mut options = Cli::parse_from(["dofigen", "gen"])
+            .command
+            .options_mut()
+            .cloned()
+            .unwrap();
+        options.apply_user_config(&config);
+        assert!(options.offline);
+
+        let mut options = Cli::parse_from(["dofigen", "gen", "--online"])
+            .command
+            .options_mut()
+            .cloned()
+            .unwrap();
+        options.apply_user_config(&config);
+        assert!(!options.offline);
+    }
 }
@@ -0,0 +1,376 @@
+use crate::{
+    Bind, Cache, Copy, CopyOptions, CopyResource, Dofigen, Error, FromContext, ImageName, Port,
+    Result, Run, Stage, User,
+};
+
+/// Builds a Dofigen struct without filling the nested structs by hand.
+///
+/// The stage methods come from the [`StageBuild`] trait and apply to the runtime stage.
+///
+/// ```
+/// use dofigen_lib::*;
+///
+/// let dofigen = Dofigen::builder()
+///     .builder(
+///         "build",
+///         Stage::builder()
+///             .from_image("rust:1")
+///             .workdir("/app")
+///             .bind(".", "/app")
+///             .cache("/app/target")
+///             .run(["cargo build --release", "mv target/release/app /tmp/"]),
+///     )
+///     .from_image("debian:bookworm-slim")
+///     .copy_from_builder("build", ["/tmp/app"], "/bin/")
+///     .entrypoint(["/bin/app"])
+///     .expose(8080)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(dofigen.builders["build"].run.run.len(), 2);
+/// assert_eq!(dofigen.stage.copy.len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct DofigenBuilder {
+    dofigen: Dofigen,
+    error: Option<Error>,
+}
+
+/// Builds a stage, a builder or the runtime one of a [`DofigenBuilder`]
+#[derive(Debug, Default)]
+pub struct StageBuilder {
+    stage: Stage,
+    error: Option<Error>,
+}
+
+/// The methods setting the fields of a stage.
+/// The invalid values, like the image names, are reported when building
+// The base methods are named like the fromImage, fromBuilder and fromContext fields
+#[allow(clippy::wrong_self_convention)]
+pub trait StageBuild: Sized {
+    fn stage_mut(&mut self) -> &mut Stage;
+
+    /// Keeps the first error, returned when building
+    fn fail(&mut self, error: Error);
+
+    /// Bases the stage on an image, like `ubuntu:24.04`
+    fn from_image(mut self, image: &str) -> Self {
+        match image.parse::<ImageName>() {
+            Ok(image) => self.stage_mut().from = image.into(),
+            Err(err) => self.fail(Error::Custom(format!(
+                "Invalid image name '{}': {}",
+                image, err
+            ))),
+        }
+        self
+    }
+
+    /// Bases the stage on a builder
+    fn from_builder(mut self, builder: &str) -> Self {
+        self.stage_mut().from = FromContext::FromBuilder(builder.into());
+        self
+    }
+
+    /// Bases the stage on an additional build context
+    fn from_context(mut self, context: &str) -> Self {
+        self.stage_mut().from = FromContext::FromContext(Some(context.into()));
+        self
+    }
+
+    /// Sets the user and the optional group, like `1000:1000`
+    fn user(mut self, user: &str) -> Self {
+        self.stage_mut().user = Some(match user.split_once(':') {
+            Some((user, group)) => User {
+                user: user.into(),
+                group: Some(group.into()),
+            },
+            None => User {
+                user: user.into(),
+                group: None,
+            },
+        });
+        self
+    }
+
+    fn workdir(mut self, workdir: &str) -> Self {
+        self.stage_mut().workdir = Some(workdir.into());
+        self
+    }
+
+    fn arg(mut self, name: &str, value: &str) -> Self {
+        self.stage_mut().arg.insert(name.into(), value.into());
+        self
+    }
+
+    fn env(mut self, name: &str, value: &str) -> Self {
+        self.stage_mut().env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Copies paths of the build context in the stage
+    fn copy<I, S>(mut self, paths: I, target: &str) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stage_mut()
+            .copy
+            .push(copy_resource(FromContext::default(), paths, target));
+        self
+    }
+
+    /// Copies paths of a builder in the stage
+    fn copy_from_builder<I, S>(mut self, builder: &str, paths: I, target: &str) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stage_mut().copy.push(copy_resource(
+            FromContext::FromBuilder(builder.into()),
+            paths,
+            target,
+        ));
+        self
+    }
+
+    /// Adds a copy resource, like a content or a Git repository
+    fn copy_resource(mut self, resource: CopyResource) -> Self {
+        self.stage_mut().copy.push(resource);
+        self
+    }
+
+    /// Adds commands to the run of the stage
+    fn run<I, S>(mut self, commands: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stage_mut()
+            .run
+            .run
+            .extend(commands.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds commands to the run of the stage as root user
+    fn root_run<I, S>(mut self, commands: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stage_mut()
+            .root
+            .get_or_insert_with(Run::default)
+            .run
+            .extend(commands.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds a cache mount to the run of the stage
+    fn cache(mut self, target: &str) -> Self {
+        self.stage_mut().run.cache.push(Cache {
+            target: target.into(),
+            ..Default::default()
+        });
+        self
+    }
+
+    /// Adds a bind mount of the build context to the run of the stage
+    fn bind(mut self, source: &str, target: &str) -> Self {
+        self.stage_mut().run.bind.push(Bind {
+            source: Some(source.into()),
+            target: target.into(),
+            ..Default::default()
+        });
+        self
+    }
+}
+
+fn copy_resource<I, S>(from: FromContext, paths: I, target: &str) -> CopyResource
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    CopyResource::Copy(Copy {
+        from,
+        paths: paths.into_iter().map(Into::into).collect(),
+        options: CopyOptions {
+            target: Some(target.into()),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+}
+
+impl StageBuild for StageBuilder {
+    fn stage_mut(&mut self) -> &mut Stage {
+        &mut self.stage
+    }
+
+    fn fail(&mut self, error: Error) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl StageBuild for DofigenBuilder {
+    fn stage_mut(&mut self) -> &mut Stage {
+        &mut self.dofigen.stage
+    }
+
+    fn fail(&mut self, error: Error) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl StageBuilder {
+    pub fn build(self) -> Result<Stage> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.stage),
+        }
+    }
+}
+
+impl DofigenBuilder {
+    /// Adds a builder stage
+    pub fn builder(mut self, name: &str, builder: StageBuilder) -> Self {
+        match builder.build() {
+            Ok(stage) => {
+                self.dofigen.builders.insert(name.into(), stage);
+            }
+            Err(err) => self.fail(err),
+        }
+        self
+    }
+
+    /// Adds paths to the build context
+    pub fn context<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.dofigen
+            .context
+            .extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds paths ignored from the build context
+    pub fn ignore<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.dofigen
+            .ignore
+            .extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn entrypoint<I, S>(mut self, entrypoint: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.dofigen.entrypoint = entrypoint.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn cmd<I, S>(mut self, cmd: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.dofigen.cmd = cmd.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Exposes a TCP port
+    pub fn expose(mut self, port: u16) -> Self {
+        self.dofigen.expose.push(Port {
+            port,
+            ..Default::default()
+        });
+        self
+    }
+
+    pub fn volume(mut self, volume: &str) -> Self {
+        self.dofigen.volume.push(volume.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Dofigen> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.dofigen),
+        }
+    }
+}
+
+impl Dofigen {
+    pub fn builder() -> DofigenBuilder {
+        DofigenBuilder::default()
+    }
+}
+
+impl Stage {
+    pub fn builder() -> StageBuilder {
+        StageBuilder::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn build_runtime_stage() {
+        let dofigen = Dofigen::builder()
+            .from_image("ubuntu:24.04")
+            .user("1000:1000")
+            .copy(["src"], "/app/")
+            .build()
+            .unwrap();
+
+        assert_eq_sorted!(
+            dofigen,
+            Dofigen {
+                stage: Stage {
+                    from: FromContext::FromImage(ImageName {
+                        path: "ubuntu".into(),
+                        version: Some(crate::ImageVersion::Tag("24.04".into())),
+                        ..Default::default()
+                    }),
+                    user: Some(User {
+                        user: "1000".into(),
+                        group: Some("1000".into()),
+                    }),
+                    copy: vec![CopyResource::Copy(Copy {
+                        paths: vec!["src".into()],
+                        options: CopyOptions {
+                            target: Some("/app/".into()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_builder_image() {
+        let error = Dofigen::builder()
+            .builder("build", Stage::builder().from_image("Invalid Image"))
+            .build()
+            .unwrap_err();
+
+        assert_eq_sorted!(
+            error.to_string(),
+            "Invalid image name 'Invalid Image': Not matching image name pattern"
+        );
+    }
+}
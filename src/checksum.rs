@@ -0,0 +1,212 @@
+use crate::{CopyResource, Dofigen, Error, Resource, Result, Stage};
+use sha2::{Digest, Sha256, Sha512};
+use std::{fs, path::Path};
+
+/// The algorithms of the ADD checksums, used as their prefix like `sha256:`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    pub const ALL: [ChecksumAlgorithm; 3] = [
+        ChecksumAlgorithm::Sha256,
+        ChecksumAlgorithm::Sha512,
+        ChecksumAlgorithm::Blake3,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// The number of hexadecimal characters of the hashes
+    fn hex_length(&self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 | ChecksumAlgorithm::Blake3 => 64,
+            ChecksumAlgorithm::Sha512 => 128,
+        }
+    }
+
+    /// Returns the checksum of the content, prefixed by the algorithm
+    pub fn checksum(&self, content: &[u8]) -> String {
+        let hash = match self {
+            ChecksumAlgorithm::Sha256 => hex(&Sha256::digest(content)),
+            ChecksumAlgorithm::Sha512 => hex(&Sha512::digest(content)),
+            ChecksumAlgorithm::Blake3 => blake3::hash(content).to_hex().to_string(),
+        };
+        format!("{}:{}", self.name(), hash)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the algorithm of a checksum, checking its hash
+pub fn checksum_algorithm(checksum: &str) -> Result<ChecksumAlgorithm> {
+    let (prefix, hash) = checksum.split_once(':').ok_or_else(|| {
+        Error::Custom(format!(
+            "The checksum '{}' must be prefixed by its algorithm, like sha256:",
+            checksum
+        ))
+    })?;
+    let algorithm = ChecksumAlgorithm::ALL
+        .into_iter()
+        .find(|algorithm| algorithm.name() == prefix)
+        .ok_or_else(|| {
+            Error::Custom(format!(
+                "The checksum algorithm '{}' isn't supported, the supported ones are: {}",
+                prefix,
+                ChecksumAlgorithm::ALL
+                    .iter()
+                    .map(ChecksumAlgorithm::name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+    if hash.len() != algorithm.hex_length() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::Custom(format!(
+            "The {} checksum '{}' must have {} hexadecimal characters",
+            prefix,
+            checksum,
+            algorithm.hex_length()
+        )));
+    }
+    Ok(algorithm)
+}
+
+impl Dofigen {
+    /// Checks the checksums of the ADD instructions whose files are local, relatively to the given directory
+    pub fn verify_checksums<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let mut errors = vec![];
+        let mut builders = self.builders.iter().collect::<Vec<_>>();
+        builders.sort_by_key(|(name, _)| *name);
+        for (name, builder) in builders {
+            verify_stage(
+                builder,
+                &format!("builders.{}.", name),
+                dir.as_ref(),
+                &mut errors,
+            );
+        }
+        verify_stage(&self.stage, "", dir.as_ref(), &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Custom(errors.join("\n")))
+        }
+    }
+}
+
+fn verify_stage(stage: &Stage, prefix: &str, dir: &Path, errors: &mut Vec<String>) {
+    for (position, copy) in stage.copy.iter().enumerate() {
+        let CopyResource::Add(add) = copy else {
+            continue;
+        };
+        let Some(checksum) = &add.checksum else {
+            continue;
+        };
+        for file in &add.files {
+            let Resource::File(file) = file else {
+                continue;
+            };
+            let path = dir.join(file);
+            let result = checksum_algorithm(checksum).and_then(|algorithm| {
+                fs::read(&path)
+                    .map(|content| algorithm.checksum(&content))
+                    .map_err(|err| {
+                        Error::Custom(format!("Could not read file {:?}: {}", path, err))
+                    })
+            });
+            match result {
+                Ok(actual) if actual == *checksum => {}
+                Ok(actual) => errors.push(format!(
+                    "{}copy.{}.checksum: The checksum of {:?} is {} instead of {}",
+                    prefix, position, file, actual, checksum
+                )),
+                Err(err) => errors.push(format!("{}copy.{}.checksum: {}", prefix, position, err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Add;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn checksums() {
+        assert_eq_sorted!(
+            ChecksumAlgorithm::Sha256.checksum(b"dofigen"),
+            format!("sha256:{}", sha256::digest("dofigen"))
+        );
+        assert_eq_sorted!(
+            ChecksumAlgorithm::Blake3.checksum(b""),
+            "blake3:af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq_sorted!(ChecksumAlgorithm::Sha512.checksum(b"").len(), 7 + 128);
+    }
+
+    #[test]
+    fn algorithms() {
+        assert_eq_sorted!(
+            checksum_algorithm(&format!("sha512:{}", "a".repeat(128))).unwrap(),
+            ChecksumAlgorithm::Sha512
+        );
+        assert_eq_sorted!(
+            checksum_algorithm("md5:abc").unwrap_err().to_string(),
+            "The checksum algorithm 'md5' isn't supported, the supported ones are: sha256, sha512, blake3"
+        );
+        assert_eq_sorted!(
+            checksum_algorithm("blake3:abc").unwrap_err().to_string(),
+            "The blake3 checksum 'blake3:abc' must have 64 hexadecimal characters"
+        );
+        assert!(checksum_algorithm("abc").is_err());
+    }
+
+    #[test]
+    fn verify_local_files() {
+        let dir = std::env::temp_dir().join("dofigen-verify-checksums");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("archive.tar"), "content").unwrap();
+        let dofigen = |checksum: String| Dofigen {
+            builders: [(
+                "builder".to_string(),
+                Stage {
+                    copy: vec![CopyResource::Add(Add {
+                        files: vec![Resource::File("archive.tar".into())],
+                        checksum: Some(checksum),
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+
+        assert!(dofigen(ChecksumAlgorithm::Blake3.checksum(b"content"))
+            .verify_checksums(&dir)
+            .is_ok());
+        assert_eq_sorted!(
+            dofigen(ChecksumAlgorithm::Sha256.checksum(b"other"))
+                .verify_checksums(&dir)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "builders.builder.copy.0.checksum: The checksum of \"archive.tar\" is {} instead of {}",
+                ChecksumAlgorithm::Sha256.checksum(b"content"),
+                ChecksumAlgorithm::Sha256.checksum(b"other")
+            )
+        );
+    }
+}
@@ -0,0 +1,440 @@
+use crate::{generate_json_schema, Error, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+const BUILDER_FIELDS: [&str; 2] = ["fromBuilder", "builder"];
+
+/// The completions available at a position of a Dofigen file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionModel {
+    /// The path of the completed element, list items are identified by their position
+    pub path: Vec<String>,
+    /// The kind of element to complete
+    pub kind: CompletionKind,
+    /// The text already written for the completed element
+    pub prefix: String,
+    /// The available completions
+    pub items: Vec<CompletionItem>,
+}
+
+/// The kind of element to complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompletionKind {
+    Key,
+    Value,
+}
+
+/// A completion proposal
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionItemKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// The kind of completion proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompletionItemKind {
+    Field,
+    Value,
+    Builder,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+    Key(String),
+    Item(usize),
+}
+
+impl PathSegment {
+    fn name(&self) -> String {
+        match self {
+            PathSegment::Key(key) => key.clone(),
+            PathSegment::Item(index) => index.to_string(),
+        }
+    }
+}
+
+/// The path of the YAML block elements, with the indentation of each element
+#[derive(Debug, Default)]
+struct BlockPath(Vec<(usize, PathSegment)>);
+
+impl BlockPath {
+    fn push_item(&mut self, indent: usize) {
+        self.0.retain(|(i, _)| *i <= indent);
+        let index = match self.0.last() {
+            Some((i, PathSegment::Item(index))) if *i == indent => {
+                let index = index + 1;
+                self.0.pop();
+                index
+            }
+            _ => 0,
+        };
+        self.0.push((indent, PathSegment::Item(index)));
+    }
+
+    fn pop_siblings(&mut self, indent: usize) {
+        // A list can be at the same indentation than its key
+        while let Some((i, segment)) = self.0.last() {
+            if *i > indent || (*i == indent && matches!(segment, PathSegment::Key(_))) {
+                self.0.pop();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn push_key(&mut self, indent: usize, key: String) {
+        self.pop_siblings(indent);
+        self.0.push((indent, PathSegment::Key(key)));
+    }
+
+    fn segments(&self) -> Vec<PathSegment> {
+        self.0.iter().map(|(_, segment)| segment.clone()).collect()
+    }
+}
+
+/// A line of a YAML block
+struct BlockLine<'a> {
+    /// The indentation of the list items of the line
+    items: Vec<usize>,
+    /// The indentation of the content after the list items
+    indent: usize,
+    /// The content after the list items
+    content: &'a str,
+}
+
+impl<'a> BlockLine<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let mut indent = line.len() - line.trim_start().len();
+        let mut content = line.trim_start();
+        if content.starts_with('#') || content.starts_with("---") {
+            return None;
+        }
+        let mut items = vec![];
+        while content == "-" || content.starts_with("- ") {
+            items.push(indent);
+            let rest = content[1..].trim_start();
+            indent += content.len() - rest.len();
+            content = rest;
+        }
+        Some(Self {
+            items,
+            indent,
+            content,
+        })
+    }
+
+    /// Returns the key of the line and the text after it
+    fn key(&self) -> Option<(String, &'a str)> {
+        let (key, value) = if let Some(key) = self.content.strip_suffix(':') {
+            (key, "")
+        } else {
+            self.content.split_once(": ")?
+        };
+        let key = key.trim().trim_matches(|c| c == '"' || c == '\'');
+        if key.is_empty() || key.starts_with(['{', '[']) {
+            return None;
+        }
+        Some((key.to_string(), value))
+    }
+
+    fn apply(&self, path: &mut BlockPath) {
+        self.items.iter().for_each(|indent| path.push_item(*indent));
+        if let Some((key, _)) = self.key() {
+            path.push_key(self.indent, key);
+        }
+    }
+}
+
+/// Returns the completions available at the given position of a Dofigen file content.
+/// The line and the column start at 1.
+/// Only the YAML block style is supported to find the completed element.
+pub fn completion_model(content: &str, line: usize, column: usize) -> Result<CompletionModel> {
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line > lines.len() + 1 {
+        return Err(Error::Custom(format!(
+            "The line {} is out of the file",
+            line
+        )));
+    }
+    let current = lines.get(line - 1).copied().unwrap_or_default();
+    let prefix: String = current.chars().take(column.saturating_sub(1)).collect();
+
+    let mut path = BlockPath::default();
+    lines[..line - 1]
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| BlockLine::parse(line))
+        .for_each(|line| line.apply(&mut path));
+
+    let current = BlockLine::parse(&prefix).unwrap_or(BlockLine {
+        items: vec![],
+        indent: prefix.len(),
+        content: "",
+    });
+    current
+        .items
+        .iter()
+        .for_each(|indent| path.push_item(*indent));
+    let (kind, key, written) = match current.key() {
+        Some((key, value)) => (CompletionKind::Value, Some(key), value.trim_start()),
+        None => (CompletionKind::Key, None, current.content),
+    };
+    path.pop_siblings(current.indent);
+    let mut segments = path.segments();
+    segments.extend(key.map(PathSegment::Key));
+
+    let schema: Value = serde_json::from_str(&generate_json_schema()).map_err(Error::display)?;
+    let nodes = segments
+        .iter()
+        .fold(flatten(&schema, &schema), |nodes, segment| {
+            nodes
+                .iter()
+                .flat_map(|node| child(node, segment))
+                .flat_map(|node| flatten(&schema, node))
+                .collect()
+        });
+
+    let mut items = BTreeMap::new();
+    match kind {
+        CompletionKind::Key => nodes
+            .iter()
+            .filter_map(|node| node.get("properties").and_then(Value::as_object))
+            .flatten()
+            .for_each(|(label, property)| {
+                items
+                    .entry(label.clone())
+                    .or_insert_with(|| CompletionItem {
+                        label: label.clone(),
+                        kind: CompletionItemKind::Field,
+                        description: description(&schema, property),
+                    });
+            }),
+        CompletionKind::Value => {
+            nodes
+                .iter()
+                .flat_map(|node| values(node))
+                .for_each(|label| {
+                    items.entry(label.clone()).or_insert(CompletionItem {
+                        label,
+                        kind: CompletionItemKind::Value,
+                        description: None,
+                    });
+                });
+            if matches!(segments.last(), Some(PathSegment::Key(key)) if BUILDER_FIELDS.contains(&key.as_str()))
+            {
+                builder_names(&lines).into_iter().for_each(|label| {
+                    items.entry(label.clone()).or_insert(CompletionItem {
+                        label,
+                        kind: CompletionItemKind::Builder,
+                        description: None,
+                    });
+                });
+            }
+        }
+    }
+
+    Ok(CompletionModel {
+        path: segments.iter().map(PathSegment::name).collect(),
+        kind,
+        prefix: written.to_string(),
+        items: items.into_values().collect(),
+    })
+}
+
+/// Returns the schema and the schemas it references or combines
+fn flatten<'a>(root: &'a Value, node: &'a Value) -> Vec<&'a Value> {
+    let mut nodes = vec![node];
+    if let Some(reference) = node.get("$ref").and_then(Value::as_str) {
+        if let Some(target) = reference
+            .strip_prefix("#/definitions/")
+            .and_then(|name| root.get("definitions")?.get(name))
+        {
+            nodes.extend(flatten(root, target));
+        }
+    }
+    ["anyOf", "oneOf", "allOf"]
+        .iter()
+        .filter_map(|key| node.get(*key).and_then(Value::as_array))
+        .flatten()
+        .for_each(|subschema| nodes.extend(flatten(root, subschema)));
+    nodes
+}
+
+fn child<'a>(node: &'a Value, segment: &PathSegment) -> Vec<&'a Value> {
+    match segment {
+        PathSegment::Key(key) => node
+            .get("properties")
+            .and_then(|properties| properties.get(key))
+            .into_iter()
+            .chain(
+                node.get("patternProperties")
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                    .filter(|(pattern, _)| {
+                        regex::Regex::new(pattern).is_ok_and(|regex| regex.is_match(key))
+                    })
+                    .map(|(_, property)| property),
+            )
+            .collect(),
+        PathSegment::Item(_) => node.get("items").into_iter().collect(),
+    }
+}
+
+fn description(root: &Value, node: &Value) -> Option<String> {
+    flatten(root, node)
+        .iter()
+        .find_map(|node| node.get("description").and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+fn values(node: &Value) -> Vec<String> {
+    let mut values: Vec<String> = node
+        .get("enum")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.as_str().map(str::to_string))
+        .collect();
+    if node.get("type").and_then(Value::as_str) == Some("boolean") {
+        values.extend(["true".to_string(), "false".to_string()]);
+    }
+    values
+}
+
+/// Returns the names of the builders declared in the file
+fn builder_names(lines: &[&str]) -> Vec<String> {
+    let builders = vec![PathSegment::Key("builders".into())];
+    let mut path = BlockPath::default();
+    let mut names = vec![];
+    lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| BlockLine::parse(line))
+        .for_each(|line| {
+            line.items.iter().for_each(|indent| path.push_item(*indent));
+            if let Some((key, _)) = line.key() {
+                path.pop_siblings(line.indent);
+                if path.segments() == builders {
+                    names.push(key.clone());
+                }
+                path.push_key(line.indent, key);
+            }
+        });
+    names
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    const CONTENT: &str = r#"builders:
+  builder:
+    fromImage: rust
+    cache:
+      - target: /app/target
+        sharing:
+  other:
+    run:
+    - echo hello
+
+fromBuilder:
+copy:
+  - fromBuilder: builder
+
+"#;
+
+    fn labels(model: &CompletionModel) -> Vec<&str> {
+        model.items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn root_fields() {
+        let model = completion_model(CONTENT, 11, 1).unwrap();
+
+        assert_eq_sorted!(model.path, Vec::<String>::new());
+        assert_eq_sorted!(model.kind, CompletionKind::Key);
+        let labels = labels(&model);
+        assert!(labels.contains(&"builders"));
+        assert!(labels.contains(&"fromImage"));
+        assert!(labels.contains(&"extend"));
+    }
+
+    #[test]
+    fn builder_fields() {
+        let model = completion_model("builders:\n  other:\n    ", 3, 5).unwrap();
+
+        assert_eq_sorted!(model.path, vec!["builders", "other"]);
+        assert_eq_sorted!(model.kind, CompletionKind::Key);
+        let labels = labels(&model);
+        assert!(labels.contains(&"run"));
+        assert!(labels.contains(&"outputs"));
+        assert!(!labels.contains(&"extend"));
+    }
+
+    #[test]
+    fn enum_values() {
+        let model = completion_model(CONTENT, 6, 18).unwrap();
+
+        assert_eq_sorted!(
+            model.path,
+            vec!["builders", "builder", "cache", "0", "sharing"]
+        );
+        assert_eq_sorted!(model.kind, CompletionKind::Value);
+        assert_eq_sorted!(labels(&model), vec!["locked", "private", "shared"]);
+    }
+
+    #[test]
+    fn builder_names() {
+        let model = completion_model(CONTENT, 11, 14).unwrap();
+
+        assert_eq_sorted!(model.path, vec!["fromBuilder"]);
+        assert_eq_sorted!(
+            model.items,
+            vec![
+                CompletionItem {
+                    label: "builder".into(),
+                    kind: CompletionItemKind::Builder,
+                    description: None,
+                },
+                CompletionItem {
+                    label: "other".into(),
+                    kind: CompletionItemKind::Builder,
+                    description: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn list_item_fields() {
+        let model = completion_model(&format!("{}    ", CONTENT), 15, 5).unwrap();
+
+        assert_eq_sorted!(model.path, vec!["copy", "0"]);
+        let labels = labels(&model);
+        assert!(labels.contains(&"paths"));
+        assert!(labels.contains(&"target"));
+    }
+
+    #[test]
+    fn written_prefix() {
+        let model = completion_model("fromImage: alpine\nwork", 2, 5).unwrap();
+
+        assert_eq_sorted!(model.kind, CompletionKind::Key);
+        assert_eq_sorted!(model.prefix, "work");
+    }
+
+    #[test]
+    fn line_out_of_file() {
+        assert!(completion_model("fromImage: alpine", 3, 1).is_err());
+    }
+}
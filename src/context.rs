@@ -1,35 +1,84 @@
-use colored::{Color, Colorize};
-use serde::Deserialize;
-
 use crate::{
-    lock::{DockerTag, ResourceVersion, DEFAULT_NAMESPACE, DOCKER_HUB_HOST},
-    Dofigen, DofigenPatch, Error, Extend, ImageName, ImageVersion, Resource, Result,
+    deprecation::{find_deprecations, DEPRECATIONS},
+    events::Listeners,
+    extend,
+    lock::{DockerTag, ResourceVersion},
+    presets::{is_preset, preset_content},
+    provenance::{FieldOrigins, PatchOperation},
+    validation, variables, ChecksumAlgorithm, ContextListener, Dofigen, DofigenPatch, Error,
+    Extend, GenerationContext, HttpCache, ImageName, LintLevel, LintMessage, LintSession,
+    NetworkFixtures, RegistryClient, ResolvedImage, Resource, Result, TimingPhase, Timings,
+    UpdateReport,
 };
 use std::{
     collections::{HashMap, HashSet},
     fs,
     io::Read,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
     str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Instant,
 };
+use url::Url;
 
 const MAX_LOAD_STACK_SIZE: usize = 10;
 
 /// The representation of the Dofigen execution context
+#[derive(Debug, Clone)]
 pub struct DofigenContext {
     pub offline: bool,
     pub update_file_resources: bool,
     pub update_url_resources: bool,
     pub update_docker_tags: bool,
+    /// Download the URL files of the ADD instructions without checksum to record their checksum
+    pub update_add_checksums: bool,
+    /// Kept for compatibility, the updates aren't printed by the context anymore
+    #[deprecated(note = "the updates are reported with `DofigenContext::update_report`")]
     pub display_updates: bool,
+    pub timings: Timings,
+    /// The fixtures serving or recording the network lookups
+    pub fixtures: Option<NetworkFixtures>,
+    /// The on-disk cache of the URL resources
+    pub http_cache: Option<HttpCache>,
+    /// The variables overriding the ones of the Dofigen files
+    pub variables: HashMap<String, String>,
+    /// The activated profiles, their patches being applied in this order
+    pub profiles: Vec<String>,
+    /// The levels of the lint rules overriding the ones of the Dofigen files, like the ones of the lint configuration file
+    pub lint_levels: HashMap<String, LintLevel>,
+    /// The profiles activated before the other ones when the Dofigen files declare them
+    pub default_profiles: Vec<String>,
+    /// The registry serving the Docker Hub images instead of Docker Hub
+    pub registry_mirror: Option<String>,
+    pub(crate) declared_profiles: HashSet<String>,
 
     // Load resources
     load_resource_stack: Vec<Resource>,
     resources: HashMap<Resource, ResourceVersion>,
     used_resources: HashSet<Resource>,
+    pub(crate) vendored_resources: HashMap<Url, PathBuf>,
 
     // Images tags
     images: HashMap<ImageName, DockerTag>,
     used_images: HashSet<ImageName>,
+
+    // Checksums of the ADD URL files
+    pub(crate) add_checksums: HashMap<Url, String>,
+    used_add_checksums: HashSet<Url>,
+
+    // Parsing messages
+    messages: Vec<LintMessage>,
+
+    // Origin of the merged fields
+    pub(crate) origins: FieldOrigins,
+
+    // Observers of the progress
+    listeners: Listeners,
 }
 
 impl DofigenContext {
@@ -75,37 +124,22 @@ impl DofigenContext {
 
     /// Get the content of a resource from cache if possible
     pub(crate) fn get_resource_content(&mut self, resource: Resource) -> Result<String> {
-        let load = match resource {
+        let load = match &resource {
             Resource::File(_) => self.update_file_resources,
+            // The presets are embedded in Dofigen
+            Resource::Url(url) if is_preset(url) => true,
             Resource::Url(_) => self.update_url_resources,
         } || !self.resources.contains_key(&resource);
 
         let version = if load {
+            let start = Instant::now();
             let version = self.load_resource_version(&resource)?;
-            let previous = self.resources.insert(resource.clone(), version.clone());
-
-            // display update
-            if self.display_updates {
-                let resource_name = resource.to_string();
-                if let Some(previous) = previous {
-                    if previous.hash != version.hash {
-                        println!(
-                            "{:>20} {} {} -> {}",
-                            "Update resource".color(Color::Green).bold(),
-                            resource_name,
-                            previous.hash,
-                            version.hash
-                        );
-                    }
-                } else {
-                    println!(
-                        "{:>20} {} {}",
-                        "Add resource".color(Color::Blue).bold(),
-                        resource_name,
-                        version.hash
-                    );
-                }
+            if matches!(&resource, Resource::Url(url) if !is_preset(url)) {
+                self.timings.add(TimingPhase::Network, start.elapsed());
             }
+            self.resources.insert(resource.clone(), version.clone());
+            self.listeners
+                .each(|listener| listener.on_resource_loaded(&resource));
 
             version
         } else {
@@ -118,21 +152,38 @@ impl DofigenContext {
     }
 
     /// Load the content of a resource
-    fn load_resource_version(&self, resource: &Resource) -> Result<ResourceVersion> {
+    fn load_resource_version(&mut self, resource: &Resource) -> Result<ResourceVersion> {
         let content = match resource.clone() {
             Resource::File(path) => fs::read_to_string(path.clone())
                 .map_err(|err| Error::Custom(format!("Could not read file {:?}: {}", path, err)))?,
+            Resource::Url(url) if is_preset(&url) => preset_content(&url)?,
+            Resource::Url(url) if self.vendored_resources.contains_key(&url) => {
+                let path = &self.vendored_resources[&url];
+                fs::read_to_string(path).map_err(|err| {
+                    Error::Custom(format!("Could not read vendored file {:?}: {}", path, err))
+                })?
+            }
             Resource::Url(url) => {
                 if self.offline {
                     return Err(Error::Custom(
                         "Offline mode can't load URL resources".to_string(),
                     ));
                 }
-                reqwest::blocking::get(url.as_ref())
-                    .map_err(Error::from)?
-                    .error_for_status()?
-                    .text()
-                    .map_err(Error::from)?
+                let http_cache = self.http_cache.as_ref();
+                let load = || -> Result<String> {
+                    if let Some(http_cache) = http_cache {
+                        return http_cache.get(url.as_str());
+                    }
+                    reqwest::blocking::get(url.as_ref())
+                        .map_err(Error::from)?
+                        .error_for_status()?
+                        .text()
+                        .map_err(Error::from)
+                };
+                match self.fixtures.as_mut() {
+                    Some(fixtures) => fixtures.resource(url.as_str(), load)?,
+                    None => load()?,
+                }
             }
         };
         let version = ResourceVersion {
@@ -143,19 +194,46 @@ impl DofigenContext {
     }
 
     fn clean_unused_resources(&mut self) {
-        for resource in self.resources.clone().keys() {
-            if !self.used_resources.contains(resource) {
-                let version = self.resources.remove(resource).unwrap();
-                if self.display_updates {
-                    println!(
-                        "{:>20} {} {}",
-                        "Remove image".color(Color::Red).bold(),
-                        resource.to_string(),
-                        version.hash
-                    );
-                }
+        self.resources
+            .retain(|resource, _| self.used_resources.contains(resource));
+    }
+
+    //////////  Vendoring  //////////
+
+    /// Writes the content of the used URL resources in the given directory.
+    /// The vendored files are then loaded instead of the URLs
+    pub fn vendor_resources<P: AsRef<Path>>(&mut self, dir: P) -> Result<Vec<(Url, PathBuf)>> {
+        let mut vendored = vec![];
+        for (resource, version) in self.used_resource_contents() {
+            let Resource::Url(url) = resource else {
+                continue;
+            };
+            if is_preset(&url) {
+                continue;
+            }
+            let path = dir.as_ref().join(vendor_path(&url));
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|err| {
+                    Error::Custom(format!("Could not create directory {:?}: {}", parent, err))
+                })?;
             }
+            fs::write(&path, &version.content).map_err(|err| {
+                Error::Custom(format!("Could not write vendored file {:?}: {}", path, err))
+            })?;
+            self.file_written(&path);
+            self.vendored_resources.insert(url.clone(), path.clone());
+            vendored.push((url, path));
         }
+        vendored.sort();
+        Ok(vendored)
+    }
+
+    pub(crate) fn used_vendored_resources(&self) -> HashMap<Url, PathBuf> {
+        self.vendored_resources
+            .iter()
+            .filter(|(url, _)| self.used_resources.contains(&Resource::Url((*url).clone())))
+            .map(|(url, path)| (url.clone(), path.clone()))
+            .collect()
     }
 
     //////////  Image management  //////////
@@ -164,31 +242,10 @@ impl DofigenContext {
         let image = image.fill();
 
         let tag = if self.update_docker_tags || !self.images.contains_key(&image) {
+            let start = Instant::now();
             let tag = self.load_image_tag(&image)?;
-            let previous = self.images.insert(image.clone(), tag.clone());
-
-            // display update
-            if self.display_updates {
-                let image_name = image.to_string();
-                if let Some(previous) = previous {
-                    if previous.digest != tag.digest {
-                        println!(
-                            "{:>20} {} {} -> {}",
-                            "Update image".color(Color::Green).bold(),
-                            image_name,
-                            previous.digest,
-                            tag.digest
-                        );
-                    }
-                } else {
-                    println!(
-                        "{:>20} {} {}",
-                        "Add image".color(Color::Blue).bold(),
-                        image_name,
-                        tag.digest
-                    );
-                }
-            }
+            self.timings.add(TimingPhase::Network, start.elapsed());
+            self.images.insert(image.clone(), tag.clone());
 
             tag
         } else {
@@ -196,6 +253,8 @@ impl DofigenContext {
         };
 
         self.used_images.insert(image.clone());
+        self.listeners
+            .each(|listener| listener.on_image_resolved(&image, &tag));
         Ok(tag)
     }
 
@@ -206,94 +265,190 @@ impl DofigenContext {
             ));
         }
 
-        let tag = match image
-            .version
-            .clone()
-            .ok_or(Error::Custom("No version found for image".into()))?
-        {
-            ImageVersion::Tag(tag) => tag,
-            _ => {
-                return Err(Error::Custom("Image version is not a tag".to_string()));
-            }
+        let load = || {
+            RegistryClient::new()
+                .with_mirror(self.registry_mirror.clone())
+                .get_tag(image)
+        };
+        match self.fixtures.as_mut() {
+            Some(fixtures) => fixtures.image_tag(&image.to_string(), load),
+            None => load(),
+        }
+    }
+
+    /// Resolves the digest, the platforms and the labels of an image from its registry
+    pub fn resolve_image(&mut self, image: &ImageName) -> Result<ResolvedImage> {
+        if self.offline {
+            return Err(Error::Custom(
+                "Offline mode can't resolve image".to_string(),
+            ));
+        }
+
+        let start = Instant::now();
+        let load = || {
+            RegistryClient::new()
+                .with_mirror(self.registry_mirror.clone())
+                .resolve(image)
         };
+        let resolved = match self.fixtures.as_mut() {
+            Some(fixtures) => fixtures.resolved_image(&image.fill().to_string(), load)?,
+            None => load()?,
+        };
+        self.timings.add(TimingPhase::Network, start.elapsed());
+        Ok(resolved)
+    }
 
-        let host = image
-            .host
-            .clone()
-            .ok_or(Error::Custom("No host found for image".into()))?;
+    fn clean_unused_images(&mut self) {
+        self.images
+            .retain(|image, _| self.used_images.contains(image));
+    }
 
-        let client = reqwest::blocking::Client::new();
+    //////////  ADD checksums  //////////
 
-        let docker_tag = if self.load_from_api(host.as_str()) {
-            let mut repo = image.path.clone();
-            let namespace = if repo.contains("/") {
-                let mut parts = image.path.split("/");
-                let ret = parts.next().unwrap();
-                repo = parts.collect::<Vec<&str>>().join("/");
-                ret
-            } else {
-                DEFAULT_NAMESPACE
-            };
-            let request_url = format!(
-                "https://{host}/v2/namespaces/{namespace}/repositories/{repo}/tags/{tag}",
-                namespace = namespace,
-                repo = repo,
-                tag = tag
-            );
-            let response = client.get(&request_url).send().map_err(Error::from)?;
-
-            let response: DockerHubTagResponse = response.json().map_err(Error::from)?;
-            DockerTag {
-                digest: response
-                    .digest
-                    .or(response.images.get(0).map(|img| img.digest.clone()))
-                    .ok_or(Error::Custom("No digest found in response".to_string()))?,
-            }
-        } else {
-            let request_url = format!(
-                "https://{host}/v2/{path}/manifests/{tag}",
-                path = image.path,
-                tag = tag
-            );
-            let response = client.head(&request_url).send().map_err(Error::from)?;
-
-            let digest = response
-                .headers()
-                .get("Docker-Content-Digest")
-                .ok_or(Error::Custom("No digest found in response".to_string()))?;
-            let digest = digest
-                .to_str()
-                .map_err(|err| Error::display(err))?
-                .to_string();
-
-            DockerTag { digest }
+    /// Returns the recorded checksum of an ADD URL file, downloading it when updating the checksums
+    pub(crate) fn get_add_checksum(&mut self, url: &Url) -> Result<Option<String>> {
+        if self.update_add_checksums {
+            let start = Instant::now();
+            let checksum = self.load_add_checksum(url)?;
+            self.timings.add(TimingPhase::Network, start.elapsed());
+            self.add_checksums.insert(url.clone(), checksum);
+        }
+
+        let checksum = self.add_checksums.get(url).cloned();
+        if checksum.is_some() {
+            self.used_add_checksums.insert(url.clone());
+        }
+        Ok(checksum)
+    }
+
+    fn load_add_checksum(&mut self, url: &Url) -> Result<String> {
+        if self.offline {
+            return Err(Error::Custom(
+                "Offline mode can't download ADD files".to_string(),
+            ));
+        }
+
+        let load = || -> Result<String> {
+            let content = reqwest::blocking::get(url.as_ref())
+                .map_err(Error::from)?
+                .error_for_status()?
+                .bytes()
+                .map_err(Error::from)?;
+            Ok(ChecksumAlgorithm::Sha256.checksum(&content))
         };
+        match self.fixtures.as_mut() {
+            Some(fixtures) => fixtures.checksum(url.as_str(), load),
+            None => load(),
+        }
+    }
 
-        Ok(docker_tag)
+    pub(crate) fn used_add_checksums(&self) -> HashMap<Url, String> {
+        self.used_add_checksums
+            .iter()
+            .map(|url| (url.clone(), self.add_checksums[url].clone()))
+            .collect()
     }
 
-    fn load_from_api(&self, host: &str) -> bool {
-        host == DOCKER_HUB_HOST || host == "docker.io"
+    fn clean_unused_add_checksums(&mut self) {
+        self.add_checksums
+            .retain(|url, _| self.used_add_checksums.contains(url));
     }
 
-    fn clean_unused_images(&mut self) {
-        for image in self.images.clone().keys() {
-            if !self.used_images.contains(image) {
-                let tag = self.images.remove(image).unwrap();
-                if self.display_updates {
-                    println!(
-                        "{:>20} {} {}",
-                        "Remove image".color(Color::Red).bold(),
-                        image.to_string(),
-                        tag.digest
-                    );
-                }
+    //////////  Deprecations  //////////
+
+    /// Reports the deprecated fields used in a parsed Dofigen document.
+    /// Nothing is reported with the strict feature since the deprecated aliases are rejected while deserializing
+    pub(crate) fn check_deprecations(&mut self, document: &serde_yaml::Value) {
+        if cfg!(feature = "strict") {
+            return;
+        }
+        let resource = self.current_resource().map(|resource| resource.to_string());
+        for mut message in find_deprecations(document, DEPRECATIONS) {
+            if let Some(resource) = &resource {
+                message.message = format!("{} (in {})", message.message, resource);
             }
+            self.listeners
+                .each(|listener| listener.on_lint_message(&message));
+            self.messages.push(message);
         }
     }
 
+    //////////  Listeners  //////////
+
+    /// Adds a listener notified of the progress of the context and of its clones
+    pub fn add_listener(&mut self, listener: Arc<dyn ContextListener>) {
+        self.listeners.push(listener);
+    }
+
+    /// Notifies the listeners that a file was written, for the files written by the embedding tools
+    pub fn file_written(&self, path: &Path) {
+        self.listeners
+            .each(|listener| listener.on_file_written(path));
+    }
+
     //////////  Getters  //////////
 
+    /// Returns the messages raised while parsing the Dofigen documents
+    pub fn get_lint_messages(&self) -> Vec<LintMessage> {
+        self.messages.clone()
+    }
+
+    /// Returns the resource of the extend chain that introduced the value at the given path of the last parsed configuration.
+    /// The values defined by the main document have no origin
+    pub fn field_origin(&self, path: &[String]) -> Option<&Resource> {
+        self.origins.get(path)
+    }
+
+    /// Returns the operation of the extend chain layer that last changed the value at the given path of the last parsed configuration
+    pub fn field_operation(&self, path: &[String]) -> PatchOperation {
+        self.origins.operation(path)
+    }
+
+    /// Adds the extended resource that introduced the faulty value to the messages of the merged configuration
+    /// and notifies the listeners of them
+    pub fn with_field_origins(&self, messages: Vec<LintMessage>) -> Vec<LintMessage> {
+        messages
+            .into_iter()
+            .map(|mut message| {
+                if let Some(resource) = self.field_origin(&message.path) {
+                    message.message = format!(
+                        "{} (introduced by {})",
+                        message.message,
+                        resource.to_string()
+                    );
+                }
+                self.listeners
+                    .each(|listener| listener.on_lint_message(&message));
+                message
+            })
+            .collect()
+    }
+
+    /// Checks the best practice rules of the Dofigen struct with the levels of its lint field overridden by the context ones.
+    /// The messages give the extended file that introduced their field
+    pub fn lint(&self, dofigen: &Dofigen) -> Vec<(&'static str, LintMessage)> {
+        let mut levels = dofigen.lint.clone();
+        levels.extend(self.lint_levels.clone());
+        LintSession::check_rules(dofigen, &levels)
+            .into_iter()
+            .map(|(rule, message)| (rule, self.with_field_origins(vec![message]).remove(0)))
+            .collect()
+    }
+
+    /// Returns the local files loaded while parsing, the Dofigen file and its extended files, sorted
+    pub fn used_files(&self) -> Vec<PathBuf> {
+        let mut files = self
+            .used_resources
+            .iter()
+            .filter_map(|resource| match resource {
+                Resource::File(path) => Some(path.clone()),
+                Resource::Url(_) => None,
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
     pub(crate) fn used_resource_contents(&self) -> HashMap<Resource, ResourceVersion> {
         self.used_resources
             .iter()
@@ -341,16 +496,21 @@ impl DofigenContext {
     pub fn resource_updates(
         &self,
         previous: &DofigenContext,
+    ) -> Vec<UpdateCommand<Resource, ResourceVersion>> {
+        self.resource_changes(previous, |resource| matches!(resource, Resource::Url(_)))
+    }
+
+    fn resource_changes(
+        &self,
+        previous: &DofigenContext,
+        filter: impl Fn(&Resource) -> bool,
     ) -> Vec<UpdateCommand<Resource, ResourceVersion>> {
         let mut updates = vec![];
 
         let mut previous_resources = previous.resources.clone();
         let current_resources = self.used_resource_contents();
 
-        for (resource, version) in current_resources
-            .into_iter()
-            .filter(|(r, _)| matches!(r, Resource::Url(_)))
-        {
+        for (resource, version) in current_resources.into_iter().filter(|(r, _)| filter(r)) {
             if let Some(previous_version) = previous_resources.remove(&resource) {
                 if version.hash != previous_version.hash {
                     updates.push(UpdateCommand::Update(resource, version, previous_version))
@@ -360,10 +520,7 @@ impl DofigenContext {
             }
         }
 
-        for (resource, version) in previous_resources
-            .into_iter()
-            .filter(|(r, _)| matches!(r, Resource::Url(_)))
-        {
+        for (resource, version) in previous_resources.into_iter().filter(|(r, _)| filter(r)) {
             updates.push(UpdateCommand::Remove(resource, version));
         }
 
@@ -372,6 +529,14 @@ impl DofigenContext {
         updates
     }
 
+    /// Returns the changes of the images and the resources, local files included, since the previous context
+    pub fn update_report(&self, previous: &DofigenContext) -> UpdateReport {
+        UpdateReport::new(
+            self.image_updates(previous),
+            self.resource_changes(previous, |_| true),
+        )
+    }
+
     //////////  Dofigen parsing  //////////
 
     /// Parse an Dofigen from a string.
@@ -466,9 +631,26 @@ impl DofigenContext {
     /// );
     /// ```
     pub fn parse_from_string(&mut self, input: &str) -> Result<Dofigen> {
-        self.merge_extended_image(
-            serde_yaml::from_str(input).map_err(|err| Error::Deserialize(err))?,
-        )
+        let start = Instant::now();
+        self.origins.clear();
+        self.declared_profiles.clear();
+        self.origins.push_layer(None, input);
+        let mut document: serde_yaml::Value =
+            serde_yaml::from_str(input).map_err(|err| Error::deserialize(err, input, None))?;
+        self.check_deprecations(&document);
+        let templated = extend::has_templates(&document);
+        if templated {
+            extend::expand_templates(&mut document)?;
+        }
+        let dofigen = serde_yaml::from_value(document).or_else(|err| {
+            if templated {
+                return Err(Error::display(err));
+            }
+            // The parsed document has lost the source locations: the input is only parsed again to locate the error
+            serde_yaml::from_str(input).map_err(|err| Error::deserialize(err, input, None))
+        })?;
+        self.timings.add(TimingPhase::Parse, start.elapsed());
+        self.merge_extended_image(dofigen)
     }
 
     /// Parse an Dofigen from an IO stream.
@@ -563,10 +745,12 @@ impl DofigenContext {
     ///     }
     /// );
     /// ```
-    pub fn parse_from_reader<R: Read>(&mut self, reader: R) -> Result<Dofigen> {
-        self.merge_extended_image(
-            serde_yaml::from_reader(reader).map_err(|err| Error::Deserialize(err))?,
-        )
+    pub fn parse_from_reader<R: Read>(&mut self, mut reader: R) -> Result<Dofigen> {
+        let mut input = String::new();
+        reader
+            .read_to_string(&mut input)
+            .map_err(Error::display)?;
+        self.parse_from_string(&input)
     }
 
     /// Parse an Dofigen from a Resource (File or Url)
@@ -594,36 +778,150 @@ impl DofigenContext {
     /// );
     /// ```
     pub fn parse_from_resource(&mut self, resource: Resource) -> Result<Dofigen> {
-        let dofigen = resource.load(self)?;
-        self.merge_extended_image(dofigen)
+        // The phase is ended before returning the errors to keep the nested phases balanced
+        let start = self.timings.start();
+        self.origins.clear();
+        self.declared_profiles.clear();
+        let dofigen = resource.load_layer(self, false, &HashMap::new());
+        self.timings.end(TimingPhase::Parse, start);
+        self.merge_extended_image(dofigen?)
     }
 
     fn merge_extended_image(&mut self, dofigen: Extend<DofigenPatch>) -> Result<Dofigen> {
-        Ok(dofigen.merge(self)?.into())
+        let start = self.timings.start();
+        let dofigen = self.merge_layers(dofigen);
+        self.timings.end(TimingPhase::Extend, start);
+        let dofigen = dofigen?;
+        validation::check_builder_references(&dofigen)?;
+        Ok(dofigen)
+    }
+
+    fn merge_layers(&mut self, dofigen: Extend<DofigenPatch>) -> Result<Dofigen> {
+        let dofigen = dofigen.merge(self)?.into();
+        if let Some(profile) = self
+            .profiles
+            .iter()
+            .find(|profile| !self.declared_profiles.contains(*profile))
+        {
+            return Err(Error::Custom(format!(
+                "The profile '{}' isn't declared in the Dofigen files",
+                profile
+            )));
+        }
+        variables::interpolate_variables(dofigen, &self.variables)
+    }
+
+    /// Generates the Dockerfile of each resource in parallel threads, at most one per available CPU.
+    /// Each thread works on a clone of the context for the resources it takes and the loaded resources,
+    /// images and messages are then merged back, so the context can be used to write a single lock file
+    pub fn generate_many(&mut self, resources: &[Resource]) -> Vec<Result<String>> {
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(resources.len());
+        let next = AtomicUsize::new(0);
+        let workers = thread::scope(|scope| {
+            (0..workers)
+                .map(|_| {
+                    let mut context = self.clone();
+                    let next = &next;
+                    scope.spawn(move || {
+                        let mut dockerfiles = vec![];
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(resource) = resources.get(index) else {
+                                break;
+                            };
+                            // A failed load can leave its resources in the stack
+                            context.load_resource_stack.clear();
+                            dockerfiles.push((index, context.generate_resource(resource)));
+                        }
+                        (context, dockerfiles)
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().expect("A generation thread panicked"))
+                .collect::<Vec<_>>()
+        });
+
+        let mut dockerfiles = workers
+            .into_iter()
+            .flat_map(|(context, dockerfiles)| {
+                self.merge_context(context);
+                dockerfiles
+            })
+            .collect::<Vec<_>>();
+        dockerfiles.sort_by_key(|(index, _)| *index);
+        dockerfiles
+            .into_iter()
+            .map(|(_, dockerfile)| dockerfile)
+            .collect()
+    }
+
+    fn generate_resource(&mut self, resource: &Resource) -> Result<String> {
+        let dofigen = self.parse_from_resource(resource.clone())?;
+        let start = Instant::now();
+        let dockerfile = GenerationContext::from(dofigen).generate_dockerfile();
+        self.timings.add(TimingPhase::Generation, start.elapsed());
+        dockerfile
+    }
+
+    fn merge_context(&mut self, other: DofigenContext) {
+        self.timings.merge(&other.timings);
+        self.resources.extend(other.resources);
+        self.used_resources.extend(other.used_resources);
+        self.images.extend(other.images);
+        self.used_images.extend(other.used_images);
+        self.add_checksums.extend(other.add_checksums);
+        self.used_add_checksums.extend(other.used_add_checksums);
+        other.messages.into_iter().for_each(|message| {
+            if !self.messages.contains(&message) {
+                self.messages.push(message);
+            }
+        });
     }
 
     pub fn clean_unused(&mut self) {
         self.clean_unused_resources();
         self.clean_unused_images();
+        self.clean_unused_add_checksums();
     }
 
     //////////  Constructors  //////////
 
+    #[allow(deprecated)]
     pub fn new() -> Self {
         Self {
             offline: false,
             update_docker_tags: false,
             update_file_resources: true,
             update_url_resources: false,
+            update_add_checksums: false,
             display_updates: true,
+            timings: Timings::default(),
+            fixtures: None,
+            http_cache: None,
+            variables: HashMap::new(),
+            profiles: vec![],
+            lint_levels: HashMap::new(),
+            default_profiles: vec![],
+            registry_mirror: None,
+            declared_profiles: HashSet::new(),
             load_resource_stack: vec![],
             resources: HashMap::new(),
             used_resources: HashSet::new(),
+            vendored_resources: HashMap::new(),
             images: HashMap::new(),
             used_images: HashSet::new(),
+            add_checksums: HashMap::new(),
+            used_add_checksums: HashSet::new(),
+            messages: vec![],
+            origins: FieldOrigins::default(),
+            listeners: Listeners::default(),
         }
     }
 
+    #[allow(deprecated)]
     pub fn from(
         resources: HashMap<Resource, ResourceVersion>,
         images: HashMap<ImageName, DockerTag>,
@@ -633,20 +931,53 @@ impl DofigenContext {
             update_docker_tags: false,
             update_file_resources: true,
             update_url_resources: false,
+            update_add_checksums: false,
             display_updates: true,
+            timings: Timings::default(),
+            fixtures: None,
+            http_cache: None,
+            variables: HashMap::new(),
+            profiles: vec![],
+            lint_levels: HashMap::new(),
+            default_profiles: vec![],
+            registry_mirror: None,
+            declared_profiles: HashSet::new(),
             load_resource_stack: vec![],
             resources,
             used_resources: HashSet::new(),
+            vendored_resources: HashMap::new(),
             images,
             used_images: HashSet::new(),
+            add_checksums: HashMap::new(),
+            used_add_checksums: HashSet::new(),
+            messages: vec![],
+            origins: FieldOrigins::default(),
+            listeners: Listeners::default(),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq, PartialOrd, Eq)]
-pub struct DockerHubTagResponse {
-    pub digest: Option<String>,
-    images: Vec<DockerTag>,
+/// The relative path of a vendored URL resource: its host and then its path.
+/// The URLs with a query or without path are named by their hash
+fn vendor_path(url: &Url) -> PathBuf {
+    let mut path = PathBuf::from(match url.port() {
+        Some(port) => format!("{}_{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    });
+    let segments = url
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|segment| !segment.is_empty() && *segment != "..")
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if segments.is_empty() || url.query().is_some() {
+        path.push(format!("{}.yml", sha256::digest(url.as_str())));
+    } else {
+        path.extend(segments);
+    }
+    path
 }
 
 #[derive(PartialEq, PartialOrd, Eq)]
@@ -0,0 +1,218 @@
+use crate::{dofigen_struct::*, lock::DockerTag, DofigenContext};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// An image used by a Dofigen configuration, for the dependency update tools
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageDependency {
+    /// The path of the field using the image in the effective configuration, like builders.builder.fromImage
+    pub path: String,
+
+    /// The image name, without its version
+    pub image: String,
+
+    /// The tag of the image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+
+    /// The digest of the image, pinned in the configuration or locked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+}
+
+impl Dofigen {
+    /// Lists the images used by the configuration with their locked digests
+    pub fn image_dependencies(&self, context: &DofigenContext) -> Vec<ImageDependency> {
+        let tags = context.used_image_tags();
+        self.used_images()
+            .into_iter()
+            .map(|(path, image)| image_dependency(path.join("."), image, &tags))
+            .collect()
+    }
+
+    /// Lists the images used by the configuration with the path of their field, the builders first by name
+    pub(crate) fn used_images(&self) -> Vec<(Vec<String>, &ImageName)> {
+        let mut images = vec![];
+        let mut builders = self.builders.iter().collect::<Vec<_>>();
+        builders.sort_by_key(|(name, _)| *name);
+        for (name, builder) in builders {
+            builder.collect_images(vec!["builders".into(), name.clone()], &mut images);
+        }
+        self.stage.collect_images(vec![], &mut images);
+        images
+    }
+}
+
+fn image_dependency(
+    path: String,
+    image: &ImageName,
+    tags: &HashMap<ImageName, DockerTag>,
+) -> ImageDependency {
+    let (tag, digest) = match &image.version {
+        Some(ImageVersion::Digest(digest)) => (None, Some(digest.clone())),
+        _ => {
+            let filled = image.fill();
+            let tag = match &filled.version {
+                Some(ImageVersion::Tag(tag)) => Some(tag.clone()),
+                _ => None,
+            };
+            (tag, tags.get(&filled).map(|tag| tag.digest.clone()))
+        }
+    };
+    ImageDependency {
+        path,
+        image: ImageName {
+            version: None,
+            ..image.clone()
+        }
+        .to_string(),
+        tag,
+        digest,
+    }
+}
+
+impl Stage {
+    fn collect_images<'a>(
+        &'a self,
+        prefix: Vec<String>,
+        images: &mut Vec<(Vec<String>, &'a ImageName)>,
+    ) {
+        collect_from_image(&self.from, field_path(&prefix, &["fromImage"]), images);
+        for (position, copy) in self.copy.iter().enumerate() {
+            if let CopyResource::Copy(copy) = copy {
+                collect_from_image(
+                    &copy.from,
+                    field_path(&prefix, &["copy", &position.to_string(), "fromImage"]),
+                    images,
+                );
+            }
+        }
+        if let Some(root) = &self.root {
+            root.collect_images(field_path(&prefix, &["root"]), images);
+        }
+        self.run.collect_images(prefix, images);
+    }
+}
+
+impl Run {
+    fn collect_images<'a>(
+        &'a self,
+        prefix: Vec<String>,
+        images: &mut Vec<(Vec<String>, &'a ImageName)>,
+    ) {
+        for (position, bind) in self.bind.iter().enumerate() {
+            collect_from_image(
+                &bind.from,
+                field_path(&prefix, &["bind", &position.to_string(), "fromImage"]),
+                images,
+            );
+        }
+        for (position, cache) in self.cache.iter().enumerate() {
+            collect_from_image(
+                &cache.from,
+                field_path(&prefix, &["cache", &position.to_string(), "fromImage"]),
+                images,
+            );
+        }
+    }
+}
+
+/// Appends the field names to the path
+fn field_path(prefix: &[String], fields: &[&str]) -> Vec<String> {
+    prefix
+        .iter()
+        .cloned()
+        .chain(fields.iter().map(|field| field.to_string()))
+        .collect()
+}
+
+fn collect_from_image<'a>(
+    from: &'a FromContext,
+    path: Vec<String>,
+    images: &mut Vec<(Vec<String>, &'a ImageName)>,
+) {
+    if let FromContext::FromImage(image) = from {
+        images.push((path, image));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn list_images() {
+        let dofigen = Dofigen {
+            builders: HashMap::from([(
+                "builder".into(),
+                Stage {
+                    from: FromContext::FromImage(ImageName {
+                        path: "rust".into(),
+                        version: Some(ImageVersion::Tag("1.85".into())),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )]),
+            stage: Stage {
+                from: FromContext::FromImage(ImageName {
+                    host: Some("ghcr.io".into()),
+                    path: "org/runtime".into(),
+                    version: Some(ImageVersion::Digest("sha256:def".into())),
+                    ..Default::default()
+                }),
+                copy: vec![CopyResource::Copy(Copy {
+                    from: FromContext::FromImage(ImageName {
+                        path: "composer".into(),
+                        ..Default::default()
+                    }),
+                    paths: vec!["/usr/bin/composer".into()],
+                    ..Default::default()
+                })],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let rust = ImageName {
+            path: "rust".into(),
+            version: Some(ImageVersion::Tag("1.85".into())),
+            ..Default::default()
+        };
+        let mut context = DofigenContext::from(
+            HashMap::new(),
+            HashMap::from([(
+                rust.fill(),
+                DockerTag {
+                    digest: "sha256:abc".into(),
+                },
+            )]),
+        );
+        context.get_image_tag(&rust).unwrap();
+
+        assert_eq_sorted!(
+            dofigen.image_dependencies(&context),
+            vec![
+                ImageDependency {
+                    path: "builders.builder.fromImage".into(),
+                    image: "rust".into(),
+                    tag: Some("1.85".into()),
+                    digest: Some("sha256:abc".into()),
+                },
+                ImageDependency {
+                    path: "fromImage".into(),
+                    image: "ghcr.io/org/runtime".into(),
+                    tag: None,
+                    digest: Some("sha256:def".into()),
+                },
+                ImageDependency {
+                    path: "copy.0.fromImage".into(),
+                    image: "composer".into(),
+                    tag: Some("latest".into()),
+                    digest: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{LintMessage, MessageLevel};
+use serde_yaml::Value;
+
+/// The paths of the structures that contains the stage fields
+const STAGE_SCOPES: &[&[&str]] = &[&[], &["builders", "*"]];
+
+/// The deprecated fields of the Dofigen structure
+pub(crate) const DEPRECATIONS: &[Deprecation] = &[
+    // Plural and former names kept as serde aliases of the current fields, rejected by the strict feature
+    Deprecation {
+        field: "adds",
+        replacement: "copy",
+        scopes: STAGE_SCOPES,
+    },
+    Deprecation {
+        field: "artifacts",
+        replacement: "copy",
+        scopes: STAGE_SCOPES,
+    },
+    Deprecation {
+        field: "envs",
+        replacement: "env",
+        scopes: STAGE_SCOPES,
+    },
+    Deprecation {
+        field: "ports",
+        replacement: "expose",
+        scopes: &[&[]],
+    },
+];
+
+/// Represents a deprecated field of the Dofigen structure.
+/// The field is still deserialized using a serde alias, but its use is reported with a replacement hint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deprecation {
+    /// The deprecated field name
+    pub field: &'static str,
+
+    /// The field to use instead
+    pub replacement: &'static str,
+
+    /// The paths of the structures containing the field
+    /// A `*` part matches any key
+    pub scopes: &'static [&'static [&'static str]],
+}
+
+impl Deprecation {
+    pub(crate) fn in_scope(&self, path: &[String]) -> bool {
+        self.scopes.iter().any(|scope| {
+            scope.len() == path.len()
+                && scope
+                    .iter()
+                    .zip(path.iter())
+                    .all(|(part, key)| *part == "*" || part == key)
+        })
+    }
+
+    fn message(&self) -> String {
+        format!(
+            "The '{}' field is deprecated, use '{}' instead",
+            self.field, self.replacement
+        )
+    }
+}
+
+/// Finds the deprecated fields used in a YAML document
+pub(crate) fn find_deprecations(value: &Value, deprecations: &[Deprecation]) -> Vec<LintMessage> {
+    let mut messages = vec![];
+    visit_value(value, &mut vec![], deprecations, &mut messages);
+    messages
+}
+
+fn visit_value(
+    value: &Value,
+    path: &mut Vec<String>,
+    deprecations: &[Deprecation],
+    messages: &mut Vec<LintMessage>,
+) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, child) in mapping {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    Value::Number(key) => key.to_string(),
+                    _ => continue,
+                };
+                if let Some(deprecation) = deprecations
+                    .iter()
+                    .find(|deprecation| deprecation.field == key && deprecation.in_scope(path))
+                {
+                    messages.push(LintMessage {
+                        level: MessageLevel::Warn,
+                        path: [path.clone(), vec![key.clone()]].concat(),
+                        message: deprecation.message(),
+                    });
+                }
+                path.push(key);
+                visit_value(child, path, deprecations, messages);
+                path.pop();
+            }
+        }
+        Value::Sequence(sequence) => {
+            for (position, child) in sequence.iter().enumerate() {
+                path.push(position.to_string());
+                visit_value(child, path, deprecations, messages);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    const TEST_DEPRECATIONS: &[Deprecation] = &[
+        Deprecation {
+            field: "script",
+            replacement: "run",
+            scopes: STAGE_SCOPES,
+        },
+        Deprecation {
+            field: "dst",
+            replacement: "target",
+            scopes: &[&["cache", "*"], &["builders", "*", "cache", "*"]],
+        },
+    ];
+
+    #[test]
+    fn no_deprecated_field() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+run: echo Hello
+builders:
+  builder:
+    run: echo Hello
+"#,
+        )
+        .unwrap();
+
+        assert_eq_sorted!(find_deprecations(&value, TEST_DEPRECATIONS), vec![]);
+    }
+
+    #[test]
+    fn stage_fields() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+script: echo Hello
+builders:
+  builder:
+    script: echo Hello
+    copy:
+      - script: not a stage field
+"#,
+        )
+        .unwrap();
+
+        assert_eq_sorted!(
+            find_deprecations(&value, TEST_DEPRECATIONS),
+            vec![
+                LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["script".into()],
+                    message: "The 'script' field is deprecated, use 'run' instead".into(),
+                },
+                LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["builders".into(), "builder".into(), "script".into()],
+                    message: "The 'script' field is deprecated, use 'run' instead".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn list_item_fields() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+cache:
+  - target: /tmp
+  - dst: /var/cache
+"#,
+        )
+        .unwrap();
+
+        assert_eq_sorted!(
+            find_deprecations(&value, TEST_DEPRECATIONS),
+            vec![LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["cache".into(), "1".into(), "dst".into()],
+                message: "The 'dst' field is deprecated, use 'target' instead".into(),
+            },]
+        );
+    }
+}
@@ -30,12 +30,18 @@ macro_rules! impl_from_patch_and_add {
 impl_from_patch_and_add!(Dofigen, DofigenPatch);
 impl_from_patch_and_add!(Stage, StagePatch);
 impl_from_patch_and_add!(Healthcheck, HealthcheckPatch);
+impl_from_patch_and_add!(Checks, ChecksPatch);
 impl_from_patch_and_add!(ImageName, ImageNamePatch);
 impl_from_patch_and_add!(Run, RunPatch);
 impl_from_patch_and_add!(Cache, CachePatch);
 impl_from_patch_and_add!(Bind, BindPatch);
+impl_from_patch_and_add!(Secret, SecretPatch);
+impl_from_patch_and_add!(Package, PackagePatch);
+impl_from_patch_and_add!(OutputUse, OutputUsePatch);
+impl_from_patch_and_add!(CompilerCache, CompilerCachePatch);
 impl_from_patch_and_add!(Port, PortPatch);
 impl_from_patch_and_add!(User, UserPatch);
+impl_from_patch_and_add!(UserDefinition, UserDefinitionPatch);
 impl_from_patch_and_add!(CopyOptions, CopyOptionsPatch);
 impl_from_patch_and_add!(Copy, CopyPatch);
 impl_from_patch_and_add!(CopyContent, CopyContentPatch);
@@ -81,6 +87,18 @@ enum VecPatchCommand<T> {
     Append(Vec<T>),
 }
 
+impl<T> VecPatchCommand<T> {
+    /// The position targeted by the command
+    fn position(&self) -> Option<usize> {
+        match self {
+            VecPatchCommand::Replace(pos, _)
+            | VecPatchCommand::InsertBefore(pos, _)
+            | VecPatchCommand::InsertAfter(pos, _) => Some(*pos),
+            VecPatchCommand::ReplaceAll(_) | VecPatchCommand::Append(_) => None,
+        }
+    }
+}
+
 /// Patch for Vec<T> that handle some commands based on the position:
 /// - `_` to replace the whole list
 /// - `+` to append to the list
@@ -143,6 +161,16 @@ enum StringOrNumber {
     Number(usize),
 }
 
+#[cfg(feature = "permissive")]
+impl StringOrNumber {
+    fn into_string(self) -> String {
+        match self {
+            StringOrNumber::String(s) => s,
+            StringOrNumber::Number(n) => n.to_string(),
+        }
+    }
+}
+
 /// One or many for deserialization
 #[cfg(feature = "permissive")]
 #[derive(Deserialize, Debug, Clone, PartialEq)]
@@ -354,13 +382,13 @@ where
             #[cfg(feature = "permissive")]
             VecDeepPatchDeserializable::Vec(v) => VecDeepPatch {
                 commands: vec![VecDeepPatchCommand::ReplaceAll(
-                    v.0.iter().map(|p| p.clone().into()).collect(),
+                    v.0.into_iter().map(|p| p.into()).collect(),
                 )],
             },
             #[cfg(not(feature = "permissive"))]
             VecDeepPatchDeserializable::Vec(v) => VecDeepPatch {
                 commands: vec![VecDeepPatchCommand::ReplaceAll(
-                    v.iter().map(|p| p.clone().into()).collect(),
+                    v.into_iter().map(|p| p.into()).collect(),
                 )],
             },
             VecDeepPatchDeserializable::Map(v) => VecDeepPatch {
@@ -1031,7 +1059,7 @@ where
         T: Clone + From<P>,
         P: Clone,
     {
-        patch.iter().map(|p| p.clone().into()).collect()
+        patch.0.into_iter().map(|p| p.into()).collect()
     }
 
     #[cfg(not(feature = "permissive"))]
@@ -1040,7 +1068,7 @@ where
         T: Clone + From<P>,
         P: Clone,
     {
-        patch.iter().map(|p| p.clone().into()).collect()
+        patch.into_iter().map(|p| p.into()).collect()
     }
 
     impl<'de, T, P> de::Visitor<'de> for VecDeepPatchCommandsVisitor<T, P>
@@ -1071,24 +1099,24 @@ where
                             commands.push(VecDeepPatchCommand::Append(map_vec(map.next_value()?)));
                         }
                         key => {
-                            if key.starts_with('+') {
-                                let pos = key[1..key.len()].parse::<usize>().unwrap();
+                            if let Some(position) = key.strip_prefix('+') {
+                                let pos = parse_position(key, position)?;
                                 commands.push(VecDeepPatchCommand::InsertBefore(
                                     pos,
                                     map_vec(map.next_value()?),
                                 ));
-                            } else if key.ends_with('+') {
-                                let pos = key[..key.len() - 1].parse::<usize>().unwrap();
+                            } else if let Some(position) = key.strip_suffix('+') {
+                                let pos = parse_position(key, position)?;
                                 commands.push(VecDeepPatchCommand::InsertAfter(
                                     pos,
                                     map_vec(map.next_value()?),
                                 ));
-                            } else if key.ends_with('<') {
-                                let pos = key[..key.len() - 1].parse::<usize>().unwrap();
+                            } else if let Some(position) = key.strip_suffix('<') {
+                                let pos = parse_position(key, position)?;
                                 commands.push(VecDeepPatchCommand::Patch(pos, map.next_value()?));
                             } else {
                                 let value: P = map.next_value()?;
-                                let pos = key.parse::<usize>().unwrap();
+                                let pos = parse_position(key, key)?;
                                 commands.push(VecDeepPatchCommand::Replace(pos, value.into()));
                             }
                         }
@@ -1112,19 +1140,70 @@ where
     deserializer.deserialize_any(visitor)
 }
 
+/// Deserializes a string given as a string or a number, like a user ID
+#[cfg(feature = "permissive")]
+pub(crate) fn deserialize_from_string_or_number<'de, D>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let val: Option<StringOrNumber> = Deserialize::deserialize(deserializer)?;
+
+    Ok(val.map(StringOrNumber::into_string))
+}
+
 #[cfg(feature = "permissive")]
 pub(crate) fn deserialize_from_optional_string_or_number<'de, D>(
     deserializer: D,
 ) -> Result<Option<Option<String>>, D::Error>
 where
     D: Deserializer<'de>,
+{
+    deserialize_from_string_or_number(deserializer).map(Some)
+}
+
+/// Deserializes a number given as a number or a string, like a port, with the same result for both
+#[cfg(feature = "permissive")]
+pub(crate) fn deserialize_from_number_or_string<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
 {
     let val: Option<StringOrNumber> = Deserialize::deserialize(deserializer)?;
 
-    Ok(Some(val.map(|val| match val {
-        StringOrNumber::String(s) => s,
-        StringOrNumber::Number(n) => n.to_string(),
-    })))
+    val.map(|val| {
+        let val = val.into_string();
+        val.parse().map_err(|err| {
+            de::Error::custom(format!("Error while parsing the number '{}': {}", val, err))
+        })
+    })
+    .transpose()
+}
+
+#[cfg(feature = "permissive")]
+pub(crate) fn deserialize_from_optional_number_or_string<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    deserialize_from_number_or_string(deserializer).map(Some)
+}
+
+/// Parses the position of a list patch key, like `2` in `+2`
+fn parse_position<E: de::Error>(key: &str, position: &str) -> Result<usize, E> {
+    position.parse().map_err(|_| {
+        E::custom(format!(
+            "The key '{}' isn't a valid list patch position",
+            key
+        ))
+    })
 }
 
 fn sort_commands<T, P>(a: &VecDeepPatchCommand<T, P>, b: &VecDeepPatchCommand<T, P>) -> Ordering
@@ -1270,15 +1349,16 @@ impl<T> Merge for VecPatch<T>
 where
     T: Clone,
 {
-    fn merge(self, other: Self) -> Self {
+    fn merge(mut self, other: Self) -> Self {
         if other.commands.len() == 1
             && matches!(other.commands.first(), Some(VecPatchCommand::ReplaceAll(_)))
         {
             return other;
         }
-        if self.commands.len() == 1 {
-            if let Some(VecPatchCommand::ReplaceAll(self_vec)) = self.commands.first() {
-                let mut self_vec = self_vec.clone();
+        if self.commands.len() == 1
+            && matches!(self.commands.first(), Some(VecPatchCommand::ReplaceAll(_)))
+        {
+            if let Some(VecPatchCommand::ReplaceAll(mut self_vec)) = self.commands.pop() {
                 self_vec.apply(other);
                 return VecPatch {
                     commands: vec![VecPatchCommand::ReplaceAll(self_vec)],
@@ -1286,34 +1366,44 @@ where
             }
         }
 
-        let mut commands: Vec<VecPatchCommand<T>> = vec![];
+        let mut commands: Vec<VecPatchCommand<T>> =
+            Vec::with_capacity(self.commands.len() + other.commands.len());
 
-        let mut self_it = self.commands.iter();
-        let mut rhs_it = other.commands.iter();
+        let mut self_it = self.commands.into_iter();
+        let mut rhs_it = other.commands.into_iter();
 
         let mut self_next = self_it.next();
         let mut rhs_next = rhs_it.next();
 
-        while let (Some(self_command), Some(rhs_command)) = (self_next, rhs_next) {
-            match (self_command.clone(), rhs_command.clone()) {
+        loop {
+            let (self_command, rhs_command) = match (self_next.take(), rhs_next.take()) {
+                (Some(self_command), Some(rhs_command)) => (self_command, rhs_command),
+                (self_command, rhs_command) => {
+                    commands.extend(self_command.into_iter().chain(self_it));
+                    commands.extend(rhs_command.into_iter().chain(rhs_it));
+                    break;
+                }
+            };
+            match (self_command, rhs_command) {
                 (VecPatchCommand::ReplaceAll(_), _) | (_, VecPatchCommand::ReplaceAll(_)) => {
                     panic!("Cannot combine a replace all with other commands");
                 }
-                (VecPatchCommand::Append(elements), VecPatchCommand::Append(rhs_elements)) => {
+                (VecPatchCommand::Append(mut elements), VecPatchCommand::Append(rhs_elements)) => {
                     // For append, we first add self elements then rhs elements
                     // Since we apply the self first and then the rhs the rhs elements will be added after the self elements
-                    let mut elements = elements;
                     elements.extend(rhs_elements);
                     commands.push(VecPatchCommand::Append(elements));
                     self_next = self_it.next();
                     rhs_next = rhs_it.next();
                 }
-                (self_command, VecPatchCommand::Append(_)) => {
+                (self_command, rhs_command @ VecPatchCommand::Append(_)) => {
                     commands.push(self_command);
                     self_next = self_it.next();
+                    rhs_next = Some(rhs_command);
                 }
-                (VecPatchCommand::Append(_), rhs_command) => {
+                (self_command @ VecPatchCommand::Append(_), rhs_command) => {
                     commands.push(rhs_command);
+                    self_next = Some(self_command);
                     rhs_next = rhs_it.next();
                 }
                 (
@@ -1327,75 +1417,57 @@ where
                     } else if self_pos < rhs_pos {
                         commands.push(VecPatchCommand::Replace(self_pos, self_val));
                         self_next = self_it.next();
+                        rhs_next = Some(VecPatchCommand::Replace(rhs_pos, rhs_val));
                     } else {
                         commands.push(VecPatchCommand::Replace(rhs_pos, rhs_val));
+                        self_next = Some(VecPatchCommand::Replace(self_pos, self_val));
                         rhs_next = rhs_it.next();
                     }
                 }
                 (
                     VecPatchCommand::InsertBefore(self_pos, self_val),
                     VecPatchCommand::InsertBefore(rhs_pos, mut rhs_val),
-                )
-                | (
+                ) if self_pos == rhs_pos => {
+                    // We first add rhs elements then self elements
+                    // For insert before, the position is the position of the first element added by the self patch
+                    rhs_val.extend(self_val);
+                    commands.push(VecPatchCommand::InsertBefore(rhs_pos, rhs_val));
+                    self_next = self_it.next();
+                    rhs_next = rhs_it.next();
+                }
+                (
                     VecPatchCommand::InsertAfter(self_pos, self_val),
                     VecPatchCommand::InsertAfter(rhs_pos, mut rhs_val),
-                ) => {
-                    if self_pos == rhs_pos {
-                        // We first add rhs elements then self elements
-                        // For insert before, the position is the position of the first element added by the self patch
-                        // For insert after, the position does not change so we append rhs elements after the elements, after that the self elements are added
-                        rhs_val.extend(self_val);
-                        commands.push(rhs_command.clone());
-                        self_next = self_it.next();
-                        rhs_next = rhs_it.next();
-                    } else if self_pos < rhs_pos {
-                        commands.push(self_command.clone());
-                        self_next = self_it.next();
-                    } else {
-                        commands.push(rhs_command.clone());
-                        rhs_next = rhs_it.next();
-                    }
+                ) if self_pos == rhs_pos => {
+                    // For insert after, the position does not change so we append rhs elements after the elements, after that the self elements are added
+                    rhs_val.extend(self_val);
+                    commands.push(VecPatchCommand::InsertAfter(rhs_pos, rhs_val));
+                    self_next = self_it.next();
+                    rhs_next = rhs_it.next();
                 }
-                (
-                    VecPatchCommand::Replace(self_pos, _)
-                    | VecPatchCommand::InsertBefore(self_pos, _)
-                    | VecPatchCommand::InsertAfter(self_pos, _),
-                    VecPatchCommand::Replace(rhs_pos, _)
-                    | VecPatchCommand::InsertBefore(rhs_pos, _)
-                    | VecPatchCommand::InsertAfter(rhs_pos, _),
-                ) => {
-                    if self_pos == rhs_pos {
-                        match (self_command, rhs_command) {
-                            (VecPatchCommand::InsertBefore(_, _), _)
-                            | (_, VecPatchCommand::InsertAfter(_, _)) => {
-                                commands.push(self_command.clone());
-                                self_next = self_it.next();
-                            }
-                            (_, VecPatchCommand::InsertBefore(_, _))
-                            | (VecPatchCommand::InsertAfter(_, _), _) => {
-                                commands.push(rhs_command.clone());
-                                rhs_next = rhs_it.next();
-                            }
-                            _ => panic!("This case should have been reached"),
+                (self_command, rhs_command) => {
+                    // For a same position, InsertBefore comes first and InsertAfter last
+                    let self_first = match self_command.position().cmp(&rhs_command.position()) {
+                        Ordering::Less => true,
+                        Ordering::Greater => false,
+                        Ordering::Equal => {
+                            matches!(self_command, VecPatchCommand::InsertBefore(_, _))
+                                || matches!(rhs_command, VecPatchCommand::InsertAfter(_, _))
                         }
-                    } else if self_pos < rhs_pos {
-                        commands.push(self_command.clone());
+                    };
+                    if self_first {
+                        commands.push(self_command);
                         self_next = self_it.next();
+                        rhs_next = Some(rhs_command);
                     } else {
-                        commands.push(rhs_command.clone());
+                        commands.push(rhs_command);
+                        self_next = Some(self_command);
                         rhs_next = rhs_it.next();
                     }
                 }
             }
         }
 
-        let remaining_commands = if self_next.is_some() {
-            std::iter::once(self_next.unwrap()).chain(self_it)
-        } else {
-            std::iter::once(rhs_next.unwrap()).chain(self_it)
-        };
-        remaining_commands.for_each(|c| commands.push(c.clone()));
-
         Self { commands }
     }
 }
@@ -1405,7 +1477,7 @@ where
     T: Clone + Patch<P> + From<P>,
     P: Clone + Merge,
 {
-    fn merge(self, other: Self) -> Self {
+    fn merge(mut self, other: Self) -> Self {
         if other.commands.len() == 1
             && matches!(
                 other.commands.first(),
@@ -1414,9 +1486,13 @@ where
         {
             return other;
         }
-        if self.commands.len() == 1 {
-            if let Some(VecDeepPatchCommand::ReplaceAll(self_vec)) = self.commands.first() {
-                let mut self_vec = self_vec.clone();
+        if self.commands.len() == 1
+            && matches!(
+                self.commands.first(),
+                Some(VecDeepPatchCommand::ReplaceAll(_))
+            )
+        {
+            if let Some(VecDeepPatchCommand::ReplaceAll(mut self_vec)) = self.commands.pop() {
                 self_vec.apply(other);
                 return VecDeepPatch {
                     commands: vec![VecDeepPatchCommand::ReplaceAll(self_vec)],
@@ -1424,158 +1500,104 @@ where
             }
         }
 
-        let mut commands: Vec<VecDeepPatchCommand<T, P>> = vec![];
+        let mut commands: Vec<VecDeepPatchCommand<T, P>> =
+            Vec::with_capacity(self.commands.len() + other.commands.len());
 
-        let mut self_it = self.commands.iter();
-        let mut rhs_it = other.commands.iter();
+        let mut self_it = self.commands.into_iter();
+        let mut rhs_it = other.commands.into_iter();
 
         let mut self_next = self_it.next();
         let mut rhs_next = rhs_it.next();
 
-        while let (Some(self_command), Some(rhs_command)) = (self_next, rhs_next) {
-            match (self_command.clone(), rhs_command.clone()) {
+        loop {
+            let (self_command, rhs_command) = match (self_next.take(), rhs_next.take()) {
+                (Some(self_command), Some(rhs_command)) => (self_command, rhs_command),
+                (self_command, rhs_command) => {
+                    commands.extend(self_command.into_iter().chain(self_it));
+                    commands.extend(rhs_command.into_iter().chain(rhs_it));
+                    break;
+                }
+            };
+            match (self_command, rhs_command) {
                 (VecDeepPatchCommand::ReplaceAll(_), _)
                 | (_, VecDeepPatchCommand::ReplaceAll(_)) => {
                     panic!("Cannot combine a replace all with other commands");
                 }
                 (
-                    VecDeepPatchCommand::Append(elements),
+                    VecDeepPatchCommand::Append(mut elements),
                     VecDeepPatchCommand::Append(rhs_elements),
                 ) => {
                     // For append, we first add self elements then rhs elements
                     // Since we apply the self first and then the rhs the rhs elements will be added after the self elements
-                    let mut elements = elements;
                     elements.extend(rhs_elements);
                     commands.push(VecDeepPatchCommand::Append(elements));
                     self_next = self_it.next();
                     rhs_next = rhs_it.next();
                 }
-                (self_command, VecDeepPatchCommand::Append(_)) => {
-                    commands.push(self_command);
-                    self_next = self_it.next();
-                }
-                (VecDeepPatchCommand::Append(_), rhs_command) => {
-                    commands.push(rhs_command);
-                    rhs_next = rhs_it.next();
-                }
                 (
-                    VecDeepPatchCommand::Replace(self_pos, self_val),
+                    VecDeepPatchCommand::Replace(self_pos, _),
                     VecDeepPatchCommand::Replace(rhs_pos, rhs_val),
-                ) => {
-                    if self_pos == rhs_pos {
-                        commands.push(VecDeepPatchCommand::Replace(rhs_pos, rhs_val));
-                        self_next = self_it.next();
-                        rhs_next = rhs_it.next();
-                    } else if self_pos < rhs_pos {
-                        commands.push(VecDeepPatchCommand::Replace(self_pos, self_val));
-                        self_next = self_it.next();
-                    } else {
-                        commands.push(VecDeepPatchCommand::Replace(rhs_pos, rhs_val));
-                        rhs_next = rhs_it.next();
-                    }
+                )
+                | (
+                    VecDeepPatchCommand::Patch(self_pos, _),
+                    VecDeepPatchCommand::Replace(rhs_pos, rhs_val),
+                ) if self_pos == rhs_pos => {
+                    commands.push(VecDeepPatchCommand::Replace(rhs_pos, rhs_val));
+                    self_next = self_it.next();
+                    rhs_next = rhs_it.next();
                 }
                 (
-                    VecDeepPatchCommand::Replace(self_pos, self_val),
+                    VecDeepPatchCommand::Replace(self_pos, mut self_val),
                     VecDeepPatchCommand::Patch(rhs_pos, rhs_val),
-                ) => {
-                    if self_pos == rhs_pos {
-                        let mut val = self_val.clone();
-                        val.apply(rhs_val);
-                        commands.push(VecDeepPatchCommand::Replace(rhs_pos, val));
-                        self_next = self_it.next();
-                        rhs_next = rhs_it.next();
-                    } else if self_pos < rhs_pos {
-                        commands.push(VecDeepPatchCommand::Replace(self_pos, self_val));
-                        self_next = self_it.next();
-                    } else {
-                        commands.push(VecDeepPatchCommand::Patch(rhs_pos, rhs_val));
-                        rhs_next = rhs_it.next();
-                    }
-                }
-                (
-                    VecDeepPatchCommand::Patch(self_pos, self_val),
-                    VecDeepPatchCommand::Replace(rhs_pos, rhs_val),
-                ) => {
-                    if self_pos == rhs_pos {
-                        commands.push(VecDeepPatchCommand::Replace(rhs_pos, rhs_val));
-                        self_next = self_it.next();
-                        rhs_next = rhs_it.next();
-                    } else if self_pos < rhs_pos {
-                        commands.push(VecDeepPatchCommand::Patch(self_pos, self_val));
-                        self_next = self_it.next();
-                    } else {
-                        commands.push(VecDeepPatchCommand::Replace(rhs_pos, rhs_val));
-                        rhs_next = rhs_it.next();
-                    }
+                ) if self_pos == rhs_pos => {
+                    self_val.apply(rhs_val);
+                    commands.push(VecDeepPatchCommand::Replace(rhs_pos, self_val));
+                    self_next = self_it.next();
+                    rhs_next = rhs_it.next();
                 }
                 (
                     VecDeepPatchCommand::Patch(self_pos, self_val),
                     VecDeepPatchCommand::Patch(rhs_pos, rhs_val),
-                ) => {
-                    if self_pos == rhs_pos {
-                        commands.push(VecDeepPatchCommand::Patch(rhs_pos, self_val.merge(rhs_val)));
-                        self_next = self_it.next();
-                        rhs_next = rhs_it.next();
-                    } else if self_pos < rhs_pos {
-                        commands.push(VecDeepPatchCommand::Patch(self_pos, self_val));
-                        self_next = self_it.next();
-                    } else {
-                        commands.push(VecDeepPatchCommand::Patch(rhs_pos, rhs_val));
-                        rhs_next = rhs_it.next();
-                    }
+                ) if self_pos == rhs_pos => {
+                    commands.push(VecDeepPatchCommand::Patch(rhs_pos, self_val.merge(rhs_val)));
+                    self_next = self_it.next();
+                    rhs_next = rhs_it.next();
                 }
                 (
                     VecDeepPatchCommand::InsertBefore(self_pos, self_val),
                     VecDeepPatchCommand::InsertBefore(rhs_pos, mut rhs_val),
-                )
-                | (
+                ) if self_pos == rhs_pos => {
+                    // We first add rhs elements then self elements
+                    // For insert before, the position is the position of the first element added by the self patch
+                    rhs_val.extend(self_val);
+                    commands.push(VecDeepPatchCommand::InsertBefore(rhs_pos, rhs_val));
+                    self_next = self_it.next();
+                    rhs_next = rhs_it.next();
+                }
+                (
                     VecDeepPatchCommand::InsertAfter(self_pos, self_val),
                     VecDeepPatchCommand::InsertAfter(rhs_pos, mut rhs_val),
-                ) => {
-                    if self_pos == rhs_pos {
-                        // We first add rhs elements then self elements
-                        // For insert before, the position is the position of the first element added by the self patch
-                        // For insert after, the position does not change so we append rhs elements after the elements, after that the self elements are added
-                        rhs_val.extend(self_val);
-                        commands.push(rhs_command.clone());
-                        self_next = self_it.next();
-                        rhs_next = rhs_it.next();
-                    } else if self_pos < rhs_pos {
-                        commands.push(self_command.clone());
-                        self_next = self_it.next();
-                    } else {
-                        commands.push(rhs_command.clone());
-                        rhs_next = rhs_it.next();
-                    }
+                ) if self_pos == rhs_pos => {
+                    // For insert after, the position does not change so we append rhs elements after the elements, after that the self elements are added
+                    rhs_val.extend(self_val);
+                    commands.push(VecDeepPatchCommand::InsertAfter(rhs_pos, rhs_val));
+                    self_next = self_it.next();
+                    rhs_next = rhs_it.next();
                 }
-                (
-                    VecDeepPatchCommand::Replace(_, _)
-                    | VecDeepPatchCommand::Patch(_, _)
-                    | VecDeepPatchCommand::InsertBefore(_, _)
-                    | VecDeepPatchCommand::InsertAfter(_, _),
-                    VecDeepPatchCommand::Replace(_, _)
-                    | VecDeepPatchCommand::Patch(_, _)
-                    | VecDeepPatchCommand::InsertBefore(_, _)
-                    | VecDeepPatchCommand::InsertAfter(_, _),
-                ) => {
-                    if sort_commands(self_command, rhs_command) == Ordering::Less {
-                        commands.push(self_command.clone());
+                (self_command, rhs_command) => {
+                    if sort_commands(&self_command, &rhs_command) == Ordering::Less {
+                        commands.push(self_command);
                         self_next = self_it.next();
+                        rhs_next = Some(rhs_command);
                     } else {
-                        commands.push(rhs_command.clone());
+                        commands.push(rhs_command);
+                        self_next = Some(self_command);
                         rhs_next = rhs_it.next();
                     }
                 }
             }
         }
 
-        let remaining_commands = if self_next.is_some() {
-            std::iter::once(self_next.unwrap()).chain(self_it)
-        } else {
-            std::iter::once(rhs_next.unwrap()).chain(self_it)
-        };
-        remaining_commands.for_each(|c| commands.push(c.clone()));
-
         Self { commands }
     }
 }
@@ -1609,14 +1631,13 @@ where
     fn merge(mut self, other: Self) -> Self {
         for (key, value) in other.patches {
             match value {
-                Some(value) => match self.patches.get_mut(&key) {
-                    Some(Some(patch)) => {
-                        *patch = patch.clone().merge(value);
-                    }
-                    _ => {
-                        self.patches.insert(key, Some(value));
-                    }
-                },
+                Some(value) => {
+                    let patch = match self.patches.remove(&key) {
+                        Some(Some(patch)) => patch.merge(value),
+                        _ => value,
+                    };
+                    self.patches.insert(key, Some(patch));
+                }
                 None => {
                     self.patches.remove(&key);
                 }
@@ -2067,6 +2088,43 @@ mod test {
                 }
             );
         }
+
+        #[test]
+        fn merge_appends() {
+            let first: VecPatch<String> = serde_yaml::from_str("'+': [item2]").unwrap();
+            let second: VecPatch<String> = serde_yaml::from_str("'+': [item3]").unwrap();
+
+            let mut data: Vec<String> = vec!["item1".into()];
+            data.apply(first.merge(second));
+
+            assert_eq_sorted!(data, vec!["item1", "item2", "item3"]);
+        }
+
+        #[test]
+        fn merge_inserts_at_same_position() {
+            let first: VecPatch<String> = serde_yaml::from_str(
+                r#"
+                "+0": [before1]
+                "0+": [after1]
+            "#,
+            )
+            .unwrap();
+            let second: VecPatch<String> = serde_yaml::from_str(
+                r#"
+                "+0": [before2]
+                "0+": [after2]
+            "#,
+            )
+            .unwrap();
+
+            let mut data: Vec<String> = vec!["item1".into(), "item2".into()];
+            data.apply(first.merge(second));
+
+            assert_eq_sorted!(
+                data,
+                vec!["before2", "before1", "item1", "after2", "after1", "item2"]
+            );
+        }
     }
 
     mod vec_deep_patch {
@@ -2582,6 +2640,64 @@ mod test {
                 }
             );
         }
+
+        #[test]
+        fn merge_patches() {
+            let first: VecDeepPatch<SubTestStruct, SubTestStructPatch> = serde_yaml::from_str(
+                r#"
+                "0<":
+                  num: 10
+                "+":
+                  - name: sub3
+                    num: 3
+            "#,
+            )
+            .unwrap();
+            let second: VecDeepPatch<SubTestStruct, SubTestStructPatch> = serde_yaml::from_str(
+                r#"
+                "0<":
+                  name: sub0
+                "+":
+                  - name: sub4
+                    num: 4
+            "#,
+            )
+            .unwrap();
+
+            let mut data = vec![
+                SubTestStruct {
+                    name: "sub1".into(),
+                    num: 1,
+                },
+                SubTestStruct {
+                    name: "sub2".into(),
+                    num: 2,
+                },
+            ];
+            data.apply(first.merge(second));
+
+            assert_eq_sorted!(
+                data,
+                vec![
+                    SubTestStruct {
+                        name: "sub0".into(),
+                        num: 10
+                    },
+                    SubTestStruct {
+                        name: "sub2".into(),
+                        num: 2
+                    },
+                    SubTestStruct {
+                        name: "sub3".into(),
+                        num: 3
+                    },
+                    SubTestStruct {
+                        name: "sub4".into(),
+                        num: 4
+                    },
+                ]
+            );
+        }
     }
 
     mod hashmap_patch {
@@ -2867,5 +2983,130 @@ mod test {
                 assert_eq_sorted!(ret, TestStruct { test: None })
             }
         }
+
+        #[cfg(feature = "permissive")]
+        mod from_number_or_string {
+            use super::*;
+
+            #[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+            struct TestStruct {
+                #[serde(deserialize_with = "deserialize_from_number_or_string", default)]
+                pub test: Option<u16>,
+            }
+
+            #[test]
+            fn string() {
+                let ret: TestStruct = serde_yaml::from_str("test: \"123\"").unwrap();
+                assert_eq_sorted!(ret, TestStruct { test: Some(123) })
+            }
+
+            #[test]
+            fn number() {
+                let ret: TestStruct = serde_yaml::from_str("test: 123").unwrap();
+                assert_eq_sorted!(ret, TestStruct { test: Some(123) })
+            }
+
+            #[test]
+            fn out_of_range() {
+                let ret = serde_yaml::from_str::<TestStruct>("test: \"70000\"");
+                assert_eq_sorted!(
+                    ret.unwrap_err().to_string(),
+                    "Error while parsing the number '70000': number too large to fit in target type"
+                )
+            }
+        }
+
+        /// The users and ports written as numbers, quoted numbers or strings give the same structs
+        #[cfg(feature = "permissive")]
+        mod permissive_numbers {
+            use super::*;
+            use crate::DofigenContext;
+
+            /// Samples of the ranges with their bounds
+            fn samples(max: u32) -> impl Iterator<Item = u32> {
+                (0..=max).step_by(4099).chain([1, 80, 1000, 65534, max])
+            }
+
+            fn parse_all(documents: &[String]) -> Vec<Dofigen> {
+                documents
+                    .iter()
+                    .map(|document| {
+                        DofigenContext::new()
+                            .parse_from_string(&format!("fromImage: alpine\n{}", document))
+                            .unwrap_or_else(|err| panic!("{}: {}", document, err))
+                    })
+                    .collect()
+            }
+
+            fn assert_same(documents: Vec<String>) {
+                let parsed = parse_all(&documents);
+                parsed
+                    .iter()
+                    .zip(&documents)
+                    .for_each(|(dofigen, document)| {
+                        assert_eq!(
+                            dofigen, &parsed[0],
+                            "{} differs from {}",
+                            document, documents[0]
+                        )
+                    });
+            }
+
+            #[test]
+            fn users() {
+                for id in samples(100_000) {
+                    assert_same(vec![
+                        format!("user: {}", id),
+                        format!("user: \"{}\"", id),
+                        format!("user: {{user: {}}}", id),
+                        format!("user: {{user: \"{}\"}}", id),
+                    ]);
+                    assert_same(vec![
+                        format!("user: {id}:{id}"),
+                        format!("user: \"{id}:{id}\""),
+                        format!("user: {{user: {id}, group: {id}}}"),
+                        format!("user: {{user: \"{id}\", group: \"{id}\"}}"),
+                    ]);
+                }
+            }
+
+            #[test]
+            fn ports() {
+                for port in samples(u16::MAX.into()) {
+                    assert_same(vec![
+                        format!("expose: {}", port),
+                        format!("expose: \"{}\"", port),
+                        format!("expose: [{}]", port),
+                        format!("expose: [{{port: {}}}]", port),
+                        format!("expose: [{{port: \"{}\"}}]", port),
+                    ]);
+                    assert_same(vec![
+                        format!("expose: \"{}/udp\"", port),
+                        format!("expose: [{{port: {}, protocol: udp}}]", port),
+                        format!("expose: [{{port: \"{}\", protocol: udp}}]", port),
+                    ]);
+                    assert_same(vec![
+                        format!("expose: \"{}-{}/tcp\"", port, u16::MAX),
+                        format!(
+                            "expose: [{{port: {}, end: {}, protocol: tcp}}]",
+                            port,
+                            u16::MAX
+                        ),
+                        format!(
+                            "expose: [{{port: \"{}\", end: \"{}\", protocol: tcp}}]",
+                            port,
+                            u16::MAX
+                        ),
+                    ]);
+                }
+            }
+
+            #[test]
+            fn invalid_port() {
+                let ret = DofigenContext::new()
+                    .parse_from_string("fromImage: alpine\nexpose: [{port: \"80a\"}]");
+                assert!(ret.is_err());
+            }
+        }
     }
 }
@@ -0,0 +1,298 @@
+use crate::{Dofigen, Error, Result};
+use serde_yaml::Value;
+use std::collections::HashMap;
+
+/// The number of unchanged lines displayed around the changed ones
+const CONTEXT_LINES: usize = 3;
+
+/// A field of the effective Dofigen structs that differs between two files
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// The path of the field
+    pub path: Vec<String>,
+
+    /// The value of the field in the first file, if it has one
+    pub old: Option<String>,
+
+    /// The value of the field in the second file, if it has one
+    pub new: Option<String>,
+}
+
+/// The kind of a line of a diff hunk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineTag {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// A group of changed lines with the unchanged lines around them, like the hunks of a unified diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffHunk {
+    /// The first line of the hunk in the old content, from 1
+    pub old_start: usize,
+    pub old_len: usize,
+
+    /// The first line of the hunk in the new content, from 1
+    pub new_start: usize,
+    pub new_len: usize,
+
+    pub lines: Vec<(LineTag, String)>,
+}
+
+impl DiffHunk {
+    /// Returns the header of the hunk, like `@@ -1,4 +1,5 @@`
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_start, self.old_len, self.new_start, self.new_len
+        )
+    }
+}
+
+impl Dofigen {
+    /// Returns the fields of the effective structs that differ from the other one,
+    /// in the order of the other struct followed by its removed fields
+    pub fn diff_fields(&self, other: &Dofigen) -> Result<Vec<FieldChange>> {
+        let old = leaves(self)?;
+        let new = leaves(other)?;
+        let old_values = old.iter().cloned().collect::<HashMap<_, _>>();
+        let new_values = new.iter().cloned().collect::<HashMap<_, _>>();
+        let changes = new
+            .into_iter()
+            .filter_map(|(path, value)| match old_values.get(&path) {
+                Some(old) if *old == value => None,
+                old => Some(FieldChange {
+                    old: old.cloned(),
+                    new: Some(value),
+                    path,
+                }),
+            })
+            .chain(
+                old.into_iter()
+                    .filter(|(path, _)| !new_values.contains_key(path))
+                    .map(|(path, value)| FieldChange {
+                        path,
+                        old: Some(value),
+                        new: None,
+                    }),
+            )
+            .collect();
+        Ok(changes)
+    }
+}
+
+/// Returns the scalar values of the effective struct by path, in the order of the effective content
+fn leaves(dofigen: &Dofigen) -> Result<Vec<(Vec<String>, String)>> {
+    let value = serde_yaml::to_value(dofigen).map_err(Error::from)?;
+    let mut leaves = vec![];
+    collect_leaves(&value, &mut vec![], &mut leaves);
+    Ok(leaves)
+}
+
+fn collect_leaves(value: &Value, path: &mut Vec<String>, leaves: &mut Vec<(Vec<String>, String)>) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (key, child) in mapping {
+                path.push(scalar(key));
+                collect_leaves(child, path, leaves);
+                path.pop();
+            }
+        }
+        Value::Sequence(sequence) if !sequence.is_empty() => {
+            for (position, child) in sequence.iter().enumerate() {
+                path.push(position.to_string());
+                collect_leaves(child, path, leaves);
+                path.pop();
+            }
+        }
+        value => leaves.push((path.clone(), scalar(value))),
+    }
+}
+
+/// Formats a scalar on a single line
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::Mapping(_) => "{}".into(),
+        Value::Sequence(_) => "[]".into(),
+        Value::String(string) if string.contains('\n') => format!("{:?}", string),
+        Value::String(string) => string.clone(),
+        value => serde_yaml::to_string(value)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+    }
+}
+
+/// Returns the hunks of the changed lines between two contents, with their longest common subsequence kept unchanged
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffHunk> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+
+    // Length of the longest common subsequence of the ends of the contents
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((LineTag::Equal, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            lines.push((LineTag::Delete, old[i]));
+            i += 1;
+        } else {
+            lines.push((LineTag::Insert, new[j]));
+            j += 1;
+        }
+    }
+
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, (tag, _))| *tag != LineTag::Equal)
+        .map(|(position, _)| position)
+        .collect::<Vec<_>>();
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for position in changed {
+        let start = position.saturating_sub(CONTEXT_LINES);
+        let end = (position + CONTEXT_LINES + 1).min(lines.len());
+        match ranges.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            let before = &lines[..start];
+            let hunk = &lines[start..end];
+            let count = |lines: &[(LineTag, &str)], tag: LineTag| {
+                lines
+                    .iter()
+                    .filter(|(line_tag, _)| *line_tag == LineTag::Equal || *line_tag == tag)
+                    .count()
+            };
+            DiffHunk {
+                old_start: count(before, LineTag::Delete) + 1,
+                old_len: count(hunk, LineTag::Delete),
+                new_start: count(before, LineTag::Insert) + 1,
+                new_len: count(hunk, LineTag::Insert),
+                lines: hunk
+                    .iter()
+                    .map(|(tag, line)| (*tag, line.to_string()))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FromContext, ImageName, ImageVersion, Stage};
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn field_changes() {
+        let old = Dofigen {
+            stage: Stage {
+                from: FromContext::FromImage(ImageName {
+                    path: "alpine".into(),
+                    version: Some(ImageVersion::Tag("3.19".into())),
+                    ..Default::default()
+                }),
+                workdir: Some("/app".into()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let new = Dofigen {
+            stage: Stage {
+                from: FromContext::FromImage(ImageName {
+                    path: "alpine".into(),
+                    version: Some(ImageVersion::Tag("3.20".into())),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            entrypoint: vec!["/app/server".into()],
+            ..Default::default()
+        };
+
+        assert_eq_sorted!(
+            old.diff_fields(&new).unwrap(),
+            vec![
+                FieldChange {
+                    path: vec!["fromImage".into(), "tag".into()],
+                    old: Some("3.19".into()),
+                    new: Some("3.20".into()),
+                },
+                FieldChange {
+                    path: vec!["entrypoint".into(), "0".into()],
+                    old: None,
+                    new: Some("/app/server".into()),
+                },
+                FieldChange {
+                    path: vec!["workdir".into()],
+                    old: Some("/app".into()),
+                    new: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn same_lines() {
+        assert_eq_sorted!(diff_lines("FROM alpine\n", "FROM alpine\n"), vec![]);
+    }
+
+    #[test]
+    fn changed_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+
+        assert_eq_sorted!(
+            diff_lines(old, new),
+            vec![
+                DiffHunk {
+                    old_start: 1,
+                    old_len: 5,
+                    new_start: 1,
+                    new_len: 5,
+                    lines: vec![
+                        (LineTag::Equal, "a".into()),
+                        (LineTag::Delete, "b".into()),
+                        (LineTag::Insert, "B".into()),
+                        (LineTag::Equal, "c".into()),
+                        (LineTag::Equal, "d".into()),
+                        (LineTag::Equal, "e".into()),
+                    ],
+                },
+                DiffHunk {
+                    old_start: 8,
+                    old_len: 3,
+                    new_start: 8,
+                    new_len: 4,
+                    lines: vec![
+                        (LineTag::Equal, "h".into()),
+                        (LineTag::Equal, "i".into()),
+                        (LineTag::Equal, "j".into()),
+                        (LineTag::Insert, "k".into()),
+                    ],
+                },
+            ]
+        );
+    }
+}
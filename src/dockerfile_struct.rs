@@ -49,6 +49,40 @@ impl InstructionOptionOption {
     }
 }
 
+impl DockerfileLine {
+    /// Generates the line content, using the given separator for the line continuations
+    pub fn generate_content_with_separator(&self, separator: &str) -> String {
+        match self {
+            DockerfileLine::Instruction(instruction) => {
+                instruction.generate_content_with_separator(separator)
+            }
+            line => line.generate_content(),
+        }
+    }
+}
+
+impl DockerfileInsctruction {
+    /// Generates the instruction content, using the given separator for the line continuations.
+    /// The heredocs content is kept as is since the escape character doesn't apply to it
+    pub fn generate_content_with_separator(&self, line_separator: &str) -> String {
+        let separator = if !self.options.is_empty() || self.content.contains("\\\n") {
+            line_separator
+        } else {
+            " "
+        };
+        let mut content = vec![self.command.clone()];
+        for option in &self.options {
+            content.push(option.generate_content());
+        }
+        if self.content.starts_with("<<") {
+            content.push(self.content.clone());
+        } else {
+            content.push(self.content.replace(LINE_SEPARATOR, line_separator));
+        }
+        content.join(separator)
+    }
+}
+
 impl DockerfileContent for DockerfileLine {
     fn generate_content(&self) -> String {
         match self {
@@ -65,17 +99,7 @@ impl DockerfileContent for DockerfileLine {
 
 impl DockerfileContent for DockerfileInsctruction {
     fn generate_content(&self) -> String {
-        let separator = if !self.options.is_empty() || self.content.contains("\\\n") {
-            LINE_SEPARATOR
-        } else {
-            " "
-        };
-        let mut content = vec![self.command.clone()];
-        for option in &self.options {
-            content.push(option.generate_content());
-        }
-        content.push(self.content.clone());
-        content.join(separator)
+        self.generate_content_with_separator(LINE_SEPARATOR)
     }
 }
 
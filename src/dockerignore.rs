@@ -0,0 +1,220 @@
+use crate::{Error, Result};
+use regex::Regex;
+use std::str::FromStr;
+
+/// Represents the content of a .dockerignore file
+/// See https://docs.docker.com/build/concepts/context/#dockerignore-files
+#[derive(Debug, Clone, Default)]
+pub struct DockerIgnore {
+    patterns: Vec<DockerIgnorePattern>,
+}
+
+/// Represents a pattern of a .dockerignore file
+#[derive(Debug, Clone)]
+pub struct DockerIgnorePattern {
+    /// The cleaned pattern, relative to the root of the context
+    pub pattern: String,
+    /// The pattern is an exception, prefixed with `!`, that includes back the matching paths
+    pub exception: bool,
+    regex: Regex,
+}
+
+impl DockerIgnore {
+    /// Returns the patterns in the order of the file
+    pub fn patterns(&self) -> &[DockerIgnorePattern] {
+        &self.patterns
+    }
+
+    /// Checks if a path of the context is ignored.
+    /// The last pattern matching the path or one of its parent directories decides
+    pub fn matches(&self, path: &str) -> bool {
+        let path = clean_path(path);
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(&path))
+            .is_some_and(|pattern| !pattern.exception)
+    }
+}
+
+impl DockerIgnorePattern {
+    fn matches(&self, path: &str) -> bool {
+        let mut parent = Some(path);
+        while let Some(path) = parent {
+            if self.regex.is_match(path) {
+                return true;
+            }
+            parent = path.rsplit_once('/').map(|(parent, _)| parent);
+        }
+        false
+    }
+}
+
+impl FromStr for DockerIgnore {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let patterns = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(DockerIgnorePattern::from_str)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { patterns })
+    }
+}
+
+impl FromStr for DockerIgnorePattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (exception, pattern) = match s.strip_prefix('!') {
+            Some(pattern) => (true, pattern.trim()),
+            None => (false, s),
+        };
+        let pattern = clean_path(pattern);
+        let regex = Regex::new(&pattern_regex(&pattern)).map_err(|err| {
+            Error::Custom(format!("Invalid .dockerignore pattern '{}': {}", s, err))
+        })?;
+        Ok(Self {
+            pattern,
+            exception,
+            regex,
+        })
+    }
+}
+
+/// Removes the leading and trailing separators and the current directory parts of a path
+fn clean_path(path: &str) -> String {
+    path.split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Converts a pattern to a regex, like Docker does
+/// See https://github.com/moby/patternmatcher
+fn pattern_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if !in_class => {
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                    }
+                    regex.push_str(if chars.peek().is_none() {
+                        ".*"
+                    } else {
+                        "(.*/)?"
+                    });
+                } else {
+                    regex.push_str("[^/]*");
+                }
+            }
+            '?' if !in_class => regex.push_str("[^/]"),
+            '[' if !in_class => {
+                in_class = true;
+                regex.push('[');
+            }
+            ']' if in_class => {
+                in_class = false;
+                regex.push(']');
+            }
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex::escape(&escaped.to_string()));
+                }
+            }
+            c if in_class => regex.push(c),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    fn parse(content: &str) -> DockerIgnore {
+        content.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_patterns() {
+        let ignore = parse("# comment\n\n  /target/  \n! ./src//main.rs\n");
+
+        assert_eq_sorted!(
+            ignore
+                .patterns()
+                .iter()
+                .map(|pattern| (pattern.pattern.as_str(), pattern.exception))
+                .collect::<Vec<_>>(),
+            vec![("target", false), ("src/main.rs", true)]
+        );
+    }
+
+    #[test]
+    fn simple_patterns() {
+        let ignore = parse("target\n*.md\nsrc/?.rs\n");
+
+        assert!(ignore.matches("target"));
+        assert!(ignore.matches("target/debug/app"));
+        assert!(ignore.matches("README.md"));
+        assert!(!ignore.matches("docs/README.md"));
+        assert!(ignore.matches("src/a.rs"));
+        assert!(!ignore.matches("src/ab.rs"));
+        assert!(!ignore.matches("Cargo.toml"));
+    }
+
+    #[test]
+    fn double_star() {
+        let ignore = parse("**/*.md\nsrc/**/test\n");
+
+        assert!(ignore.matches("README.md"));
+        assert!(ignore.matches("docs/api/README.md"));
+        assert!(ignore.matches("src/test"));
+        assert!(ignore.matches("src/a/b/test/file.rs"));
+        assert!(!ignore.matches("test"));
+    }
+
+    #[test]
+    fn exceptions() {
+        let ignore = parse("*.md\n!README*.md\nREADME-secret.md\n");
+
+        assert!(ignore.matches("CHANGELOG.md"));
+        assert!(!ignore.matches("README.md"));
+        assert!(ignore.matches("README-secret.md"));
+    }
+
+    #[test]
+    fn character_classes() {
+        let ignore = parse("file[0-9].txt\nother[^a].txt\n");
+
+        assert!(ignore.matches("file1.txt"));
+        assert!(!ignore.matches("filea.txt"));
+        assert!(ignore.matches("otherb.txt"));
+        assert!(!ignore.matches("othera.txt"));
+    }
+
+    #[test]
+    fn generated_context() {
+        let ignore = parse("**\n!/src\n/src/*.test.rs\n");
+
+        assert!(ignore.matches("Cargo.toml"));
+        assert!(!ignore.matches("src/main.rs"));
+        assert!(!ignore.matches("./src/lib/mod.rs"));
+        assert!(ignore.matches("src/main.test.rs"));
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!("file[a-".parse::<DockerIgnore>().is_err());
+    }
+}
@@ -22,6 +22,28 @@ use url::Url;
     )
 )]
 pub struct Dofigen {
+    /// The Dockerfile syntax, the frontend image used to build it. Defaults to docker/dockerfile:1.11
+    /// See https://docs.docker.com/reference/dockerfile/#syntax
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syntax: Option<String>,
+
+    /// The escape character of the Dockerfile, also used for the line continuations. Defaults to the backslash.
+    /// The backtick is useful on Windows, where the backslash is the path separator
+    /// See https://docs.docker.com/reference/dockerfile/#escape
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub escape: Option<DockerfileEscape>,
+
+    /// The configuration of the build checks run by BuildKit, generated as check directive
+    /// See https://docs.docker.com/reference/build-checks/
+    #[patch(name = "Option<ChecksPatch>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<Checks>,
+
+    /// The levels of the rules of the lint command, by rule identifier, to disable them or change their severity
+    #[patch(name = "HashMapPatch<String, LintLevel>")]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub lint: HashMap<String, LintLevel>,
+
     /// The context of the Docker build
     /// This is used to generate a .dockerignore file
     #[patch(name = "VecPatch<String>")]
@@ -35,7 +57,55 @@ pub struct Dofigen {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub ignore: Vec<String>,
 
-    /// The builder stages of the Dockerfile
+    /// The additional build contexts, by name, to give with the --build-context option of the build.
+    /// Their names can be used as fromContext values
+    /// See https://docs.docker.com/reference/cli/docker/buildx/build/#build-context
+    #[patch(
+        name = "HashMapPatch<String, String>",
+        attribute(serde(rename = "additionalContexts"))
+    )]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub additional_contexts: HashMap<String, String>,
+
+    /// The build args declared before the first stage, by name.
+    /// They can be used in the stages that forward them with forwardArgs
+    /// See https://docs.docker.com/reference/dockerfile/#understand-how-arg-and-from-interact
+    #[patch(
+        name = "HashMapPatch<String, String>",
+        attribute(serde(rename = "globalArg"))
+    )]
+    #[cfg_attr(not(feature = "strict"), patch(attribute(serde(alias = "globalArgs"))))]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub global_arg: HashMap<String, String>,
+
+    /// The platforms the image is built for, given with the --platform option of the build, like linux/amd64.
+    /// The stages then declare the predefined platform args they use, like TARGETARCH
+    /// See https://docs.docker.com/build/building/multi-platform/
+    #[patch(name = "VecPatch<String>")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<String>,
+
+    /// The variables, by name, replacing the ${name} patterns of the string values when the file is parsed.
+    /// They are merged with the ones of the extended files and can be overridden from the command line.
+    /// Use $${name} to keep a ${name} pattern
+    #[patch(name = "HashMapPatch<String, String>")]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, String>,
+
+    /// The values, by variable name, of the variants generated with the --matrix option of the generate command.
+    /// Each combination of the values overrides the variables to generate its own Dockerfile
+    #[patch(name = "HashMapPatch<String, Vec<String>>")]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub matrix: HashMap<String, Vec<String>>,
+
+    /// The users, by name, created in the stages using them.
+    /// Their names can be used as user and chown values and are replaced by their IDs
+    #[patch(name = "HashMapDeepPatch<String, UserDefinitionPatch>")]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub users: HashMap<String, UserDefinition>,
+
+    /// The builder stages of the Dockerfile.
+    /// The names that aren't valid stage names are lowercased and their invalid characters replaced
     #[patch(name = "HashMapDeepPatch<String, StagePatch>")]
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub builders: HashMap<String, Stage>,
@@ -86,6 +156,21 @@ pub struct Dofigen {
     #[patch(name = "Option<HealthcheckPatch>")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub healthcheck: Option<Healthcheck>,
+
+    /// The system call signal sent to the container to exit
+    /// See https://docs.docker.com/reference/dockerfile/#stopsignal
+    #[patch(attribute(serde(rename = "stopSignal")))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<String>,
+
+    /// The order of the metadata instructions, generated after the runtime stage.
+    /// The instructions that are not listed keep the default order after the listed ones
+    #[patch(
+        name = "VecPatch<MetadataInstruction>",
+        attribute(serde(rename = "instructionOrder"))
+    )]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub instruction_order: Vec<MetadataInstruction>,
 }
 
 /// Represents a Dockerfile stage
@@ -109,6 +194,25 @@ pub struct Stage {
     #[patch(name = "FromContextPatch", attribute(serde(flatten, default)))]
     pub from: FromContext,
 
+    /// The build arg defining the base image of the stage, to override it at build time.
+    /// The fromImage is then its default value
+    /// See https://docs.docker.com/reference/dockerfile/#understand-how-arg-and-from-interact
+    #[patch(attribute(serde(rename = "fromArg")))]
+    #[serde(rename = "fromArg", skip_serializing_if = "Option::is_none")]
+    pub from_arg: Option<String>,
+
+    /// The platform of the stage base image, like $BUILDPLATFORM to cross-compile for $TARGETPLATFORM.
+    /// The predefined platform args used by the run instructions are then declared in the stage
+    /// See https://docs.docker.com/reference/dockerfile/#from and https://docs.docker.com/reference/dockerfile/#automatic-platform-args-in-the-global-scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+
+    /// The builder is provided externally, at build time, by its base image or build context.
+    /// Only the FROM instruction is generated for it
+    /// See https://docs.docker.com/reference/cli/docker/buildx/build/#build-context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external: Option<bool>,
+
     /// The user and group of the stage
     /// See https://docs.docker.com/reference/dockerfile/#user
     #[cfg_attr(
@@ -131,6 +235,12 @@ pub struct Stage {
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub arg: HashMap<String, String>,
 
+    /// The global build args, from globalArg or fromArg, declared again in the stage to use their value
+    /// See https://docs.docker.com/reference/dockerfile/#scope
+    #[patch(name = "VecPatch<String>", attribute(serde(rename = "forwardArgs")))]
+    #[serde(rename = "forwardArgs", skip_serializing_if = "Vec::is_empty")]
+    pub forward_args: Vec<String>,
+
     /// The environment variables of the stage
     /// See https://docs.docker.com/reference/dockerfile/#env
     #[patch(name = "HashMapPatch<String, String>")]
@@ -160,6 +270,39 @@ pub struct Stage {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub copy: Vec<CopyResource>,
 
+    /// The paths produced by the stage that can be used by the other stages
+    #[patch(name = "VecPatch<String>")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+
+    /// The builders outputs to copy in the stage
+    #[patch(
+        name = "VecDeepPatch<OutputUse, OutputUsePatch>",
+        attribute(serde(rename = "use"))
+    )]
+    #[serde(rename = "use", skip_serializing_if = "Vec::is_empty")]
+    pub uses: Vec<OutputUse>,
+
+    /// The compiler cache service used by the run instructions of the stage
+    #[patch(
+        name = "Option<CompilerCachePatch>",
+        attribute(serde(rename = "compilerCache"))
+    )]
+    #[serde(rename = "compilerCache", skip_serializing_if = "Option::is_none")]
+    pub compiler_cache: Option<CompilerCache>,
+
+    /// The packages and tools installed by the stage, declared for the software bill of materials
+    #[cfg_attr(
+        feature = "permissive",
+        patch(name = "VecDeepPatch<Package, ParsableStruct<PackagePatch>>")
+    )]
+    #[cfg_attr(
+        not(feature = "permissive"),
+        patch(name = "VecDeepPatch<Package, PackagePatch>")
+    )]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<Package>,
+
     /// The run instructions of the stage as root user
     #[patch(name = "Option<RunPatch>")]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -221,6 +364,36 @@ pub struct Run {
     #[cfg_attr(not(feature = "strict"), patch(attribute(serde(alias = "binds"))))]
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub bind: Vec<Bind>,
+
+    /// The secrets mounted during the run, given with the --secret option of the build
+    /// See https://docs.docker.com/reference/dockerfile/#run---mounttypesecret
+    #[cfg_attr(
+        feature = "permissive",
+        patch(name = "VecDeepPatch<Secret, ParsableStruct<SecretPatch>>")
+    )]
+    #[cfg_attr(
+        not(feature = "permissive"),
+        patch(name = "VecDeepPatch<Secret, SecretPatch>")
+    )]
+    #[cfg_attr(not(feature = "strict"), patch(attribute(serde(alias = "secrets"))))]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub secret: Vec<Secret>,
+
+    /// The network access during the run
+    /// See https://docs.docker.com/reference/dockerfile/#run---network
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<RunNetwork>,
+
+    /// The security mode of the run. The insecure mode needs the security.insecure entitlement and a labs syntax
+    /// See https://docs.docker.com/reference/dockerfile/#run---security
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub security: Option<RunSecurity>,
+
+    /// A value declared as build arg before the run to force it to run again when it changes.
+    /// It can be a fixed value or a period, like `auto: daily`, to run it again once per period
+    #[patch(attribute(serde(rename = "cacheBust")))]
+    #[serde(rename = "cacheBust", skip_serializing_if = "Option::is_none")]
+    pub cache_bust: Option<CacheBust>,
 }
 
 /// Represents a cache definition during a run
@@ -318,6 +491,164 @@ pub struct Bind {
     pub readwrite: Option<bool>,
 }
 
+/// Represents a secret mounted during a run
+/// See https://docs.docker.com/reference/dockerfile/#run---mounttypesecret
+#[derive(Serialize, Debug, Clone, PartialEq, Default, Patch)]
+#[patch(
+    attribute(derive(Deserialize, Debug, Clone, PartialEq, Default)),
+    attribute(serde(default))
+)]
+#[cfg_attr(
+    feature = "json_schema",
+    patch(
+        attribute(derive(JsonSchema)),
+        attribute(schemars(title = "Secret", rename = "Secret"))
+    )
+)]
+pub struct Secret {
+    /// The id of the secret given to the build
+    pub id: String,
+
+    /// The target path of the secret file. Defaults to /run/secrets/<id>
+    #[cfg_attr(
+        not(feature = "strict"),
+        patch(attribute(serde(alias = "dst", alias = "destination")))
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+
+    /// The environment variable receiving the secret value instead of a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub env: Option<String>,
+
+    /// Makes the run fail when the secret isn't given to the build
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required: Option<bool>,
+}
+
+/// Represents a package or tool installed by a stage, declared for the software bill of materials
+/// See https://cyclonedx.org/docs/1.5/json/#components
+#[derive(Serialize, Debug, Clone, PartialEq, Default, Patch)]
+#[patch(
+    attribute(derive(Deserialize, Debug, Clone, PartialEq, Default)),
+    attribute(serde(deny_unknown_fields, default))
+)]
+#[cfg_attr(
+    feature = "json_schema",
+    patch(
+        attribute(derive(JsonSchema)),
+        attribute(schemars(title = "Package", rename = "Package"))
+    )
+)]
+pub struct Package {
+    /// The name of the package
+    pub name: String,
+
+    /// The installed version of the package
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// The SPDX license expression of the package, like MIT or Apache-2.0
+    #[cfg_attr(not(feature = "strict"), patch(attribute(serde(alias = "licence"))))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+
+    /// The package URL identifying the package, like pkg:deb/debian/curl@7.88.1
+    /// See https://github.com/package-url/purl-spec
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+}
+
+/// Represents the use of a builder output in a stage
+#[derive(Serialize, Debug, Clone, PartialEq, Default, Patch)]
+#[patch(
+    attribute(derive(Deserialize, Debug, Clone, PartialEq, Default)),
+    attribute(serde(deny_unknown_fields, default))
+)]
+#[cfg_attr(
+    feature = "json_schema",
+    patch(
+        attribute(derive(JsonSchema)),
+        attribute(schemars(title = "OutputUse", rename = "OutputUse"))
+    )
+)]
+pub struct OutputUse {
+    /// The builder that declares the output
+    pub builder: String,
+
+    /// The position of the output in the builder outputs
+    pub output: usize,
+
+    /// The target path of the output in the stage. Defaults to the output path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+/// Represents a compiler cache service shared between the builds
+#[derive(Serialize, Debug, Clone, PartialEq, Default, Patch)]
+#[patch(
+    attribute(derive(Deserialize, Debug, Clone, PartialEq, Default)),
+    attribute(serde(deny_unknown_fields, default))
+)]
+#[cfg_attr(
+    feature = "json_schema",
+    patch(
+        attribute(derive(JsonSchema)),
+        attribute(schemars(title = "CompilerCache", rename = "CompilerCache"))
+    )
+)]
+pub struct CompilerCache {
+    /// The compiler cache tool
+    #[patch(attribute(serde(rename = "type")))]
+    #[serde(rename = "type")]
+    pub kind: CompilerCacheType,
+
+    /// The S3 bucket storing the cache
+    pub bucket: String,
+
+    /// The region of the bucket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+
+    /// The endpoint of an S3 compatible storage
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    /// The version of the tool to install. Defaults to the latest one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// Represents the configuration of the build checks
+/// See https://docs.docker.com/build/checks/#configure-checks
+#[derive(Serialize, Debug, Clone, PartialEq, Default, Patch)]
+#[patch(
+    attribute(derive(Deserialize, Debug, Clone, PartialEq, Default)),
+    attribute(serde(deny_unknown_fields, default))
+)]
+#[cfg_attr(
+    feature = "json_schema",
+    patch(
+        attribute(derive(JsonSchema)),
+        attribute(schemars(title = "Checks", rename = "Checks"))
+    )
+)]
+pub struct Checks {
+    /// The checks to skip, like JSONArgsRecommended, or all to skip them all
+    #[patch(name = "VecPatch<String>")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skip: Vec<String>,
+
+    /// The experimental checks to enable, or all to enable them all
+    #[patch(name = "VecPatch<String>")]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub experimental: Vec<String>,
+
+    /// Makes the build fail when a check finds a problem
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<bool>,
+}
+
 /// Represents the Dockerfile healthcheck instruction
 /// See https://docs.docker.com/reference/dockerfile/#healthcheck
 #[derive(Serialize, Debug, Clone, PartialEq, Default, Patch)]
@@ -348,6 +679,11 @@ pub struct Healthcheck {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub start: Option<String>,
 
+    /// The interval between two tests during the start period, needs Docker Engine 25 or later
+    #[patch(attribute(serde(rename = "startInterval")))]
+    #[serde(rename = "startInterval", skip_serializing_if = "Option::is_none")]
+    pub start_interval: Option<String>,
+
     /// The number of retries
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retries: Option<u16>,
@@ -517,7 +853,7 @@ pub struct Add {
     #[patch(name = "CopyOptionsPatch", attribute(serde(flatten)))]
     pub options: CopyOptions,
 
-    /// The checksum of the files
+    /// The checksum of the files, prefixed by its algorithm: sha256, sha512 or blake3
     /// See https://docs.docker.com/reference/dockerfile/#add---checksum
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checksum: Option<String>,
@@ -585,14 +921,55 @@ pub struct CopyOptions {
 pub struct User {
     /// The user name or ID
     /// The ID is preferred
+    #[cfg_attr(
+        feature = "permissive",
+        patch(attribute(serde(deserialize_with = "deserialize_from_string_or_number", default)))
+    )]
     pub user: String,
 
     /// The group name or ID
     /// The ID is preferred
+    #[cfg_attr(
+        feature = "permissive",
+        patch(attribute(serde(
+            deserialize_with = "deserialize_from_optional_string_or_number",
+            default
+        )))
+    )]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub group: Option<String>,
 }
 
+/// Represents a user created in the stages using it
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Patch)]
+#[patch(
+    attribute(derive(Deserialize, Debug, Clone, PartialEq, Default)),
+    attribute(serde(deny_unknown_fields, default))
+)]
+#[cfg_attr(
+    feature = "json_schema",
+    patch(
+        attribute(derive(JsonSchema)),
+        attribute(schemars(title = "UserDefinition", rename = "UserDefinition"))
+    )
+)]
+pub struct UserDefinition {
+    /// The user ID
+    pub uid: u16,
+
+    /// The ID of the group of the user, named as the user. Defaults to the user ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u16>,
+
+    /// The home directory of the user. Defaults to /home/<name>
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub home: Option<String>,
+
+    /// The login shell of the user. Defaults to /sbin/nologin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<String>,
+}
+
 /// Represents a port definition
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Patch)]
 #[patch(
@@ -607,9 +984,24 @@ pub struct User {
     )
 )]
 pub struct Port {
-    /// The port number
+    /// The port number, the first one of a range
+    #[cfg_attr(
+        feature = "permissive",
+        patch(attribute(serde(deserialize_with = "deserialize_from_number_or_string", default)))
+    )]
     pub port: u16,
 
+    /// The last port number of a range, like 8010 for `8000-8010`
+    #[cfg_attr(
+        feature = "permissive",
+        patch(attribute(serde(
+            deserialize_with = "deserialize_from_optional_number_or_string",
+            default
+        )))
+    )]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<u16>,
+
     /// The protocol of the port
     #[serde(skip_serializing_if = "Option::is_none")]
     pub protocol: Option<PortProtocol>,
@@ -654,6 +1046,91 @@ pub enum CacheSharing {
     Locked,
 }
 
+/// Represents the network access of a run
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum RunNetwork {
+    Default,
+    None,
+    Host,
+}
+
+/// Represents the security mode of a run
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum RunSecurity {
+    Sandbox,
+    Insecure,
+}
+
+/// Represents the value forcing a run to run again
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum CacheBust {
+    /// A value changing on the given period
+    Auto { auto: CacheBustPeriod },
+    /// A fixed value to change manually
+    Value(String),
+}
+
+/// Represents the period after which a run runs again
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum CacheBustPeriod {
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// Represents the level of a lint rule
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum LintLevel {
+    Off,
+    Warn,
+    Error,
+}
+
+/// Represents the escape character of a Dockerfile
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum DockerfileEscape {
+    #[default]
+    #[serde(rename = "\\")]
+    Backslash,
+    #[serde(rename = "`")]
+    Backtick,
+}
+
+/// Represents a compiler cache tool
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum CompilerCacheType {
+    /// See https://github.com/mozilla/sccache
+    #[default]
+    Sccache,
+}
+
+/// Represents a metadata instruction of the Dockerfile
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum MetadataInstruction {
+    Volume,
+    Expose,
+    Healthcheck,
+    StopSignal,
+    Entrypoint,
+    Cmd,
+}
+
 /// Represents a port protocol
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
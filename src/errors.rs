@@ -9,6 +9,16 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub enum Error {
     #[error("Error while deserializing the document{loc}: {0}", loc = location_into(.0.location()))]
     Deserialize(#[from] serde_yaml::Error),
+    #[error("{}", syntax_report(.resource, *.line, *.column, .message, .snippet))]
+    Syntax {
+        /// The file or URL of the document, if any
+        resource: Option<String>,
+        line: usize,
+        column: usize,
+        message: String,
+        /// The lines of the document around the error, with a marker at the column
+        snippet: String,
+    },
     #[error("Error while parsing: {0}")]
     ParseFromStr(#[from] serde::de::value::Error),
     #[error("{0}")]
@@ -23,6 +33,56 @@ impl Error {
     pub fn display<S: Display>(error: S) -> Self {
         Self::Custom(format!("{}", error))
     }
+
+    /// Converts a deserialization error of a document into a syntax error pointing to its location
+    pub fn deserialize(error: serde_yaml::Error, content: &str, resource: Option<String>) -> Self {
+        let Some(location) = error.location() else {
+            return Self::Deserialize(error);
+        };
+        let (line, column) = (location.line(), location.column());
+        let message =
+            error
+                .to_string()
+                .replacen(&format!(" at line {} column {}", line, column), "", 1);
+        Self::Syntax {
+            resource,
+            line,
+            column,
+            message,
+            snippet: snippet(content, line, column),
+        }
+    }
+}
+
+/// Renders the line of the error with a marker under its column
+fn snippet(content: &str, line: usize, column: usize) -> String {
+    let Some(text) = content.lines().nth(line.saturating_sub(1)) else {
+        return String::new();
+    };
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{gutter} |\n{line} | {text}\n{gutter} | {marker}^",
+        marker = " ".repeat(column.saturating_sub(1))
+    )
+}
+
+fn syntax_report(
+    resource: &Option<String>,
+    line: usize,
+    column: usize,
+    message: &str,
+    snippet: &str,
+) -> String {
+    let location = match resource {
+        Some(resource) => format!("{}:{}:{}", resource, line, column),
+        None => format!("the document at line {}, column {}", line, column),
+    };
+    let mut report = format!("Error while deserializing {}: {}", location, message);
+    if !snippet.is_empty() {
+        report.push('\n');
+        report.push_str(snippet);
+    }
+    report
 }
 
 fn location_into(location: Option<Location>) -> String {
@@ -39,3 +99,34 @@ fn report(mut err: &dyn std::error::Error) -> String {
     }
     s
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn syntax_error_report() {
+        let content = "fromImage: alpine\nrun:\n  - echo\n - ls\n";
+        let error = serde_yaml::from_str::<serde_yaml::Value>(content).unwrap_err();
+
+        let error = Error::deserialize(error, content, Some("dofigen.yml".into()));
+
+        assert_eq_sorted!(
+            error.to_string(),
+            "Error while deserializing dofigen.yml:4:2: did not find expected key, while parsing a block mapping\n  |\n4 |  - ls\n  |  ^"
+        );
+    }
+
+    #[test]
+    fn syntax_error_without_resource() {
+        let error = crate::DofigenContext::new()
+            .parse_from_string("fromImage: alpine\nrun:\n  - echo\n - ls\n")
+            .unwrap_err();
+
+        assert_eq_sorted!(
+            error.to_string(),
+            "Error while deserializing the document at line 4, column 2: did not find expected key, while parsing a block mapping\n  |\n4 |  - ls\n  |  ^"
+        );
+    }
+}
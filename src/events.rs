@@ -0,0 +1,109 @@
+use crate::{lock::DockerTag, ImageName, LintMessage, Resource};
+use std::{fmt, path::Path, sync::Arc};
+
+/// Observes the progress of a Dofigen context, for the tools embedding the library like editors or servers.
+/// All the methods do nothing by default
+pub trait ContextListener: Send + Sync {
+    /// Called when the content of a file, URL or preset resource is loaded, not when it's served by the lock file
+    fn on_resource_loaded(&self, _resource: &Resource) {}
+
+    /// Called when the locked tag of an image is known, loaded from its registry or from the lock file
+    fn on_image_resolved(&self, _image: &ImageName, _tag: &DockerTag) {}
+
+    /// Called for each message raised while parsing or checking a Dofigen struct
+    fn on_lint_message(&self, _message: &LintMessage) {}
+
+    /// Called when a file is written for the context, like a vendored resource or a generated file
+    fn on_file_written(&self, _path: &Path) {}
+}
+
+/// The listeners of a context, shared with its clones
+#[derive(Clone, Default)]
+pub(crate) struct Listeners(Vec<Arc<dyn ContextListener>>);
+
+impl Listeners {
+    pub(crate) fn push(&mut self, listener: Arc<dyn ContextListener>) {
+        self.0.push(listener);
+    }
+
+    pub(crate) fn each<F: Fn(&dyn ContextListener)>(&self, notify: F) {
+        self.0.iter().for_each(|listener| notify(listener.as_ref()));
+    }
+}
+
+impl fmt::Debug for Listeners {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Listeners({})", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{lock::Lock, DofigenContext, ImageVersion, Result};
+    use pretty_assertions_sorted::assert_eq_sorted;
+    use std::{collections::HashMap, sync::Mutex};
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl ContextListener for Recorder {
+        fn on_resource_loaded(&self, resource: &Resource) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("resource {}", resource.to_string()));
+        }
+
+        fn on_image_resolved(&self, image: &ImageName, tag: &DockerTag) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("image {} {}", image.to_string(), tag.digest));
+        }
+
+        fn on_lint_message(&self, message: &LintMessage) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("lint {}", message.path.join(".")));
+        }
+    }
+
+    #[test]
+    fn context_events() -> Result<()> {
+        let image = ImageName {
+            path: "ubuntu".into(),
+            version: Some(ImageVersion::Tag("24.04".into())),
+            ..Default::default()
+        };
+        let mut context = DofigenContext::from(
+            HashMap::new(),
+            HashMap::from([(
+                image.fill(),
+                DockerTag {
+                    digest: "sha256:abc".into(),
+                },
+            )]),
+        );
+        let recorder = Arc::new(Recorder::default());
+        context.add_listener(recorder.clone());
+
+        context.parse_from_resource("tests/cases/simple.yml".parse()?)?;
+        context
+            .parse_from_string("fromImage:\n  path: ubuntu\n  tag: '24.04'\nenvs:\n  A: b\n")?
+            .lock(&mut context)?;
+
+        let mut expected = vec![
+            "resource tests/cases/simple.yml".to_string(),
+            "image registry.hub.docker.com:443/ubuntu:24.04 sha256:abc".to_string(),
+        ];
+        // The deprecated fields are only reported when they are accepted
+        #[cfg(not(feature = "strict"))]
+        expected.insert(1, "lint envs".to_string());
+        assert_eq_sorted!(recorder.events.lock().unwrap().clone(), expected);
+        Ok(())
+    }
+}
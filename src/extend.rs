@@ -1,13 +1,23 @@
 #[cfg(feature = "permissive")]
 use crate::OneOrMany;
-use crate::{dofigen_struct::*, DofigenContext, Error, Result};
+use crate::{
+    dofigen_struct::*,
+    variables::{scalar_string, Interpolator},
+    DofigenContext, Error, Result,
+};
 use relative_path::RelativePath;
 #[cfg(feature = "json_schema")]
 use schemars::JsonSchema;
 use serde::{de::DeserializeOwned, Deserialize};
-use std::iter;
+use serde_yaml::{Mapping, Value};
+use std::{collections::HashMap, fmt, iter};
 use struct_patch::Merge;
 
+const PARAMS_KEY: &str = "params";
+const TEMPLATES_KEY: &str = "templates";
+const BUILDERS_KEY: &str = "builders";
+const USES_KEY: &str = "uses";
+
 #[cfg(feature = "permissive")]
 type VecType<T> = OneOrMany<T>;
 
@@ -24,11 +34,142 @@ type VecType<T> = Vec<T>;
 )]
 pub struct Extend<T: Default + Merge> {
     #[serde(alias = "extends")]
-    pub extend: VecType<Resource>,
+    pub extend: VecType<ExtendResource>,
+
+    /// The parameters, by name, of the layer given by the files extending it.
+    /// Their `${name}` patterns are replaced in the layer before merging it
+    pub params: HashMap<String, ExtendParam>,
+
+    /// The stage templates, by name, that the builders of the file can instantiate with `uses`.
+    /// They are expanded in the builders before merging the layer
+    pub templates: HashMap<String, StageTemplate>,
 
     // Can't use #[serde(flatten)] because of nested flattening is not managed by serde
     #[serde(flatten)]
     pub value: T,
+
+    /// The patches, by profile name, applied to the layer when their profile is activated
+    pub profiles: HashMap<String, T>,
+}
+
+/// A file extended by a layer, with the values of the parameters it declares
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum ExtendResource {
+    Resource(Resource),
+    WithParams {
+        path: Resource,
+        /// The values of the parameters, by name
+        #[serde(default)]
+        #[cfg_attr(
+            feature = "json_schema",
+            schemars(with = "HashMap<String, serde_json::Value>")
+        )]
+        with: HashMap<String, Value>,
+    },
+}
+
+/// A parameter declared by an extended layer
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub struct ExtendParam {
+    /// The type of the value. Defaults to string
+    #[serde(rename = "type", default)]
+    pub param_type: ExtendParamType,
+
+    /// The files extending the layer must give the value
+    #[serde(default)]
+    pub required: bool,
+
+    /// The value used when the extending file doesn't give one
+    #[cfg_attr(feature = "json_schema", schemars(with = "Option<serde_json::Value>"))]
+    pub default: Option<Value>,
+}
+
+/// The type of the value of a parameter
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub enum ExtendParamType {
+    #[default]
+    String,
+    Int,
+    Bool,
+}
+
+impl fmt::Display for ExtendParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExtendParamType::String => "a string",
+            ExtendParamType::Int => "an int",
+            ExtendParamType::Bool => "a bool",
+        })
+    }
+}
+
+impl ExtendParamType {
+    /// Converts the value to the type, the scalars being accepted as strings
+    fn convert(&self, value: Value) -> Option<Value> {
+        match (self, value) {
+            (ExtendParamType::String, Value::String(string)) => Some(Value::String(string)),
+            (ExtendParamType::String, value @ (Value::Number(_) | Value::Bool(_))) => {
+                Some(Value::String(scalar_string(&value)))
+            }
+            (ExtendParamType::Int, Value::Number(number)) if number.is_i64() || number.is_u64() => {
+                Some(Value::Number(number))
+            }
+            (ExtendParamType::Int, Value::String(string)) => {
+                string.parse::<i64>().ok().map(Value::from)
+            }
+            (ExtendParamType::Bool, Value::Bool(boolean)) => Some(Value::Bool(boolean)),
+            (ExtendParamType::Bool, Value::String(string)) => {
+                string.parse::<bool>().ok().map(Value::Bool)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A stage template with parameters, instantiated by the builders of its file
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[cfg_attr(feature = "json_schema", derive(JsonSchema))]
+pub struct StageTemplate {
+    /// The parameters, by name, given by the builders using the template.
+    /// Their `${name}` patterns are replaced in the template fields
+    #[serde(default)]
+    pub params: HashMap<String, ExtendParam>,
+
+    /// The fields of the stage, overridden by the ones of the builders using the template
+    #[serde(flatten)]
+    #[cfg_attr(feature = "json_schema", schemars(with = "StagePatch"))]
+    pub stage: Mapping,
+}
+
+/// The template instantiated by a builder, with the values of its parameters
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateUse {
+    template: String,
+    #[serde(default)]
+    with: HashMap<String, Value>,
+}
+
+impl ExtendResource {
+    pub fn resource(&self) -> &Resource {
+        match self {
+            ExtendResource::Resource(resource) => resource,
+            ExtendResource::WithParams { path, .. } => path,
+        }
+    }
+
+    fn params(&self) -> HashMap<String, Value> {
+        match self {
+            ExtendResource::Resource(_) => HashMap::new(),
+            ExtendResource::WithParams { with, .. } => with.clone(),
+        }
+    }
 }
 
 impl<P> Extend<P>
@@ -36,25 +177,42 @@ where
     P: Default + DeserializeOwned + Clone + Merge,
 {
     pub fn merge(&self, context: &mut DofigenContext) -> Result<P> {
-        if self.extend.is_empty() {
-            return Ok(self.value.clone().into());
-        }
+        let merged = if self.extend.is_empty() {
+            self.value.clone()
+        } else {
+            // load extends files
+            self.extend
+                .iter()
+                .map(|extend| {
+                    let ret = extend
+                        .resource()
+                        .load_layer::<Self>(context, true, &extend.params())?
+                        .merge(context)?;
+                    context.pop_resource_stack();
+                    Ok(ret)
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .chain(iter::once(self.value.clone()))
+                .reduce(|a, b| a.merge(b))
+                .expect("Since we have at least one value, we should have a merged value")
+        };
 
-        // load extends files
-        let merged: Option<P> = self
-            .extend
+        // The default and activated profiles patch the layer in their activation order
+        context
+            .declared_profiles
+            .extend(self.profiles.keys().cloned());
+        let mut profiles = self.profiles.clone();
+        let merged = context
+            .default_profiles
             .iter()
-            .map(|extend| {
-                let ret = extend.load::<Self>(context)?.merge(context)?;
-                context.pop_resource_stack();
-                Ok(ret)
-            })
-            .collect::<Result<Vec<_>>>()?
-            .into_iter()
-            .chain(iter::once(self.value.clone()))
-            .reduce(|a, b| a.merge(b));
-
-        Ok(merged.expect("Since we have at least one value, we should have a merged value"))
+            .chain(context.profiles.iter())
+            .filter_map(|profile| profiles.remove(profile))
+            .fold(merged, |merged, patch| merged.merge(patch));
+
+        // The layer fields override the ones of its extends
+        context.origins.commit_layer();
+        Ok(merged)
     }
 }
 
@@ -100,17 +258,189 @@ impl Resource {
     where
         T: DeserializeOwned,
     {
-        Ok(
-            serde_yaml::from_str(self.load_resource_content(context)?.as_str()).map_err(|err| {
+        let content = self.load_resource_content(context)?;
+        self.parse_content(&content)
+    }
+
+    /// Loads a layer of the extend chain, tracking the origin of its fields.
+    /// The fields of the main document are not attributed to its resource
+    /// The given values of its parameters are replaced in the layer before parsing it
+    pub(crate) fn load_layer<T>(
+        &self,
+        context: &mut DofigenContext,
+        extended: bool,
+        params: &HashMap<String, Value>,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let content = self.load_resource_content(context)?;
+        let resource = if extended {
+            context.current_resource().cloned()
+        } else {
+            None
+        };
+        context.origins.push_layer(resource, &content);
+        self.parse_layer(&content, params, context)
+    }
+
+    fn parse_layer<T>(
+        &self,
+        content: &str,
+        params: &HashMap<String, Value>,
+        context: &mut DofigenContext,
+    ) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let mut document: Value = serde_yaml::from_str(content)
+            .map_err(|err| Error::deserialize(err, content, Some(self.to_string())))?;
+        context.check_deprecations(&document);
+        let declared = match document.as_mapping_mut() {
+            Some(mapping) => mapping.remove(PARAMS_KEY),
+            None => None,
+        };
+        if declared.is_none() && params.is_empty() && !has_templates(&document) {
+            return self.parse_content(content);
+        }
+        let declarations: HashMap<String, ExtendParam> = match declared.clone() {
+            Some(declared) => serde_yaml::from_value(declared).map_err(|err| {
                 Error::Custom(format!(
-                    "Could not deserialize resource {:?}: {}",
-                    self, err
+                    "Invalid parameters in {}: {}",
+                    self.to_string(),
+                    err
                 ))
             })?,
-        )
+            None => HashMap::new(),
+        };
+
+        let interpolator = Interpolator::new(self.param_values(declarations, params)?);
+        let mut document = interpolator.value(document);
+        if let (Some(mapping), Some(declared)) = (document.as_mapping_mut(), declared) {
+            mapping.insert(PARAMS_KEY.into(), declared);
+        }
+        expand_templates(&mut document)?;
+        serde_yaml::from_value(document).map_err(|err| {
+            Error::Custom(format!(
+                "Error while deserializing {}: {}",
+                self.to_string(),
+                err
+            ))
+        })
+    }
+
+    /// Returns the typed values of the parameters declared by the layer
+    fn param_values(
+        &self,
+        declared: HashMap<String, ExtendParam>,
+        params: &HashMap<String, Value>,
+    ) -> Result<HashMap<String, Value>> {
+        param_values(&self.to_string(), declared, params)
+    }
+
+    fn parse_content<T>(&self, content: &str) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        serde_yaml::from_str(content)
+            .map_err(|err| Error::deserialize(err, content, Some(self.to_string())))
     }
 }
 
+/// Returns the typed values of the parameters declared by the owner, a layer or a template
+fn param_values(
+    owner: &str,
+    declared: HashMap<String, ExtendParam>,
+    params: &HashMap<String, Value>,
+) -> Result<HashMap<String, Value>> {
+    let mut names = params.keys().collect::<Vec<_>>();
+    names.sort();
+    if let Some(name) = names.iter().find(|name| !declared.contains_key(**name)) {
+        return Err(Error::Custom(format!(
+            "The parameter '{}' isn't declared by {}",
+            name, owner
+        )));
+    }
+
+    let mut values = HashMap::new();
+    for (name, param) in declared {
+        let Some(value) = params.get(&name).cloned().or(param.default) else {
+            if param.required {
+                return Err(Error::Custom(format!(
+                    "The parameter '{}' of {} is required",
+                    name, owner
+                )));
+            }
+            continue;
+        };
+        let value = param.param_type.convert(value).ok_or_else(|| {
+            Error::Custom(format!(
+                "The parameter '{}' of {} must be {}",
+                name, owner, param.param_type
+            ))
+        })?;
+        values.insert(name, value);
+    }
+    Ok(values)
+}
+
+/// Returns whether the document declares stage templates
+pub(crate) fn has_templates(document: &Value) -> bool {
+    document
+        .as_mapping()
+        .is_some_and(|mapping| mapping.contains_key(TEMPLATES_KEY))
+}
+
+/// Replaces the builders using a template of the document by the template fields, with the values of its parameters.
+/// The fields of the builder override the ones of the template
+pub(crate) fn expand_templates(document: &mut Value) -> Result<()> {
+    let Some(mapping) = document.as_mapping_mut() else {
+        return Ok(());
+    };
+    let templates: HashMap<String, StageTemplate> = match mapping.get(TEMPLATES_KEY) {
+        Some(templates) => serde_yaml::from_value(templates.clone())
+            .map_err(|err| Error::Custom(format!("Invalid stage templates: {}", err)))?,
+        None => return Ok(()),
+    };
+    let Some(builders) = mapping
+        .get_mut(BUILDERS_KEY)
+        .and_then(Value::as_mapping_mut)
+    else {
+        return Ok(());
+    };
+
+    for (name, builder) in builders.iter_mut() {
+        let Some(builder) = builder.as_mapping_mut() else {
+            continue;
+        };
+        let Some(template_use) = builder.remove(USES_KEY) else {
+            continue;
+        };
+        let name = scalar_string(name);
+        let template_use: TemplateUse = serde_yaml::from_value(template_use).map_err(|err| {
+            Error::Custom(format!("Invalid uses of the builder '{}': {}", name, err))
+        })?;
+        let template = templates.get(&template_use.template).ok_or_else(|| {
+            Error::Custom(format!(
+                "The template '{}' used by the builder '{}' isn't declared",
+                template_use.template, name
+            ))
+        })?;
+        let interpolator = Interpolator::new(param_values(
+            &format!("the template '{}'", template_use.template),
+            template.params.clone(),
+            &template_use.with,
+        )?);
+        let Value::Mapping(mut stage) = interpolator.value(Value::Mapping(template.stage.clone()))
+        else {
+            unreachable!("The interpolation of a mapping is a mapping");
+        };
+        stage.extend(std::mem::take(builder));
+        *builder = stage;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -263,4 +593,98 @@ fromImage:
             }
         }
     }
+
+    mod params {
+        use super::*;
+
+        fn declared() -> HashMap<String, ExtendParam> {
+            HashMap::from([
+                (
+                    "port".to_string(),
+                    ExtendParam {
+                        param_type: ExtendParamType::Int,
+                        required: true,
+                        default: None,
+                    },
+                ),
+                (
+                    "image".to_string(),
+                    ExtendParam {
+                        default: Some(Value::from("nginx")),
+                        ..Default::default()
+                    },
+                ),
+            ])
+        }
+
+        #[test]
+        fn typed_values() {
+            let values = Resource::File("base.yml".into())
+                .param_values(
+                    declared(),
+                    &HashMap::from([("port".to_string(), Value::from("8080"))]),
+                )
+                .unwrap();
+
+            assert_eq_sorted!(
+                values,
+                HashMap::from([
+                    ("port".to_string(), Value::from(8080)),
+                    ("image".to_string(), Value::from("nginx")),
+                ])
+            );
+        }
+
+        #[test]
+        fn invalid_values() {
+            let resource = Resource::File("base.yml".into());
+            let error = |params: HashMap<String, Value>| {
+                resource
+                    .param_values(declared(), &params)
+                    .unwrap_err()
+                    .to_string()
+            };
+
+            assert_eq_sorted!(
+                error(HashMap::new()),
+                "The parameter 'port' of base.yml is required"
+            );
+            assert_eq_sorted!(
+                error(HashMap::from([("port".to_string(), Value::from(true))])),
+                "The parameter 'port' of base.yml must be an int"
+            );
+            assert_eq_sorted!(
+                error(HashMap::from([
+                    ("port".to_string(), Value::from(80)),
+                    ("host".to_string(), Value::from("localhost")),
+                ])),
+                "The parameter 'host' isn't declared by base.yml"
+            );
+        }
+    }
+
+    mod templates {
+        use super::*;
+
+        #[test]
+        fn undeclared_template() {
+            let mut document: Value = serde_yaml::from_str(
+                r#"
+templates:
+  base:
+    fromImage: alpine
+builders:
+  api:
+    uses:
+      template: go-build
+"#,
+            )
+            .unwrap();
+
+            assert_eq_sorted!(
+                expand_templates(&mut document).unwrap_err().to_string(),
+                "The template 'go-build' used by the builder 'api' isn't declared"
+            );
+        }
+    }
 }
@@ -0,0 +1,284 @@
+use crate::{lock::DockerTag, Error, ResolvedImage, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// The network lookups of a context, served from or recorded to a fixtures file.
+/// This makes the registry and URL lookups deterministic in tests.
+/// The clones share their content, so the lookups recorded by parallel generations are all saved
+#[derive(Debug, Clone)]
+pub struct NetworkFixtures {
+    path: PathBuf,
+    mode: FixturesMode,
+    content: Arc<Mutex<FixturesContent>>,
+}
+
+/// How the network fixtures are used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixturesMode {
+    /// The lookups are served from the fixtures file, without any network access
+    Replay,
+    /// The lookups are done over the network and saved in the fixtures file
+    Record,
+}
+
+/// The content of a fixtures file
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FixturesContent {
+    /// The content of the URL resources, by URL
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub resources: BTreeMap<String, String>,
+    /// The tags of the images, by image name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub image_tags: BTreeMap<String, DockerTag>,
+    /// The resolved images, by image name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub resolved_images: BTreeMap<String, ResolvedImage>,
+    /// The checksums of the ADD files, by URL
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+}
+
+impl NetworkFixtures {
+    /// Serves the lookups from the given fixtures file
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = fs::read_to_string(&path).map_err(|err| {
+            Error::Custom(format!("Could not read fixtures file {:?}: {}", path, err))
+        })?;
+        let content = serde_yaml::from_str(&content).map_err(Error::Deserialize)?;
+        Ok(Self {
+            path,
+            mode: FixturesMode::Replay,
+            content: Arc::new(Mutex::new(content)),
+        })
+    }
+
+    /// Records the lookups in the given fixtures file, keeping its previous entries
+    pub fn record<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = if path.exists() {
+            Self::replay(&path)?.content
+        } else {
+            Default::default()
+        };
+        Ok(Self {
+            path,
+            mode: FixturesMode::Record,
+            content,
+        })
+    }
+
+    pub fn mode(&self) -> FixturesMode {
+        self.mode
+    }
+
+    pub fn content(&self) -> FixturesContent {
+        self.lock().clone()
+    }
+
+    pub(crate) fn resource<F>(&self, url: &str, load: F) -> Result<String>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        self.lookup("URL resource", url, |content| &mut content.resources, load)
+    }
+
+    pub(crate) fn image_tag<F>(&self, image: &str, load: F) -> Result<DockerTag>
+    where
+        F: FnOnce() -> Result<DockerTag>,
+    {
+        self.lookup("image tag", image, |content| &mut content.image_tags, load)
+    }
+
+    pub(crate) fn resolved_image<F>(&self, image: &str, load: F) -> Result<ResolvedImage>
+    where
+        F: FnOnce() -> Result<ResolvedImage>,
+    {
+        self.lookup(
+            "resolved image",
+            image,
+            |content| &mut content.resolved_images,
+            load,
+        )
+    }
+
+    pub(crate) fn checksum<F>(&self, url: &str, load: F) -> Result<String>
+    where
+        F: FnOnce() -> Result<String>,
+    {
+        self.lookup("ADD checksum", url, |content| &mut content.checksums, load)
+    }
+
+    fn lookup<T, E, F>(&self, kind: &str, key: &str, entries: E, load: F) -> Result<T>
+    where
+        T: Clone,
+        E: FnOnce(&mut FixturesContent) -> &mut BTreeMap<String, T>,
+        F: FnOnce() -> Result<T>,
+    {
+        match self.mode {
+            FixturesMode::Replay => entries(&mut self.lock()).get(key).cloned().ok_or_else(|| {
+                Error::Custom(format!(
+                    "No {} fixture recorded for '{}' in {:?}",
+                    kind, key, self.path
+                ))
+            }),
+            FixturesMode::Record => {
+                let value = load()?;
+                let mut content = self.lock();
+                entries(&mut content).insert(key.to_string(), value.clone());
+                self.save(&content)?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// A panicking lookup can't leave the content half updated, so a poisoned lock is still usable
+    fn lock(&self) -> MutexGuard<'_, FixturesContent> {
+        self.content
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn save(&self, content: &FixturesContent) -> Result<()> {
+        let content = serde_yaml::to_string(content).map_err(Error::display)?;
+        fs::write(&self.path, content).map_err(|err| {
+            Error::Custom(format!(
+                "Could not write fixtures file {:?}: {}",
+                self.path, err
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ImageName;
+    use assert_fs::TempDir;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    fn tag(digest: &str) -> DockerTag {
+        DockerTag {
+            digest: digest.into(),
+        }
+    }
+
+    #[test]
+    fn record_then_replay() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("fixtures.yml");
+
+        let fixtures = NetworkFixtures::record(&path).unwrap();
+        let recorded = fixtures
+            .image_tag("docker.io/library/ubuntu:latest", || Ok(tag("sha256:abc")))
+            .unwrap();
+        assert_eq_sorted!(recorded, tag("sha256:abc"));
+
+        let fixtures = NetworkFixtures::replay(&path).unwrap();
+        let replayed = fixtures
+            .image_tag("docker.io/library/ubuntu:latest", || {
+                panic!("The replay mode must not load")
+            })
+            .unwrap();
+        assert_eq_sorted!(replayed, tag("sha256:abc"));
+    }
+
+    #[test]
+    fn record_then_replay_resolved_image() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("fixtures.yml");
+        let resolved = ResolvedImage {
+            image: ImageName {
+                path: "ubuntu".into(),
+                ..Default::default()
+            }
+            .fill(),
+            digest: "sha256:abc".into(),
+            labels: BTreeMap::from([("version".into(), "24.04".into())]),
+            ..Default::default()
+        };
+
+        NetworkFixtures::record(&path)
+            .unwrap()
+            .resolved_image("docker.io/library/ubuntu:latest", || Ok(resolved.clone()))
+            .unwrap();
+        let replayed = NetworkFixtures::replay(&path)
+            .unwrap()
+            .resolved_image("docker.io/library/ubuntu:latest", || {
+                panic!("The replay mode must not load")
+            })
+            .unwrap();
+
+        assert_eq_sorted!(replayed, resolved);
+    }
+
+    #[test]
+    fn record_keeps_previous_entries() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("fixtures.yml");
+        fs::write(&path, "resources:\n  https://example.com/a.yml: 'a: 1'\n").unwrap();
+
+        let fixtures = NetworkFixtures::record(&path).unwrap();
+        fixtures
+            .resource("https://example.com/b.yml", || Ok("b: 2".into()))
+            .unwrap();
+
+        assert_eq_sorted!(
+            NetworkFixtures::replay(&path).unwrap().content().resources,
+            BTreeMap::from([
+                ("https://example.com/a.yml".into(), "a: 1".into()),
+                ("https://example.com/b.yml".into(), "b: 2".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn clones_share_recorded_entries() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("fixtures.yml");
+        let fixtures = NetworkFixtures::record(&path).unwrap();
+
+        std::thread::scope(|scope| {
+            for name in ["a", "b"] {
+                let fixtures = fixtures.clone();
+                scope.spawn(move || {
+                    fixtures
+                        .resource(&format!("https://example.com/{}.yml", name), || {
+                            Ok(format!("{}: 1", name))
+                        })
+                        .unwrap();
+                });
+            }
+        });
+
+        assert_eq_sorted!(
+            NetworkFixtures::replay(&path).unwrap().content().resources,
+            BTreeMap::from([
+                ("https://example.com/a.yml".into(), "a: 1".into()),
+                ("https://example.com/b.yml".into(), "b: 1".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn replay_missing_entry() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("fixtures.yml");
+        fs::write(&path, "{}").unwrap();
+
+        let error = NetworkFixtures::replay(&path)
+            .unwrap()
+            .resource("https://example.com/a.yml", || Ok("a: 1".into()))
+            .unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("No URL resource fixture recorded for 'https://example.com/a.yml'"));
+    }
+}
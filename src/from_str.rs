@@ -12,6 +12,7 @@ const URL_REGEX: &str = "https?://(?:.+@)?[a-zA-Z0-9_-]+(?:\\.[a-zA-Z0-9_-]+)+(/
 
 macro_rules! impl_parsable_patch {
     ($struct:ty, $patch:ty, $param:ident, $expression:expr) => {
+        #[cfg(feature = "permissive")]
         impl Patch<ParsableStruct<$patch>> for $struct {
             fn apply(&mut self, patch: ParsableStruct<$patch>) {
                 self.apply(patch.0);
@@ -30,6 +31,7 @@ macro_rules! impl_parsable_patch {
             }
         }
 
+        #[cfg(feature = "permissive")]
         impl From<ParsableStruct<$patch>> for $struct {
             fn from(value: ParsableStruct<$patch>) -> Self {
                 value.0.into()
@@ -46,6 +48,21 @@ macro_rules! impl_parsable_patch {
     };
 }
 
+/// Parses the structures from the same shorthand strings as the Dofigen files
+macro_rules! impl_parsable_struct {
+    ($($struct:ty => $patch:ty),* $(,)?) => {
+        $(
+            impl FromStr for $struct {
+                type Err = crate::Error;
+
+                fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+                    Ok(s.parse::<$patch>()?.into())
+                }
+            }
+        )*
+    };
+}
+
 impl_parsable_patch!(ImageName, ImageNamePatch, s, {
     let regex = Regex::new(r"^(?:(?<host>[^:\/.]+(?:\.[^:\/.]+)+)(?::(?<port>\d{1,5}))?\/)?(?<path>[a-zA-Z0-9-]{1,63}(?:\/[a-zA-Z0-9-]{1,63})*)(?:(?<version_char>[:@])(?<version_value>[a-zA-Z0-9_.:-]{1,128}))?$").unwrap();
     let Some(captures) = regex.captures(s) else {
@@ -69,6 +86,17 @@ impl_parsable_patch!(ImageName, ImageNamePatch, s, {
     })
 });
 
+/// Parses an image name, also used for the image arguments of the CLI
+impl FromStr for ImageName {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.parse::<ImageNamePatch>()
+            .map(Into::into)
+            .map_err(|err| crate::Error::Custom(err.to_string()))
+    }
+}
+
 impl_parsable_patch!(CopyResource, CopyResourcePatch, s, {
     let parts_regex = format!(
         r"^(?:(?<git>(?:{git_http}|{git_ssh}))|(?<url>{url})|\S+)(?: (?:{git_http}|{git_ssh}|{url}|\S+))*(?: \S+)?$",
@@ -165,12 +193,18 @@ impl_parsable_patch!(User, UserPatch, s, {
 });
 
 impl_parsable_patch!(Port, PortPatch, s, {
-    let regex = Regex::new(r"^(?<port>\d+)(?:/(?<protocol>(tcp|udp)))?$").unwrap();
+    let regex = Regex::new(r"^(?<port>\d+)(?:-(?<end>\d+))?(?:/(?<protocol>(tcp|udp)))?$").unwrap();
     let Some(captures) = regex.captures(s) else {
-        return Err(Error::custom("Not matching chown pattern"));
+        return Err(Error::custom("Not matching port pattern"));
     };
     Ok(Self {
         port: Some(captures["port"].parse().map_err(Error::custom)?),
+        end: Some(
+            captures
+                .name("end")
+                .map(|m| m.as_str().parse().map_err(Error::custom))
+                .transpose()?,
+        ),
         protocol: Some(captures.name("protocol").map(|m| match m.as_str() {
             "tcp" => PortProtocol::Tcp,
             "udp" => PortProtocol::Udp,
@@ -211,6 +245,32 @@ impl_parsable_patch!(Bind, BindPatch, s, {
     })
 });
 
+impl_parsable_patch!(Secret, SecretPatch, s, {
+    let regex = Regex::new(r"^(?P<id>[a-zA-Z0-9_.-]+)(?: (?P<target>\S+))?$").unwrap();
+    let Some(captures) = regex.captures(s) else {
+        return Err(Error::custom("Not matching secret pattern"));
+    };
+    Ok(Self {
+        id: Some(captures["id"].into()),
+        target: Some(captures.name("target").map(|m| m.as_str().into())),
+        env: Some(None),
+        required: Some(None),
+    })
+});
+
+impl_parsable_patch!(Package, PackagePatch, s, {
+    let regex = Regex::new(r"^(?P<name>[^\s=]+)(?:=(?P<version>\S+))?$").unwrap();
+    let Some(captures) = regex.captures(s) else {
+        return Err(Error::custom("Not matching package pattern"));
+    };
+    Ok(Self {
+        name: Some(captures["name"].into()),
+        version: Some(captures.name("version").map(|m| m.as_str().into())),
+        license: Some(None),
+        purl: Some(None),
+    })
+});
+
 impl_parsable_patch!(Cache, CachePatch, s, {
     let regex = Regex::new(r"^(?:(?:(?P<fromType>image|builder|context)\((?P<from>[^:]+)\):)?(?P<source>\S+) )?(?P<target>\S+)$").unwrap();
     let Some(captures) = regex.captures(s) else {
@@ -241,6 +301,19 @@ impl_parsable_patch!(Cache, CachePatch, s, {
     })
 });
 
+impl_parsable_struct!(
+    CopyResource => CopyResourcePatch,
+    Copy => CopyPatch,
+    AddGitRepo => AddGitRepoPatch,
+    Add => AddPatch,
+    User => UserPatch,
+    Port => PortPatch,
+    Bind => BindPatch,
+    Secret => SecretPatch,
+    Package => PackagePatch,
+    Cache => CachePatch,
+);
+
 #[cfg(test)]
 mod test_from_str {
     use super::*;
@@ -668,6 +741,22 @@ mod test_from_str {
             assert_eq_sorted!(result.protocol, Some(Some(PortProtocol::Udp)));
         }
 
+        #[test]
+        fn range() {
+            let result = PortPatch::from_str("8000-8010/tcp").unwrap();
+
+            assert_eq_sorted!(result.port, Some(8000));
+            assert_eq_sorted!(result.end, Some(Some(8010)));
+            assert_eq_sorted!(result.protocol, Some(Some(PortProtocol::Tcp)));
+        }
+
+        #[test]
+        fn invalid_range() {
+            let result = PortPatch::from_str("8000-");
+
+            assert!(result.is_err());
+        }
+
         #[test]
         fn invalid() {
             let result = PortPatch::from_str("80/invalid");
@@ -675,4 +764,101 @@ mod test_from_str {
             assert!(result.is_err());
         }
     }
+
+    mod secret {
+
+        use super::*;
+
+        #[test]
+        fn id() {
+            let result = SecretPatch::from_str("npm_token").unwrap();
+
+            assert_eq_sorted!(result.id, Some("npm_token".into()));
+            assert_eq_sorted!(result.target, Some(None));
+        }
+
+        #[test]
+        fn with_target() {
+            let result = SecretPatch::from_str("npmrc /root/.npmrc").unwrap();
+
+            assert_eq_sorted!(result.id, Some("npmrc".into()));
+            assert_eq_sorted!(result.target, Some(Some("/root/.npmrc".into())));
+        }
+    }
+
+    mod package {
+
+        use super::*;
+
+        #[test]
+        fn name() {
+            let result = PackagePatch::from_str("curl").unwrap();
+
+            assert_eq_sorted!(result.name, Some("curl".into()));
+            assert_eq_sorted!(result.version, Some(None));
+        }
+
+        #[test]
+        fn with_version() {
+            let result = PackagePatch::from_str("curl=8.5.0-r0").unwrap();
+
+            assert_eq_sorted!(result.name, Some("curl".into()));
+            assert_eq_sorted!(result.version, Some(Some("8.5.0-r0".into())));
+        }
+    }
+
+    mod structs {
+        use super::*;
+
+        #[test]
+        fn copy_resource() {
+            let result: CopyResource = "src1 src2 /app".parse().unwrap();
+
+            assert_eq_sorted!(
+                result,
+                CopyResource::Copy(Copy {
+                    paths: vec!["src1".into(), "src2".into()],
+                    options: CopyOptions {
+                        target: Some("/app".into()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+            );
+        }
+
+        #[test]
+        fn user() {
+            let result: User = "1000:1000".parse().unwrap();
+
+            assert_eq_sorted!(
+                result,
+                User {
+                    user: "1000".into(),
+                    group: Some("1000".into()),
+                }
+            );
+        }
+
+        #[test]
+        fn port() {
+            let result: Port = "53/udp".parse().unwrap();
+
+            assert_eq_sorted!(
+                result,
+                Port {
+                    port: 53,
+                    end: None,
+                    protocol: Some(PortProtocol::Udp),
+                }
+            );
+        }
+
+        #[test]
+        fn invalid() {
+            let result = "80/invalid".parse::<Port>();
+
+            assert!(result.is_err());
+        }
+    }
 }
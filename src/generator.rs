@@ -1,4 +1,10 @@
 use crate::errors::Error;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     dockerfile_struct::*, dofigen_struct::*, LintMessage, LintSession, Result, DOCKERFILE_VERSION,
@@ -7,6 +13,16 @@ use crate::{
 
 pub const LINE_SEPARATOR: &str = " \\\n    ";
 pub const DEFAULT_FROM: &str = "scratch";
+/// The build arg declared before the runs with a cache bust value
+const CACHE_BUST_ARG: &str = "CACHE_BUST";
+const METADATA_INSTRUCTIONS_ORDER: [MetadataInstruction; 6] = [
+    MetadataInstruction::Volume,
+    MetadataInstruction::Expose,
+    MetadataInstruction::Healthcheck,
+    MetadataInstruction::StopSignal,
+    MetadataInstruction::Entrypoint,
+    MetadataInstruction::Cmd,
+];
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct GenerationContext {
@@ -16,6 +32,10 @@ pub struct GenerationContext {
     pub(crate) default_from: FromContext,
     state_stack: Vec<GenerationContextState>,
     pub(crate) lint_session: LintSession,
+    /// The generation time, in seconds since the Unix epoch, used by the cache bust periods
+    timestamp: u64,
+    /// The labels added to the runtime image
+    labels: BTreeMap<String, String>,
 }
 
 impl GenerationContext {
@@ -53,8 +73,23 @@ impl GenerationContext {
         }
     }
 
-    pub fn from(dofigen: Dofigen) -> Self {
-        let lint_session = LintSession::analyze(&dofigen);
+    /// Returns the Dockerfile syntax used to build the generated Dockerfile
+    pub fn syntax(&self) -> String {
+        self.dofigen
+            .syntax
+            .clone()
+            .unwrap_or(format!("docker/dockerfile:{}", DOCKERFILE_VERSION))
+    }
+
+    /// Checks if the Dockerfile syntax supports the copy --parents flag, only available in the labs channel
+    fn supports_copy_parents(&self) -> bool {
+        self.syntax().contains("labs")
+    }
+
+    pub fn from(mut dofigen: Dofigen) -> Self {
+        let renamed = dofigen.sanitize_stage_names();
+        let mut lint_session = LintSession::analyze(&dofigen);
+        lint_session.add_renamed_stages(&renamed);
         Self {
             dofigen,
             user: None,
@@ -62,26 +97,82 @@ impl GenerationContext {
             default_from: FromContext::default(),
             lint_session,
             state_stack: vec![],
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            labels: BTreeMap::new(),
         }
     }
 
+    /// Adds labels to the runtime image, like the lock file labels
+    pub fn add_labels(&mut self, labels: BTreeMap<String, String>) {
+        self.labels.extend(labels);
+    }
+
     pub fn generate_dockerfile(&mut self) -> Result<String> {
+        Ok(self
+            .dockerfile_lines()?
+            .map(|line| format!("{}\n", line))
+            .collect())
+    }
+
+    /// Writes the Dockerfile content to the writer, line by line
+    pub fn generate_dockerfile_to<W: Write>(&mut self, writer: &mut W) -> Result<()> {
+        for line in self.dockerfile_lines()? {
+            writeln!(writer, "{}", line).map_err(Error::display)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the lines of the Dockerfile, an instruction spanning several lines with its continuations
+    pub fn dockerfile_lines(&mut self) -> Result<impl Iterator<Item = String>> {
         let mut lines = self.dofigen.clone().generate_dockerfile_lines(self)?;
         let mut line_number = 1;
 
+        // The parser directives must follow the syntax one, before any other comment
+        if let Some(escape) = self.dofigen.escape {
+            lines.insert(
+                line_number,
+                DockerfileLine::Comment(format!("escape={}", escape)),
+            );
+            line_number += 1;
+        }
+        if let Some(checks) = self.dofigen.checks.as_ref().and_then(Checks::directive) {
+            lines.insert(
+                line_number,
+                DockerfileLine::Comment(format!("check={}", checks)),
+            );
+            line_number += 1;
+        }
+
         for line in FILE_HEADER_COMMENTS {
             lines.insert(line_number, DockerfileLine::Comment(line.to_string()));
             line_number += 1;
         }
 
-        Ok(format!(
-            "{}\n",
-            lines
-                .iter()
-                .map(DockerfileLine::generate_content)
-                .collect::<Vec<String>>()
-                .join("\n")
-        ))
+        // Document the additional build contexts to give to the build
+        if !self.dofigen.additional_contexts.is_empty() {
+            let mut contexts = self.dofigen.additional_contexts.iter().collect::<Vec<_>>();
+            contexts.sort();
+            lines.insert(
+                line_number,
+                DockerfileLine::Comment("Build it with the additional contexts:".into()),
+            );
+            line_number += 1;
+            for (name, source) in contexts {
+                lines.insert(
+                    line_number,
+                    DockerfileLine::Comment(format!("  --build-context {}={}", name, source)),
+                );
+                line_number += 1;
+            }
+        }
+
+        let separator = format!(" {}\n    ", self.dofigen.escape.unwrap_or_default());
+        Ok(lines
+            .into_iter()
+            .map(move |line| line.generate_content_with_separator(&separator)))
     }
 
     pub fn generate_dockerignore(&self) -> Result<String> {
@@ -127,6 +218,34 @@ pub trait DockerfileGenerator {
 }
 
 impl Stage {
+    /// Replaces the renamed builders in the origins and the outputs used by the stage
+    fn rename_builders(&mut self, renamed: &BTreeMap<String, String>) {
+        let rename = |from: &mut FromContext| {
+            if let FromContext::FromBuilder(builder) = from {
+                if let Some(sanitized) = renamed.get(builder) {
+                    *builder = sanitized.clone();
+                }
+            }
+        };
+        rename(&mut self.from);
+        for copy in self.copy.iter_mut() {
+            if let CopyResource::Copy(copy) = copy {
+                rename(&mut copy.from);
+            }
+        }
+        for output in self.uses.iter_mut() {
+            if let Some(sanitized) = renamed.get(&output.builder) {
+                output.builder = sanitized.clone();
+            }
+        }
+        for run in std::iter::once(&mut self.run).chain(self.root.iter_mut()) {
+            run.cache
+                .iter_mut()
+                .for_each(|cache| rename(&mut cache.from));
+            run.bind.iter_mut().for_each(|bind| rename(&mut bind.from));
+        }
+    }
+
     pub fn from(&self, context: &GenerationContext) -> FromContext {
         match &self.from {
             FromContext::FromImage(image) => FromContext::FromImage(image.clone()),
@@ -145,7 +264,27 @@ impl Stage {
     }
 
     pub fn user(&self, context: &GenerationContext) -> Option<User> {
-        self.user.clone().or(context.user.clone())
+        self.user
+            .as_ref()
+            .map(|user| context.dofigen.resolve_user(user))
+            .or(context.user.clone())
+    }
+
+    /// Returns the predefined platform args used by the run instructions and the env of the stage
+    fn used_platform_args(&self) -> Vec<String> {
+        let regex = Regex::new(r"\$\{?((?:BUILD|TARGET)(?:PLATFORM|OS|ARCH|VARIANT))\b").unwrap();
+        let mut args = self
+            .run
+            .run
+            .iter()
+            .chain(self.root.iter().flat_map(|root| root.run.iter()))
+            .chain(self.env.values())
+            .flat_map(|content| regex.captures_iter(content))
+            .map(|captures| captures[1].to_string())
+            .collect::<Vec<_>>();
+        args.sort();
+        args.dedup();
+        args
     }
 }
 
@@ -155,6 +294,13 @@ impl Run {
     }
 }
 
+impl UserDefinition {
+    /// Returns the ID of the group of the user
+    pub fn group_id(&self) -> u16 {
+        self.gid.unwrap_or(self.uid)
+    }
+}
+
 impl User {
     pub fn uid(&self) -> Option<u16> {
         self.user.parse::<u16>().ok()
@@ -238,15 +384,13 @@ impl ToString for User {
 
 impl ToString for Port {
     fn to_string(&self) -> String {
+        let port = match self.end {
+            Some(end) => format!("{}-{}", self.port, end),
+            None => self.port.to_string(),
+        };
         match &self.protocol {
-            Some(protocol) => {
-                format!(
-                    "{port}/{protocol}",
-                    port = self.port,
-                    protocol = protocol.to_string()
-                )
-            }
-            _ => self.port.to_string(),
+            Some(protocol) => format!("{}/{}", port, protocol.to_string()),
+            _ => port,
         }
     }
 }
@@ -279,6 +423,94 @@ impl ToString for CacheSharing {
     }
 }
 
+impl std::fmt::Display for DockerfileEscape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DockerfileEscape::Backslash => "\\",
+            DockerfileEscape::Backtick => "`",
+        })
+    }
+}
+
+impl Checks {
+    /// The value of the check directive, if any check is configured
+    fn directive(&self) -> Option<String> {
+        let mut parts = vec![];
+        if !self.skip.is_empty() {
+            parts.push(format!("skip={}", self.skip.join(",")));
+        }
+        if !self.experimental.is_empty() {
+            parts.push(format!("experimental={}", self.experimental.join(",")));
+        }
+        if let Some(error) = self.error {
+            parts.push(format!("error={}", error));
+        }
+        (!parts.is_empty()).then(|| parts.join(";"))
+    }
+}
+
+impl std::fmt::Display for RunNetwork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RunNetwork::Default => "default",
+            RunNetwork::None => "none",
+            RunNetwork::Host => "host",
+        })
+    }
+}
+
+impl std::fmt::Display for RunSecurity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RunSecurity::Sandbox => "sandbox",
+            RunSecurity::Insecure => "insecure",
+        })
+    }
+}
+
+impl CacheBust {
+    /// The value of the cache bust at the given timestamp, in seconds since the Unix epoch
+    fn value(&self, timestamp: u64) -> String {
+        let days = timestamp / 86400;
+        match self {
+            CacheBust::Value(value) => value.clone(),
+            CacheBust::Auto {
+                auto: CacheBustPeriod::Hourly,
+            } => format!("{}T{:02}", civil_date(days), timestamp % 86400 / 3600),
+            CacheBust::Auto {
+                auto: CacheBustPeriod::Daily,
+            } => civil_date(days),
+            // The Unix epoch is a Thursday
+            CacheBust::Auto {
+                auto: CacheBustPeriod::Weekly,
+            } => civil_date(days - (days + 3) % 7),
+            CacheBust::Auto {
+                auto: CacheBustPeriod::Monthly,
+            } => civil_date(days)[..7].to_string(),
+        }
+    }
+}
+
+/// Formats the date of a number of days since the Unix epoch as YYYY-MM-DD
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_date(days: u64) -> String {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
 impl ToString for FromContext {
     fn to_string(&self) -> String {
         match self {
@@ -310,8 +542,16 @@ fn add_copy_options(
     copy_options: &CopyOptions,
     context: &GenerationContext,
 ) {
-    if let Some(chown) = copy_options.chown.as_ref().or(context.user.as_ref().into()) {
-        inst_options.push(InstructionOption::WithValue("chown".into(), chown.into()));
+    if let Some(chown) = copy_options
+        .chown
+        .as_ref()
+        .map(|chown| context.dofigen.resolve_user(chown))
+        .or(context.user.clone())
+    {
+        inst_options.push(InstructionOption::WithValue(
+            "chown".into(),
+            chown.to_string(),
+        ));
     }
     if let Some(chmod) = &copy_options.chmod {
         inst_options.push(InstructionOption::WithValue("chmod".into(), chmod.into()));
@@ -321,6 +561,15 @@ fn add_copy_options(
     }
 }
 
+fn add_exclude_options(inst_options: &mut Vec<InstructionOption>, exclude: &[String]) {
+    let mut exclude = exclude.to_vec();
+    exclude.sort();
+    exclude.dedup();
+    for path in exclude {
+        inst_options.push(InstructionOption::WithValue("exclude".into(), path));
+    }
+}
+
 impl DockerfileGenerator for Copy {
     fn generate_dockerfile_lines(
         &self,
@@ -338,11 +587,12 @@ impl DockerfileGenerator for Copy {
         }
         add_copy_options(&mut options, &self.options, context);
 
-        for path in self.exclude.iter() {
-            options.push(InstructionOption::WithValue("exclude".into(), path.clone()));
-        }
+        add_exclude_options(&mut options, &self.exclude);
 
         if self.parents.unwrap_or(false) {
+            if !context.supports_copy_parents() {
+                return self.generate_parents_emulation(options);
+            }
             options.push(InstructionOption::Flag("parents".into()));
         }
 
@@ -419,9 +669,7 @@ impl DockerfileGenerator for AddGitRepo {
         let mut options: Vec<InstructionOption> = vec![];
         add_copy_options(&mut options, &self.options, context);
 
-        for path in self.exclude.iter() {
-            options.push(InstructionOption::WithValue("exclude".into(), path.clone()));
-        }
+        add_exclude_options(&mut options, &self.exclude);
         if let Some(keep_git_dir) = &self.keep_git_dir {
             options.push(InstructionOption::WithValue(
                 "keep-git-dir".into(),
@@ -447,10 +695,44 @@ impl DockerfileGenerator for Dofigen {
             ..Default::default()
         });
         let mut lines = vec![
-            DockerfileLine::Comment(format!("syntax=docker/dockerfile:{}", DOCKERFILE_VERSION)),
+            DockerfileLine::Comment(format!("syntax={}", context.syntax())),
             DockerfileLine::Empty,
         ];
 
+        // The args of the stages base images and the global args must be declared before the first FROM
+        let mut global_arg = self.global_arg.iter().collect::<Vec<_>>();
+        global_arg.sort();
+        let global_args = context
+            .lint_session
+            .get_sorted_builders()
+            .iter()
+            .filter_map(|name| self.builders.get(name))
+            .chain(std::iter::once(&self.stage))
+            .filter_map(|stage| {
+                stage.from_arg.as_ref().map(|arg| match &stage.from {
+                    FromContext::FromImage(image) => format!("{}={}", arg, image.to_string()),
+                    _ => arg.clone(),
+                })
+            })
+            .chain(global_arg.into_iter().map(|(name, value)| {
+                if value.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}={}", name, value)
+                }
+            }))
+            .collect::<Vec<_>>();
+        if !global_args.is_empty() {
+            for arg in global_args {
+                lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "ARG".into(),
+                    content: arg,
+                    options: vec![],
+                }));
+            }
+            lines.push(DockerfileLine::Empty);
+        }
+
         for name in context.lint_session.get_sorted_builders() {
             context.push_state(GenerationContextState {
                 stage_name: Some(name.clone()),
@@ -474,68 +756,299 @@ impl DockerfileGenerator for Dofigen {
         lines.append(&mut self.stage.generate_dockerfile_lines(context)?);
         context.pop_state();
 
-        self.volume.iter().for_each(|volume| {
-            lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
-                command: "VOLUME".into(),
-                content: volume.clone(),
-                options: vec![],
-            }))
-        });
+        let mut order = self.instruction_order.clone();
+        order.extend(METADATA_INSTRUCTIONS_ORDER);
+        let mut generated = vec![];
+        for instruction in order {
+            if !generated.contains(&instruction) {
+                lines.append(&mut self.metadata_lines(instruction));
+                generated.push(instruction);
+            }
+        }
 
-        self.expose.iter().for_each(|port| {
+        // The labels are last since they can change on each lock
+        if !context.labels.is_empty() {
             lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
-                command: "EXPOSE".into(),
-                content: port.to_string(),
+                command: "LABEL".into(),
+                content: context
+                    .labels
+                    .iter()
+                    .map(|(key, value)| format!("{}=\"{}\"", key, value))
+                    .collect::<Vec<String>>()
+                    .join(LINE_SEPARATOR),
                 options: vec![],
-            }))
+            }));
+        }
+        Ok(lines)
+    }
+}
+
+impl Dofigen {
+    /// Removes the builders the runtime stage doesn't depend on and returns their names
+    pub fn prune_unused_builders(&mut self) -> Vec<String> {
+        let used = LintSession::analyze(self).used_builders();
+        let mut pruned = self
+            .builders
+            .keys()
+            .filter(|name| !used.contains(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        pruned.sort();
+        for name in pruned.iter() {
+            self.builders.remove(name);
+        }
+        pruned
+    }
+
+    /// Renames the builders whose names aren't valid stage names, and their references.
+    /// Returns the new name of each renamed builder
+    pub fn sanitize_stage_names(&mut self) -> BTreeMap<String, String> {
+        let mut names = self.builders.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        let mut taken = names
+            .iter()
+            .filter(|name| is_valid_stage_name(name))
+            .cloned()
+            .chain(std::iter::once("runtime".to_string()))
+            .collect::<HashSet<_>>();
+        let mut renamed = BTreeMap::new();
+        for name in names.into_iter().filter(|name| !is_valid_stage_name(name)) {
+            let base = sanitize_stage_name(&name);
+            let mut sanitized = base.clone();
+            let mut suffix = 2;
+            while !taken.insert(sanitized.clone()) {
+                sanitized = format!("{}-{}", base, suffix);
+                suffix += 1;
+            }
+            renamed.insert(name, sanitized);
+        }
+
+        for (name, sanitized) in renamed.iter() {
+            let builder = self.builders.remove(name).unwrap();
+            self.builders.insert(sanitized.clone(), builder);
+        }
+        for stage in self
+            .builders
+            .values_mut()
+            .chain(std::iter::once(&mut self.stage))
+        {
+            stage.rename_builders(&renamed);
+        }
+        renamed
+    }
+
+    /// Replaces the names of the defined users and their groups by their IDs
+    pub fn resolve_user(&self, user: &User) -> User {
+        let group = user
+            .group
+            .as_ref()
+            .map(|group| match self.users.get(group) {
+                Some(definition) => definition.group_id().to_string(),
+                None => group.clone(),
+            });
+        match self.users.get(&user.user) {
+            Some(definition) => User {
+                user: definition.uid.to_string(),
+                group: group.or(Some(definition.group_id().to_string())),
+            },
+            None => User {
+                user: user.user.clone(),
+                group,
+            },
+        }
+    }
+
+    /// Returns the names of the defined users referenced by a stage
+    fn referenced_users<'a>(&'a self, stage: &'a Stage) -> BTreeSet<&'a String> {
+        let copy_chowns = stage.copy.iter().filter_map(|copy| match copy {
+            CopyResource::Copy(copy) => copy.options.chown.as_ref(),
+            CopyResource::Content(content) => content.options.chown.as_ref(),
+            CopyResource::Add(add) => add.options.chown.as_ref(),
+            CopyResource::AddGitRepo(add) => add.options.chown.as_ref(),
         });
-        if let Some(healthcheck) = &self.healthcheck {
-            let mut options = vec![];
-            if let Some(interval) = &healthcheck.interval {
-                options.push(InstructionOption::WithValue(
-                    "interval".into(),
-                    interval.into(),
-                ));
+        let cache_chowns = stage
+            .run
+            .cache
+            .iter()
+            .chain(stage.root.iter().flat_map(|root| root.cache.iter()))
+            .filter_map(|cache| cache.chown.as_ref());
+        stage
+            .user
+            .iter()
+            .chain(copy_chowns)
+            .chain(cache_chowns)
+            .flat_map(|user| std::iter::once(&user.user).chain(user.group.iter()))
+            .filter(|name| self.users.contains_key(*name))
+            .collect()
+    }
+
+    /// Returns the run instructions creating the defined users referenced by a stage.
+    /// The users created by the builder the stage is based on are inherited
+    fn users_creation(&self, stage: &Stage, context: &GenerationContext) -> Run {
+        let scratch = stage.from_arg.is_none()
+            && matches!(stage.from(context), FromContext::FromContext(Some(name)) if name == DEFAULT_FROM);
+        if self.users.is_empty() || scratch {
+            return Run::default();
+        }
+
+        let mut inherited = BTreeSet::new();
+        let mut visited = HashSet::new();
+        let mut from = &stage.from;
+        while let FromContext::FromBuilder(name) = from {
+            match self.builders.get(name) {
+                Some(builder) if visited.insert(name) => {
+                    inherited.extend(self.referenced_users(builder));
+                    from = &builder.from;
+                }
+                _ => break,
+            }
+        }
+
+        let run = self
+            .referenced_users(stage)
+            .into_iter()
+            .filter(|name| !inherited.contains(name))
+            .flat_map(|name| {
+                let definition = &self.users[name];
+                let home = definition.home.clone().unwrap_or(format!("/home/{}", name));
+                vec![
+                    format!(
+                        "echo '{name}:x:{uid}:{gid}::{home}:{shell}' >> /etc/passwd",
+                        uid = definition.uid,
+                        gid = definition.group_id(),
+                        shell = definition.shell.as_deref().unwrap_or("/sbin/nologin"),
+                    ),
+                    format!("echo '{}:x:{}:' >> /etc/group", name, definition.group_id()),
+                    format!("mkdir -p {}", home),
+                    format!(
+                        "chown {}:{} {}",
+                        definition.uid,
+                        definition.group_id(),
+                        home
+                    ),
+                ]
+            })
+            .collect();
+        Run {
+            run,
+            ..Default::default()
+        }
+    }
+
+    fn metadata_lines(&self, instruction: MetadataInstruction) -> Vec<DockerfileLine> {
+        let mut lines = vec![];
+        match instruction {
+            MetadataInstruction::Volume => self.volume.iter().for_each(|volume| {
+                lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "VOLUME".into(),
+                    content: volume.clone(),
+                    options: vec![],
+                }))
+            }),
+            MetadataInstruction::Expose => self.expose.iter().for_each(|port| {
+                lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "EXPOSE".into(),
+                    content: port.to_string(),
+                    options: vec![],
+                }))
+            }),
+            MetadataInstruction::Healthcheck => {
+                if let Some(healthcheck) = &self.healthcheck {
+                    let mut options = vec![];
+                    if let Some(interval) = &healthcheck.interval {
+                        options.push(InstructionOption::WithValue(
+                            "interval".into(),
+                            interval.into(),
+                        ));
+                    }
+                    if let Some(timeout) = &healthcheck.timeout {
+                        options.push(InstructionOption::WithValue(
+                            "timeout".into(),
+                            timeout.into(),
+                        ));
+                    }
+                    if let Some(start_period) = &healthcheck.start {
+                        options.push(InstructionOption::WithValue(
+                            "start-period".into(),
+                            start_period.into(),
+                        ));
+                    }
+                    if let Some(start_interval) = &healthcheck.start_interval {
+                        options.push(InstructionOption::WithValue(
+                            "start-interval".into(),
+                            start_interval.into(),
+                        ));
+                    }
+                    if let Some(retries) = &healthcheck.retries {
+                        options.push(InstructionOption::WithValue(
+                            "retries".into(),
+                            retries.to_string(),
+                        ));
+                    }
+                    lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "HEALTHCHECK".into(),
+                        content: format!("CMD {}", healthcheck.cmd.clone()),
+                        options,
+                    }))
+                }
             }
-            if let Some(timeout) = &healthcheck.timeout {
-                options.push(InstructionOption::WithValue(
-                    "timeout".into(),
-                    timeout.into(),
-                ));
+            MetadataInstruction::StopSignal => {
+                if let Some(stop_signal) = &self.stop_signal {
+                    lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "STOPSIGNAL".into(),
+                        content: stop_signal.clone(),
+                        options: vec![],
+                    }))
+                }
             }
-            if let Some(start_period) = &healthcheck.start {
-                options.push(InstructionOption::WithValue(
-                    "start-period".into(),
-                    start_period.into(),
-                ));
+            MetadataInstruction::Entrypoint => {
+                if !self.entrypoint.is_empty() {
+                    lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "ENTRYPOINT".into(),
+                        content: string_vec_into(self.entrypoint.to_vec()),
+                        options: vec![],
+                    }))
+                }
             }
-            if let Some(retries) = &healthcheck.retries {
-                options.push(InstructionOption::WithValue(
-                    "retries".into(),
-                    retries.to_string(),
-                ));
+            MetadataInstruction::Cmd => {
+                if !self.cmd.is_empty() {
+                    lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "CMD".into(),
+                        content: string_vec_into(self.cmd.to_vec()),
+                        options: vec![],
+                    }))
+                }
             }
-            lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
-                command: "HEALTHCHECK".into(),
-                content: format!("CMD {}", healthcheck.cmd.clone()),
-                options,
-            }))
-        }
-        if !self.entrypoint.is_empty() {
-            lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
-                command: "ENTRYPOINT".into(),
-                content: string_vec_into(self.entrypoint.to_vec()),
-                options: vec![],
-            }))
         }
-        if !self.cmd.is_empty() {
-            lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
-                command: "CMD".into(),
-                content: string_vec_into(self.cmd.to_vec()),
-                options: vec![],
-            }))
+        lines
+    }
+}
+
+impl DockerfileGenerator for OutputUse {
+    fn generate_dockerfile_lines(
+        &self,
+        context: &mut GenerationContext,
+    ) -> Result<Vec<DockerfileLine>> {
+        let path = context
+            .dofigen
+            .builders
+            .get(&self.builder)
+            .and_then(|builder| builder.outputs.get(self.output))
+            .cloned()
+            .ok_or(Error::Custom(format!(
+                "The builder '{}' has no output at position {}",
+                self.builder, self.output
+            )))?;
+        Copy {
+            from: FromContext::FromBuilder(self.builder.clone()),
+            paths: vec![path.clone()],
+            options: CopyOptions {
+                target: Some(self.target.clone().unwrap_or(path)),
+                ..Default::default()
+            },
+            ..Default::default()
         }
-        Ok(lines)
+        .generate_dockerfile_lines(context)
     }
 }
 
@@ -557,12 +1070,54 @@ impl DockerfileGenerator for Stage {
                 command: "FROM".into(),
                 content: format!(
                     "{image_name} AS {stage_name}",
-                    image_name = self.from(context).to_string()
+                    image_name = match &self.from_arg {
+                        Some(arg) => format!("${{{}}}", arg),
+                        None => self.from(context).to_string(),
+                    }
                 ),
-                options: vec![],
+                options: self
+                    .platform
+                    .iter()
+                    .map(|platform| {
+                        InstructionOption::WithValue("platform".into(), platform.clone())
+                    })
+                    .collect(),
             }),
         ];
 
+        // External stages are built elsewhere
+        if self.external.unwrap_or(false) {
+            context.pop_state();
+            return Ok(lines);
+        }
+
+        // Platform args
+        if self.platform.is_some() || !context.dofigen.platforms.is_empty() {
+            self.used_platform_args()
+                .into_iter()
+                .filter(|arg| !self.arg.contains_key(arg))
+                .for_each(|arg| {
+                    lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "ARG".into(),
+                        content: arg,
+                        options: vec![],
+                    }))
+                });
+        }
+
+        // Forwarded global args, unless the stage defines them
+        let mut forwarded = vec![];
+        for arg in &self.forward_args {
+            if !self.arg.contains_key(arg) && !forwarded.contains(arg) {
+                lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "ARG".into(),
+                    content: arg.clone(),
+                    options: vec![],
+                }));
+                forwarded.push(arg.clone());
+            }
+        }
+
         // Arg
         if !self.arg.is_empty() {
             let mut keys = self.arg.keys().collect::<Vec<&String>>();
@@ -583,11 +1138,12 @@ impl DockerfileGenerator for Stage {
 
         // Env
         if !self.env.is_empty() {
+            let mut env = self.env.iter().collect::<Vec<_>>();
+            env.sort();
             lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
                 command: "ENV".into(),
-                content: self
-                    .env
-                    .iter()
+                content: env
+                    .into_iter()
                     .map(|(key, value)| format!("{}=\"{}\"", key, value))
                     .collect::<Vec<String>>()
                     .join(LINE_SEPARATOR),
@@ -609,25 +1165,36 @@ impl DockerfileGenerator for Stage {
             lines.append(&mut copy.generate_dockerfile_lines(context)?);
         }
 
+        // Builders outputs
+        for output_use in self.uses.iter() {
+            lines.append(&mut output_use.generate_dockerfile_lines(context)?);
+        }
+
         // Root
-        if let Some(root) = &self.root {
-            if !root.is_empty() {
-                let root_user = User::new("0");
-                // User
-                lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
-                    command: "USER".into(),
-                    content: root_user.to_string(),
-                    options: vec![],
-                }));
+        let root = self.root.as_ref().filter(|root| !root.is_empty());
+        let users_creation = context.dofigen.users_creation(self, context);
+        if root.is_some() || !users_creation.is_empty() {
+            let root_user = User::new("0");
+            // User
+            lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                command: "USER".into(),
+                content: root_user.to_string(),
+                options: vec![],
+            }));
 
-                context.push_state(GenerationContextState {
-                    user: Some(Some(root_user)),
-                    ..Default::default()
-                });
-                // Run
+            context.push_state(GenerationContextState {
+                user: Some(Some(root_user)),
+                ..Default::default()
+            });
+            // Users
+            if !users_creation.is_empty() {
+                lines.append(&mut users_creation.generate_dockerfile_lines(context)?);
+            }
+            // Run
+            if let Some(root) = root {
                 lines.append(&mut root.generate_dockerfile_lines(context)?);
-                context.pop_state();
             }
+            context.pop_state();
         }
 
         // User
@@ -640,7 +1207,18 @@ impl DockerfileGenerator for Stage {
         }
 
         // Run
-        lines.append(&mut self.run.generate_dockerfile_lines(context)?);
+        let mut run_lines = self.run.generate_dockerfile_lines(context)?;
+        if let (Some(compiler_cache), false) = (&self.compiler_cache, run_lines.is_empty()) {
+            lines.append(&mut compiler_cache.generate_dockerfile_lines(context)?);
+            run_lines.iter_mut().for_each(|line| {
+                if let DockerfileLine::Instruction(instruction) = line {
+                    instruction
+                        .options
+                        .append(&mut compiler_cache.secret_mounts());
+                }
+            });
+        }
+        lines.append(&mut run_lines);
 
         context.pop_state();
 
@@ -648,6 +1226,73 @@ impl DockerfileGenerator for Stage {
     }
 }
 
+impl CompilerCache {
+    /// Returns the environment variables configuring the tool
+    fn env(&self) -> Vec<(&str, String)> {
+        match self.kind {
+            CompilerCacheType::Sccache => [
+                ("RUSTC_WRAPPER", Some("sccache".to_string())),
+                ("SCCACHE_BUCKET", Some(self.bucket.clone())),
+                ("SCCACHE_REGION", self.region.clone()),
+                ("SCCACHE_ENDPOINT", self.endpoint.clone()),
+            ]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (key, value)))
+            .collect(),
+        }
+    }
+
+    /// Returns the mounts of the secrets needed to access the storage
+    fn secret_mounts(&self) -> Vec<InstructionOption> {
+        match self.kind {
+            CompilerCacheType::Sccache => ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY"]
+                .iter()
+                .map(|secret| {
+                    InstructionOption::WithOptions(
+                        "mount".into(),
+                        vec![
+                            InstructionOptionOption::new("type", "secret".into()),
+                            InstructionOptionOption::new("id", secret.to_string()),
+                            InstructionOptionOption::new("env", secret.to_string()),
+                        ],
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DockerfileGenerator for CompilerCache {
+    fn generate_dockerfile_lines(
+        &self,
+        _context: &mut GenerationContext,
+    ) -> Result<Vec<DockerfileLine>> {
+        let install = match self.kind {
+            CompilerCacheType::Sccache => match &self.version {
+                Some(version) => format!("cargo install --locked sccache --version {}", version),
+                None => "cargo install --locked sccache".to_string(),
+            },
+        };
+        Ok(vec![
+            DockerfileLine::Instruction(DockerfileInsctruction {
+                command: "RUN".into(),
+                content: install,
+                options: vec![],
+            }),
+            DockerfileLine::Instruction(DockerfileInsctruction {
+                command: "ENV".into(),
+                content: self
+                    .env()
+                    .iter()
+                    .map(|(key, value)| format!("{}=\"{}\"", key, value))
+                    .collect::<Vec<String>>()
+                    .join(LINE_SEPARATOR),
+                options: vec![],
+            }),
+        ])
+    }
+}
+
 impl DockerfileGenerator for Run {
     fn generate_dockerfile_lines(
         &self,
@@ -715,7 +1360,12 @@ impl DockerfileGenerator for Run {
                     cache_options.push(InstructionOptionOption::new("source", source.clone()));
                 }
             }
-            if let Some(user) = cache.chown.as_ref().or(context.user.as_ref()) {
+            if let Some(user) = cache
+                .chown
+                .as_ref()
+                .map(|chown| context.dofigen.resolve_user(chown))
+                .or(context.user.clone())
+            {
                 if let Some(uid) = user.uid() {
                     cache_options.push(InstructionOptionOption::new("uid", uid.to_string()));
                 }
@@ -740,17 +1390,117 @@ impl DockerfileGenerator for Run {
             ));
         }
 
-        Ok(vec![DockerfileLine::Instruction(DockerfileInsctruction {
-            command: "RUN".into(),
-            content,
-            options,
-        })])
-    }
-}
+        // Mount secrets
+        for secret in self.secret.iter() {
+            let mut secret_options = vec![
+                InstructionOptionOption::new("type", "secret".into()),
+                InstructionOptionOption::new("id", secret.id.clone()),
+            ];
+            if let Some(target) = secret.target.as_ref() {
+                secret_options.push(InstructionOptionOption::new("target", target.clone()));
+            }
+            if let Some(env) = secret.env.as_ref() {
+                secret_options.push(InstructionOptionOption::new("env", env.clone()));
+            }
+            if secret.required.unwrap_or(false) {
+                secret_options.push(InstructionOptionOption::new("required", "true".into()));
+            }
+            options.push(InstructionOption::WithOptions(
+                "mount".into(),
+                secret_options,
+            ));
+        }
 
-fn copy_paths_into(paths: Vec<String>, target: &Option<String>) -> String {
-    let mut parts = paths.clone();
-    parts.push(target.clone().unwrap_or("./".into()));
+        // Network
+        if let Some(network) = &self.network {
+            options.push(InstructionOption::WithValue(
+                "network".into(),
+                network.to_string(),
+            ));
+        }
+
+        // Security
+        if let Some(security) = &self.security {
+            options.push(InstructionOption::WithValue(
+                "security".into(),
+                security.to_string(),
+            ));
+        }
+
+        let mut lines = vec![];
+        if let Some(cache_bust) = &self.cache_bust {
+            lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+                command: "ARG".into(),
+                content: format!("{}={}", CACHE_BUST_ARG, cache_bust.value(context.timestamp)),
+                options: vec![],
+            }));
+        }
+        lines.push(DockerfileLine::Instruction(DockerfileInsctruction {
+            command: "RUN".into(),
+            content,
+            options,
+        }));
+        Ok(lines)
+    }
+}
+
+impl Copy {
+    /// Generates a copy instruction by preserved directory, to keep the directory structure without the --parents flag
+    fn generate_parents_emulation(
+        &self,
+        options: Vec<InstructionOption>,
+    ) -> Result<Vec<DockerfileLine>> {
+        let mut groups: Vec<(String, Vec<String>)> = vec![];
+        for path in self.paths.iter() {
+            let parents = preserved_parents(path)?;
+            match groups.iter_mut().find(|(dir, _)| *dir == parents) {
+                Some((_, paths)) => paths.push(path.clone()),
+                None => groups.push((parents, vec![path.clone()])),
+            }
+        }
+        Ok(groups
+            .into_iter()
+            .map(|(parents, paths)| {
+                let target = if parents.is_empty() {
+                    self.options.target.clone()
+                } else {
+                    let target = self.options.target.as_deref().unwrap_or("./");
+                    Some(format!("{}/{}/", target.trim_end_matches('/'), parents))
+                };
+                DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "COPY".into(),
+                    content: copy_paths_into(paths, &target),
+                    options: options.clone(),
+                })
+            })
+            .collect())
+    }
+}
+
+/// Returns the directories of a path kept by the copy --parents flag.
+/// The part of the path before a `/./` pivot is not kept
+fn preserved_parents(path: &str) -> Result<String> {
+    let relative = path
+        .rsplit_once("/./")
+        .map(|(_, relative)| relative)
+        .unwrap_or(path);
+    let relative = relative.trim_start_matches("./").trim_start_matches('/');
+    let parents = relative
+        .rsplit_once('/')
+        .map(|(parents, _)| parents)
+        .unwrap_or_default();
+    if parents.contains(['*', '?', '[']) {
+        return Err(Error::Custom(format!(
+            "The copy of '{}' with parents can't be generated without a Dockerfile syntax supporting the --parents flag, like docker/dockerfile:1-labs",
+            path
+        )));
+    }
+    Ok(parents.to_string())
+}
+
+fn copy_paths_into(paths: Vec<String>, target: &Option<String>) -> String {
+    let mut parts = paths.clone();
+    parts.push(target.clone().unwrap_or("./".into()));
     parts
         .iter()
         .map(|p| format!("\"{}\"", p))
@@ -769,6 +1519,34 @@ fn string_vec_into(string_vec: Vec<String>) -> String {
     )
 }
 
+/// Checks if a name can be used as Dockerfile stage name: lowercase letters, digits, '-', '_' and '.', starting with a letter
+fn is_valid_stage_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || "-_.".contains(c))
+}
+
+/// Lowercases the name and replaces its invalid characters, prefixing it when it doesn't start with a letter
+fn sanitize_stage_name(name: &str) -> String {
+    let mut sanitized = String::new();
+    for c in name.to_lowercase().chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() || "_.".contains(c) {
+            sanitized.push(c);
+        } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+            sanitized.push('-');
+        }
+    }
+    let sanitized = sanitized.trim_end_matches('-');
+    if sanitized.starts_with(|c: char| c.is_ascii_lowercase()) {
+        sanitized.to_string()
+    } else {
+        format!("stage-{}", sanitized)
+            .trim_end_matches('-')
+            .to_string()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -783,6 +1561,9 @@ mod test {
                 default_from: FromContext::default(),
                 lint_session: LintSession::default(),
                 state_stack: vec![],
+                // 2025-03-12T15:20:00Z
+                timestamp: 1741792800,
+                labels: BTreeMap::new(),
             }
         }
     }
@@ -849,6 +1630,241 @@ mod test {
                 ]
             );
         }
+
+        #[test]
+        fn external_stage() {
+            let stage = Stage {
+                from: FromContext::FromContext(Some("prebuilt".into())),
+                external: Some(true),
+                run: Run {
+                    run: vec!["make".into()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lines = stage.generate_dockerfile_lines(&mut GenerationContext {
+                stage_name: "builder".into(),
+                ..Default::default()
+            });
+
+            assert_eq_sorted!(
+                lines.unwrap(),
+                vec![
+                    DockerfileLine::Comment("builder".into()),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "FROM".into(),
+                        content: "prebuilt AS builder".into(),
+                        options: vec![],
+                    }),
+                ]
+            );
+        }
+
+        #[test]
+        fn platform_args() {
+            let stage = Stage {
+                from: FromContext::FromImage(ImageName {
+                    path: "golang".into(),
+                    ..Default::default()
+                }),
+                platform: Some("$BUILDPLATFORM".into()),
+                arg: HashMap::from([("TARGETOS".into(), "".into())]),
+                env: HashMap::from([("CGO_ENABLED".into(), "0".into())]),
+                run: Run {
+                    run: vec![
+                        "GOOS=$TARGETOS GOARCH=${TARGETARCH} go build -o /app".into(),
+                        "echo $TARGETARCH".into(),
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lines = stage
+                .generate_dockerfile_lines(&mut GenerationContext {
+                    stage_name: "builder".into(),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            assert_eq_sorted!(
+                lines[1..4].to_vec(),
+                vec![
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "FROM".into(),
+                        content: "golang AS builder".into(),
+                        options: vec![InstructionOption::WithValue(
+                            "platform".into(),
+                            "$BUILDPLATFORM".into()
+                        )],
+                    }),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "ARG".into(),
+                        content: "TARGETARCH".into(),
+                        options: vec![],
+                    }),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "ARG".into(),
+                        content: "TARGETOS".into(),
+                        options: vec![],
+                    }),
+                ]
+            );
+        }
+    }
+
+    mod compiler_cache {
+        use super::*;
+
+        #[test]
+        fn sccache() {
+            let stage = Stage {
+                from: FromContext::FromImage(ImageName {
+                    path: "rust".into(),
+                    ..Default::default()
+                }),
+                compiler_cache: Some(CompilerCache {
+                    bucket: "build-cache".into(),
+                    region: Some("eu-west-1".into()),
+                    version: Some("0.8.2".into()),
+                    ..Default::default()
+                }),
+                run: Run {
+                    run: vec!["cargo build --release".into()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lines = stage.generate_dockerfile_lines(&mut GenerationContext {
+                stage_name: "builder".into(),
+                ..Default::default()
+            });
+
+            assert_eq_sorted!(
+                lines.unwrap(),
+                vec![
+                    DockerfileLine::Comment("builder".into()),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "FROM".into(),
+                        content: "rust AS builder".into(),
+                        options: vec![],
+                    }),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "RUN".into(),
+                        content: "cargo install --locked sccache --version 0.8.2".into(),
+                        options: vec![],
+                    }),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "ENV".into(),
+                        content: "RUSTC_WRAPPER=\"sccache\" \\\n    SCCACHE_BUCKET=\"build-cache\" \\\n    SCCACHE_REGION=\"eu-west-1\"".into(),
+                        options: vec![],
+                    }),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "RUN".into(),
+                        content: "cargo build --release".into(),
+                        options: vec![
+                            InstructionOption::WithOptions(
+                                "mount".into(),
+                                vec![
+                                    InstructionOptionOption::new("type", "secret".into()),
+                                    InstructionOptionOption::new("id", "AWS_ACCESS_KEY_ID".into()),
+                                    InstructionOptionOption::new("env", "AWS_ACCESS_KEY_ID".into()),
+                                ],
+                            ),
+                            InstructionOption::WithOptions(
+                                "mount".into(),
+                                vec![
+                                    InstructionOptionOption::new("type", "secret".into()),
+                                    InstructionOptionOption::new("id", "AWS_SECRET_ACCESS_KEY".into()),
+                                    InstructionOptionOption::new("env", "AWS_SECRET_ACCESS_KEY".into()),
+                                ],
+                            ),
+                        ],
+                    }),
+                ]
+            );
+        }
+    }
+
+    mod stage_name {
+        use super::*;
+        use std::collections::HashMap;
+
+        #[test]
+        fn sanitize() {
+            assert_eq_sorted!(sanitize_stage_name("My Builder"), "my-builder");
+            assert_eq_sorted!(sanitize_stage_name("2fast"), "stage-2fast");
+            assert_eq_sorted!(sanitize_stage_name("a/b:c"), "a-b-c");
+            assert_eq_sorted!(sanitize_stage_name("!!"), "stage");
+            assert!(is_valid_stage_name("build_1.0-x"));
+            assert!(!is_valid_stage_name("Build"));
+        }
+
+        #[test]
+        fn rename_builders() {
+            let mut dofigen = Dofigen {
+                builders: HashMap::from([
+                    ("My Builder".into(), Stage::default()),
+                    ("my-builder".into(), Stage::default()),
+                    (
+                        "Runtime".into(),
+                        Stage {
+                            from: FromContext::FromBuilder("My Builder".into()),
+                            ..Default::default()
+                        },
+                    ),
+                ]),
+                stage: Stage {
+                    copy: vec![CopyResource::Copy(Copy {
+                        from: FromContext::FromBuilder("Runtime".into()),
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            assert_eq_sorted!(
+                dofigen.sanitize_stage_names(),
+                BTreeMap::from([
+                    ("My Builder".into(), "my-builder-2".into()),
+                    ("Runtime".into(), "runtime-2".into()),
+                ])
+            );
+            assert_eq_sorted!(
+                dofigen.builders["runtime-2"].from,
+                FromContext::FromBuilder("my-builder-2".into())
+            );
+            assert_eq_sorted!(
+                dofigen.stage.copy,
+                vec![CopyResource::Copy(Copy {
+                    from: FromContext::FromBuilder("runtime-2".into()),
+                    ..Default::default()
+                })]
+            );
+        }
+    }
+
+    mod output_use {
+        use super::*;
+
+        #[test]
+        fn missing_output() {
+            let output_use = OutputUse {
+                builder: "builder".into(),
+                output: 0,
+                ..Default::default()
+            };
+
+            let lines = output_use.generate_dockerfile_lines(&mut GenerationContext::default());
+
+            assert_eq_sorted!(
+                lines.unwrap_err().to_string(),
+                "The builder 'builder' has no output at position 0"
+            );
+        }
     }
 
     mod copy {
@@ -883,6 +1899,65 @@ mod test {
             );
         }
 
+        #[test]
+        fn with_exclude() {
+            let copy = Copy {
+                paths: vec!["src".into()],
+                exclude: vec![
+                    "src/*.test.js".into(),
+                    "src/*.md".into(),
+                    "src/*.test.js".into(),
+                ],
+                ..Default::default()
+            };
+
+            let lines = copy
+                .generate_dockerfile_lines(&mut GenerationContext::default())
+                .unwrap();
+
+            assert_eq_sorted!(
+                lines,
+                vec![DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "COPY".into(),
+                    content: "\"src\" \"./\"".into(),
+                    options: vec![
+                        InstructionOption::Flag("link".into()),
+                        InstructionOption::WithValue("exclude".into(), "src/*.md".into()),
+                        InstructionOption::WithValue("exclude".into(), "src/*.test.js".into()),
+                    ],
+                })]
+            );
+        }
+
+        #[test]
+        fn add_git_repo_with_exclude() {
+            let add = AddGitRepo {
+                repo: "https://github.com/lenra-io/dofigen.git".into(),
+                exclude: vec!["docs".into()],
+                options: CopyOptions {
+                    target: Some("/app".into()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lines = add
+                .generate_dockerfile_lines(&mut GenerationContext::default())
+                .unwrap();
+
+            assert_eq_sorted!(
+                lines,
+                vec![DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "ADD".into(),
+                    content: "\"https://github.com/lenra-io/dofigen.git\" \"/app\"".into(),
+                    options: vec![
+                        InstructionOption::Flag("link".into()),
+                        InstructionOption::WithValue("exclude".into(), "docs".into()),
+                    ],
+                })]
+            );
+        }
+
         #[test]
         fn from_content() {
             let copy = CopyContent {
@@ -907,6 +1982,92 @@ mod test {
                 })]
             );
         }
+
+        #[test]
+        fn with_parents_emulation() {
+            let copy = Copy {
+                paths: vec![
+                    "package.json".into(),
+                    "packages/a/package.json".into(),
+                    "packages/b/package.json".into(),
+                    "./packages/a/./src/".into(),
+                    "packages/a/index.js".into(),
+                ],
+                options: CopyOptions {
+                    target: Some("/app".into()),
+                    ..Default::default()
+                },
+                parents: Some(true),
+                ..Default::default()
+            };
+
+            let lines = copy
+                .generate_dockerfile_lines(&mut GenerationContext::default())
+                .unwrap();
+
+            let copy_instruction = |content: &str| {
+                DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "COPY".into(),
+                    content: content.into(),
+                    options: vec![InstructionOption::Flag("link".into())],
+                })
+            };
+            assert_eq_sorted!(
+                lines,
+                vec![
+                    copy_instruction("\"package.json\" \"/app\""),
+                    copy_instruction(
+                        "\"packages/a/package.json\" \"packages/a/index.js\" \"/app/packages/a/\""
+                    ),
+                    copy_instruction("\"packages/b/package.json\" \"/app/packages/b/\""),
+                    copy_instruction("\"./packages/a/./src/\" \"/app/src/\""),
+                ]
+            );
+        }
+
+        #[test]
+        fn with_parents_labs_syntax() {
+            let copy = Copy {
+                paths: vec!["packages/*/package.json".into()],
+                parents: Some(true),
+                ..Default::default()
+            };
+
+            let lines = copy
+                .generate_dockerfile_lines(&mut GenerationContext {
+                    dofigen: Dofigen {
+                        syntax: Some("docker/dockerfile:1-labs".into()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .unwrap();
+
+            assert_eq_sorted!(
+                lines,
+                vec![DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "COPY".into(),
+                    content: "\"packages/*/package.json\" \"./\"".into(),
+                    options: vec![
+                        InstructionOption::Flag("link".into()),
+                        InstructionOption::Flag("parents".into()),
+                    ],
+                })]
+            );
+        }
+
+        #[test]
+        fn with_parents_emulation_glob() {
+            let copy = Copy {
+                paths: vec!["packages/*/package.json".into()],
+                parents: Some(true),
+                ..Default::default()
+            };
+
+            let lines = copy.generate_dockerfile_lines(&mut GenerationContext::default());
+
+            assert!(lines.is_err());
+        }
     }
 
     mod image_name {
@@ -1012,6 +2173,140 @@ mod test {
             );
         }
 
+        #[test]
+        fn with_network() {
+            let builder = Run {
+                run: vec!["make test".into()],
+                network: Some(RunNetwork::None),
+                ..Default::default()
+            };
+            assert_eq_sorted!(
+                builder
+                    .generate_dockerfile_lines(&mut GenerationContext::default())
+                    .unwrap(),
+                vec![DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "RUN".into(),
+                    content: "make test".into(),
+                    options: vec![InstructionOption::WithValue(
+                        "network".into(),
+                        "none".into()
+                    )],
+                })]
+            );
+        }
+
+        #[test]
+        fn with_secrets() {
+            let builder = Run {
+                run: vec!["npm ci".into()],
+                secret: vec![
+                    Secret {
+                        id: "npmrc".into(),
+                        target: Some("/root/.npmrc".into()),
+                        ..Default::default()
+                    },
+                    Secret {
+                        id: "pip_token".into(),
+                        env: Some("PIP_TOKEN".into()),
+                        required: Some(true),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            };
+            assert_eq_sorted!(
+                builder
+                    .generate_dockerfile_lines(&mut GenerationContext::default())
+                    .unwrap(),
+                vec![DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "RUN".into(),
+                    content: "npm ci".into(),
+                    options: vec![
+                        InstructionOption::WithOptions(
+                            "mount".into(),
+                            vec![
+                                InstructionOptionOption::new("type", "secret".into()),
+                                InstructionOptionOption::new("id", "npmrc".into()),
+                                InstructionOptionOption::new("target", "/root/.npmrc".into()),
+                            ]
+                        ),
+                        InstructionOption::WithOptions(
+                            "mount".into(),
+                            vec![
+                                InstructionOptionOption::new("type", "secret".into()),
+                                InstructionOptionOption::new("id", "pip_token".into()),
+                                InstructionOptionOption::new("env", "PIP_TOKEN".into()),
+                                InstructionOptionOption::new("required", "true".into()),
+                            ]
+                        ),
+                    ],
+                })]
+            );
+        }
+
+        #[test]
+        fn with_security() {
+            let builder = Run {
+                run: vec!["mount-fuse".into()],
+                security: Some(RunSecurity::Insecure),
+                ..Default::default()
+            };
+            assert_eq_sorted!(
+                builder
+                    .generate_dockerfile_lines(&mut GenerationContext::default())
+                    .unwrap(),
+                vec![DockerfileLine::Instruction(DockerfileInsctruction {
+                    command: "RUN".into(),
+                    content: "mount-fuse".into(),
+                    options: vec![InstructionOption::WithValue(
+                        "security".into(),
+                        "insecure".into()
+                    )],
+                })]
+            );
+        }
+
+        #[test]
+        fn with_cache_bust() {
+            let builder = Run {
+                run: vec!["apt-get update".into()],
+                cache_bust: Some(CacheBust::Auto {
+                    auto: CacheBustPeriod::Daily,
+                }),
+                ..Default::default()
+            };
+            assert_eq_sorted!(
+                builder
+                    .generate_dockerfile_lines(&mut GenerationContext::default())
+                    .unwrap(),
+                vec![
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "ARG".into(),
+                        content: "CACHE_BUST=2025-03-12".into(),
+                        options: vec![],
+                    }),
+                    DockerfileLine::Instruction(DockerfileInsctruction {
+                        command: "RUN".into(),
+                        content: "apt-get update".into(),
+                        options: vec![],
+                    })
+                ]
+            );
+        }
+
+        #[test]
+        fn cache_bust_values() {
+            let timestamp = 1741792800;
+            let auto = |auto| CacheBust::Auto { auto }.value(timestamp);
+            assert_eq!(auto(CacheBustPeriod::Hourly), "2025-03-12T15");
+            assert_eq!(auto(CacheBustPeriod::Daily), "2025-03-12");
+            assert_eq!(auto(CacheBustPeriod::Weekly), "2025-03-10");
+            assert_eq!(auto(CacheBustPeriod::Monthly), "2025-03");
+            assert_eq!(CacheBust::Value("v2".into()).value(timestamp), "v2");
+            assert_eq!(civil_date(0), "1970-01-01");
+            assert_eq!(civil_date(11016), "2000-02-29");
+        }
+
         #[test]
         fn with_script_and_caches_with_named_user() {
             let builder = Run {
@@ -0,0 +1,331 @@
+use crate::{dofigen_struct::*, generator::DEFAULT_FROM, visitor::*};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// The kind of a node of the stages graph
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum GraphNodeKind {
+    /// An image from a registry
+    Image,
+    /// A named build context
+    Context,
+    /// A builder stage
+    Builder,
+    /// The runtime stage
+    Runtime,
+}
+
+/// The way a stage depends on another node
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "camelCase")]
+pub enum GraphEdgeKind {
+    /// The base of the stage
+    From,
+    /// Files copied in the stage
+    Copy,
+    /// A cache or bind mount of a run
+    Mount,
+    /// A builder output used by the stage
+    Use,
+}
+
+/// A node of the stages graph
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GraphNode {
+    pub kind: GraphNodeKind,
+    pub id: String,
+}
+
+/// A dependency between two nodes of the stages graph
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GraphEdge {
+    /// The node the stage depends on
+    pub from: String,
+    /// The dependent stage
+    pub to: String,
+    pub kind: GraphEdgeKind,
+}
+
+/// The dependency graph of the stages, the images and the contexts of a Dofigen struct
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct StageGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl Dofigen {
+    /// Builds the dependency graph of the stages
+    pub fn stage_graph(&self) -> StageGraph {
+        let mut visitor = GraphVisitor::default();
+        visitor.visit_dofigen(self);
+
+        StageGraph {
+            nodes: visitor.nodes.into_iter().collect(),
+            edges: visitor.edges.into_iter().collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct GraphVisitor<'a> {
+    nodes: BTreeSet<GraphNode>,
+    edges: BTreeSet<GraphEdge>,
+    stage: &'a str,
+    kind: Option<GraphEdgeKind>,
+}
+
+impl GraphVisitor<'_> {
+    fn add(&mut self, from: &FromContext, kind: GraphEdgeKind) {
+        let node = match from {
+            FromContext::FromImage(image) => GraphNode {
+                kind: GraphNodeKind::Image,
+                id: image.to_string(),
+            },
+            FromContext::FromBuilder(builder) => GraphNode {
+                kind: GraphNodeKind::Builder,
+                id: builder.clone(),
+            },
+            FromContext::FromContext(Some(context)) => GraphNode {
+                kind: GraphNodeKind::Context,
+                id: context.clone(),
+            },
+            FromContext::FromContext(None) => return,
+        };
+        self.edges.insert(GraphEdge {
+            from: node.id.clone(),
+            to: self.stage.to_string(),
+            kind,
+        });
+        self.nodes.insert(node);
+    }
+}
+
+impl<'a> Visitor<'a> for GraphVisitor<'a> {
+    fn visit_stage(&mut self, name: &'a str, stage: &'a Stage) {
+        self.stage = name;
+        self.nodes.insert(GraphNode {
+            kind: if name == RUNTIME_STAGE_NAME {
+                GraphNodeKind::Runtime
+            } else {
+                GraphNodeKind::Builder
+            },
+            id: name.into(),
+        });
+        if stage.from == FromContext::FromContext(None) && !stage.external.unwrap_or_default() {
+            self.add(
+                &FromContext::FromImage(ImageName {
+                    path: DEFAULT_FROM.into(),
+                    ..Default::default()
+                }),
+                GraphEdgeKind::From,
+            );
+        }
+        self.kind = Some(GraphEdgeKind::From);
+        walk_stage(self, name, stage);
+    }
+
+    fn visit_from(&mut self, from: &'a FromContext) {
+        if let Some(kind) = self.kind {
+            self.add(from, kind);
+        }
+    }
+
+    fn visit_copy(&mut self, copy: &'a Copy) {
+        self.kind = Some(GraphEdgeKind::Copy);
+        walk_copy(self, copy);
+    }
+
+    fn visit_cache(&mut self, cache: &'a Cache) {
+        self.kind = Some(GraphEdgeKind::Mount);
+        walk_cache(self, cache);
+    }
+
+    fn visit_bind(&mut self, bind: &'a Bind) {
+        self.kind = Some(GraphEdgeKind::Mount);
+        walk_bind(self, bind);
+    }
+
+    fn visit_output_use(&mut self, output: &'a OutputUse) {
+        self.add(
+            &FromContext::FromBuilder(output.builder.clone()),
+            GraphEdgeKind::Use,
+        );
+    }
+}
+
+impl StageGraph {
+    /// Renders the graph in the Graphviz DOT language
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec!["digraph dofigen {".to_string(), "  rankdir=LR;".into()];
+        for node in self.nodes.iter() {
+            let style = match node.kind {
+                GraphNodeKind::Image => "shape=box, style=rounded",
+                GraphNodeKind::Context => "shape=folder",
+                GraphNodeKind::Builder => "shape=box",
+                GraphNodeKind::Runtime => "shape=box, style=bold",
+            };
+            lines.push(format!("  {:?} [{}];", node.id, style));
+        }
+        for edge in self.edges.iter() {
+            lines.push(format!(
+                "  {:?} -> {:?} [label=\"{}\"];",
+                edge.from, edge.to, edge.kind
+            ));
+        }
+        lines.push("}".into());
+        lines.join("\n")
+    }
+
+    /// Renders the graph as a Mermaid flowchart
+    pub fn to_mermaid(&self) -> String {
+        let id = |name: &str| {
+            self.nodes
+                .iter()
+                .position(|node| node.id == name)
+                .map(|position| format!("n{}", position))
+                .unwrap_or_default()
+        };
+        let mut lines = vec!["flowchart LR".to_string()];
+        for (position, node) in self.nodes.iter().enumerate() {
+            let label = node.id.replace('"', "#quot;");
+            lines.push(match node.kind {
+                GraphNodeKind::Image => format!("  n{}([\"{}\"])", position, label),
+                GraphNodeKind::Context => format!("  n{}[(\"{}\")]", position, label),
+                GraphNodeKind::Builder => format!("  n{}[\"{}\"]", position, label),
+                GraphNodeKind::Runtime => format!("  n{}[[\"{}\"]]", position, label),
+            });
+        }
+        for edge in self.edges.iter() {
+            lines.push(format!(
+                "  {} -->|{}| {}",
+                id(&edge.from),
+                edge.kind,
+                id(&edge.to)
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+impl std::fmt::Display for GraphEdgeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            GraphEdgeKind::From => "from",
+            GraphEdgeKind::Copy => "copy",
+            GraphEdgeKind::Mount => "mount",
+            GraphEdgeKind::Use => "use",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+    use std::collections::HashMap;
+
+    fn dofigen() -> Dofigen {
+        Dofigen {
+            builders: HashMap::from([(
+                "builder".into(),
+                Stage {
+                    from: FromContext::FromImage(ImageName {
+                        path: "rust".into(),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )]),
+            stage: Stage {
+                copy: vec![
+                    CopyResource::Copy(Copy {
+                        from: FromContext::FromBuilder("builder".into()),
+                        ..Default::default()
+                    }),
+                    CopyResource::Copy(Copy {
+                        from: FromContext::FromContext(Some("assets".into())),
+                        ..Default::default()
+                    }),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn nodes_and_edges() {
+        let graph = dofigen().stage_graph();
+
+        assert_eq_sorted!(
+            graph
+                .nodes
+                .iter()
+                .map(|node| node.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["rust", "scratch", "assets", "builder", "runtime"]
+        );
+        assert_eq_sorted!(
+            graph.edges,
+            vec![
+                GraphEdge {
+                    from: "assets".into(),
+                    to: "runtime".into(),
+                    kind: GraphEdgeKind::Copy,
+                },
+                GraphEdge {
+                    from: "builder".into(),
+                    to: "runtime".into(),
+                    kind: GraphEdgeKind::Copy,
+                },
+                GraphEdge {
+                    from: "rust".into(),
+                    to: "builder".into(),
+                    kind: GraphEdgeKind::From,
+                },
+                GraphEdge {
+                    from: "scratch".into(),
+                    to: "runtime".into(),
+                    kind: GraphEdgeKind::From,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mermaid() {
+        assert_eq_sorted!(
+            dofigen().stage_graph().to_mermaid(),
+            r#"flowchart LR
+  n0(["rust"])
+  n1(["scratch"])
+  n2[("assets")]
+  n3["builder"]
+  n4[["runtime"]]
+  n2 -->|copy| n4
+  n3 -->|copy| n4
+  n0 -->|from| n3
+  n1 -->|from| n4"#
+        );
+    }
+
+    #[test]
+    fn dot() {
+        assert_eq_sorted!(
+            dofigen().stage_graph().to_dot(),
+            r#"digraph dofigen {
+  rankdir=LR;
+  "rust" [shape=box, style=rounded];
+  "scratch" [shape=box, style=rounded];
+  "assets" [shape=folder];
+  "builder" [shape=box];
+  "runtime" [shape=box, style=bold];
+  "assets" -> "runtime" [label="copy"];
+  "builder" -> "runtime" [label="copy"];
+  "rust" -> "builder" [label="from"];
+  "scratch" -> "runtime" [label="from"];
+}"#
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{Error, Result};
+use reqwest::{
+    blocking::Client,
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf};
+
+const CACHE_DIR_ENV: &str = "DOFIGEN_CACHE_DIR";
+
+/// An on-disk cache of the URL resources, revalidated with their ETag and Last-Modified headers
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+    /// Ignore the cached entries and download the resources again
+    pub refresh: bool,
+    client: Client,
+}
+
+/// A cached URL resource
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+    content: String,
+}
+
+impl HttpCache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            refresh: false,
+            client: Client::new(),
+        }
+    }
+
+    /// The cache directory: the DOFIGEN_CACHE_DIR environment variable or the user cache directory
+    pub fn default_dir() -> Option<PathBuf> {
+        if let Some(dir) = env::var_os(CACHE_DIR_ENV) {
+            return Some(dir.into());
+        }
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .map(|dir| dir.join("dofigen"))
+    }
+
+    /// Loads the content of a URL, from the cache when the server confirms it didn't change.
+    /// The cached content is also used when the server can't be reached
+    pub(crate) fn get(&self, url: &str) -> Result<String> {
+        let cached = if self.refresh { None } else { self.read(url) };
+
+        let mut request = self.client.get(url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = match request.send() {
+            Ok(response) => response,
+            Err(err) => return cached.map(|cached| cached.content).ok_or(Error::from(err)),
+        };
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(cached.content);
+            }
+        }
+        let response = response.error_for_status()?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let content = response.text().map_err(Error::from)?;
+
+        if etag.is_some() || last_modified.is_some() {
+            self.write(&CacheEntry {
+                url: url.to_string(),
+                etag,
+                last_modified,
+                content: content.clone(),
+            });
+        }
+        Ok(content)
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.yml", sha256::digest(url)))
+    }
+
+    fn read(&self, url: &str) -> Option<CacheEntry> {
+        let content = fs::read_to_string(self.entry_path(url)).ok()?;
+        serde_yaml::from_str::<CacheEntry>(&content)
+            .ok()
+            .filter(|entry| entry.url == url)
+    }
+
+    /// The cache is only an optimization: the entries that can't be written are ignored
+    fn write(&self, entry: &CacheEntry) {
+        if let Ok(content) = serde_yaml::to_string(entry) {
+            let _ = fs::create_dir_all(&self.dir)
+                .and_then(|_| fs::write(self.entry_path(&entry.url), content));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use assert_fs::TempDir;
+    use httptest::{matchers::*, responders::*, Expectation, Server, ServerBuilder};
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    fn run_server() -> Server {
+        ServerBuilder::new()
+            .bind_addr("127.0.0.1:0".parse().unwrap())
+            .run()
+            .unwrap()
+    }
+
+    #[test]
+    fn revalidate_with_etag() {
+        let dir = TempDir::new().unwrap();
+        let server = run_server();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/dofigen.yml"),
+                not(request::headers(contains(key("if-none-match")))),
+            ])
+            .respond_with(
+                status_code(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .body("fromImage:\n  path: ubuntu\n"),
+            ),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/dofigen.yml"),
+                request::headers(contains(("if-none-match", "\"v1\""))),
+            ])
+            .respond_with(status_code(304)),
+        );
+        let url = server.url_str("/dofigen.yml");
+        let cache = HttpCache::new(dir.path());
+
+        let first = cache.get(&url).unwrap();
+        let second = cache.get(&url).unwrap();
+
+        assert_eq_sorted!(first, "fromImage:\n  path: ubuntu\n");
+        assert_eq_sorted!(second, first);
+    }
+
+    #[test]
+    fn revalidate_with_last_modified() {
+        let dir = TempDir::new().unwrap();
+        let server = run_server();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/dofigen.yml"))
+                .times(2)
+                .respond_with(cycle![
+                    status_code(200)
+                        .insert_header("Last-Modified", "Wed, 01 Jan 2025 00:00:00 GMT")
+                        .body("first"),
+                    status_code(200)
+                        .insert_header("Last-Modified", "Thu, 02 Jan 2025 00:00:00 GMT")
+                        .body("second"),
+                ]),
+        );
+        let url = server.url_str("/dofigen.yml");
+        let cache = HttpCache::new(dir.path());
+
+        assert_eq_sorted!(cache.get(&url).unwrap(), "first");
+        assert_eq_sorted!(cache.get(&url).unwrap(), "second");
+        assert_eq_sorted!(
+            cache.read(&url).unwrap().last_modified,
+            Some("Thu, 02 Jan 2025 00:00:00 GMT".into())
+        );
+    }
+
+    #[test]
+    fn refresh_ignores_cache() {
+        let dir = TempDir::new().unwrap();
+        let server = run_server();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/dofigen.yml"),
+                not(request::headers(contains(key("if-none-match")))),
+            ])
+            .times(2)
+            .respond_with(
+                status_code(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .body("content"),
+            ),
+        );
+        let url = server.url_str("/dofigen.yml");
+        let mut cache = HttpCache::new(dir.path());
+
+        cache.get(&url).unwrap();
+        cache.refresh = true;
+
+        assert_eq_sorted!(cache.get(&url).unwrap(), "content");
+    }
+
+    #[test]
+    fn unreachable_server_uses_cache() {
+        let dir = TempDir::new().unwrap();
+        let server = run_server();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/dofigen.yml")).respond_with(
+                status_code(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .body("content"),
+            ),
+        );
+        let url = server.url_str("/dofigen.yml");
+        let cache = HttpCache::new(dir.path());
+        cache.get(&url).unwrap();
+        drop(server);
+
+        assert_eq_sorted!(cache.get(&url).unwrap(), "content");
+    }
+}
@@ -17,24 +17,80 @@
 //! let dockerfile = generate_dockerfile(&dofigen).unwrap();
 //! ```
 
+mod builder;
+mod checksum;
+#[cfg(feature = "json_schema")]
+mod completion;
 mod context;
+mod dependencies;
+mod deprecation;
 mod deserialize;
+mod diff;
 mod dockerfile_struct;
+mod dockerignore;
 mod dofigen_struct;
 mod errors;
+mod events;
 mod extend;
-#[cfg(feature = "permissive")]
+mod fixtures;
 mod from_str;
 mod generator;
+mod graph;
+mod http_cache;
 #[cfg(feature = "json_schema")]
 mod json_schema;
 mod linter;
 pub mod lock;
+mod oci_config;
+mod presets;
+mod provenance;
+mod registry;
+mod registry_auth;
+mod sbom;
+mod stats;
+mod timings;
+mod update_report;
+mod validation;
+mod variables;
+mod variant;
+mod visitor;
+#[cfg(feature = "json_schema")]
+pub use completion::*;
 #[cfg(feature = "json_schema")]
 use schemars::gen::*;
 pub use {
-    context::*, deserialize::*, dofigen_struct::*, errors::*, extend::*,
-    generator::GenerationContext, linter::*,
+    builder::*,
+    checksum::*,
+    context::*,
+    dependencies::*,
+    deprecation::Deprecation,
+    deserialize::*,
+    diff::*,
+    dockerignore::*,
+    dofigen_struct::*,
+    errors::*,
+    events::ContextListener,
+    extend::*,
+    fixtures::*,
+    generator::GenerationContext,
+    graph::*,
+    http_cache::HttpCache,
+    linter::{
+        read_lint_config, LintMessage, LintRule, LintSession, MessageLevel, StageLintInfo,
+        LINT_CONFIG_FILE, LINT_RULES,
+    },
+    oci_config::*,
+    presets::{PRESETS, PRESET_SCHEME},
+    provenance::PatchOperation,
+    registry::*,
+    registry_auth::*,
+    sbom::*,
+    stats::*,
+    timings::*,
+    update_report::*,
+    variables::MatrixCombination,
+    variant::*,
+    visitor::*,
 };
 
 #[cfg(all(feature = "strict", feature = "permissive"))]
@@ -1,12 +1,24 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::LazyLock,
+};
 
-use crate::dofigen_struct::*;
+use crate::{dofigen_struct::*, Error, Result};
+use regex::Regex;
 
 const WARN_MESSAGE_FROM_CONTEXT: &str =
     "Prefer to use fromImage and fromBuilder instead of fromContext";
 const WARN_MESSAGE_FROM_CONTEXT_UNLESS: &str =
     "(unless it's really from a build context: https://docs.docker.com/reference/cli/docker/buildx/build/#build-context)";
 
+/// The root directories of the file system whose content usually comes from the base image
+const SYSTEM_DIRECTORIES: [&str; 21] = [
+    "bin", "boot", "dev", "etc", "home", "lib", "lib32", "lib64", "libx32", "media", "mnt", "opt",
+    "proc", "root", "run", "sbin", "srv", "sys", "tmp", "usr", "var",
+];
+
 #[derive(Debug, Clone, PartialEq)]
 struct StageDependency {
     stage: String,
@@ -56,16 +68,362 @@ impl Linter for Dofigen {
         }
 
         session.check_dependencies();
+        check_outputs(session, self);
+        check_builder_copy_paths(session, self);
+        check_context_copies(session, self);
+        check_run_security_syntax(session, self);
+        check_forward_args(session, self);
+    }
+}
+
+/// Returns the stages with their path, the builders first by name
+fn stages_with_path(dofigen: &Dofigen) -> Vec<(Vec<String>, &Stage)> {
+    let mut builders = dofigen.builders.iter().collect::<Vec<_>>();
+    builders.sort_by_key(|(name, _)| *name);
+    builders
+        .into_iter()
+        .map(|(name, builder)| (vec!["builders".to_string(), name.clone()], builder))
+        .chain([(vec![], &dofigen.stage)])
+        .collect()
+}
+
+/// Checks that the forwarded args are global and that the global args used by the stages are forwarded
+fn check_forward_args(session: &mut LintSession, dofigen: &Dofigen) {
+    let stages = stages_with_path(dofigen);
+    let global_args = dofigen
+        .global_arg
+        .keys()
+        .chain(
+            stages
+                .iter()
+                .filter_map(|(_, stage)| stage.from_arg.as_ref()),
+        )
+        .collect::<BTreeSet<_>>();
+    let arg_use = Regex::new(&format!(
+        r"\$\{{?({})\b",
+        global_args
+            .iter()
+            .map(|arg| regex::escape(arg))
+            .collect::<Vec<_>>()
+            .join("|")
+    ))
+    .unwrap();
+
+    for (path, stage) in stages {
+        for (position, arg) in stage.forward_args.iter().enumerate() {
+            if !global_args.contains(arg) {
+                session.messages.push(LintMessage {
+                    level: MessageLevel::Error,
+                    message: format!(
+                        "The arg '{}' isn't a global arg, declare it with globalArg or fromArg",
+                        arg
+                    ),
+                    path: [
+                        path.clone(),
+                        vec!["forwardArgs".into(), position.to_string()],
+                    ]
+                    .concat(),
+                });
+            }
+        }
+
+        let used_args = stage
+            .run
+            .run
+            .iter()
+            .chain(stage.root.iter().flat_map(|root| root.run.iter()))
+            .chain(stage.env.values())
+            .flat_map(|content| arg_use.captures_iter(content))
+            .map(|captures| captures[1].to_string())
+            .collect::<HashSet<_>>();
+        for arg in global_args.iter().filter(|arg| {
+            !stage.forward_args.contains(arg) && !stage.arg.contains_key(arg.as_str())
+        }) {
+            if used_args.contains(arg.as_str()) {
+                session.messages.push(LintMessage {
+                    level: MessageLevel::Warn,
+                    message: format!(
+                        "The global arg '{}' is used but not forwarded, its value is empty in the stage. Add it to forwardArgs",
+                        arg
+                    ),
+                    path: [path.clone(), vec!["forwardArgs".into()]].concat(),
+                });
+            }
+        }
+    }
+}
+
+/// Checks that the syntax supports the security option of the runs, only available in the labs syntax
+fn check_run_security_syntax(session: &mut LintSession, dofigen: &Dofigen) {
+    if dofigen
+        .syntax
+        .as_ref()
+        .is_some_and(|syntax| syntax.contains("labs"))
+    {
+        return;
+    }
+    for (path, stage) in stages_with_path(dofigen) {
+        let runs = std::iter::once((path.clone(), &stage.run)).chain(
+            stage
+                .root
+                .iter()
+                .map(|root| ([path.clone(), vec!["root".into()]].concat(), root)),
+        );
+        for (path, run) in runs {
+            if run.security.is_some() {
+                session.messages.push(LintMessage {
+                    level: MessageLevel::Warn,
+                    message:
+                        "The security option needs a labs syntax, like docker/dockerfile:1-labs"
+                            .into(),
+                    path: [path, vec!["security".into()]].concat(),
+                });
+            }
+        }
+    }
+}
+
+/// Checks that the used builders outputs exist and that the declared outputs are used
+fn check_outputs(session: &mut LintSession, dofigen: &Dofigen) {
+    let mut used_outputs = HashSet::new();
+
+    for (path, stage) in stages_with_path(dofigen) {
+        for (position, output_use) in stage.uses.iter().enumerate() {
+            if let Some(builder) = dofigen.builders.get(&output_use.builder) {
+                if output_use.output < builder.outputs.len() {
+                    used_outputs.insert((output_use.builder.clone(), output_use.output));
+                } else {
+                    session.messages.push(LintMessage {
+                        level: MessageLevel::Error,
+                        message: format!(
+                            "The builder '{}' has no output at position {}",
+                            output_use.builder, output_use.output
+                        ),
+                        path: [path.clone(), vec!["use".into(), position.to_string()]].concat(),
+                    });
+                }
+            } // the else is already managed in check_dependencies
+        }
+    }
+
+    let mut builders = dofigen.builders.iter().collect::<Vec<_>>();
+    builders.sort_by_key(|(name, _)| *name);
+    for (name, builder) in builders {
+        for (position, output) in builder.outputs.iter().enumerate() {
+            if !used_outputs.contains(&(name.clone(), position)) {
+                session.messages.push(LintMessage {
+                    level: MessageLevel::Warn,
+                    message: format!(
+                        "The output '{}' of the builder '{}' is not used",
+                        output, name
+                    ),
+                    path: vec![
+                        "builders".into(),
+                        name.clone(),
+                        "outputs".into(),
+                        position.to_string(),
+                    ],
+                });
+            }
+        }
+    }
+}
+
+/// Checks that the build context is restricted when files are copied from it, to keep the builds fast.
+/// The warning is only given once, on the first copy from the context
+fn check_context_copies(session: &mut LintSession, dofigen: &Dofigen) {
+    if !dofigen.context.is_empty() || !dofigen.ignore.is_empty() {
+        return;
+    }
+    let first_copy = stages_with_path(dofigen)
+        .into_iter()
+        .filter(|(_, stage)| !stage.external.unwrap_or(false))
+        .find_map(|(path, stage)| {
+            stage
+                .copy
+                .iter()
+                .position(|copy| {
+                    matches!(
+                        copy,
+                        CopyResource::Copy(Copy {
+                            from: FromContext::FromContext(None),
+                            ..
+                        })
+                    )
+                })
+                .map(|position| [path, vec!["copy".into(), position.to_string()]].concat())
+        });
+
+    if let Some(path) = first_copy {
+        session.messages.push(LintMessage {
+            level: MessageLevel::Warn,
+            message: "The files are copied from the whole build context. Define the context or ignore fields to only send the used files to the build".into(),
+            path,
+        });
+    }
+}
+
+/// Checks that the paths copied from the builders are produced by them.
+/// This is a heuristic: a path is expected to be in a system directory or related to the workdir, the copy targets, the outputs or the run commands of the builder
+fn check_builder_copy_paths(session: &mut LintSession, dofigen: &Dofigen) {
+    for (path, stage) in stages_with_path(dofigen) {
+        for (position, copy) in stage.copy.iter().enumerate() {
+            let CopyResource::Copy(Copy {
+                from: FromContext::FromBuilder(name),
+                paths,
+                ..
+            }) = copy
+            else {
+                continue;
+            };
+            let Some(builder) = dofigen.builders.get(name) else {
+                continue; // already managed in check_dependencies
+            };
+            let Some((workdir, known_paths)) = builder_known_paths(dofigen, builder) else {
+                continue;
+            };
+            paths
+                .iter()
+                .filter(|copied| copied.starts_with('/') && !copied.contains('$'))
+                .filter(|copied| {
+                    let root = copied.trim_start_matches('/').split('/').next();
+                    !root.is_some_and(|root| SYSTEM_DIRECTORIES.contains(&root))
+                })
+                .filter(|copied| !known_paths.iter().any(|known| related_paths(copied, known)))
+                .for_each(|copied| {
+                    session.messages.push(LintMessage {
+                        level: MessageLevel::Warn,
+                        message: format!(
+                            "The path '{}' is outside of the workdir '{}' of the builder '{}' and isn't used by its instructions",
+                            copied, workdir, name
+                        ),
+                        path: [path.clone(), vec!["copy".into(), position.to_string()]].concat(),
+                    });
+                });
+        }
+    }
+}
+
+/// Returns the workdir and the absolute paths produced or used by a builder and the builders it is based on.
+/// Returns None when the content of the builder can't be guessed
+fn builder_known_paths(dofigen: &Dofigen, builder: &Stage) -> Option<(String, Vec<String>)> {
+    let mut workdir = None;
+    let mut known_paths = vec![];
+    let mut visited = HashSet::new();
+    let mut current = Some(builder);
+    while let Some(stage) = current {
+        if stage.external.unwrap_or(false) {
+            return None;
+        }
+        if workdir.is_none() {
+            workdir = stage
+                .workdir
+                .clone()
+                .filter(|workdir| workdir.starts_with('/'));
+        }
+        known_paths.extend(stage.workdir.clone());
+        known_paths.extend(stage.outputs.clone());
+        known_paths.extend(stage.copy.iter().filter_map(|copy| {
+            match copy {
+                CopyResource::Copy(copy) => &copy.options,
+                CopyResource::Content(content) => &content.options,
+                CopyResource::AddGitRepo(repo) => &repo.options,
+                CopyResource::Add(add) => &add.options,
+            }
+            .target
+            .clone()
+        }));
+        known_paths.extend(
+            stage
+                .uses
+                .iter()
+                .filter_map(|output_use| output_use.target.clone()),
+        );
+        stage
+            .root
+            .iter()
+            .chain(std::iter::once(&stage.run))
+            .flat_map(|run| run.run.iter())
+            .flat_map(|command| {
+                command.split(|c: char| c.is_whitespace() || "=:;'\"()".contains(c))
+            })
+            .filter(|word| word.starts_with('/'))
+            .for_each(|word| known_paths.push(word.to_string()));
+
+        current = match &stage.from {
+            FromContext::FromBuilder(name) if visited.insert(name.clone()) => {
+                dofigen.builders.get(name)
+            }
+            _ => None,
+        };
     }
+    let known_paths = known_paths
+        .into_iter()
+        .filter(|path| path.starts_with('/'))
+        .collect();
+    workdir.map(|workdir| (workdir, known_paths))
+}
+
+/// Checks if one of the paths contains the other
+fn related_paths(a: &str, b: &str) -> bool {
+    let a = a.trim_end_matches('/');
+    let b = b.trim_end_matches('/');
+    let contains = |parent: &str, child: &str| {
+        child == parent || child.starts_with(&format!("{}/", parent)) || parent.is_empty()
+    };
+    contains(a, b) || contains(b, a)
 }
 
 impl Linter for Stage {
     fn analyze(&self, session: &mut LintSession) {
         let name = session.current_path.last().cloned();
 
+        let external = self.external.unwrap_or(false);
+
+        // Check external stage
+        if external {
+            match name.clone() {
+                None => session.add_message(
+                    MessageLevel::Error,
+                    "The runtime stage can't be external".into(),
+                ),
+                Some(name) => {
+                    if self.from.is_empty() {
+                        session.add_message(
+                            MessageLevel::Error,
+                            format!(
+                                "The external builder '{}' must define its base with fromImage or fromContext",
+                                name
+                            ),
+                        );
+                    }
+                    if *self
+                        != (Stage {
+                            from: self.from.clone(),
+                            external: self.external,
+                            outputs: self.outputs.clone(),
+                            ..Default::default()
+                        })
+                    {
+                        session.add_message(
+                            MessageLevel::Warn,
+                            format!(
+                                "The builder '{}' is external, its instructions are ignored",
+                                name
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+
         // Check empty stage
-        if let Some(name) = name.clone() {
-            if self.copy.is_empty() && self.run.run.is_empty() && self.root.is_none() {
+        if let Some(name) = name.clone().filter(|_| !external) {
+            if self.copy.is_empty()
+                && self.uses.is_empty()
+                && self.run.run.is_empty()
+                && self.root.is_none()
+            {
                 session.add_message(
                     MessageLevel::Warn,
                     format!("The builder '{}' is empty and should be removed", name),
@@ -96,13 +454,39 @@ impl Linter for Stage {
             },
         );
 
-        // Check the use of fromContext
-        if let FromContext::FromContext(Some(_)) = self.from {
-            linter_path!(session, "fromContext".into(), {
-                session.add_message(MessageLevel::Warn, WARN_MESSAGE_FROM_CONTEXT.to_string());
+        // Check the default of the base image arg
+        if let Some(arg) = &self.from_arg {
+            if matches!(
+                self.from,
+                FromContext::FromBuilder(_) | FromContext::FromContext(Some(_))
+            ) {
+                linter_path!(session, "fromArg".into(), {
+                    session.add_message(
+                        MessageLevel::Error,
+                        format!(
+                            "The default value of the fromArg '{}' must be defined with fromImage",
+                            arg
+                        ),
+                    );
+                });
+            }
+        }
+
+        // Check the use of the compiler cache
+        if self.compiler_cache.is_some() && self.run.is_empty() && !external {
+            linter_path!(session, "compilerCache".into(), {
+                session.add_message(
+                    MessageLevel::Warn,
+                    "The compiler cache is not used without run instructions".into(),
+                );
             });
         }
 
+        // Check the use of fromContext
+        if let (FromContext::FromContext(Some(context)), false) = (&self.from, external) {
+            session.check_from_context(context, WARN_MESSAGE_FROM_CONTEXT.to_string());
+        }
+
         linter_path!(session, "copy".into(), {
             for (position, copy) in self.copy.iter().enumerate() {
                 linter_path!(session, position.to_string(), {
@@ -119,9 +503,9 @@ impl Linter for Stage {
 
         self.run.analyze(session);
 
-        // Check if the user is using the username instead of the UID
+        // Check if the user is using the username instead of the UID, unless it's a defined user
         if let Some(user) = &self.user {
-            if user.uid().is_none() {
+            if user.uid().is_none() && !session.users.contains(&user.user) {
                 linter_path!(session, "user".into(), {
                     session.add_message(
                         MessageLevel::Warn,
@@ -137,6 +521,7 @@ impl Linter for CopyResource {
     fn analyze(&self, session: &mut LintSession) {
         match self {
             CopyResource::Copy(copy) => copy.analyze(session),
+            CopyResource::AddGitRepo(add_git_repo) => add_git_repo.analyze(session),
             _ => {}
         }
     }
@@ -144,21 +529,67 @@ impl Linter for CopyResource {
 
 impl Linter for Copy {
     fn analyze(&self, session: &mut LintSession) {
-        match &self.from {
-            FromContext::FromContext(Some(_)) => {
-                linter_path!(session, "fromContext".into(), {
+        if let FromContext::FromContext(Some(context)) = &self.from {
+            session.check_from_context(
+                context,
+                format!(
+                    "{} {}",
+                    WARN_MESSAGE_FROM_CONTEXT, WARN_MESSAGE_FROM_CONTEXT_UNLESS
+                ),
+            );
+        }
+
+        check_excludes(session, &self.exclude, &self.paths);
+    }
+}
+
+impl Linter for AddGitRepo {
+    fn analyze(&self, session: &mut LintSession) {
+        check_excludes(session, &self.exclude, &[]);
+    }
+}
+
+fn check_excludes(session: &mut LintSession, exclude: &[String], paths: &[String]) {
+    let normalize = |path: &String| {
+        path.trim_start_matches("./")
+            .trim_end_matches('/')
+            .to_string()
+    };
+    let paths: Vec<String> = paths.iter().map(normalize).collect();
+    let mut previous = HashSet::new();
+
+    linter_path!(session, "exclude".into(), {
+        for (position, pattern) in exclude.iter().enumerate() {
+            linter_path!(session, position.to_string(), {
+                let normalized = normalize(pattern);
+                if pattern.trim().is_empty() {
+                    session.add_message(
+                        MessageLevel::Error,
+                        "The exclude pattern can't be empty".into(),
+                    );
+                } else if ["", ".", "*", "**"].contains(&normalized.as_str()) {
+                    session.add_message(
+                        MessageLevel::Warn,
+                        format!("The exclude pattern '{}' excludes everything", pattern),
+                    );
+                } else if paths.contains(&normalized) {
                     session.add_message(
                         MessageLevel::Warn,
                         format!(
-                            "{} {}",
-                            WARN_MESSAGE_FROM_CONTEXT, WARN_MESSAGE_FROM_CONTEXT_UNLESS
+                            "The exclude pattern '{}' excludes the copied path itself",
+                            pattern
                         ),
                     );
-                });
-            }
-            _ => {}
+                }
+                if !previous.insert(normalized) {
+                    session.add_message(
+                        MessageLevel::Warn,
+                        format!("The exclude pattern '{}' is duplicated", pattern),
+                    );
+                }
+            });
         }
-    }
+    });
 }
 
 impl Linter for Run {
@@ -183,6 +614,15 @@ impl Linter for Run {
             }
         }
 
+        if self.security == Some(RunSecurity::Insecure) {
+            linter_path!(session, "security".into(), {
+                session.add_message(
+                    MessageLevel::Warn,
+                    "The insecure security mode runs the commands with all the capabilities and needs the security.insecure entitlement".to_string(),
+                );
+            });
+        }
+
         linter_path!(session, "run".into(), {
             for (position, command) in self.run.iter().enumerate() {
                 linter_path!(session, position.to_string(), {
@@ -199,16 +639,14 @@ impl Linter for Run {
         linter_path!(session, "bind".into(), {
             for (position, bind) in self.bind.iter().enumerate() {
                 linter_path!(session, position.to_string(), {
-                    if let FromContext::FromContext(Some(_)) = bind.from {
-                        linter_path!(session, "fromContext".into(), {
-                            session.add_message(
-                                MessageLevel::Warn,
-                                format!(
-                                    "{} {}",
-                                    WARN_MESSAGE_FROM_CONTEXT, WARN_MESSAGE_FROM_CONTEXT_UNLESS
-                                ),
-                            );
-                        });
+                    if let FromContext::FromContext(Some(context)) = &bind.from {
+                        session.check_from_context(
+                            context,
+                            format!(
+                                "{} {}",
+                                WARN_MESSAGE_FROM_CONTEXT, WARN_MESSAGE_FROM_CONTEXT_UNLESS
+                            ),
+                        );
                     }
                 });
             }
@@ -217,16 +655,14 @@ impl Linter for Run {
         linter_path!(session, "cache".into(), {
             for (position, cache) in self.cache.iter().enumerate() {
                 linter_path!(session, position.to_string(), {
-                    if let FromContext::FromContext(Some(_)) = cache.from {
-                        linter_path!(session, "fromContext".into(), {
-                            session.add_message(
-                                MessageLevel::Warn,
-                                format!(
-                                    "{} {}",
-                                    WARN_MESSAGE_FROM_CONTEXT, WARN_MESSAGE_FROM_CONTEXT_UNLESS
-                                ),
-                            );
-                        });
+                    if let FromContext::FromContext(Some(context)) = &cache.from {
+                        session.check_from_context(
+                            context,
+                            format!(
+                                "{} {}",
+                                WARN_MESSAGE_FROM_CONTEXT, WARN_MESSAGE_FROM_CONTEXT_UNLESS
+                            ),
+                        );
                     }
                 });
             }
@@ -248,11 +684,22 @@ impl StageDependencyGetter for Stage {
                 origin: [origin.clone(), vec!["from".into()]].concat(),
             });
         }
+        // The instructions of the external stages are not generated
+        if self.external.unwrap_or(false) {
+            return dependencies;
+        }
         for (position, copy) in self.copy.iter().enumerate() {
             dependencies.append(&mut copy.get_dependencies(
                 &[origin.clone(), vec!["copy".into(), position.to_string()]].concat(),
             ));
         }
+        for (position, output_use) in self.uses.iter().enumerate() {
+            dependencies.push(StageDependency {
+                stage: output_use.builder.clone(),
+                path: "/".into(),
+                origin: [origin.clone(), vec!["use".into(), position.to_string()]].concat(),
+            });
+        }
         dependencies.append(&mut self.run.get_dependencies(origin));
         if let Some(root) = &self.root {
             dependencies.append(
@@ -314,6 +761,8 @@ pub struct LintSession {
     messages: Vec<LintMessage>,
     stage_infos: HashMap<String, StageLintInfo>,
     recursive_stage_dependencies: HashMap<String, Vec<String>>,
+    additional_contexts: HashSet<String>,
+    users: HashSet<String>,
 }
 
 impl LintSession {
@@ -333,36 +782,87 @@ impl LintSession {
         });
     }
 
+    /// Warns about the use of a fromContext, unless it's a declared additional context
+    fn check_from_context(&mut self, context: &str, message: String) {
+        if self.additional_contexts.contains(context) {
+            return;
+        }
+        let message = if self.additional_contexts.is_empty() {
+            message
+        } else {
+            format!(
+                "The context '{}' is not declared in the additionalContexts",
+                context
+            )
+        };
+        linter_path!(self, "fromContext".into(), {
+            self.add_message(MessageLevel::Warn, message);
+        });
+    }
+
+    /// Warns about the builders renamed since their names aren't valid stage names
+    pub(crate) fn add_renamed_stages(&mut self, renamed: &BTreeMap<String, String>) {
+        for (name, sanitized) in renamed {
+            self.messages.push(LintMessage {
+                level: MessageLevel::Warn,
+                message: format!(
+                    "The builder name '{}' isn't a valid stage name, the stage is named '{}'",
+                    name, sanitized
+                ),
+                path: vec!["builders".into(), name.clone()],
+            });
+        }
+    }
+
     pub fn messages(&self) -> Vec<LintMessage> {
         self.messages.clone()
     }
 
+    /// Sorts the builders so that each one comes after the builders it depends on, by name otherwise.
+    /// The builders of a cycle are added at the end, the cycle being reported as an error
     pub fn get_sorted_builders(&mut self) -> Vec<String> {
-        let mut stages: Vec<(String, Vec<String>)> = self
+        let mut names = self.stage_infos.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        for name in names.iter() {
+            self.get_stage_recursive_dependencies(name.clone());
+        }
+
+        let mut dependencies = self
             .stage_infos
-            .clone()
-            .keys()
-            .map(|name| {
+            .iter()
+            .map(|(name, info)| {
                 (
                     name.clone(),
-                    self.get_stage_recursive_dependencies(name.clone()),
+                    info.dependencies
+                        .iter()
+                        .map(|dependency| dependency.stage.clone())
+                        .filter(|stage| stage != name && self.stage_infos.contains_key(stage))
+                        .collect::<HashSet<_>>(),
                 )
             })
-            .collect();
+            .collect::<HashMap<_, _>>();
 
-        stages.sort_by(|(a_stage, a_deps), (b_stage, b_deps)| {
-            if a_deps.contains(b_stage) {
-                return std::cmp::Ordering::Greater;
-            }
-            if b_deps.contains(a_stage) {
-                return std::cmp::Ordering::Less;
+        let mut sorted = vec![];
+        let mut ready = dependencies
+            .iter()
+            .filter(|(_, stages)| stages.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect::<BTreeSet<_>>();
+        while let Some(name) = ready.pop_first() {
+            dependencies.remove(&name);
+            for (dependent, stages) in dependencies.iter_mut() {
+                if stages.remove(&name) && stages.is_empty() {
+                    ready.insert(dependent.clone());
+                }
             }
-            a_stage.cmp(b_stage)
-        });
+            sorted.push(name);
+        }
+        let mut cyclic = dependencies.into_keys().collect::<Vec<_>>();
+        cyclic.sort();
+        sorted.append(&mut cyclic);
 
-        stages
+        sorted
             .into_iter()
-            .map(|(stage, _)| stage)
             .filter(|name| *name != "runtime")
             .collect()
     }
@@ -410,6 +910,23 @@ impl LintSession {
         deps
     }
 
+    /// Returns the builders the runtime stage depends on, directly or through other builders
+    pub fn used_builders(&self) -> HashSet<String> {
+        let mut used = HashSet::new();
+        let mut stages = vec!["runtime".to_string()];
+        while let Some(stage) = stages.pop() {
+            let Some(info) = self.stage_infos.get(&stage) else {
+                continue;
+            };
+            for dependency in info.dependencies.iter() {
+                if dependency.stage != "runtime" && used.insert(dependency.stage.clone()) {
+                    stages.push(dependency.stage.clone());
+                }
+            }
+        }
+        used
+    }
+
     /// Checks if dependencies are using path that are in cache
     fn check_dependencies(&mut self) {
         let dependencies = self
@@ -425,18 +942,14 @@ impl LintSession {
             .collect::<HashMap<_, _>>();
 
         // Check if there is unused builders
-        let used_builders = dependencies
-            .iter()
-            .map(|dep| dep.stage.clone())
-            .collect::<HashSet<_>>();
-
+        let used_builders = self.used_builders();
         let unused_builders = self
             .stage_infos
             .keys()
             .filter(|name| name != &"runtime")
             .map(|name| name.clone())
             .filter(|name| !used_builders.contains(name))
-            .collect::<HashSet<_>>();
+            .collect::<BTreeSet<_>>();
 
         linter_path!(self, "builders".into(), {
             for builder in unused_builders {
@@ -526,11 +1039,46 @@ impl LintSession {
 
     /// Analyze the given Dofigen configuration and return a lint session
     pub fn analyze(dofigen: &Dofigen) -> Self {
-        let mut session = Self::default();
+        let mut session = Self {
+            additional_contexts: dofigen.additional_contexts.keys().cloned().collect(),
+            users: dofigen.users.keys().cloned().collect(),
+            ..Default::default()
+        };
         dofigen.analyze(&mut session);
 
         session
     }
+
+    /// Checks the best practice rules with the given levels, by rule identifier,
+    /// and returns their messages with the identifier of their rule
+    pub fn check_rules(
+        dofigen: &Dofigen,
+        levels: &HashMap<String, LintLevel>,
+    ) -> Vec<(&'static str, LintMessage)> {
+        LINT_RULES
+            .iter()
+            .filter_map(|rule| {
+                let level = match levels.get(rule.id) {
+                    Some(LintLevel::Off) => return None,
+                    Some(LintLevel::Warn) => MessageLevel::Warn,
+                    Some(LintLevel::Error) => MessageLevel::Error,
+                    None => rule.level.clone(),
+                };
+                let mut session = Self::default();
+                (rule.check)(&mut session, dofigen);
+                Some(session.messages.into_iter().map(move |message| {
+                    (
+                        rule.id,
+                        LintMessage {
+                            level: level.clone(),
+                            ..message
+                        },
+                    )
+                }))
+            })
+            .flatten()
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -552,56 +1100,247 @@ pub enum MessageLevel {
     Error,
 }
 
-#[cfg(test)]
-mod test {
-    use crate::Dofigen;
+////////// Rules //////////
 
-    use super::*;
-    use pretty_assertions_sorted::assert_eq_sorted;
+/// The file configuring the levels of the lint rules, read next to the Dofigen file
+pub const LINT_CONFIG_FILE: &str = ".dofigenlint.yml";
 
-    mod stage_dependencies {
-        use super::*;
+/// The apt install commands
+static APT_INSTALL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bapt(-get)? +(\S+ +)*install\b").unwrap());
 
-        #[test]
-        fn builders_dependencies() {
-            let dofigen = Dofigen {
-                builders: HashMap::from([
-                    (
-                        "builder1".into(),
-                        Stage {
-                            copy: vec![CopyResource::Copy(Copy {
-                                from: FromContext::FromBuilder("builder2".into()),
-                                paths: vec!["/path/to/copy".into()],
-                                options: Default::default(),
-                                ..Default::default()
-                            })],
-                            ..Default::default()
-                        },
-                    ),
-                    (
-                        "builder2".into(),
-                        Stage {
-                            copy: vec![CopyResource::Copy(Copy {
-                                from: FromContext::FromBuilder("builder3".into()),
-                                paths: vec!["/path/to/copy".into()],
-                                options: Default::default(),
-                                ..Default::default()
-                            })],
-                            ..Default::default()
-                        },
-                    ),
-                    (
-                        "builder3".into(),
-                        Stage {
-                            run: Run {
-                                run: vec!["echo Hello".into()].into(),
-                                ..Default::default()
-                            },
-                            ..Default::default()
-                        },
-                    ),
-                ]),
-                stage: Stage {
+/// The names of the env and args that look like secrets
+static SECRET_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(password|passwd|secret|token|api_?key|private_?key)").unwrap()
+});
+
+/// A best practice rule checked by the lint command.
+/// Unlike the checks of the lint session, the rules can be disabled or have their level changed
+#[derive(Debug, Clone)]
+pub struct LintRule {
+    /// The identifier of the rule, like unpinned-image
+    pub id: &'static str,
+
+    /// The level of the messages of the rule
+    pub level: MessageLevel,
+
+    /// What the rule checks
+    pub description: &'static str,
+
+    check: fn(&mut LintSession, &Dofigen),
+}
+
+/// The best practice rules, by identifier
+pub const LINT_RULES: [LintRule; 3] = [
+    LintRule {
+        id: "unpinned-image",
+        level: MessageLevel::Warn,
+        description: "The images must be pinned with a tag other than latest or a digest",
+        check: check_unpinned_images,
+    },
+    LintRule {
+        id: "apt-get-cleanup",
+        level: MessageLevel::Warn,
+        description: "The apt-get install commands must use a cache or remove the package lists",
+        check: check_apt_get_cleanup,
+    },
+    LintRule {
+        id: "secret-in-env",
+        level: MessageLevel::Error,
+        description:
+            "The secrets must be mounted in the run instructions instead of being set as env or arg",
+        check: check_secrets_in_env,
+    },
+];
+
+impl LintRule {
+    /// Returns the rule with the given identifier
+    pub fn find(id: &str) -> Option<&'static LintRule> {
+        LINT_RULES.iter().find(|rule| rule.id == id)
+    }
+}
+
+/// Returns the message of an unknown lint rule identifier
+pub(crate) fn unknown_rule_message(id: &str) -> String {
+    format!(
+        "The lint rule '{}' doesn't exist, the rules are: {}",
+        id,
+        LINT_RULES
+            .iter()
+            .map(|rule| rule.id)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Reads the levels of the lint rules, by rule identifier, from a lint configuration file
+pub fn read_lint_config<P: AsRef<Path>>(path: P) -> Result<HashMap<String, LintLevel>> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(|err| {
+        Error::Custom(format!(
+            "Unable to read the file {}: {}",
+            path.display(),
+            err
+        ))
+    })?;
+    let levels: HashMap<String, LintLevel> = serde_yaml::from_str(&content).map_err(|err| {
+        Error::Custom(format!(
+            "Invalid lint configuration {}: {}",
+            path.display(),
+            err
+        ))
+    })?;
+    let mut ids = levels.keys().collect::<Vec<_>>();
+    ids.sort();
+    if let Some(id) = ids.into_iter().find(|id| LintRule::find(id).is_none()) {
+        return Err(Error::Custom(format!(
+            "{}: {}",
+            path.display(),
+            unknown_rule_message(id)
+        )));
+    }
+    Ok(levels)
+}
+
+impl Dofigen {
+    /// Checks the best practice rules and returns their messages with the identifier of their rule.
+    /// The levels of the lint field disable the rules or change their severity
+    pub fn lint(&self) -> Vec<(&'static str, LintMessage)> {
+        LintSession::check_rules(self, &self.lint)
+    }
+}
+
+fn check_unpinned_images(session: &mut LintSession, dofigen: &Dofigen) {
+    for (path, image) in dofigen.used_images() {
+        let tag = match &image.version {
+            Some(ImageVersion::Digest(_)) => continue,
+            Some(ImageVersion::Tag(tag)) if tag != "latest" => continue,
+            Some(ImageVersion::Tag(tag)) => tag.as_str(),
+            None => "latest",
+        };
+        session.messages.push(LintMessage {
+            level: MessageLevel::Warn,
+            message: format!(
+                "The image '{}' uses the {} tag, pin it with a version tag or a digest",
+                image.path, tag
+            ),
+            path,
+        });
+    }
+}
+
+fn check_apt_get_cleanup(session: &mut LintSession, dofigen: &Dofigen) {
+    for (prefix, stage) in stages_with_path(dofigen) {
+        let runs = stage
+            .root
+            .iter()
+            .map(|root| (vec!["root".to_string()], root))
+            .chain([(vec![], &stage.run)]);
+        for (run_path, run) in runs {
+            let cached = run
+                .cache
+                .iter()
+                .any(|cache| cache.target.starts_with("/var/cache/apt"));
+            let cleaned = run
+                .run
+                .iter()
+                .any(|command| command.contains("/var/lib/apt/lists"));
+            if cached || cleaned {
+                continue;
+            }
+            if let Some(position) = run
+                .run
+                .iter()
+                .position(|command| APT_INSTALL.is_match(command))
+            {
+                session.messages.push(LintMessage {
+                    level: MessageLevel::Warn,
+                    message: "The apt-get install command keeps the package lists in the image, use a cache on /var/cache/apt or remove /var/lib/apt/lists/*".into(),
+                    path: [
+                        prefix.clone(),
+                        run_path,
+                        vec!["run".into(), position.to_string()],
+                    ]
+                    .concat(),
+                });
+            }
+        }
+    }
+}
+
+fn check_secrets_in_env(session: &mut LintSession, dofigen: &Dofigen) {
+    for (prefix, stage) in stages_with_path(dofigen) {
+        for (field, values) in [("env", &stage.env), ("arg", &stage.arg)] {
+            let mut names = values
+                .iter()
+                .filter(|(name, value)| SECRET_NAME.is_match(name) && !value.is_empty())
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                session.messages.push(LintMessage {
+                    level: MessageLevel::Error,
+                    message: format!(
+                        "The {} '{}' looks like a secret and is kept in the image, mount it with the secret field of the run instead",
+                        field, name
+                    ),
+                    path: [prefix.clone(), vec![field.into(), name.clone()]].concat(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Dofigen;
+
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    mod stage_dependencies {
+        use super::*;
+
+        #[test]
+        fn builders_dependencies() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([
+                    (
+                        "builder1".into(),
+                        Stage {
+                            copy: vec![CopyResource::Copy(Copy {
+                                from: FromContext::FromBuilder("builder2".into()),
+                                paths: vec!["/path/to/copy".into()],
+                                options: Default::default(),
+                                ..Default::default()
+                            })],
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "builder2".into(),
+                        Stage {
+                            copy: vec![CopyResource::Copy(Copy {
+                                from: FromContext::FromBuilder("builder3".into()),
+                                paths: vec!["/path/to/copy".into()],
+                                options: Default::default(),
+                                ..Default::default()
+                            })],
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "builder3".into(),
+                        Stage {
+                            run: Run {
+                                run: vec!["echo Hello".into()].into(),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                    ),
+                ]),
+                stage: Stage {
                     copy: vec![CopyResource::Copy(Copy {
                         from: FromContext::FromBuilder("builder1".into()),
                         paths: vec!["/path/to/copy".into()],
@@ -703,18 +1442,35 @@ mod test {
 
             assert_eq_sorted!(
                 lint_session.messages,
-                vec![LintMessage {
-                    level: MessageLevel::Error,
-                    path: vec![
-                        "builders".into(),
-                        "builder3".into(),
-                        "copy".into(),
-                        "0".into(),
-                    ],
-                    message:
-                        "Circular dependency detected: builder1 -> builder2 -> builder3 -> builder1"
-                            .into(),
-                },]
+                vec![
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["builders".into(), "builder1".into()],
+                        message: "The builder 'builder1' is not used and should be removed".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["builders".into(), "builder2".into()],
+                        message: "The builder 'builder2' is not used and should be removed".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["builders".into(), "builder3".into()],
+                        message: "The builder 'builder3' is not used and should be removed".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Error,
+                        path: vec![
+                            "builders".into(),
+                            "builder3".into(),
+                            "copy".into(),
+                            "0".into(),
+                        ],
+                        message:
+                            "Circular dependency detected: builder1 -> builder2 -> builder3 -> builder1"
+                                .into(),
+                    },
+                ]
             );
         }
 
@@ -751,6 +1507,97 @@ mod test {
             );
         }
 
+        #[test]
+        fn builders_topological_order() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([
+                    (
+                        "app".into(),
+                        Stage {
+                            from: FromContext::FromBuilder("zig".into()),
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "deps".into(),
+                        Stage {
+                            from: FromContext::FromBuilder("base".into()),
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "zig".into(),
+                        Stage {
+                            from: FromContext::FromBuilder("deps".into()),
+                            ..Default::default()
+                        },
+                    ),
+                    ("base".into(), Stage::default()),
+                    ("lint".into(), Stage::default()),
+                ]),
+                ..Default::default()
+            };
+
+            let mut lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.get_sorted_builders(),
+                vec!["base", "deps", "lint", "zig", "app"]
+            );
+        }
+
+        #[test]
+        fn builder_used_by_unused_builder() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([
+                    (
+                        "deps".into(),
+                        Stage {
+                            from: FromContext::FromImage(ImageName {
+                                path: "rust".into(),
+                                ..Default::default()
+                            }),
+                            run: Run {
+                                run: vec!["cargo fetch".into()],
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "tests".into(),
+                        Stage {
+                            from: FromContext::FromBuilder("deps".into()),
+                            run: Run {
+                                run: vec!["cargo test".into()],
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                    ),
+                ]),
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["builders".into(), "deps".into()],
+                        message: "The builder 'deps' is not used and should be removed".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["builders".into(), "tests".into()],
+                        message: "The builder 'tests' is not used and should be removed".into(),
+                    },
+                ]
+            );
+        }
+
         #[test]
         fn builder_not_found() {
             let dofigen = Dofigen {
@@ -1049,16 +1896,20 @@ mod test {
                 },]
             );
         }
-    }
-
-    mod user {
-        use super::*;
 
         #[test]
-        fn uid() {
+        fn external() {
             let dofigen = Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        from: FromContext::FromContext(Some("prebuilt".into())),
+                        external: Some(true),
+                        ..Default::default()
+                    },
+                )]),
                 stage: Stage {
-                    user: Some(User::new("1000")),
+                    from: FromContext::FromBuilder("builder".into()),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -1070,10 +1921,22 @@ mod test {
         }
 
         #[test]
-        fn username() {
+        fn external_with_instructions() {
             let dofigen = Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        external: Some(true),
+                        run: Run {
+                            run: vec!["echo Hello".into()],
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                )]),
                 stage: Stage {
-                    user: Some(User::new("test")),
+                    from: FromContext::FromBuilder("builder".into()),
+                    external: Some(true),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -1083,28 +1946,39 @@ mod test {
 
             assert_eq_sorted!(
                 lint_session.messages,
-                vec![LintMessage {
-                    level: MessageLevel::Warn,
-                    path: vec!["user".into()],
-                    message: "UID should be used instead of username".into(),
-                },]
+                vec![
+                    LintMessage {
+                        level: MessageLevel::Error,
+                        path: vec!["builders".into(), "builder".into()],
+                        message: "The external builder 'builder' must define its base with fromImage or fromContext".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["builders".into(), "builder".into()],
+                        message: "The builder 'builder' is external, its instructions are ignored"
+                            .into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Error,
+                        path: vec![],
+                        message: "The runtime stage can't be external".into(),
+                    },
+                ]
             );
         }
     }
 
-    mod from_context {
+    mod compiler_cache {
         use super::*;
 
         #[test]
-        fn stage_and_copy() {
+        fn without_run() {
             let dofigen = Dofigen {
                 stage: Stage {
-                    from: FromContext::FromContext(Some("php:8.3-fpm-alpine".into())),
-                    copy: vec![CopyResource::Copy(Copy {
-                        from: FromContext::FromContext(Some("composer:latest".into())),
-                        paths: vec!["/usr/bin/composer".into()],
+                    compiler_cache: Some(CompilerCache {
+                        bucket: "build-cache".into(),
                         ..Default::default()
-                    })],
+                    }),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -1112,13 +1986,207 @@ mod test {
 
             let lint_session = LintSession::analyze(&dofigen);
 
-            assert_eq_sorted!(lint_session.messages, vec![
-                LintMessage {
-                    level: MessageLevel::Warn,
-                    path: vec!["fromContext".into()],
-                    message: "Prefer to use fromImage and fromBuilder instead of fromContext".into(),   
-                },
-                LintMessage {
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["compilerCache".into()],
+                    message: "The compiler cache is not used without run instructions".into(),
+                },]
+            );
+        }
+    }
+
+    mod user {
+        use super::*;
+
+        #[test]
+        fn uid() {
+            let dofigen = Dofigen {
+                stage: Stage {
+                    user: Some(User::new("1000")),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(lint_session.messages, vec![]);
+        }
+
+        #[test]
+        fn username() {
+            let dofigen = Dofigen {
+                stage: Stage {
+                    user: Some(User::new("test")),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["user".into()],
+                    message: "UID should be used instead of username".into(),
+                },]
+            );
+        }
+    }
+
+    mod from_arg {
+        use super::*;
+
+        #[test]
+        fn default_from_builder() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        from: FromContext::FromImage(ImageName {
+                            path: "rust".into(),
+                            ..Default::default()
+                        }),
+                        from_arg: Some("BUILDER_IMAGE".into()),
+                        run: Run {
+                            run: vec!["cargo build".into()],
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                )]),
+                stage: Stage {
+                    from: FromContext::FromBuilder("builder".into()),
+                    from_arg: Some("BASE_IMAGE".into()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Error,
+                    message:
+                        "The default value of the fromArg 'BASE_IMAGE' must be defined with fromImage"
+                            .into(),
+                    path: vec!["fromArg".into()],
+                }]
+            );
+        }
+    }
+
+    mod forward_args {
+        use super::*;
+
+        #[test]
+        fn not_global_arg() {
+            let dofigen = Dofigen {
+                global_arg: HashMap::from([("VERSION".into(), "1.0".into())]),
+                stage: Stage {
+                    forward_args: vec!["VERSION".into(), "GIT_SHA".into()],
+                    run: Run {
+                        run: vec!["echo $VERSION".into()],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Error,
+                    message:
+                        "The arg 'GIT_SHA' isn't a global arg, declare it with globalArg or fromArg"
+                            .into(),
+                    path: vec!["forwardArgs".into(), "1".into()],
+                }]
+            );
+        }
+
+        #[test]
+        fn used_without_forward() {
+            let dofigen = Dofigen {
+                global_arg: HashMap::from([("GIT_SHA".into(), "".into())]),
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        from: FromContext::FromImage(ImageName {
+                            path: "rust".into(),
+                            ..Default::default()
+                        }),
+                        from_arg: Some("RUST_IMAGE".into()),
+                        run: Run {
+                            run: vec!["cargo build".into()],
+                            ..Default::default()
+                        },
+                        env: HashMap::from([("SHA".into(), "${GIT_SHA}".into())]),
+                        ..Default::default()
+                    },
+                )]),
+                stage: Stage {
+                    copy: vec![CopyResource::Copy(Copy {
+                        from: FromContext::FromBuilder("builder".into()),
+                        paths: vec!["/app".into()],
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    message:
+                        "The global arg 'GIT_SHA' is used but not forwarded, its value is empty in the stage. Add it to forwardArgs"
+                            .into(),
+                    path: vec!["builders".into(), "builder".into(), "forwardArgs".into()],
+                }]
+            );
+        }
+    }
+
+    mod from_context {
+        use super::*;
+
+        #[test]
+        fn stage_and_copy() {
+            let dofigen = Dofigen {
+                stage: Stage {
+                    from: FromContext::FromContext(Some("php:8.3-fpm-alpine".into())),
+                    copy: vec![CopyResource::Copy(Copy {
+                        from: FromContext::FromContext(Some("composer:latest".into())),
+                        paths: vec!["/usr/bin/composer".into()],
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(lint_session.messages, vec![
+                LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["fromContext".into()],
+                    message: "Prefer to use fromImage and fromBuilder instead of fromContext".into(),   
+                },
+                LintMessage {
                     level: MessageLevel::Warn,
                     path: vec!["copy".into(), "0".into(), "fromContext".into()],
                     message: "Prefer to use fromImage and fromBuilder instead of fromContext (unless it's really from a build context: https://docs.docker.com/reference/cli/docker/buildx/build/#build-context)".into(),
@@ -1169,11 +2237,104 @@ mod test {
                 }
             ]);
         }
+
+        #[test]
+        fn additional_contexts() {
+            let dofigen = Dofigen {
+                additional_contexts: HashMap::from([
+                    ("base".into(), "docker-image://alpine:3.21".into()),
+                    ("assets".into(), "../assets".into()),
+                ]),
+                stage: Stage {
+                    from: FromContext::FromContext(Some("base".into())),
+                    copy: vec![
+                        CopyResource::Copy(Copy {
+                            from: FromContext::FromContext(Some("assets".into())),
+                            paths: vec!["/images".into()],
+                            ..Default::default()
+                        }),
+                        CopyResource::Copy(Copy {
+                            from: FromContext::FromContext(Some("docs".into())),
+                            paths: vec!["/index.html".into()],
+                            ..Default::default()
+                        }),
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["copy".into(), "1".into(), "fromContext".into()],
+                    message: "The context 'docs' is not declared in the additionalContexts".into(),
+                }]
+            );
+        }
     }
 
     mod run {
         use super::*;
 
+        #[test]
+        fn insecure_security() {
+            let dofigen = Dofigen {
+                syntax: Some("docker/dockerfile:1-labs".into()),
+                stage: Stage {
+                    run: Run {
+                        run: vec!["mount-fuse".into()],
+                        security: Some(RunSecurity::Insecure),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    message: "The insecure security mode runs the commands with all the capabilities and needs the security.insecure entitlement".into(),
+                    path: vec!["security".into()],
+                }]
+            );
+        }
+
+        #[test]
+        fn security_without_labs_syntax() {
+            let dofigen = Dofigen {
+                stage: Stage {
+                    root: Some(Run {
+                        run: vec!["apk add fuse".into()],
+                        security: Some(RunSecurity::Sandbox),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    message:
+                        "The security option needs a labs syntax, like docker/dockerfile:1-labs"
+                            .into(),
+                    path: vec!["root".into(), "security".into()],
+                }]
+            );
+        }
+
         #[test]
         fn empty_run() {
             let dofigen = Dofigen {
@@ -1215,4 +2376,451 @@ mod test {
             );
         }
     }
+
+    mod exclude {
+        use super::*;
+
+        #[test]
+        fn valid_patterns() {
+            let dofigen = Dofigen {
+                stage: Stage {
+                    copy: vec![CopyResource::Copy(Copy {
+                        paths: vec!["src".into()],
+                        exclude: vec!["src/*.test.js".into(), "**/node_modules".into()],
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+                context: vec!["src".into()],
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(lint_session.messages, vec![]);
+        }
+
+        #[test]
+        fn conflicting_patterns() {
+            let dofigen = Dofigen {
+                stage: Stage {
+                    copy: vec![
+                        CopyResource::Copy(Copy {
+                            paths: vec!["./src/".into()],
+                            exclude: vec!["src".into(), "".into(), "*.md".into(), "./*.md".into()],
+                            ..Default::default()
+                        }),
+                        CopyResource::AddGitRepo(AddGitRepo {
+                            repo: "https://github.com/lenra-io/dofigen.git".into(),
+                            exclude: vec!["**".into()],
+                            ..Default::default()
+                        }),
+                    ],
+                    ..Default::default()
+                },
+                context: vec!["src".into()],
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["copy".into(), "0".into(), "exclude".into(), "0".into()],
+                        message: "The exclude pattern 'src' excludes the copied path itself".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Error,
+                        path: vec!["copy".into(), "0".into(), "exclude".into(), "1".into()],
+                        message: "The exclude pattern can't be empty".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["copy".into(), "0".into(), "exclude".into(), "3".into()],
+                        message: "The exclude pattern './*.md' is duplicated".into(),
+                    },
+                    LintMessage {
+                        level: MessageLevel::Warn,
+                        path: vec!["copy".into(), "1".into(), "exclude".into(), "0".into()],
+                        message: "The exclude pattern '**' excludes everything".into(),
+                    },
+                ]
+            );
+        }
+    }
+
+    mod builder_copy_paths {
+        use super::*;
+
+        fn dofigen_copying(builder: Stage, paths: Vec<String>) -> Dofigen {
+            Dofigen {
+                builders: HashMap::from([("builder".into(), builder)]),
+                stage: Stage {
+                    copy: vec![CopyResource::Copy(Copy {
+                        from: FromContext::FromBuilder("builder".into()),
+                        paths,
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+
+        fn rust_builder() -> Stage {
+            Stage {
+                from: FromContext::FromImage(ImageName {
+                    path: "rust".into(),
+                    ..Default::default()
+                }),
+                workdir: Some("/src".into()),
+                run: Run {
+                    run: vec![
+                        "cargo build --release".into(),
+                        "mv target/release/app /build/app".into(),
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn outside_of_workdir() {
+            let dofigen = dofigen_copying(rust_builder(), vec!["/app/target/release/app".into()]);
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["copy".into(), "0".into()],
+                    message: "The path '/app/target/release/app' is outside of the workdir '/src' of the builder 'builder' and isn't used by its instructions".into(),
+                }]
+            );
+        }
+
+        #[test]
+        fn plausible_paths() {
+            let dofigen = dofigen_copying(
+                rust_builder(),
+                vec![
+                    "/src/target/release/app".into(),
+                    "/build/app".into(),
+                    "/usr/local/cargo/bin/cargo".into(),
+                    "target/release/app".into(),
+                ],
+            );
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(lint_session.messages, vec![]);
+        }
+
+        #[test]
+        fn without_workdir() {
+            let dofigen = dofigen_copying(
+                Stage {
+                    workdir: None,
+                    ..rust_builder()
+                },
+                vec!["/app/target/release/app".into()],
+            );
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(lint_session.messages, vec![]);
+        }
+    }
+
+    mod context_copies {
+        use super::*;
+
+        fn dofigen_copying_context() -> Dofigen {
+            Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        copy: vec![CopyResource::Copy(Copy {
+                            paths: vec!["Cargo.toml".into(), "src".into()],
+                            ..Default::default()
+                        })],
+                        ..Default::default()
+                    },
+                )]),
+                stage: Stage {
+                    from: FromContext::FromBuilder("builder".into()),
+                    copy: vec![CopyResource::Copy(Copy {
+                        paths: vec!["config.yml".into()],
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn unrestricted_context() {
+            let dofigen = dofigen_copying_context();
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec!["builders".into(), "builder".into(), "copy".into(), "0".into()],
+                    message: "The files are copied from the whole build context. Define the context or ignore fields to only send the used files to the build".into(),
+                }]
+            );
+        }
+
+        #[test]
+        fn restricted_context() {
+            let dofigen = Dofigen {
+                context: vec!["Cargo.toml".into(), "src".into(), "config.yml".into()],
+                ..dofigen_copying_context()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(lint_session.messages, vec![]);
+        }
+
+        #[test]
+        fn ignored_files() {
+            let dofigen = Dofigen {
+                ignore: vec!["target".into()],
+                ..dofigen_copying_context()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(lint_session.messages, vec![]);
+        }
+    }
+
+    mod outputs {
+        use super::*;
+
+        #[test]
+        fn missing_output() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        run: Run {
+                            run: vec!["echo Hello > /out".into()],
+                            ..Default::default()
+                        },
+                        outputs: vec!["/out".into()],
+                        ..Default::default()
+                    },
+                )]),
+                stage: Stage {
+                    uses: vec![
+                        OutputUse {
+                            builder: "builder".into(),
+                            output: 0,
+                            ..Default::default()
+                        },
+                        OutputUse {
+                            builder: "builder".into(),
+                            output: 1,
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Error,
+                    path: vec!["use".into(), "1".into()],
+                    message: "The builder 'builder' has no output at position 1".into(),
+                },]
+            );
+        }
+
+        #[test]
+        fn unused_output() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        run: Run {
+                            run: vec!["echo Hello > /out".into()],
+                            ..Default::default()
+                        },
+                        outputs: vec!["/out".into(), "/logs".into()],
+                        ..Default::default()
+                    },
+                )]),
+                stage: Stage {
+                    uses: vec![OutputUse {
+                        builder: "builder".into(),
+                        output: 0,
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let lint_session = LintSession::analyze(&dofigen);
+
+            assert_eq_sorted!(
+                lint_session.messages,
+                vec![LintMessage {
+                    level: MessageLevel::Warn,
+                    path: vec![
+                        "builders".into(),
+                        "builder".into(),
+                        "outputs".into(),
+                        "1".into()
+                    ],
+                    message: "The output '/logs' of the builder 'builder' is not used".into(),
+                },]
+            );
+        }
+    }
+
+    mod rules {
+        use super::*;
+
+        fn messages(dofigen: &Dofigen) -> Vec<String> {
+            dofigen
+                .lint()
+                .into_iter()
+                .map(|(rule, message)| {
+                    format!("{} {}: {}", rule, message.path.join("."), message.message)
+                })
+                .collect()
+        }
+
+        #[test]
+        fn best_practices() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        from: FromContext::FromImage(ImageName {
+                            path: "debian".into(),
+                            version: Some(ImageVersion::Tag("12".into())),
+                            ..Default::default()
+                        }),
+                        run: Run {
+                            run: vec!["apt-get update && apt-get install -y curl".into()],
+                            cache: vec![Cache {
+                                target: "/var/cache/apt".into(),
+                                ..Default::default()
+                            }],
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                )]),
+                stage: Stage {
+                    from: FromContext::FromImage(ImageName {
+                        path: "debian".into(),
+                        version: Some(ImageVersion::Tag("12".into())),
+                        ..Default::default()
+                    }),
+                    env: HashMap::from([("LOG_LEVEL".into(), "info".into())]),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            assert_eq_sorted!(messages(&dofigen), Vec::<String>::new());
+        }
+
+        #[test]
+        fn all_rules() {
+            let dofigen = Dofigen {
+                builders: HashMap::from([(
+                    "builder".into(),
+                    Stage {
+                        from: FromContext::FromImage(ImageName {
+                            path: "node".into(),
+                            version: Some(ImageVersion::Tag("latest".into())),
+                            ..Default::default()
+                        }),
+                        arg: HashMap::from([("NPM_TOKEN".into(), "abc".into())]),
+                        ..Default::default()
+                    },
+                )]),
+                stage: Stage {
+                    from: FromContext::FromImage(ImageName {
+                        path: "debian".into(),
+                        ..Default::default()
+                    }),
+                    env: HashMap::from([
+                        ("DB_PASSWORD".into(), "changeme".into()),
+                        ("API_KEY".into(), "".into()),
+                    ]),
+                    root: Some(Run {
+                        run: vec!["apt-get install -y --no-install-recommends curl".into()],
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            assert_eq_sorted!(
+                messages(&dofigen),
+                vec![
+                    "unpinned-image builders.builder.fromImage: The image 'node' uses the latest tag, pin it with a version tag or a digest",
+                    "unpinned-image fromImage: The image 'debian' uses the latest tag, pin it with a version tag or a digest",
+                    "apt-get-cleanup root.run.0: The apt-get install command keeps the package lists in the image, use a cache on /var/cache/apt or remove /var/lib/apt/lists/*",
+                    "secret-in-env builders.builder.arg.NPM_TOKEN: The arg 'NPM_TOKEN' looks like a secret and is kept in the image, mount it with the secret field of the run instead",
+                    "secret-in-env env.DB_PASSWORD: The env 'DB_PASSWORD' looks like a secret and is kept in the image, mount it with the secret field of the run instead",
+                ]
+            );
+        }
+
+        #[test]
+        fn rule_levels() {
+            let dofigen = Dofigen {
+                lint: HashMap::from([
+                    ("unpinned-image".into(), LintLevel::Error),
+                    ("secret-in-env".into(), LintLevel::Off),
+                ]),
+                stage: Stage {
+                    from: FromContext::FromImage(ImageName {
+                        path: "debian".into(),
+                        ..Default::default()
+                    }),
+                    env: HashMap::from([("DB_PASSWORD".into(), "changeme".into())]),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            let messages = dofigen.lint();
+
+            assert_eq_sorted!(
+                messages,
+                vec![(
+                    "unpinned-image",
+                    LintMessage {
+                        level: MessageLevel::Error,
+                        path: vec!["fromImage".into()],
+                        message: "The image 'debian' uses the latest tag, pin it with a version tag or a digest".into(),
+                    },
+                )]
+            );
+        }
+    }
 }
@@ -1,19 +1,32 @@
 use crate::{dofigen_struct::*, DofigenContext, Error, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
 
 pub(crate) const DOCKER_HUB_HOST: &str = "registry.hub.docker.com";
 pub(crate) const DEFAULT_NAMESPACE: &str = "library";
 const DEFAULT_TAG: &str = "latest";
 const DEFAULT_PORT: u16 = 443;
 
+/// The image label giving the digest of the lock file used to generate the Dockerfile
+pub const LOCK_LABEL: &str = "io.lenra.dofigen.lock";
+/// The image label giving the Dofigen version that generated the Dockerfile
+pub const VERSION_LABEL: &str = "io.lenra.dofigen.version";
+
+/// The locked version of an image tag
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, PartialOrd, Eq)]
 pub struct DockerTag {
     pub digest: String,
 }
 
+/// The locked version of an extended resource
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, PartialOrd, Eq)]
 pub struct ResourceVersion {
+    /// The SHA-256 hash of the content
     pub hash: String,
     pub content: String,
 }
@@ -32,6 +45,8 @@ impl ImageName {
     }
 }
 
+/// The lock file of a Dofigen file, like dofigen.lock.
+/// It pins the image tags to their digests and the extended resources to their content
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LockFile {
     /// The effective Dofigen configuration
@@ -46,10 +61,28 @@ pub struct LockFile {
 
     /// The files used in the Dofigen file for 'extend' fields
     pub resources: HashMap<String, ResourceVersion>,
+
+    /// The local files loaded instead of the URL resources, by URL
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub vendor: HashMap<String, String>,
+
+    /// The checksums of the URL files of the ADD instructions without checksum, by URL
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub checksums: HashMap<String, String>,
 }
 
 impl LockFile {
-    fn images(&self) -> HashMap<ImageName, DockerTag> {
+    /// Reads a lock file
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<LockFile> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|err| Error::Custom(format!("Could not read file {:?}: {}", path, err)))?;
+        serde_yaml::from_str(&content)
+            .map_err(|err| Error::deserialize(err, &content, Some(path.display().to_string())))
+    }
+
+    /// Returns the locked image tags, their names being filled with the default host, port and tag
+    pub fn images(&self) -> HashMap<ImageName, DockerTag> {
         let mut images = HashMap::new();
         for (host, namespaces) in self.images.clone() {
             let (host, port) = if host.contains(":") {
@@ -85,7 +118,8 @@ impl LockFile {
         images
     }
 
-    fn resources(&self) -> HashMap<Resource, ResourceVersion> {
+    /// Returns the locked extended resources
+    pub fn resources(&self) -> HashMap<Resource, ResourceVersion> {
         self.resources
             .clone()
             .into_iter()
@@ -93,8 +127,81 @@ impl LockFile {
             .collect()
     }
 
+    /// Returns the locked digest of the image, or its own digest when the image is pinned by digest
+    pub fn digest_for(&self, image: &ImageName) -> Option<String> {
+        if let Some(ImageVersion::Digest(digest)) = &image.version {
+            return Some(digest.clone());
+        }
+        self.images()
+            .get(&image.fill())
+            .map(|tag| tag.digest.clone())
+    }
+
+    /// Checks that the tags of the images used by the Dofigen struct are all locked
+    pub fn is_locked(&self, dofigen: &Dofigen) -> bool {
+        let mut context = self.to_context();
+        context.offline = true;
+        dofigen.lock(&mut context).is_ok()
+    }
+
+    /// Parses the effective configuration, whose image tags are replaced by their digests
+    pub fn effective(&self) -> Result<Dofigen> {
+        DofigenContext::new().parse_from_string(&self.effective)
+    }
+
     pub fn to_context(&self) -> DofigenContext {
-        DofigenContext::from(self.resources(), self.images())
+        let mut context = DofigenContext::from(self.resources(), self.images());
+        context.vendored_resources = self
+            .vendor
+            .iter()
+            .filter_map(|(url, path)| Some((url.parse().ok()?, PathBuf::from(path))))
+            .collect();
+        context.add_checksums = self
+            .checksums
+            .iter()
+            .filter_map(|(url, checksum)| Some((url.parse().ok()?, checksum.clone())))
+            .collect();
+        context
+    }
+
+    /// Returns a digest of the locked content: the effective configuration, the images digests and the resources hashes.
+    /// It doesn't depend on the order of the fields in the lock file
+    pub fn digest(&self) -> Result<String> {
+        let effective: Value = serde_yaml::from_str(&self.effective).map_err(Error::from)?;
+        let mut lines = vec![serde_yaml::to_string(&canonical(effective)).map_err(Error::from)?];
+        let mut images = self
+            .images()
+            .into_iter()
+            .map(|(image, tag)| format!("{} {}", image.to_string(), tag.digest))
+            .collect::<Vec<_>>();
+        images.sort();
+        lines.append(&mut images);
+        let mut resources = self
+            .resources
+            .iter()
+            .map(|(resource, version)| format!("{} {}", resource, version.hash))
+            .collect::<Vec<_>>();
+        resources.sort();
+        lines.append(&mut resources);
+        let mut checksums = self
+            .checksums
+            .iter()
+            .map(|(url, checksum)| format!("{} {}", url, checksum))
+            .collect::<Vec<_>>();
+        checksums.sort();
+        lines.append(&mut checksums);
+        Ok(sha256::digest(lines.join("\n")))
+    }
+
+    /// Returns the image labels linking the image to this lock file and the Dofigen version
+    pub fn labels(&self) -> Result<BTreeMap<String, String>> {
+        Ok(BTreeMap::from([
+            (LOCK_LABEL.to_string(), self.digest()?),
+            (
+                VERSION_LABEL.to_string(),
+                env!("CARGO_PKG_VERSION").to_string(),
+            ),
+        ]))
     }
 
     pub fn from_context(effective: &Dofigen, context: &DofigenContext) -> Result<LockFile> {
@@ -129,14 +236,55 @@ impl LockFile {
             .map(|(resource, content)| (resource.to_string(), content.clone()))
             .collect();
 
+        let vendor = context
+            .used_vendored_resources()
+            .into_iter()
+            .map(|(url, path)| (url.to_string(), path.to_string_lossy().to_string()))
+            .collect();
+
+        let checksums = context
+            .used_add_checksums()
+            .into_iter()
+            .map(|(url, checksum)| (url.to_string(), checksum))
+            .collect();
+
         Ok(LockFile {
             effective: serde_yaml::to_string(effective).map_err(Error::from)?,
             images,
             resources: files,
+            vendor,
+            checksums,
         })
     }
 }
 
+/// Sorts the keys of the mappings to get the same content whatever the order of the fields
+fn canonical(value: Value) -> Value {
+    match value {
+        Value::Mapping(mapping) => {
+            let mut entries = mapping
+                .into_iter()
+                .map(|(key, value)| {
+                    (
+                        serde_yaml::to_string(&key).unwrap_or_default(),
+                        key,
+                        canonical(value),
+                    )
+                })
+                .collect::<Vec<_>>();
+            entries.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+            Value::Mapping(
+                entries
+                    .into_iter()
+                    .map(|(_, key, value)| (key, value))
+                    .collect::<Mapping>(),
+            )
+        }
+        Value::Sequence(sequence) => Value::Sequence(sequence.into_iter().map(canonical).collect()),
+        value => value,
+    }
+}
+
 pub trait Lock: Sized {
     fn lock(&self, context: &mut DofigenContext) -> Result<Self>;
 }
@@ -231,6 +379,7 @@ impl Lock for CopyResource {
     fn lock(&self, context: &mut DofigenContext) -> Result<Self> {
         match self {
             Self::Copy(resource) => Ok(Self::Copy(resource.lock(context)?)),
+            Self::Add(resource) => Ok(Self::Add(resource.lock(context)?)),
             other => Ok(other.clone()),
         }
     }
@@ -245,6 +394,20 @@ impl Lock for Copy {
     }
 }
 
+impl Lock for Add {
+    fn lock(&self, context: &mut DofigenContext) -> Result<Self> {
+        // Only a single URL file can be checked
+        let checksum = match (&self.checksum, self.files.as_slice()) {
+            (None, [Resource::Url(url)]) => context.get_add_checksum(url)?,
+            (checksum, _) => checksum.clone(),
+        };
+        Ok(Self {
+            checksum,
+            ..self.clone()
+        })
+    }
+}
+
 impl Lock for Run {
     fn lock(&self, context: &mut DofigenContext) -> Result<Self> {
         Ok(Self {
@@ -284,3 +447,132 @@ impl Ord for ResourceVersion {
         self.hash.cmp(&other.hash)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    fn lockfile(effective: &str) -> LockFile {
+        LockFile {
+            effective: effective.into(),
+            images: HashMap::new(),
+            resources: HashMap::from([(
+                "dofigen.yml".into(),
+                ResourceVersion {
+                    hash: "abc".into(),
+                    content: String::new(),
+                },
+            )]),
+            vendor: HashMap::new(),
+            checksums: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn digest_ignores_fields_order() {
+        let digest = lockfile("env:\n  A: a\n  B: b\nfromImage:\n  path: alpine\n")
+            .digest()
+            .unwrap();
+
+        assert_eq_sorted!(
+            lockfile("fromImage:\n  path: alpine\nenv:\n  B: b\n  A: a\n")
+                .digest()
+                .unwrap(),
+            digest
+        );
+        assert_ne!(
+            lockfile("fromImage:\n  path: ubuntu\n").digest().unwrap(),
+            digest
+        );
+    }
+
+    #[test]
+    fn query_images() {
+        let lockfile = LockFile {
+            images: HashMap::from([(
+                "registry.hub.docker.com:443".to_string(),
+                HashMap::from([(
+                    DEFAULT_NAMESPACE.to_string(),
+                    HashMap::from([(
+                        "alpine".to_string(),
+                        HashMap::from([(
+                            "3.20".to_string(),
+                            DockerTag {
+                                digest: "sha256:abc".into(),
+                            },
+                        )]),
+                    )]),
+                )]),
+            )]),
+            ..lockfile("fromImage:\n  path: alpine\n")
+        };
+        let image = |name: &str| name.parse::<ImageName>().unwrap();
+
+        assert_eq_sorted!(
+            lockfile.digest_for(&image("alpine:3.20")),
+            Some("sha256:abc".into())
+        );
+        assert_eq_sorted!(lockfile.digest_for(&image("alpine:3.21")), None);
+        assert_eq_sorted!(
+            lockfile.digest_for(&image("alpine@sha256:def")),
+            Some("sha256:def".into())
+        );
+
+        let dofigen = |name: &str| Dofigen {
+            stage: Stage {
+                from: image(name).into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(lockfile.is_locked(&dofigen("alpine:3.20")));
+        assert!(!lockfile.is_locked(&dofigen("alpine:3.21")));
+    }
+
+    #[test]
+    fn recorded_add_checksums() {
+        let url = "https://example.com/archive.tar.gz";
+        let lockfile = LockFile {
+            checksums: HashMap::from([(url.to_string(), "sha256:abc".to_string())]),
+            ..lockfile("fromImage:\n  path: alpine\n")
+        };
+        let add = |files: Vec<Resource>| {
+            CopyResource::Add(Add {
+                files,
+                ..Default::default()
+            })
+        };
+        let dofigen = Dofigen {
+            stage: Stage {
+                from: FromContext::FromContext(None),
+                copy: vec![
+                    add(vec![Resource::Url(url.parse().unwrap())]),
+                    add(vec![Resource::File("archive.tar.gz".into())]),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut context = lockfile.to_context();
+        context.offline = true;
+        let locked = dofigen.lock(&mut context).unwrap();
+
+        assert_eq_sorted!(
+            locked.stage.copy,
+            vec![
+                CopyResource::Add(Add {
+                    files: vec![Resource::Url(url.parse().unwrap())],
+                    checksum: Some("sha256:abc".into()),
+                    ..Default::default()
+                }),
+                add(vec![Resource::File("archive.tar.gz".into())]),
+            ]
+        );
+        assert_eq_sorted!(
+            LockFile::from_context(&locked, &context).unwrap().checksums,
+            lockfile.checksums
+        );
+    }
+}
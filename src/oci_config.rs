@@ -0,0 +1,216 @@
+use crate::{Dofigen, FromContext, Port, Stage, User};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// The OCI image configuration of the runtime stage, for the tools that build the image layers separately
+/// See https://github.com/opencontainers/image-spec/blob/main/config.md
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OciImageConfig {
+    /// The CPU architecture of the runtime stage platform
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<String>,
+
+    /// The operating system of the runtime stage platform
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+
+    /// The CPU variant of the runtime stage platform
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+
+    /// The execution parameters of the containers
+    pub config: OciConfig,
+}
+
+/// The execution parameters of the containers of an OCI image
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct OciConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub exposed_ports: BTreeMap<String, OciEmpty>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entrypoint: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cmd: Vec<String>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub volumes: BTreeMap<String, OciEmpty>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<String>,
+}
+
+/// The empty object used as value of the sets of the OCI image configuration
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct OciEmpty {}
+
+impl Dofigen {
+    /// Returns the runtime stage followed by the builders it's based on
+    fn runtime_stage_chain(&self) -> Vec<&Stage> {
+        let mut chain = vec![&self.stage];
+        let mut visited = HashSet::new();
+        while let FromContext::FromBuilder(name) = &chain.last().unwrap().from {
+            match self.builders.get(name) {
+                Some(builder) if visited.insert(name) => chain.push(builder),
+                _ => break,
+            }
+        }
+        chain
+    }
+}
+
+impl From<&Dofigen> for OciImageConfig {
+    fn from(dofigen: &Dofigen) -> Self {
+        let chain = dofigen.runtime_stage_chain();
+
+        // The runtime stage overrides the values of the builders it's based on
+        let mut env = BTreeMap::new();
+        chain
+            .iter()
+            .rev()
+            .for_each(|stage| env.extend(stage.env.iter()));
+
+        let mut platform = dofigen
+            .stage
+            .platform
+            .as_deref()
+            .map(|platform| platform.split('/').map(str::to_string))
+            .into_iter()
+            .flatten();
+
+        Self {
+            os: platform.next(),
+            architecture: platform.next(),
+            variant: platform.next(),
+            config: OciConfig {
+                user: Some(
+                    dofigen
+                        .stage
+                        .user
+                        .as_ref()
+                        .map(|user| dofigen.resolve_user(user))
+                        .unwrap_or(User::new("1000"))
+                        .to_string(),
+                ),
+                // The OCI configuration has no port ranges, they are exposed port by port
+                exposed_ports: dofigen
+                    .expose
+                    .iter()
+                    .flat_map(|port| {
+                        (port.port..=port.end.unwrap_or(port.port)).map(|number| {
+                            Port {
+                                port: number,
+                                end: None,
+                                protocol: port.protocol.clone(),
+                            }
+                            .to_string()
+                        })
+                    })
+                    .map(|port| (port, OciEmpty {}))
+                    .collect(),
+                env: env
+                    .into_iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect(),
+                entrypoint: dofigen.entrypoint.clone(),
+                cmd: dofigen.cmd.clone(),
+                volumes: dofigen
+                    .volume
+                    .iter()
+                    .map(|volume| (volume.clone(), OciEmpty {}))
+                    .collect(),
+                working_dir: chain.iter().find_map(|stage| stage.workdir.clone()),
+                stop_signal: dofigen.stop_signal.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::PortProtocol;
+    use pretty_assertions_sorted::assert_eq_sorted;
+    use std::collections::HashMap;
+
+    #[test]
+    fn runtime_config() {
+        let dofigen = Dofigen {
+            builders: HashMap::from([(
+                "base".into(),
+                Stage {
+                    workdir: Some("/app".into()),
+                    env: HashMap::from([
+                        ("PATH".into(), "/app/bin".into()),
+                        ("MODE".into(), "base".into()),
+                    ]),
+                    ..Default::default()
+                },
+            )]),
+            stage: Stage {
+                from: FromContext::FromBuilder("base".into()),
+                platform: Some("linux/arm64/v8".into()),
+                env: HashMap::from([("MODE".into(), "runtime".into())]),
+                ..Default::default()
+            },
+            expose: vec![
+                Port {
+                    port: 8080,
+                    end: None,
+                    protocol: None,
+                },
+                Port {
+                    port: 53,
+                    end: None,
+                    protocol: Some(PortProtocol::Udp),
+                },
+                Port {
+                    port: 9000,
+                    end: Some(9001),
+                    protocol: Some(PortProtocol::Tcp),
+                },
+            ],
+            volume: vec!["/data".into()],
+            entrypoint: vec!["/app/bin/server".into()],
+            cmd: vec!["--verbose".into()],
+            stop_signal: Some("SIGTERM".into()),
+            ..Default::default()
+        };
+
+        assert_eq_sorted!(
+            OciImageConfig::from(&dofigen),
+            OciImageConfig {
+                architecture: Some("arm64".into()),
+                os: Some("linux".into()),
+                variant: Some("v8".into()),
+                config: OciConfig {
+                    user: Some("1000:1000".into()),
+                    exposed_ports: BTreeMap::from([
+                        ("53/udp".into(), OciEmpty {}),
+                        ("8080".into(), OciEmpty {}),
+                        ("9000/tcp".into(), OciEmpty {}),
+                        ("9001/tcp".into(), OciEmpty {}),
+                    ]),
+                    env: vec!["MODE=runtime".into(), "PATH=/app/bin".into()],
+                    entrypoint: vec!["/app/bin/server".into()],
+                    cmd: vec!["--verbose".into()],
+                    volumes: BTreeMap::from([("/data".into(), OciEmpty {})]),
+                    working_dir: Some("/app".into()),
+                    stop_signal: Some("SIGTERM".into()),
+                },
+            }
+        );
+    }
+}
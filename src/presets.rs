@@ -0,0 +1,70 @@
+use crate::{Error, Result};
+use url::Url;
+
+/// The URL scheme of the presets, like `preset://rust-musl`
+pub const PRESET_SCHEME: &str = "preset";
+
+/// The presets embedded in Dofigen, by name.
+/// They evolve with the Dofigen versions, the lock file keeping track of their changes
+pub const PRESETS: &[(&str, &str)] = &[
+    ("go", include_str!("presets/go.yml")),
+    ("node", include_str!("presets/node.yml")),
+    ("python", include_str!("presets/python.yml")),
+    ("rust", include_str!("presets/rust.yml")),
+    ("rust-musl", include_str!("presets/rust-musl.yml")),
+];
+
+pub(crate) fn is_preset(url: &Url) -> bool {
+    url.scheme() == PRESET_SCHEME
+}
+
+/// Returns the content of the preset of the URL
+pub(crate) fn preset_content(url: &Url) -> Result<String> {
+    let name = url.host_str().unwrap_or_default();
+    PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, content)| content.to_string())
+        .ok_or_else(|| {
+            Error::Custom(format!(
+                "Unknown preset '{}', the available presets are: {}",
+                name,
+                PRESETS
+                    .iter()
+                    .map(|(preset, _)| *preset)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DofigenContext, GenerationContext, Resource};
+
+    #[test]
+    fn parse_presets() {
+        for (name, _) in PRESETS {
+            let mut context = DofigenContext::new();
+            let dofigen = context
+                .parse_from_string(&format!("extend:\n  - {}://{}", PRESET_SCHEME, name))
+                .unwrap_or_else(|err| panic!("The preset '{}' is invalid: {}", name, err));
+            GenerationContext::from(dofigen)
+                .generate_dockerfile()
+                .unwrap_or_else(|err| panic!("The preset '{}' can't be generated: {}", name, err));
+        }
+    }
+
+    #[test]
+    fn unknown_preset() {
+        let mut context = DofigenContext::new();
+        let error = context
+            .parse_from_resource(Resource::Url("preset://cobol".parse().unwrap()))
+            .unwrap_err();
+
+        assert!(error.to_string().contains(
+            "Unknown preset 'cobol', the available presets are: go, node, python, rust, rust-musl"
+        ));
+    }
+}
@@ -0,0 +1,389 @@
+use crate::{deprecation::DEPRECATIONS, Resource};
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+
+/// The fields of the extend chain layers and their profiles are ignored since they are not merged as is
+const EXTEND_FIELDS: &[&str] = &["extend", "extends", "profiles"];
+
+/// The operation of the layer that last changed a value of the merged configuration
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PatchOperation {
+    /// The value is defined or replaces the previous one
+    #[default]
+    Set,
+    /// The fields of the structure are merged with the previous ones
+    Merge,
+    /// The element is appended to the list, with the `+` key
+    Append,
+    /// The element is inserted in the list, with the `+N` or `N+` keys
+    Insert,
+    /// The element of the list is replaced, with the `N` key
+    Replace,
+    /// The element of the list is patched, with the `N<` key
+    Patch,
+}
+
+impl std::fmt::Display for PatchOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PatchOperation::Set => "set",
+            PatchOperation::Merge => "merge",
+            PatchOperation::Append => "append",
+            PatchOperation::Insert => "insert",
+            PatchOperation::Replace => "replace",
+            PatchOperation::Patch => "patch",
+        })
+    }
+}
+
+/// The layer that last changed a value of the merged configuration, and how
+#[derive(Debug, Clone, PartialEq, Default)]
+struct OriginNode {
+    resource: Option<Resource>,
+    operation: PatchOperation,
+    children: OriginChildren,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+enum OriginChildren {
+    #[default]
+    None,
+    Fields(BTreeMap<String, OriginNode>),
+    Elements(Vec<OriginNode>),
+}
+
+/// A key of the patch structure of a list
+enum ListPatchKey {
+    ReplaceAll,
+    Append,
+    InsertBefore(usize),
+    InsertAfter(usize),
+    Replace(usize),
+    Patch(usize),
+}
+
+impl ListPatchKey {
+    fn parse(key: &Value) -> Option<Self> {
+        let key = match key {
+            Value::Number(position) => return Some(Self::Replace(position.as_u64()? as usize)),
+            Value::String(key) => key.as_str(),
+            _ => return None,
+        };
+        Some(match key {
+            "_" => Self::ReplaceAll,
+            "+" => Self::Append,
+            _ => {
+                if let Some(position) = key.strip_prefix('+') {
+                    Self::InsertBefore(position.parse().ok()?)
+                } else if let Some(position) = key.strip_suffix('+') {
+                    Self::InsertAfter(position.parse().ok()?)
+                } else if let Some(position) = key.strip_suffix('<') {
+                    Self::Patch(position.parse().ok()?)
+                } else {
+                    Self::Replace(key.parse().ok()?)
+                }
+            }
+        })
+    }
+}
+
+/// Tracks the resource of the extend chain that introduced each field of the merged configuration.
+/// The layers are pushed while they are loaded and committed once their own extends are merged,
+/// so the committed order follows the merge order and the last layer defining a field wins
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FieldOrigins {
+    pending: Vec<(Option<Resource>, Value)>,
+    root: OriginNode,
+}
+
+impl FieldOrigins {
+    /// Starts tracking a new configuration
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+        self.root = OriginNode::default();
+    }
+
+    /// Registers a loaded layer, the main document having no resource
+    pub(crate) fn push_layer(&mut self, resource: Option<Resource>, content: &str) {
+        // Deserialization errors are reported while parsing the Dofigen struct
+        let value = serde_yaml::from_str(content).unwrap_or(Value::Null);
+        self.pending.push((resource, value));
+    }
+
+    /// Applies the fields of the last registered layer over the previous ones
+    pub(crate) fn commit_layer(&mut self) {
+        let Some((resource, Value::Mapping(mut mapping))) = self.pending.pop() else {
+            return;
+        };
+        EXTEND_FIELDS.iter().for_each(|field| {
+            mapping.remove(*field);
+        });
+        self.root
+            .merge_fields(&resource, &mapping, &mut vec![], PatchOperation::Merge);
+    }
+
+    /// Returns the node of the deepest tracked value of the given path
+    fn node(&self, path: &[String]) -> &OriginNode {
+        let mut node = &self.root;
+        for key in path {
+            let child = match &node.children {
+                OriginChildren::Fields(fields) => fields.get(key),
+                OriginChildren::Elements(elements) => key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|position| elements.get(position)),
+                OriginChildren::None => None,
+            };
+            match child {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+        node
+    }
+
+    /// Returns the extended resource that introduced the value at the given path.
+    /// The values of the main document have no origin
+    pub(crate) fn get(&self, path: &[String]) -> Option<&Resource> {
+        self.node(path).resource.as_ref()
+    }
+
+    /// Returns the operation of the layer that last changed the value at the given path
+    pub(crate) fn operation(&self, path: &[String]) -> PatchOperation {
+        self.node(path).operation
+    }
+}
+
+impl OriginNode {
+    fn new(resource: &Option<Resource>, value: &Value, operation: PatchOperation) -> Self {
+        let mut node = Self::default();
+        node.apply(resource, value, &mut vec![], operation);
+        node
+    }
+
+    /// Applies a layer value over the node.
+    /// The lists and the scalars replace the previous value, while the mappings are merged
+    fn apply(
+        &mut self,
+        resource: &Option<Resource>,
+        value: &Value,
+        path: &mut Vec<String>,
+        operation: PatchOperation,
+    ) {
+        self.resource = resource.clone();
+        self.operation = operation;
+        match value {
+            Value::Mapping(mapping) => {
+                let keys = mapping
+                    .keys()
+                    .map(ListPatchKey::parse)
+                    .collect::<Option<Vec<_>>>();
+                match (&mut self.children, keys) {
+                    (OriginChildren::Elements(elements), Some(keys)) => {
+                        let elements = std::mem::take(elements);
+                        self.operation = PatchOperation::Merge;
+                        self.children = OriginChildren::Elements(patch_elements(
+                            elements, keys, mapping, resource,
+                        ));
+                    }
+                    // A patch of an undefined list, that can't be a mapping with numeric keys
+                    (OriginChildren::None, Some(keys))
+                        if !keys
+                            .iter()
+                            .any(|key| matches!(key, ListPatchKey::Replace(_))) =>
+                    {
+                        self.children = OriginChildren::Elements(patch_elements(
+                            vec![],
+                            keys,
+                            mapping,
+                            resource,
+                        ));
+                    }
+                    _ => self.merge_fields(resource, mapping, path, operation),
+                }
+            }
+            Value::Sequence(sequence) => {
+                self.children = OriginChildren::Elements(
+                    sequence
+                        .iter()
+                        .map(|child| OriginNode::new(resource, child, PatchOperation::Set))
+                        .collect(),
+                );
+            }
+            _ => self.children = OriginChildren::None,
+        }
+    }
+
+    fn merge_fields(
+        &mut self,
+        resource: &Option<Resource>,
+        mapping: &Mapping,
+        path: &mut Vec<String>,
+        operation: PatchOperation,
+    ) {
+        self.operation = if matches!(self.children, OriginChildren::Fields(_)) {
+            PatchOperation::Merge
+        } else {
+            self.children = OriginChildren::Fields(BTreeMap::new());
+            operation
+        };
+        let OriginChildren::Fields(fields) = &mut self.children else {
+            unreachable!("The children are fields");
+        };
+        for (key, child) in mapping {
+            let key = match key {
+                Value::String(key) => key.clone(),
+                Value::Number(key) => key.to_string(),
+                _ => continue,
+            };
+            // The deprecated fields are merged into their replacement
+            let key = DEPRECATIONS
+                .iter()
+                .find(|deprecation| deprecation.field == key && deprecation.in_scope(path))
+                .map(|deprecation| deprecation.replacement.to_string())
+                .unwrap_or(key);
+            path.push(key.clone());
+            fields
+                .entry(key)
+                .or_default()
+                .apply(resource, child, path, PatchOperation::Set);
+            path.pop();
+        }
+    }
+}
+
+/// Applies the patch of a list to the origins of its elements.
+/// The positions refer to the elements before the patch, or after the replacement of the whole list
+fn patch_elements(
+    elements: Vec<OriginNode>,
+    keys: Vec<ListPatchKey>,
+    mapping: &Mapping,
+    resource: &Option<Resource>,
+) -> Vec<OriginNode> {
+    let commands = keys.into_iter().zip(mapping.values()).collect::<Vec<_>>();
+    let new_nodes = |value: &Value, operation: PatchOperation| -> Vec<OriginNode> {
+        match value {
+            Value::Sequence(sequence) => sequence
+                .iter()
+                .map(|child| OriginNode::new(resource, child, operation))
+                .collect(),
+            value => vec![OriginNode::new(resource, value, operation)],
+        }
+    };
+
+    let elements = commands
+        .iter()
+        .find_map(|(key, value)| match key {
+            ListPatchKey::ReplaceAll => Some(new_nodes(value, PatchOperation::Set)),
+            _ => None,
+        })
+        .unwrap_or(elements);
+
+    let mut patched = vec![];
+    for (position, mut element) in elements.into_iter().enumerate() {
+        for (key, value) in &commands {
+            if matches!(key, ListPatchKey::InsertBefore(pos) if *pos == position) {
+                patched.extend(new_nodes(value, PatchOperation::Insert));
+            }
+        }
+        for (key, value) in &commands {
+            match key {
+                ListPatchKey::Replace(pos) if *pos == position => {
+                    element = OriginNode::new(resource, value, PatchOperation::Replace);
+                }
+                ListPatchKey::Patch(pos) if *pos == position => {
+                    element.apply(resource, value, &mut vec![], PatchOperation::Patch);
+                    element.operation = PatchOperation::Patch;
+                }
+                _ => {}
+            }
+        }
+        patched.push(element);
+        for (key, value) in &commands {
+            if matches!(key, ListPatchKey::InsertAfter(pos) if *pos == position) {
+                patched.extend(new_nodes(value, PatchOperation::Insert));
+            }
+        }
+    }
+    for (key, value) in &commands {
+        if let ListPatchKey::Append = key {
+            patched.extend(new_nodes(value, PatchOperation::Append));
+        }
+    }
+    patched
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+    use std::path::PathBuf;
+
+    fn path(path: &str) -> Vec<String> {
+        path.split('.').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn last_layer_wins() {
+        let base = Resource::File(PathBuf::from("base.yml"));
+        let mut origins = FieldOrigins::default();
+        origins.push_layer(
+            None,
+            "extend: base.yml\nworkdir: /app\nrun:\n  +:\n    - make",
+        );
+        origins.push_layer(
+            Some(base.clone()),
+            "fromImage: alpine\nworkdir: /src\nrun:\n  - apk add make\n  - make deps\nenvs:\n  A: b",
+        );
+        origins.commit_layer();
+        origins.commit_layer();
+
+        assert_eq_sorted!(origins.get(&path("fromImage.path")), Some(&base));
+        assert_eq_sorted!(origins.get(&path("workdir")), None);
+        assert_eq_sorted!(origins.get(&path("run.1")), Some(&base));
+        assert_eq_sorted!(origins.get(&path("run.2")), None);
+        assert_eq_sorted!(origins.operation(&path("run.2")), PatchOperation::Append);
+        assert_eq_sorted!(origins.get(&path("env.A")), Some(&base));
+    }
+
+    #[test]
+    fn replaced_list() {
+        let base = Resource::File(PathBuf::from("base.yml"));
+        let mut origins = FieldOrigins::default();
+        origins.push_layer(None, "run:\n  - make");
+        origins.push_layer(Some(base.clone()), "run:\n  - apk add make\n  - make deps");
+        origins.commit_layer();
+        origins.commit_layer();
+
+        assert_eq_sorted!(origins.get(&path("run.0")), None);
+        assert_eq_sorted!(origins.operation(&path("run.0")), PatchOperation::Set);
+        assert_eq_sorted!(origins.get(&path("run.1")), None);
+    }
+
+    #[test]
+    fn patched_list() {
+        let base = Resource::File(PathBuf::from("base.yml"));
+        let mut origins = FieldOrigins::default();
+        origins.push_layer(
+            None,
+            "copy:\n  '+0':\n    - paths: [a]\n  0<:\n    target: /app\n  1:\n    paths: [b]\n  1+:\n    - paths: [c]",
+        );
+        origins.push_layer(
+            Some(base.clone()),
+            "copy:\n  - paths: [d]\n    chmod: '755'\n  - paths: [e]\n  - paths: [f]",
+        );
+        origins.commit_layer();
+        origins.commit_layer();
+
+        // The copied paths are a, d, b, c and f
+        assert_eq_sorted!(origins.operation(&path("copy.0")), PatchOperation::Insert);
+        assert_eq_sorted!(origins.get(&path("copy.0")), None);
+        assert_eq_sorted!(origins.operation(&path("copy.1")), PatchOperation::Patch);
+        assert_eq_sorted!(origins.get(&path("copy.1.target")), None);
+        assert_eq_sorted!(origins.get(&path("copy.1.chmod")), Some(&base));
+        assert_eq_sorted!(origins.operation(&path("copy.2")), PatchOperation::Replace);
+        assert_eq_sorted!(origins.operation(&path("copy.3")), PatchOperation::Insert);
+        assert_eq_sorted!(origins.get(&path("copy.4")), Some(&base));
+        assert_eq_sorted!(origins.operation(&path("copy.4")), PatchOperation::Set);
+    }
+}
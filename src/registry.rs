@@ -0,0 +1,1024 @@
+use crate::{
+    lock::{DockerTag, DEFAULT_NAMESPACE, DOCKER_HUB_HOST},
+    Error, ImageName, ImageNamePatch, ImageVersion, RegistryAuth, RegistryCredential, Result,
+};
+use regex::Regex;
+use reqwest::{
+    blocking::{Client, RequestBuilder, Response},
+    header, StatusCode,
+};
+use serde::{de::IgnoredAny, Deserialize, Deserializer, Serialize};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    sync::LazyLock,
+};
+
+const DOCKER_HUB_REGISTRY_HOST: &str = "registry-1.docker.io";
+const DEFAULT_PORT: u16 = 443;
+const MANIFEST_MEDIA_TYPES: [&str; 4] = [
+    "application/vnd.oci.image.index.v1+json",
+    "application/vnd.docker.distribution.manifest.list.v2+json",
+    "application/vnd.oci.image.manifest.v1+json",
+    "application/vnd.docker.distribution.manifest.v2+json",
+];
+const PREFERRED_PLATFORM: (&str, &str) = ("linux", "amd64");
+
+/// The parameters of an authentication challenge, like realm="https://auth.docker.io/token"
+static CHALLENGE_PARAM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+
+/// The version at the start of a tag and the rest of the tag
+static TAG_VERSION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^v?(\d+(?:\.\d+)*)(.*)$").unwrap());
+
+/// The next page link of a Link header
+static NEXT_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<([^>]+)>;\s*rel="next""#).unwrap());
+
+/// A client for the Docker images registries
+pub struct RegistryClient {
+    client: Client,
+    scheme: &'static str,
+    auth: RegistryAuth,
+    authorizations: RefCell<HashMap<String, Authorization>>,
+    mirror: Option<String>,
+}
+
+/// The authorization sent with the requests of a registry once it required it
+#[derive(Debug, Clone, PartialEq)]
+enum Authorization {
+    Bearer(String),
+    Basic { username: String, password: String },
+}
+
+/// The description of an image from its registry
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInspection {
+    /// The inspected image
+    pub image: String,
+    /// The digest of the image manifest
+    pub digest: String,
+    /// The platforms available for the image
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<Platform>,
+    /// The configuration of the image, for the linux/amd64 platform when available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<ImageConfig>,
+    /// The tags available in the image repository
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+/// An image platform
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Platform {
+    pub os: String,
+    pub architecture: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub variant: Option<String>,
+}
+
+/// The summary of an image configuration
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workdir: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entrypoint: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cmd: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exposed_ports: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// An image resolved from its registry
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedImage {
+    /// The resolved image, with its version
+    #[serde(deserialize_with = "deserialize_image_name")]
+    pub image: ImageName,
+    /// The digest of the image manifest
+    pub digest: String,
+    /// The platforms available for the image
+    pub platforms: Vec<Platform>,
+    /// The labels of the image, for the linux/amd64 platform when available
+    pub labels: BTreeMap<String, String>,
+}
+
+fn deserialize_image_name<'de, D>(deserializer: D) -> std::result::Result<ImageName, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    ImageNamePatch::deserialize(deserializer).map(Into::into)
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, PartialOrd, Eq)]
+pub struct DockerHubTagResponse {
+    pub digest: Option<String>,
+    images: Vec<DockerTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    manifests: Vec<ManifestDescriptor>,
+    config: Option<Descriptor>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Descriptor {
+    digest: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestDescriptor {
+    digest: String,
+    platform: Option<Platform>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigBlob {
+    created: Option<String>,
+    os: Option<String>,
+    architecture: Option<String>,
+    variant: Option<String>,
+    #[serde(default)]
+    config: ConfigBlobDetails,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+struct ConfigBlobDetails {
+    user: Option<String>,
+    working_dir: Option<String>,
+    entrypoint: Option<Vec<String>>,
+    cmd: Option<Vec<String>>,
+    env: Option<Vec<String>>,
+    exposed_ports: Option<BTreeMap<String, IgnoredAny>>,
+    labels: Option<BTreeMap<String, String>>,
+}
+
+/// A bearer authentication challenge from the WWW-Authenticate header
+#[derive(Debug, Clone, PartialEq)]
+struct BearerChallenge {
+    realm: String,
+    service: Option<String>,
+    scope: Option<String>,
+}
+
+impl BearerChallenge {
+    fn parse(header: &str) -> Option<Self> {
+        let params = header.strip_prefix("Bearer ")?;
+        let params: BTreeMap<&str, &str> = CHALLENGE_PARAM
+            .captures_iter(params)
+            .map(|captures| {
+                (
+                    captures.get(1).unwrap().as_str(),
+                    captures.get(2).unwrap().as_str(),
+                )
+            })
+            .collect();
+        Some(Self {
+            realm: params.get("realm")?.to_string(),
+            service: params.get("service").map(|s| s.to_string()),
+            scope: params.get("scope").map(|s| s.to_string()),
+        })
+    }
+}
+
+impl RegistryClient {
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            scheme: "https",
+            auth: RegistryAuth::from_env(),
+            authorizations: RefCell::new(HashMap::new()),
+            mirror: None,
+        }
+    }
+
+    /// Serves the Docker Hub images from the given registry mirror, like mirror.gcr.io
+    pub fn with_mirror(mut self, mirror: Option<String>) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    /// Returns the digest of the given image tag
+    pub fn get_tag(&self, image: &ImageName) -> Result<DockerTag> {
+        let tag = match image
+            .version
+            .clone()
+            .ok_or(Error::Custom("No version found for image".into()))?
+        {
+            ImageVersion::Tag(tag) => tag,
+            _ => {
+                return Err(Error::Custom("Image version is not a tag".to_string()));
+            }
+        };
+
+        let host = image
+            .host
+            .clone()
+            .ok_or(Error::Custom("No host found for image".into()))?;
+
+        let docker_tag = if is_docker_hub(host.as_str()) && self.mirror.is_some() {
+            let (digest, _) = self.get_manifest(image, &tag)?;
+            DockerTag { digest }
+        } else if is_docker_hub(host.as_str())
+            // The Docker Hub API only gives the public images tags
+            && self.auth.credential(&registry_host(image))?.is_none()
+        {
+            let mut repo = image.path.clone();
+            let namespace = if repo.contains("/") {
+                let mut parts = image.path.split("/");
+                let ret = parts.next().unwrap();
+                repo = parts.collect::<Vec<&str>>().join("/");
+                ret
+            } else {
+                DEFAULT_NAMESPACE
+            };
+            let request_url = format!(
+                "{scheme}://{host}/v2/namespaces/{namespace}/repositories/{repo}/tags/{tag}",
+                scheme = self.scheme,
+                namespace = namespace,
+                repo = repo,
+                tag = tag
+            );
+            let response = self.client.get(&request_url).send().map_err(Error::from)?;
+
+            let response: DockerHubTagResponse = response.json().map_err(Error::from)?;
+            DockerTag {
+                digest: response
+                    .digest
+                    .or(response.images.first().map(|img| img.digest.clone()))
+                    .ok_or(Error::Custom("No digest found in response".to_string()))?,
+            }
+        } else {
+            let request_url = format!(
+                "{}/v2/{}/manifests/{}",
+                self.registry_url(image),
+                repository(image),
+                tag
+            );
+            let response = self.send(image, |client| client.head(&request_url))?;
+
+            let digest = response
+                .headers()
+                .get("Docker-Content-Digest")
+                .ok_or(Error::Custom("No digest found in response".to_string()))?;
+            let digest = digest.to_str().map_err(Error::display)?.to_string();
+
+            DockerTag { digest }
+        };
+
+        Ok(docker_tag)
+    }
+
+    /// Returns the tags of the image repository
+    pub fn list_tags(&self, image: &ImageName) -> Result<Vec<String>> {
+        let base_url = self.registry_url(image);
+        let mut url = format!("{}/v2/{}/tags/list?n=1000", base_url, repository(image));
+        let mut tags = vec![];
+        loop {
+            let response = self.send(image, |client| client.get(&url))?;
+            let next = next_link(&response);
+            let response: TagsResponse = response.json().map_err(Error::from)?;
+            tags.append(&mut response.tags.unwrap_or_default());
+            match next {
+                Some(next) if next.starts_with('/') => url = format!("{}{}", base_url, next),
+                Some(next) => url = next,
+                None => return Ok(tags),
+            }
+        }
+    }
+
+    /// Returns the description of the image: digest, platforms, configuration and tags
+    pub fn inspect(&self, image: &ImageName) -> Result<ImageInspection> {
+        let name = ImageName {
+            version: image.fill().version,
+            ..image.clone()
+        }
+        .to_string();
+        let (digest, platforms, config) = self.describe(image)?;
+        Ok(ImageInspection {
+            image: name,
+            digest,
+            platforms,
+            config,
+            tags: self.list_tags(&image.fill())?,
+        })
+    }
+
+    /// Resolves the digest, the platforms and the labels of an image
+    pub fn resolve(&self, image: &ImageName) -> Result<ResolvedImage> {
+        let (digest, platforms, config) = self.describe(image)?;
+        Ok(ResolvedImage {
+            image: image.fill(),
+            digest,
+            platforms,
+            labels: config.map(|config| config.labels).unwrap_or_default(),
+        })
+    }
+
+    /// Returns the manifest digest, the platforms and the configuration of an image
+    fn describe(&self, image: &ImageName) -> Result<(String, Vec<Platform>, Option<ImageConfig>)> {
+        let image = image.fill();
+        let reference = match image.version.as_ref() {
+            Some(ImageVersion::Tag(tag)) => tag.clone(),
+            Some(ImageVersion::Digest(digest)) => digest.clone(),
+            None => unreachable!("The image version is filled"),
+        };
+
+        let (digest, manifest) = self.get_manifest(&image, &reference)?;
+        let mut platforms: Vec<Platform> = manifest
+            .manifests
+            .iter()
+            .filter_map(|descriptor| descriptor.platform.clone())
+            // Attestation manifests are referenced with an unknown platform
+            .filter(|platform| platform.os != "unknown")
+            .collect();
+
+        let config_digest = if manifest.manifests.is_empty() {
+            manifest.config.map(|config| config.digest)
+        } else {
+            let descriptor = manifest
+                .manifests
+                .iter()
+                .find(|descriptor| {
+                    descriptor.platform.as_ref().is_some_and(|platform| {
+                        (platform.os.as_str(), platform.architecture.as_str()) == PREFERRED_PLATFORM
+                    })
+                })
+                .or(manifest.manifests.first());
+            match descriptor {
+                Some(descriptor) => self.get_manifest(&image, &descriptor.digest)?.1.config,
+                None => None,
+            }
+            .map(|config| config.digest)
+        };
+
+        let config = match config_digest {
+            Some(config_digest) => {
+                let blob = self.get_config_blob(&image, &config_digest)?;
+                if platforms.is_empty() {
+                    if let (Some(os), Some(architecture)) = (&blob.os, &blob.architecture) {
+                        platforms.push(Platform {
+                            os: os.clone(),
+                            architecture: architecture.clone(),
+                            variant: blob.variant.clone(),
+                        });
+                    }
+                }
+                Some(blob.into())
+            }
+            None => None,
+        };
+
+        Ok((digest, platforms, config))
+    }
+
+    fn get_manifest(&self, image: &ImageName, reference: &str) -> Result<(String, Manifest)> {
+        let url = format!(
+            "{}/v2/{}/manifests/{}",
+            self.registry_url(image),
+            repository(image),
+            reference
+        );
+        let response = self.send(image, |client| {
+            client
+                .get(&url)
+                .header(header::ACCEPT, MANIFEST_MEDIA_TYPES.join(", "))
+        })?;
+        let digest = response
+            .headers()
+            .get("Docker-Content-Digest")
+            .and_then(|digest| digest.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| {
+                reference
+                    .starts_with("sha256:")
+                    .then(|| reference.to_string())
+            })
+            .ok_or(Error::Custom("No digest found in response".to_string()))?;
+        let manifest = response.json().map_err(Error::from)?;
+        Ok((digest, manifest))
+    }
+
+    fn get_config_blob(&self, image: &ImageName, digest: &str) -> Result<ConfigBlob> {
+        let url = format!(
+            "{}/v2/{}/blobs/{}",
+            self.registry_url(image),
+            repository(image),
+            digest
+        );
+        self.send(image, |client| client.get(&url))?
+            .json()
+            .map_err(Error::from)
+    }
+
+    /// Sends the request, authenticating when the registry requires it,
+    /// with the credentials of the registry or an anonymous token
+    fn send<F>(&self, image: &ImageName, build: F) -> Result<Response>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let host = self.credential_host(image);
+        let request = match self.authorizations.borrow().get(&host) {
+            Some(authorization) => authorize(build(&self.client), authorization),
+            None => build(&self.client),
+        };
+        let response = request.send().map_err(Error::from)?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return check_status(response);
+        }
+        let credential = self.auth.credential(&host)?;
+        let challenge = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+            .unwrap_or_default();
+        let authorization = match (BearerChallenge::parse(&challenge), credential) {
+            (Some(challenge), credential) => {
+                Authorization::Bearer(self.get_token(&challenge, credential.as_ref())?)
+            }
+            (None, Some(RegistryCredential::Basic { username, password }))
+                if challenge.starts_with("Basic") =>
+            {
+                Authorization::Basic { username, password }
+            }
+            _ => {
+                return Err(Error::Custom(format!(
+                    "The registry requires an unsupported authentication for {}",
+                    response.url()
+                )))
+            }
+        };
+        let response = authorize(build(&self.client), &authorization)
+            .send()
+            .map_err(Error::from)?;
+        self.authorizations.borrow_mut().insert(host, authorization);
+        check_status(response)
+    }
+
+    /// Gets a token from the authorization service of the registry, anonymous when there are no credentials.
+    /// The identity tokens are exchanged with the OAuth2 refresh token grant
+    /// See https://distribution.github.io/distribution/spec/auth/oauth/
+    fn get_token(
+        &self,
+        challenge: &BearerChallenge,
+        credential: Option<&RegistryCredential>,
+    ) -> Result<String> {
+        let request = match credential {
+            Some(RegistryCredential::IdentityToken(token)) => {
+                let mut form = vec![
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", token.as_str()),
+                    ("client_id", "dofigen"),
+                ];
+                if let Some(service) = &challenge.service {
+                    form.push(("service", service));
+                }
+                if let Some(scope) = &challenge.scope {
+                    form.push(("scope", scope));
+                }
+                self.client.post(&challenge.realm).form(&form)
+            }
+            credential => {
+                let mut request = self.client.get(&challenge.realm);
+                if let Some(service) = &challenge.service {
+                    request = request.query(&[("service", service)]);
+                }
+                if let Some(scope) = &challenge.scope {
+                    request = request.query(&[("scope", scope)]);
+                }
+                match credential {
+                    Some(RegistryCredential::Basic { username, password }) => {
+                        request.basic_auth(username, Some(password))
+                    }
+                    _ => request,
+                }
+            }
+        };
+        let response: TokenResponse = check_status(request.send().map_err(Error::from)?)?
+            .json()
+            .map_err(Error::from)?;
+        response
+            .token
+            .or(response.access_token)
+            .ok_or(Error::Custom("No token found in response".to_string()))
+    }
+
+    /// Returns the registry host of the image to find its credentials, the mirror one for the Docker Hub images
+    fn credential_host(&self, image: &ImageName) -> String {
+        match &self.mirror {
+            Some(mirror) if is_docker_hub(image.host.as_deref().unwrap_or(DOCKER_HUB_HOST)) => {
+                mirror.clone()
+            }
+            _ => registry_host(image),
+        }
+    }
+
+    /// Returns the base URL of the registry API of the image
+    fn registry_url(&self, image: &ImageName) -> String {
+        let host = image.host.as_deref().unwrap_or(DOCKER_HUB_HOST);
+        let host = if is_docker_hub(host) {
+            match &self.mirror {
+                Some(mirror) => return format!("{}://{}", self.scheme, mirror),
+                None => DOCKER_HUB_REGISTRY_HOST,
+            }
+        } else {
+            host
+        };
+        match image.port {
+            Some(port) if port != DEFAULT_PORT => {
+                format!("{}://{}:{}", self.scheme, host, port)
+            }
+            _ => format!("{}://{}", self.scheme, host),
+        }
+    }
+}
+
+/// A version parsed from an image tag, like `1.2.3` or `1.2-alpine`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagVersion {
+    /// The numeric parts of the version
+    pub numbers: Vec<u64>,
+    /// The rest of the tag after the numeric parts
+    pub suffix: String,
+}
+
+impl TagVersion {
+    /// Parses the version of a tag, returns None when the tag doesn't start with a version
+    pub fn parse(tag: &str) -> Option<Self> {
+        let captures = TAG_VERSION.captures(tag)?;
+        Some(Self {
+            numbers: captures[1]
+                .split('.')
+                .map(|number| number.parse().ok())
+                .collect::<Option<_>>()?,
+            suffix: captures[2].to_string(),
+        })
+    }
+}
+
+impl Ord for TagVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numbers
+            .cmp(&other.numbers)
+            .then_with(|| self.suffix.cmp(&other.suffix))
+    }
+}
+
+impl PartialOrd for TagVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Sorts the tags with the highest versions first, followed by the tags without version in alphabetical order
+pub fn sort_tags(tags: &mut [String]) {
+    tags.sort_by_cached_key(|tag| (std::cmp::Reverse(TagVersion::parse(tag)), tag.clone()));
+}
+
+impl Default for RegistryClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<ConfigBlob> for ImageConfig {
+    fn from(blob: ConfigBlob) -> Self {
+        let config = blob.config;
+        Self {
+            created: blob.created,
+            user: config.user.filter(|user| !user.is_empty()),
+            workdir: config.working_dir.filter(|workdir| !workdir.is_empty()),
+            entrypoint: config.entrypoint.unwrap_or_default(),
+            cmd: config.cmd.unwrap_or_default(),
+            env: config.env.unwrap_or_default(),
+            exposed_ports: config
+                .exposed_ports
+                .map(|ports| ports.into_keys().collect())
+                .unwrap_or_default(),
+            labels: config.labels.unwrap_or_default(),
+        }
+    }
+}
+
+fn authorize(request: RequestBuilder, authorization: &Authorization) -> RequestBuilder {
+    match authorization {
+        Authorization::Bearer(token) => request.bearer_auth(token),
+        Authorization::Basic { username, password } => request.basic_auth(username, Some(password)),
+    }
+}
+
+/// Returns the registry host of the image, with its port when it isn't the default one, to find its credentials
+fn registry_host(image: &ImageName) -> String {
+    let host = image.host.as_deref().unwrap_or(DOCKER_HUB_HOST);
+    if is_docker_hub(host) {
+        return DOCKER_HUB_HOST.into();
+    }
+    match image.port {
+        Some(port) if port != DEFAULT_PORT => format!("{}:{}", host, port),
+        _ => host.into(),
+    }
+}
+
+fn is_docker_hub(host: &str) -> bool {
+    host == DOCKER_HUB_HOST || host == "docker.io"
+}
+
+/// Returns the repository of the image in the registry API
+fn repository(image: &ImageName) -> String {
+    let host = image.host.as_deref().unwrap_or(DOCKER_HUB_HOST);
+    if is_docker_hub(host) && !image.path.contains('/') {
+        format!("{}/{}", DEFAULT_NAMESPACE, image.path)
+    } else {
+        image.path.clone()
+    }
+}
+
+/// Returns the next page link of a paginated response
+fn next_link(response: &Response) -> Option<String> {
+    let link = response.headers().get(header::LINK)?.to_str().ok()?;
+    NEXT_LINK
+        .captures(link)
+        .map(|captures| captures.get(1).unwrap().as_str().to_string())
+}
+
+fn check_status(response: Response) -> Result<Response> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(Error::Custom(format!(
+            "The registry responded with the status {} for {}",
+            response.status(),
+            response.url()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use httptest::{matchers::*, responders::*, Expectation, Server, ServerBuilder};
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    fn test_client() -> RegistryClient {
+        RegistryClient {
+            client: Client::new(),
+            scheme: "http",
+            auth: RegistryAuth::default(),
+            authorizations: RefCell::new(HashMap::new()),
+            mirror: None,
+        }
+    }
+
+    /// The registry host must be an IPv4 address to be written in an image name
+    fn run_server() -> Server {
+        ServerBuilder::new()
+            .bind_addr("127.0.0.1:0".parse().unwrap())
+            .run()
+            .unwrap()
+    }
+
+    fn server_image(server: &Server, version: ImageVersion) -> ImageName {
+        ImageName {
+            host: Some("127.0.0.1".into()),
+            port: Some(server.addr().port()),
+            path: "org/app".into(),
+            version: Some(version),
+        }
+    }
+
+    #[test]
+    fn parse_bearer_challenge() {
+        assert_eq_sorted!(
+            BearerChallenge::parse(
+                r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/alpine:pull""#
+            ),
+            Some(BearerChallenge {
+                realm: "https://auth.docker.io/token".into(),
+                service: Some("registry.docker.io".into()),
+                scope: Some("repository:library/alpine:pull".into()),
+            })
+        );
+        assert_eq_sorted!(BearerChallenge::parse(r#"Basic realm="registry""#), None);
+    }
+
+    #[test]
+    fn parse_tag_version() {
+        assert_eq_sorted!(
+            TagVersion::parse("v1.20.3-alpine"),
+            Some(TagVersion {
+                numbers: vec![1, 20, 3],
+                suffix: "-alpine".into(),
+            })
+        );
+        assert_eq_sorted!(TagVersion::parse("latest"), None);
+    }
+
+    #[test]
+    fn sort_tags_by_version() {
+        let mut tags: Vec<String> =
+            vec!["latest", "1.9", "edge", "1.10-alpine", "1.10", "2", "1.9.1"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+
+        sort_tags(&mut tags);
+
+        assert_eq_sorted!(
+            tags,
+            vec!["2", "1.10-alpine", "1.10", "1.9.1", "1.9", "edge", "latest"]
+        );
+    }
+
+    #[test]
+    fn docker_hub_repository() {
+        let image: ImageName = "alpine:3.20".parse().unwrap();
+        let image = image.fill();
+
+        assert_eq_sorted!(repository(&image), "library/alpine");
+        assert_eq_sorted!(
+            RegistryClient::new().registry_url(&image),
+            "https://registry-1.docker.io"
+        );
+        assert_eq_sorted!(
+            RegistryClient::new()
+                .with_mirror(Some("mirror.gcr.io".into()))
+                .registry_url(&image),
+            "https://mirror.gcr.io"
+        );
+    }
+
+    #[test]
+    fn get_docker_hub_tag_from_mirror() {
+        let server = run_server();
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                "/v2/library/alpine/manifests/3.20",
+            ))
+            .respond_with(
+                status_code(200)
+                    .insert_header("Docker-Content-Digest", "sha256:alpine")
+                    .body(r#"{ "manifests": [] }"#),
+            ),
+        );
+        let image: ImageName = "alpine:3.20".parse().unwrap();
+
+        let tag = test_client()
+            .with_mirror(Some(server.addr().to_string()))
+            .get_tag(&image.fill())
+            .unwrap();
+
+        assert_eq_sorted!(tag.digest, "sha256:alpine");
+    }
+
+    fn expect_multi_platform_image(server: &Server) {
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/v2/org/app/manifests/1.0"))
+                .respond_with(
+                    status_code(200)
+                        .insert_header("Docker-Content-Digest", "sha256:index")
+                        .body(
+                            r#"{
+  "mediaType": "application/vnd.oci.image.index.v1+json",
+  "manifests": [
+    { "digest": "sha256:arm", "platform": { "os": "linux", "architecture": "arm64", "variant": "v8" } },
+    { "digest": "sha256:amd", "platform": { "os": "linux", "architecture": "amd64" } },
+    { "digest": "sha256:attestation", "platform": { "os": "unknown", "architecture": "unknown" } }
+  ]
+}"#,
+                        ),
+                ),
+        );
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                "/v2/org/app/manifests/sha256:amd",
+            ))
+            .respond_with(
+                status_code(200)
+                    .body(r#"{ "config": { "digest": "sha256:config" }, "layers": [] }"#),
+            ),
+        );
+        server.expect(
+            Expectation::matching(request::method_path(
+                "GET",
+                "/v2/org/app/blobs/sha256:config",
+            ))
+            .respond_with(status_code(200).body(
+                r#"{
+  "created": "2024-01-01T00:00:00Z",
+  "os": "linux",
+  "architecture": "amd64",
+  "config": {
+    "User": "1000",
+    "WorkingDir": "/app",
+    "Cmd": ["app"],
+    "Env": ["PATH=/usr/bin"],
+    "ExposedPorts": { "8080/tcp": {} },
+    "Labels": { "org.opencontainers.image.version": "1.0" }
+  }
+}"#,
+            )),
+        );
+    }
+
+    fn multi_platform_image_platforms() -> Vec<Platform> {
+        vec![
+            Platform {
+                os: "linux".into(),
+                architecture: "arm64".into(),
+                variant: Some("v8".into()),
+            },
+            Platform {
+                os: "linux".into(),
+                architecture: "amd64".into(),
+                variant: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn inspect_multi_platform_image() {
+        let server = run_server();
+        expect_multi_platform_image(&server);
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/v2/org/app/tags/list"))
+                .respond_with(
+                    status_code(200).body(r#"{ "name": "org/app", "tags": ["1.0", "latest"] }"#),
+                ),
+        );
+
+        let inspection = test_client()
+            .inspect(&server_image(&server, ImageVersion::Tag("1.0".into())))
+            .unwrap();
+
+        assert_eq_sorted!(
+            inspection,
+            ImageInspection {
+                image: format!("127.0.0.1:{}/org/app:1.0", server.addr().port()),
+                digest: "sha256:index".into(),
+                platforms: multi_platform_image_platforms(),
+                config: Some(ImageConfig {
+                    created: Some("2024-01-01T00:00:00Z".into()),
+                    user: Some("1000".into()),
+                    workdir: Some("/app".into()),
+                    entrypoint: vec![],
+                    cmd: vec!["app".into()],
+                    env: vec!["PATH=/usr/bin".into()],
+                    exposed_ports: vec!["8080/tcp".into()],
+                    labels: BTreeMap::from([(
+                        "org.opencontainers.image.version".into(),
+                        "1.0".into()
+                    )]),
+                }),
+                tags: vec!["1.0".into(), "latest".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn resolve_multi_platform_image() {
+        let server = run_server();
+        expect_multi_platform_image(&server);
+        let image = server_image(&server, ImageVersion::Tag("1.0".into()));
+
+        let resolved = test_client().resolve(&image).unwrap();
+
+        assert_eq_sorted!(
+            resolved,
+            ResolvedImage {
+                image,
+                digest: "sha256:index".into(),
+                platforms: multi_platform_image_platforms(),
+                labels: BTreeMap::from([("org.opencontainers.image.version".into(), "1.0".into())]),
+            }
+        );
+    }
+
+    #[test]
+    fn list_tags_with_token_authentication() {
+        let server = run_server();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/v2/org/app/tags/list"),
+                not(request::headers(contains(key("authorization")))),
+            ])
+            .respond_with(status_code(401).insert_header(
+                "WWW-Authenticate",
+                format!(
+                    r#"Bearer realm="{}",service="registry",scope="repository:org/app:pull""#,
+                    server.url("/token")
+                ),
+            )),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/token"),
+                request::query(url_decoded(contains(("scope", "repository:org/app:pull")))),
+            ])
+            .respond_with(status_code(200).body(r#"{ "token": "anonymous" }"#)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/v2/org/app/tags/list"),
+                request::headers(contains(("authorization", "Bearer anonymous"))),
+            ])
+            .times(2)
+            .respond_with(cycle![
+                status_code(200)
+                    .insert_header(
+                        "Link",
+                        r#"</v2/org/app/tags/list?n=1&last=1.0>; rel="next""#
+                    )
+                    .body(r#"{ "name": "org/app", "tags": ["1.0"] }"#),
+                status_code(200).body(r#"{ "name": "org/app", "tags": ["1.1"] }"#),
+            ]),
+        );
+
+        let tags = test_client()
+            .list_tags(&server_image(&server, ImageVersion::Tag("1.0".into())))
+            .unwrap();
+
+        assert_eq_sorted!(tags, vec!["1.0".to_string(), "1.1".to_string()]);
+    }
+
+    #[test]
+    fn get_tag_with_registry_credentials() {
+        let server = run_server();
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("HEAD", "/v2/org/app/manifests/1.0"),
+                not(request::headers(contains(key("authorization")))),
+            ])
+            .respond_with(status_code(401).insert_header(
+                "WWW-Authenticate",
+                format!(
+                    r#"Bearer realm="{}",service="registry",scope="repository:org/app:pull""#,
+                    server.url("/token")
+                ),
+            )),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("GET", "/token"),
+                // ci:secret
+                request::headers(contains(("authorization", "Basic Y2k6c2VjcmV0"))),
+            ])
+            .respond_with(status_code(200).body(r#"{ "token": "private" }"#)),
+        );
+        server.expect(
+            Expectation::matching(all_of![
+                request::method_path("HEAD", "/v2/org/app/manifests/1.0"),
+                request::headers(contains(("authorization", "Bearer private"))),
+            ])
+            .respond_with(status_code(200).insert_header("Docker-Content-Digest", "sha256:abc")),
+        );
+        let client = RegistryClient {
+            auth: RegistryAuth::with_credential(
+                None,
+                RegistryCredential::Basic {
+                    username: "ci".into(),
+                    password: "secret".into(),
+                },
+            ),
+            ..test_client()
+        };
+
+        let tag = client
+            .get_tag(&server_image(&server, ImageVersion::Tag("1.0".into())))
+            .unwrap();
+
+        assert_eq_sorted!(tag.digest, "sha256:abc");
+    }
+}
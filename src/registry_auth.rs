@@ -0,0 +1,327 @@
+use crate::{lock::DOCKER_HUB_HOST, Error, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    env, fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// The environment variable giving the username of the registries
+pub const REGISTRY_USERNAME_ENV: &str = "DOFIGEN_REGISTRY_USERNAME";
+
+/// The environment variable giving the password or access token of the registries
+pub const REGISTRY_PASSWORD_ENV: &str = "DOFIGEN_REGISTRY_PASSWORD";
+
+/// The environment variable restricting the credentials of the environment variables to a registry host
+pub const REGISTRY_HOST_ENV: &str = "DOFIGEN_REGISTRY_HOST";
+
+/// The key of the Docker Hub registry in the Docker configuration
+const DOCKER_HUB_SERVER: &str = "https://index.docker.io/v1/";
+
+/// The other hosts of the Docker Hub registry
+const DOCKER_HUB_ALIASES: [&str; 3] = ["docker.io", "index.docker.io", "registry-1.docker.io"];
+
+/// The username given by the credential helpers for the identity tokens
+const IDENTITY_TOKEN_USERNAME: &str = "<token>";
+
+/// The credentials of a registry
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryCredential {
+    /// A username with a password or an access token
+    Basic { username: String, password: String },
+
+    /// An identity token exchanged for an access token by the registry authorization service
+    IdentityToken(String),
+}
+
+/// The Docker client configuration, usually ~/.docker/config.json
+/// See https://docs.docker.com/reference/cli/docker/login/#credential-stores
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct DockerConfig {
+    auths: HashMap<String, DockerAuth>,
+    cred_helpers: HashMap<String, String>,
+    creds_store: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct DockerAuth {
+    auth: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    identitytoken: Option<String>,
+}
+
+/// The response of the get command of a credential helper
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredential {
+    username: String,
+    secret: String,
+}
+
+/// Finds the credentials of the registries, from the environment variables first and then from the Docker configuration,
+/// with its credential helpers, credential store and auths
+#[derive(Debug, Default)]
+pub struct RegistryAuth {
+    environment: Option<(Option<String>, RegistryCredential)>,
+    config: DockerConfig,
+    found: RefCell<HashMap<String, Option<RegistryCredential>>>,
+}
+
+impl RegistryAuth {
+    /// Loads the credentials from the environment variables and the Docker configuration of the user
+    pub fn from_env() -> Self {
+        let environment = match (
+            env::var(REGISTRY_USERNAME_ENV),
+            env::var(REGISTRY_PASSWORD_ENV),
+        ) {
+            (Ok(username), Ok(password)) => Some((
+                env::var(REGISTRY_HOST_ENV).ok(),
+                RegistryCredential::Basic { username, password },
+            )),
+            _ => None,
+        };
+        let config = docker_config_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            environment,
+            config,
+            found: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Uses the credentials for the given registry host, or for all of them, instead of the Docker configuration
+    pub fn with_credential(host: Option<String>, credential: RegistryCredential) -> Self {
+        Self {
+            environment: Some((host, credential)),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the credentials of the registry host, with its port when it isn't the default one.
+    /// The Docker Hub credentials are given for registry.hub.docker.com
+    pub fn credential(&self, host: &str) -> Result<Option<RegistryCredential>> {
+        if let Some(credential) = self.found.borrow().get(host) {
+            return Ok(credential.clone());
+        }
+        let credential = self.find_credential(host)?;
+        self.found
+            .borrow_mut()
+            .insert(host.to_string(), credential.clone());
+        Ok(credential)
+    }
+
+    fn find_credential(&self, host: &str) -> Result<Option<RegistryCredential>> {
+        if let Some((scope, credential)) = &self.environment {
+            if scope
+                .as_deref()
+                .is_none_or(|scope| server_host(scope) == host)
+            {
+                return Ok(Some(credential.clone()));
+            }
+        }
+        let server = if host == DOCKER_HUB_HOST {
+            DOCKER_HUB_SERVER
+        } else {
+            host
+        };
+        let helper = self
+            .config
+            .cred_helpers
+            .iter()
+            .find(|(key, _)| server_host(key) == host)
+            .map(|(_, helper)| helper);
+        if let Some(helper) = helper {
+            return helper_credential(helper, server);
+        }
+        let auth = self
+            .config
+            .auths
+            .iter()
+            .find(|(key, _)| server_host(key) == host)
+            .map(|(_, auth)| auth);
+        if let Some(credential) = auth.map(DockerAuth::credential).transpose()?.flatten() {
+            return Ok(Some(credential));
+        }
+        match &self.config.creds_store {
+            Some(store) => helper_credential(store, server),
+            None => Ok(None),
+        }
+    }
+}
+
+impl DockerAuth {
+    fn credential(&self) -> Result<Option<RegistryCredential>> {
+        if let Some(token) = &self.identitytoken {
+            return Ok(Some(RegistryCredential::IdentityToken(token.clone())));
+        }
+        if let Some(auth) = &self.auth {
+            let decoded = STANDARD
+                .decode(auth.trim())
+                .ok()
+                .and_then(|decoded| String::from_utf8(decoded).ok())
+                .ok_or(Error::Custom(
+                    "Invalid auth in the Docker configuration".into(),
+                ))?;
+            let (username, password) = decoded.split_once(':').ok_or(Error::Custom(
+                "Invalid auth in the Docker configuration".into(),
+            ))?;
+            return Ok(Some(RegistryCredential::Basic {
+                username: username.into(),
+                password: password.into(),
+            }));
+        }
+        Ok(match (&self.username, &self.password) {
+            (Some(username), Some(password)) => Some(RegistryCredential::Basic {
+                username: username.clone(),
+                password: password.clone(),
+            }),
+            _ => None,
+        })
+    }
+}
+
+/// Returns the path of the Docker configuration, in the DOCKER_CONFIG directory or ~/.docker
+fn docker_config_path() -> Option<PathBuf> {
+    env::var_os("DOCKER_CONFIG")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".docker"))
+        })
+        .map(|dir| dir.join("config.json"))
+}
+
+/// Returns the registry host of a Docker configuration key, that can be a URL like https://index.docker.io/v1/
+fn server_host(server: &str) -> &str {
+    let server = server
+        .strip_prefix("https://")
+        .or_else(|| server.strip_prefix("http://"))
+        .unwrap_or(server);
+    let host = server.split('/').next().unwrap_or(server);
+    if DOCKER_HUB_ALIASES.contains(&host) {
+        DOCKER_HUB_HOST
+    } else {
+        host
+    }
+}
+
+/// Gets the credentials of a registry from a credential helper, like docker-credential-ecr-login
+/// See https://github.com/docker/docker-credential-helpers
+fn helper_credential(helper: &str, server: &str) -> Result<Option<RegistryCredential>> {
+    let program = format!("docker-credential-{}", helper);
+    let mut child = Command::new(&program)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| Error::Custom(format!("Unable to run {}: {}", program, err)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(server.as_bytes())
+            .map_err(|err| Error::Custom(format!("Unable to run {}: {}", program, err)))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|err| Error::Custom(format!("Unable to run {}: {}", program, err)))?;
+    // The helpers fail when they have no credentials for the server
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let credential: HelperCredential = serde_yaml::from_slice(&output.stdout)
+        .map_err(|err| Error::Custom(format!("Invalid response of {}: {}", program, err)))?;
+    Ok(Some(if credential.username == IDENTITY_TOKEN_USERNAME {
+        RegistryCredential::IdentityToken(credential.secret)
+    } else {
+        RegistryCredential::Basic {
+            username: credential.username,
+            password: credential.secret,
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    fn auth(config: &str) -> RegistryAuth {
+        RegistryAuth {
+            config: serde_yaml::from_str(config).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn config_auths() {
+        let auth = auth(
+            r#"{
+                "auths": {
+                    "https://index.docker.io/v1/": { "auth": "dXNlcjpwYXNz" },
+                    "ghcr.io": { "identitytoken": "refresh" },
+                    "https://registry.local:5000": { "username": "ci", "password": "secret" }
+                }
+            }"#,
+        );
+
+        assert_eq_sorted!(
+            auth.credential(DOCKER_HUB_HOST).unwrap(),
+            Some(RegistryCredential::Basic {
+                username: "user".into(),
+                password: "pass".into(),
+            })
+        );
+        assert_eq_sorted!(
+            auth.credential("ghcr.io").unwrap(),
+            Some(RegistryCredential::IdentityToken("refresh".into()))
+        );
+        assert_eq_sorted!(
+            auth.credential("registry.local:5000").unwrap(),
+            Some(RegistryCredential::Basic {
+                username: "ci".into(),
+                password: "secret".into(),
+            })
+        );
+        assert_eq_sorted!(auth.credential("quay.io").unwrap(), None);
+    }
+
+    #[test]
+    fn environment_overrides_config() {
+        let credential = RegistryCredential::Basic {
+            username: "env".into(),
+            password: "token".into(),
+        };
+        let auth = RegistryAuth {
+            environment: Some((Some("https://ghcr.io".into()), credential.clone())),
+            ..auth(
+                r#"{ "auths": { "ghcr.io": { "auth": "dXNlcjpwYXNz" }, "quay.io": { "auth": "dXNlcjpwYXNz" } } }"#,
+            )
+        };
+
+        assert_eq_sorted!(auth.credential("ghcr.io").unwrap(), Some(credential));
+        assert_eq_sorted!(
+            auth.credential("quay.io").unwrap(),
+            Some(RegistryCredential::Basic {
+                username: "user".into(),
+                password: "pass".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn missing_credential_helper() {
+        let auth = auth(r#"{ "credHelpers": { "ghcr.io": "dofigen-missing-helper" } }"#);
+
+        assert!(auth.credential("ghcr.io").is_err());
+    }
+}
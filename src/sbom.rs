@@ -0,0 +1,345 @@
+use crate::{Dofigen, FromContext, ImageName, ImageVersion, Run, Stage};
+use regex::Regex;
+use serde::Serialize;
+
+/// The name of the runtime stage in the properties of the components
+const RUNTIME_STAGE: &str = "runtime";
+
+/// A CycloneDX software bill of materials
+/// See https://cyclonedx.org/docs/1.5/json/
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Bom {
+    pub bom_format: String,
+    pub spec_version: String,
+    pub version: u32,
+    pub metadata: BomMetadata,
+    pub components: Vec<BomComponent>,
+}
+
+/// The metadata of a CycloneDX software bill of materials
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BomMetadata {
+    pub tools: BomTools,
+    pub component: BomComponent,
+}
+
+/// The tools that produced a CycloneDX software bill of materials
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BomTools {
+    pub components: Vec<BomComponent>,
+}
+
+/// A component of a CycloneDX software bill of materials
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct BomComponent {
+    #[serde(rename = "type")]
+    pub component_type: String,
+
+    #[serde(rename = "bom-ref", skip_serializing_if = "Option::is_none")]
+    pub bom_ref: Option<String>,
+
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purl: Option<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub licenses: Vec<BomLicense>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub properties: Vec<BomProperty>,
+}
+
+/// The license of a component, as SPDX expression
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BomLicense {
+    pub expression: String,
+}
+
+/// A name and value property of a component
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BomProperty {
+    pub name: String,
+    pub value: String,
+}
+
+impl BomProperty {
+    fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl Dofigen {
+    /// Returns the software bill of materials of the declared inputs, before the image is built:
+    /// the base images and the packages of the stages, declared or installed by apt-get and apk commands
+    pub fn static_sbom(&self) -> Bom {
+        let mut builders = self.builders.iter().collect::<Vec<_>>();
+        builders.sort_by_key(|(name, _)| *name);
+        let stages = builders
+            .into_iter()
+            .map(|(name, builder)| (name.as_str(), builder))
+            .chain([(RUNTIME_STAGE, &self.stage)]);
+
+        let mut components: Vec<BomComponent> = vec![];
+        for (name, stage) in stages {
+            for component in stage_components(stage) {
+                let stage_property = BomProperty::new("dofigen:stage", name);
+                match components
+                    .iter_mut()
+                    .find(|existing| existing.bom_ref == component.bom_ref)
+                {
+                    Some(existing) => existing.properties.push(stage_property),
+                    None => components.push(BomComponent {
+                        properties: [vec![stage_property], component.properties].concat(),
+                        ..component
+                    }),
+                }
+            }
+        }
+
+        Bom {
+            bom_format: "CycloneDX".into(),
+            spec_version: "1.5".into(),
+            version: 1,
+            metadata: BomMetadata {
+                tools: BomTools {
+                    components: vec![BomComponent {
+                        component_type: "application".into(),
+                        name: "dofigen".into(),
+                        version: Some(env!("CARGO_PKG_VERSION").into()),
+                        ..Default::default()
+                    }],
+                },
+                component: BomComponent {
+                    component_type: "container".into(),
+                    name: RUNTIME_STAGE.into(),
+                    ..Default::default()
+                },
+            },
+            components,
+        }
+    }
+}
+
+/// Returns the base image and the packages of a stage
+fn stage_components(stage: &Stage) -> Vec<BomComponent> {
+    let image = match &stage.from {
+        FromContext::FromImage(image) => Some(image_component(image)),
+        _ => None,
+    };
+    let declared = stage.packages.iter().map(|package| {
+        let purl = package.purl.clone();
+        BomComponent {
+            component_type: "library".into(),
+            bom_ref: Some(
+                purl.clone()
+                    .unwrap_or(package_ref(&package.name, package.version.as_deref())),
+            ),
+            name: package.name.clone(),
+            version: package.version.clone(),
+            purl,
+            licenses: package
+                .license
+                .iter()
+                .map(|license| BomLicense {
+                    expression: license.clone(),
+                })
+                .collect(),
+            properties: vec![BomProperty::new("dofigen:source", "declared")],
+        }
+    });
+    let installed = stage
+        .root
+        .iter()
+        .chain([&stage.run])
+        .flat_map(installed_packages)
+        .filter(|(name, _)| !stage.packages.iter().any(|package| package.name == *name))
+        .map(|(name, version)| BomComponent {
+            component_type: "library".into(),
+            bom_ref: Some(package_ref(&name, version.as_deref())),
+            name,
+            version,
+            properties: vec![BomProperty::new("dofigen:source", "run")],
+            ..Default::default()
+        });
+    image.into_iter().chain(declared).chain(installed).collect()
+}
+
+fn image_component(image: &ImageName) -> BomComponent {
+    let registry = image.host.as_ref().map(|host| match image.port {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.clone(),
+    });
+    let version = match &image.version {
+        Some(ImageVersion::Tag(tag)) => Some(tag.clone()),
+        Some(ImageVersion::Digest(digest)) => Some(digest.clone()),
+        None => None,
+    };
+    let purl = format!(
+        "pkg:docker/{}{}{}",
+        image.path,
+        version
+            .as_ref()
+            .map(|version| format!("@{}", version.replace(':', "%3A")))
+            .unwrap_or_default(),
+        registry
+            .map(|registry| format!("?repository_url={}", registry))
+            .unwrap_or_default()
+    );
+    BomComponent {
+        component_type: "container".into(),
+        bom_ref: Some(purl.clone()),
+        name: image.path.clone(),
+        version,
+        purl: Some(purl),
+        ..Default::default()
+    }
+}
+
+fn package_ref(name: &str, version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("{}@{}", name, version),
+        None => name.into(),
+    }
+}
+
+/// Returns the packages, with their pinned version, installed by the apt-get and apk commands of a run
+fn installed_packages(run: &Run) -> Vec<(String, Option<String>)> {
+    let install =
+        Regex::new(r"\b(?:apt-get|apt) +(?:-\S+ +)*install\b|\bapk +(?:-\S+ +)*add\b").unwrap();
+    let mut packages: Vec<(String, Option<String>)> = vec![];
+    for command in &run.run {
+        for found in install.find_iter(command) {
+            let arguments = command[found.end()..]
+                .split(['&', ';', '|', '\n'])
+                .next()
+                .unwrap_or_default();
+            for argument in arguments.split_whitespace() {
+                if argument.starts_with('-') || argument.starts_with('$') || argument == "\\" {
+                    continue;
+                }
+                let package = match argument.split_once('=') {
+                    Some((name, version)) => (name.to_string(), Some(version.to_string())),
+                    None => (argument.to_string(), None),
+                };
+                if !packages.contains(&package) {
+                    packages.push(package);
+                }
+            }
+        }
+    }
+    packages
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Package;
+    use pretty_assertions_sorted::assert_eq_sorted;
+    use std::collections::HashMap;
+
+    #[test]
+    fn installed_packages_of_commands() {
+        let run = Run {
+            run: vec![
+                "apt-get update && apt-get install -y --no-install-recommends curl ca-certificates=20230311 && rm -rf /var/lib/apt/lists/*".into(),
+                "apk add --no-cache git".into(),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq_sorted!(
+            installed_packages(&run),
+            vec![
+                ("curl".into(), None),
+                ("ca-certificates".into(), Some("20230311".into())),
+                ("git".into(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn static_sbom() {
+        let alpine = ImageName {
+            path: "alpine".into(),
+            version: Some(ImageVersion::Tag("3.20".into())),
+            ..Default::default()
+        };
+        let dofigen = Dofigen {
+            builders: HashMap::from([(
+                "builder".into(),
+                Stage {
+                    from: FromContext::FromImage(alpine.clone()),
+                    packages: vec![Package {
+                        name: "rust".into(),
+                        version: Some("1.82.0".into()),
+                        license: Some("MIT OR Apache-2.0".into()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+            )]),
+            stage: Stage {
+                from: FromContext::FromImage(alpine),
+                run: Run {
+                    run: vec!["apk add --no-cache tini=0.19.0-r3".into()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let sbom = dofigen.static_sbom();
+
+        assert_eq_sorted!(
+            sbom.components,
+            vec![
+                BomComponent {
+                    component_type: "container".into(),
+                    bom_ref: Some("pkg:docker/alpine@3.20".into()),
+                    name: "alpine".into(),
+                    version: Some("3.20".into()),
+                    purl: Some("pkg:docker/alpine@3.20".into()),
+                    properties: vec![
+                        BomProperty::new("dofigen:stage", "builder"),
+                        BomProperty::new("dofigen:stage", "runtime"),
+                    ],
+                    ..Default::default()
+                },
+                BomComponent {
+                    component_type: "library".into(),
+                    bom_ref: Some("rust@1.82.0".into()),
+                    name: "rust".into(),
+                    version: Some("1.82.0".into()),
+                    licenses: vec![BomLicense {
+                        expression: "MIT OR Apache-2.0".into(),
+                    }],
+                    properties: vec![
+                        BomProperty::new("dofigen:stage", "builder"),
+                        BomProperty::new("dofigen:source", "declared"),
+                    ],
+                    ..Default::default()
+                },
+                BomComponent {
+                    component_type: "library".into(),
+                    bom_ref: Some("tini@0.19.0-r3".into()),
+                    name: "tini".into(),
+                    version: Some("0.19.0-r3".into()),
+                    properties: vec![
+                        BomProperty::new("dofigen:stage", "runtime"),
+                        BomProperty::new("dofigen:source", "run"),
+                    ],
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+}
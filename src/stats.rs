@@ -0,0 +1,122 @@
+use crate::{walk_run, walk_stage, Dofigen, FromContext, ImageName, Run, Stage, Visitor};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Statistics about a Dofigen document, returned by `Dofigen::stats`.
+/// They are only exposed by the library, no CLI command displays them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DofigenStats {
+    /// The number of stages, including the runtime stage
+    pub stages: usize,
+    /// The number of run lines, including the root ones
+    pub run_lines: usize,
+    /// The number of copy resources
+    pub copies: usize,
+    /// The number of distinct external images used as stage base, copy origin or mount origin
+    pub external_images: usize,
+    /// The number of cache mounts
+    pub cache_mounts: usize,
+}
+
+impl Dofigen {
+    /// Returns statistics about the Dofigen document
+    pub fn stats(&self) -> DofigenStats {
+        let mut visitor = StatsVisitor::default();
+        visitor.visit_dofigen(self);
+        visitor.stats.external_images = visitor.images.len();
+        visitor.stats
+    }
+}
+
+#[derive(Default)]
+struct StatsVisitor<'a> {
+    stats: DofigenStats,
+    images: HashSet<&'a ImageName>,
+}
+
+impl<'a> Visitor<'a> for StatsVisitor<'a> {
+    fn visit_stage(&mut self, name: &'a str, stage: &'a Stage) {
+        self.stats.stages += 1;
+        self.stats.copies += stage.copy.len();
+        walk_stage(self, name, stage);
+    }
+
+    fn visit_from(&mut self, from: &'a FromContext) {
+        if let FromContext::FromImage(image) = from {
+            self.images.insert(image);
+        }
+    }
+
+    fn visit_run(&mut self, run: &'a Run) {
+        self.stats.run_lines += run.run.len();
+        self.stats.cache_mounts += run.cache.len();
+        walk_run(self, run);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DofigenContext;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn empty() {
+        assert_eq_sorted!(
+            Dofigen::default().stats(),
+            DofigenStats {
+                stages: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn count_elements() {
+        let dofigen = DofigenContext::new()
+            .parse_from_string(
+                r#"
+builders:
+  builder:
+    fromImage:
+      path: rust
+    copy:
+      - paths: [.]
+    run:
+      - cargo build --release
+      - strip target/release/app
+    cache:
+      - target: /usr/local/cargo/registry
+      - target: /app/target
+fromImage:
+  path: alpine
+root:
+  run: [apk add --no-cache ca-certificates]
+copy:
+  - fromBuilder: builder
+    paths: [/app/target/release/app]
+  - fromImage:
+      path: rust
+    paths: [/usr/local/cargo/bin/cargo]
+bind:
+  - fromImage:
+      path: busybox
+    target: /busybox
+run: [ls /busybox]
+"#,
+            )
+            .unwrap();
+
+        assert_eq_sorted!(
+            dofigen.stats(),
+            DofigenStats {
+                stages: 2,
+                run_lines: 4,
+                copies: 3,
+                external_images: 3,
+                cache_mounts: 2,
+            }
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// The phases of a Dofigen execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimingPhase {
+    /// Deserialization of the Dofigen file
+    Parse,
+    /// Loading and merging of the extended resources
+    Extend,
+    /// Read and write of the lock file
+    LockIo,
+    /// Requests to the registries and remote resources.
+    /// This time is excluded from the phase that made the requests
+    Network,
+    /// Generation of the Dockerfile and .dockerignore files
+    Generation,
+}
+
+impl TimingPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimingPhase::Parse => "parse",
+            TimingPhase::Extend => "extend",
+            TimingPhase::LockIo => "lock IO",
+            TimingPhase::Network => "network",
+            TimingPhase::Generation => "generation",
+        }
+    }
+}
+
+/// The durations of the phases of a Dofigen execution.
+/// Each phase only counts its own time, the phases measured while another one is running being excluded from it,
+/// so the durations add up to the measured time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    durations: BTreeMap<TimingPhase, Duration>,
+    /// The time of the phases measured inside each started phase
+    nested: Vec<Duration>,
+}
+
+impl Timings {
+    /// Adds a duration to the given phase
+    pub fn add(&mut self, phase: TimingPhase, duration: Duration) {
+        self.record(phase, duration, duration);
+    }
+
+    /// Starts a phase that can contain other phases, ended with the `end` method
+    pub fn start(&mut self) -> Instant {
+        self.nested.push(Duration::ZERO);
+        Instant::now()
+    }
+
+    /// Ends a phase started with the `start` method, adding its duration without the phases measured meanwhile
+    pub fn end(&mut self, phase: TimingPhase, start: Instant) {
+        let elapsed = start.elapsed();
+        let nested = self.nested.pop().unwrap_or_default();
+        self.record(phase, elapsed.saturating_sub(nested), elapsed);
+    }
+
+    fn record(&mut self, phase: TimingPhase, own: Duration, elapsed: Duration) {
+        *self.durations.entry(phase).or_default() += own;
+        if let Some(nested) = self.nested.last_mut() {
+            *nested += elapsed;
+        }
+    }
+
+    /// Adds the durations of other timings
+    pub fn merge(&mut self, other: &Timings) {
+        other
+            .durations
+            .iter()
+            .for_each(|(phase, duration)| self.add(*phase, *duration));
+    }
+
+    /// Returns the total duration of the given phase
+    pub fn get(&self, phase: TimingPhase) -> Duration {
+        self.durations.get(&phase).cloned().unwrap_or_default()
+    }
+
+    /// Returns the durations of the measured phases
+    pub fn durations(&self) -> &BTreeMap<TimingPhase, Duration> {
+        &self.durations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn add_durations() {
+        let mut timings = Timings::default();
+
+        timings.add(TimingPhase::Network, Duration::from_millis(20));
+        timings.add(TimingPhase::Parse, Duration::from_millis(5));
+        timings.add(TimingPhase::Network, Duration::from_millis(30));
+
+        assert_eq_sorted!(timings.get(TimingPhase::Network), Duration::from_millis(50));
+        assert_eq_sorted!(timings.get(TimingPhase::Generation), Duration::ZERO);
+        assert_eq_sorted!(
+            timings.durations().keys().cloned().collect::<Vec<_>>(),
+            vec![TimingPhase::Parse, TimingPhase::Network]
+        );
+    }
+
+    #[test]
+    fn nested_phases() {
+        let mut timings = Timings::default();
+
+        let extend = timings.start();
+        let parse = timings.start();
+        std::thread::sleep(Duration::from_millis(10));
+        timings.add(TimingPhase::Network, Duration::from_millis(5));
+        timings.end(TimingPhase::Parse, parse);
+        timings.end(TimingPhase::Extend, extend);
+
+        // The parse time is excluded from the extend one and the network time from the parse one
+        let parse = timings.get(TimingPhase::Parse);
+        assert!(parse >= Duration::from_millis(5));
+        assert!(timings.get(TimingPhase::Extend) < parse);
+        assert_eq_sorted!(timings.get(TimingPhase::Network), Duration::from_millis(5));
+    }
+}
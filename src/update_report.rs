@@ -0,0 +1,295 @@
+use crate::{
+    lock::{DockerTag, ResourceVersion},
+    ImageName, ImageVersion, Resource, UpdateCommand,
+};
+use colored::{Color, Colorize};
+use serde::Serialize;
+
+/// The kind of change of a locked image or resource
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateKind {
+    Add,
+    Update,
+    Remove,
+}
+
+/// A change of a locked image tag or resource
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct UpdateEntry {
+    /// The tag of the image or the resource
+    pub name: String,
+    pub kind: UpdateKind,
+    /// The previous digest of the image or hash of the resource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    /// The new digest of the image or hash of the resource
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+}
+
+/// The changes of the tags of an image repository
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ImageUpdates {
+    pub repository: String,
+    pub updates: Vec<UpdateEntry>,
+}
+
+/// The changes of the lock file, the images being grouped by repository
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct UpdateReport {
+    pub images: Vec<ImageUpdates>,
+    pub resources: Vec<UpdateEntry>,
+}
+
+impl UpdateReport {
+    /// Builds the report from the sorted changes of the images and the resources
+    pub(crate) fn new(
+        images: Vec<UpdateCommand<ImageName, DockerTag>>,
+        resources: Vec<UpdateCommand<Resource, ResourceVersion>>,
+    ) -> Self {
+        let mut report = Self::default();
+        for command in images {
+            let (image, entry) = UpdateEntry::from_command(command, |tag| tag.digest.clone());
+            let repository = ImageName {
+                version: None,
+                ..image.clone()
+            }
+            .to_string();
+            let entry = UpdateEntry {
+                name: match &image.version {
+                    Some(ImageVersion::Tag(tag)) => tag.clone(),
+                    Some(ImageVersion::Digest(digest)) => digest.clone(),
+                    None => "latest".into(),
+                },
+                ..entry
+            };
+            match report.images.last_mut() {
+                Some(group) if group.repository == repository => group.updates.push(entry),
+                _ => report.images.push(ImageUpdates {
+                    repository,
+                    updates: vec![entry],
+                }),
+            }
+        }
+        report.resources = resources
+            .into_iter()
+            .map(|command| {
+                let (resource, entry) =
+                    UpdateEntry::from_command(command, |version| version.hash.clone());
+                UpdateEntry {
+                    name: resource.to_string(),
+                    ..entry
+                }
+            })
+            .collect();
+        report
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty() && self.resources.is_empty()
+    }
+
+    /// Renders the report for a terminal, one line per change
+    pub fn to_text(&self) -> String {
+        let mut lines = self
+            .resources
+            .iter()
+            .map(|entry| entry.to_text("resource"))
+            .collect::<Vec<_>>();
+        for group in self.images.iter() {
+            lines.push(format!(
+                "{:>20} {}",
+                "Image".color(Color::Cyan).bold(),
+                group.repository
+            ));
+            lines.extend(group.updates.iter().map(|entry| entry.to_text("tag")));
+        }
+        lines.join("\n")
+    }
+
+    /// Renders the report as Markdown, with a table for the resources and for each image repository
+    pub fn to_markdown(&self) -> String {
+        let mut lines = vec!["## Dofigen updates".to_string(), "".into()];
+        if self.is_empty() {
+            lines.push("No update.".into());
+        }
+        if !self.resources.is_empty() {
+            lines.push("### Resources".into());
+            lines.push("".into());
+            lines.extend(markdown_table("Resource", &self.resources));
+        }
+        if !self.images.is_empty() {
+            lines.push("### Images".into());
+            lines.push("".into());
+            for group in self.images.iter() {
+                lines.push(format!("#### `{}`", group.repository));
+                lines.push("".into());
+                lines.extend(markdown_table("Tag", &group.updates));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+impl UpdateEntry {
+    fn from_command<K, V>(
+        command: UpdateCommand<K, V>,
+        version: impl Fn(&V) -> String,
+    ) -> (K, Self) {
+        let (key, kind, previous, current) = match command {
+            UpdateCommand::Add(key, value) => (key, UpdateKind::Add, None, Some(version(&value))),
+            UpdateCommand::Update(key, value, previous) => (
+                key,
+                UpdateKind::Update,
+                Some(version(&previous)),
+                Some(version(&value)),
+            ),
+            UpdateCommand::Remove(key, value) => {
+                (key, UpdateKind::Remove, Some(version(&value)), None)
+            }
+        };
+        (
+            key,
+            Self {
+                name: String::new(),
+                kind,
+                previous,
+                current,
+            },
+        )
+    }
+
+    fn to_text(&self, element: &str) -> String {
+        let (kind, color) = match self.kind {
+            UpdateKind::Add => ("Add", Color::Blue),
+            UpdateKind::Update => ("Update", Color::Green),
+            UpdateKind::Remove => ("Remove", Color::Red),
+        };
+        let versions = match (&self.previous, &self.current) {
+            (Some(previous), Some(current)) => format!("{} -> {}", previous, current),
+            (Some(version), None) | (None, Some(version)) => version.clone(),
+            (None, None) => String::new(),
+        };
+        format!(
+            "{:>20} {} {}",
+            format!("{} {}", kind, element).color(color).bold(),
+            self.name,
+            versions
+        )
+    }
+}
+
+fn markdown_table(name: &str, entries: &[UpdateEntry]) -> Vec<String> {
+    let mut lines = vec![
+        format!("| {} | Change | Previous | Current |", name),
+        "| --- | --- | --- | --- |".into(),
+    ];
+    lines.extend(entries.iter().map(|entry| {
+        let version = |version: &Option<String>| {
+            version
+                .as_ref()
+                .map(|version| format!("`{}`", version))
+                .unwrap_or_default()
+        };
+        format!(
+            "| {} | {} | {} | {} |",
+            entry.name,
+            match entry.kind {
+                UpdateKind::Add => "added",
+                UpdateKind::Update => "updated",
+                UpdateKind::Remove => "removed",
+            },
+            version(&entry.previous),
+            version(&entry.current)
+        )
+    }));
+    lines.push("".into());
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    fn report() -> UpdateReport {
+        let image = |tag: &str| ImageName {
+            path: "node".into(),
+            version: Some(ImageVersion::Tag(tag.into())),
+            ..Default::default()
+        };
+        let tag = |digest: &str| DockerTag {
+            digest: digest.into(),
+        };
+        UpdateReport::new(
+            vec![
+                UpdateCommand::Add(image("20"), tag("sha256:a")),
+                UpdateCommand::Update(image("22"), tag("sha256:c"), tag("sha256:b")),
+            ],
+            vec![UpdateCommand::Remove(
+                Resource::File("base.yml".into()),
+                ResourceVersion {
+                    hash: "123".into(),
+                    content: "".into(),
+                },
+            )],
+        )
+    }
+
+    #[test]
+    fn group_images() {
+        assert_eq_sorted!(
+            report(),
+            UpdateReport {
+                images: vec![ImageUpdates {
+                    repository: "node".into(),
+                    updates: vec![
+                        UpdateEntry {
+                            name: "20".into(),
+                            kind: UpdateKind::Add,
+                            previous: None,
+                            current: Some("sha256:a".into()),
+                        },
+                        UpdateEntry {
+                            name: "22".into(),
+                            kind: UpdateKind::Update,
+                            previous: Some("sha256:b".into()),
+                            current: Some("sha256:c".into()),
+                        },
+                    ],
+                }],
+                resources: vec![UpdateEntry {
+                    name: "base.yml".into(),
+                    kind: UpdateKind::Remove,
+                    previous: Some("123".into()),
+                    current: None,
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq_sorted!(
+            report().to_markdown(),
+            r#"## Dofigen updates
+
+### Resources
+
+| Resource | Change | Previous | Current |
+| --- | --- | --- | --- |
+| base.yml | removed | `123` |  |
+
+### Images
+
+#### `node`
+
+| Tag | Change | Previous | Current |
+| --- | --- | --- | --- |
+| 20 | added |  | `sha256:a` |
+| 22 | updated | `sha256:b` | `sha256:c` |
+"#
+        );
+    }
+}
@@ -0,0 +1,560 @@
+use crate::{
+    checksum::checksum_algorithm, linter::unknown_rule_message, CopyOptions, CopyResource, Dofigen,
+    Error, FromContext, LintMessage, LintRule, MessageLevel, Result, Run, Stage,
+};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A builder name used by a field of the merged Dofigen file
+struct BuilderReference {
+    builder: String,
+    path: String,
+}
+
+/// Checks that the builders referenced by the merged Dofigen file exist and don't depend on each other cyclically
+pub(crate) fn check_builder_references(dofigen: &Dofigen) -> Result<()> {
+    let mut references = vec![];
+    let mut dependencies = BTreeMap::new();
+    let mut builders = dofigen.builders.iter().collect::<Vec<_>>();
+    builders.sort_by_key(|(name, _)| *name);
+    for (name, builder) in builders {
+        let mut builder_references = vec![];
+        stage_references(
+            builder,
+            &format!("builders.{}.", name),
+            &mut builder_references,
+        );
+        dependencies.insert(
+            name.as_str(),
+            builder_references
+                .iter()
+                .filter(|reference| dofigen.builders.contains_key(&reference.builder))
+                .map(|reference| reference.builder.clone())
+                .collect::<BTreeSet<_>>(),
+        );
+        references.append(&mut builder_references);
+    }
+    stage_references(&dofigen.stage, "", &mut references);
+
+    let mut errors = references
+        .into_iter()
+        .filter(|reference| !dofigen.builders.contains_key(&reference.builder))
+        .map(|reference| {
+            let mut message = format!(
+                "The builder '{}' referenced by {} doesn't exist",
+                reference.builder, reference.path
+            );
+            if let Some(suggestion) = closest_name(&reference.builder, dofigen.builders.keys()) {
+                message.push_str(&format!(", did you mean '{}'?", suggestion));
+            }
+            message
+        })
+        .collect::<Vec<_>>();
+    if let Some(cycle) = find_cycle(&dependencies) {
+        errors.push(format!(
+            "Circular dependency detected between the builders: {}",
+            cycle.join(" -> ")
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Custom(errors.join("\n")))
+    }
+}
+
+fn stage_references(stage: &Stage, prefix: &str, references: &mut Vec<BuilderReference>) {
+    if let FromContext::FromBuilder(builder) = &stage.from {
+        references.push(BuilderReference {
+            builder: builder.clone(),
+            path: format!("{}fromBuilder", prefix),
+        });
+    }
+    for (position, copy) in stage.copy.iter().enumerate() {
+        if let CopyResource::Copy(copy) = copy {
+            if let FromContext::FromBuilder(builder) = &copy.from {
+                references.push(BuilderReference {
+                    builder: builder.clone(),
+                    path: format!("{}copy.{}.fromBuilder", prefix, position),
+                });
+            }
+        }
+    }
+    for (position, output) in stage.uses.iter().enumerate() {
+        references.push(BuilderReference {
+            builder: output.builder.clone(),
+            path: format!("{}use.{}.builder", prefix, position),
+        });
+    }
+    run_references(&stage.run, prefix, references);
+    if let Some(root) = &stage.root {
+        run_references(root, &format!("{}root.", prefix), references);
+    }
+}
+
+fn run_references(run: &Run, prefix: &str, references: &mut Vec<BuilderReference>) {
+    let froms = run
+        .cache
+        .iter()
+        .enumerate()
+        .map(|(position, cache)| (format!("{}cache.{}", prefix, position), &cache.from))
+        .chain(
+            run.bind
+                .iter()
+                .enumerate()
+                .map(|(position, bind)| (format!("{}bind.{}", prefix, position), &bind.from)),
+        );
+    for (path, from) in froms {
+        if let FromContext::FromBuilder(builder) = from {
+            references.push(BuilderReference {
+                builder: builder.clone(),
+                path: format!("{}.fromBuilder", path),
+            });
+        }
+    }
+}
+
+/// Returns the first cycle found in the builder dependencies, starting and ending with the same builder
+fn find_cycle(dependencies: &BTreeMap<&str, BTreeSet<String>>) -> Option<Vec<String>> {
+    let mut visited = BTreeSet::new();
+    for builder in dependencies.keys() {
+        let mut path = vec![];
+        if let Some(cycle) = visit(builder, dependencies, &mut visited, &mut path) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+/// Visits the dependencies of a builder depth first, the path being the builders being visited
+fn visit<'a>(
+    builder: &'a str,
+    dependencies: &'a BTreeMap<&str, BTreeSet<String>>,
+    visited: &mut BTreeSet<&'a str>,
+    path: &mut Vec<&'a str>,
+) -> Option<Vec<String>> {
+    if let Some(position) = path.iter().position(|name| *name == builder) {
+        let mut cycle = path[position..]
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        cycle.push(builder.to_string());
+        return Some(cycle);
+    }
+    if !visited.insert(builder) {
+        return None;
+    }
+    path.push(builder);
+    for dependency in dependencies.get(builder).into_iter().flatten() {
+        if let Some(cycle) = visit(dependency, dependencies, visited, path) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    None
+}
+
+impl Dofigen {
+    /// Checks the semantic of the Dofigen struct, whatever the way it has been built,
+    /// and returns all the problems found instead of stopping at the first one
+    pub fn validate(&self) -> Vec<LintMessage> {
+        let mut messages = vec![];
+        let mut builders = self.builders.iter().collect::<Vec<_>>();
+        builders.sort_by_key(|(name, _)| *name);
+        for (name, builder) in builders {
+            validate_stage(builder, &["builders".into(), name.clone()], &mut messages);
+        }
+        validate_stage(&self.stage, &[], &mut messages);
+
+        validate_command(&self.entrypoint, "entrypoint", &mut messages);
+        if self.entrypoint.is_empty() {
+            validate_command(&self.cmd, "cmd", &mut messages);
+        } else {
+            validate_arguments(&self.cmd, "cmd", &mut messages);
+        }
+        if let Some(healthcheck) = &self.healthcheck {
+            if healthcheck.cmd.trim().is_empty() {
+                messages.push(error(
+                    vec!["healthcheck".into(), "cmd".into()],
+                    "The healthcheck command is empty".into(),
+                ));
+            }
+        }
+        let mut rules = self.lint.keys().collect::<Vec<_>>();
+        rules.sort();
+        for rule in rules {
+            if LintRule::find(rule).is_none() {
+                messages.push(error(
+                    vec!["lint".into(), rule.clone()],
+                    unknown_rule_message(rule),
+                ));
+            }
+        }
+        let platform = Regex::new(r"^[a-z0-9]+/[a-z0-9_]+(/[a-z0-9]+)?$").unwrap();
+        for (position, value) in self.platforms.iter().enumerate() {
+            if !platform.is_match(value) {
+                messages.push(error(
+                    vec!["platforms".into(), position.to_string()],
+                    format!(
+                        "The platform '{}' must be like os/arch or os/arch/variant, for example linux/arm64",
+                        value
+                    ),
+                ));
+            }
+        }
+        for (position, port) in self.expose.iter().enumerate() {
+            if port.port == 0 {
+                messages.push(error(
+                    vec!["expose".into(), position.to_string()],
+                    "The port 0 can't be exposed, the ports range from 1 to 65535".into(),
+                ));
+            }
+            if let Some(end) = port.end.filter(|end| *end <= port.port) {
+                messages.push(error(
+                    vec!["expose".into(), position.to_string(), "end".into()],
+                    format!(
+                        "The port range {}-{} is invalid, its end must be greater than its start",
+                        port.port, end
+                    ),
+                ));
+            }
+        }
+        messages
+    }
+}
+
+fn error(path: Vec<String>, message: String) -> LintMessage {
+    LintMessage {
+        level: MessageLevel::Error,
+        path,
+        message,
+    }
+}
+
+fn validate_stage(stage: &Stage, prefix: &[String], messages: &mut Vec<LintMessage>) {
+    let path = |parts: &[&str]| {
+        [
+            prefix.to_vec(),
+            parts.iter().map(|part| part.to_string()).collect(),
+        ]
+        .concat()
+    };
+    for (position, copy) in stage.copy.iter().enumerate() {
+        let options: &CopyOptions = match copy {
+            CopyResource::Copy(copy) => &copy.options,
+            CopyResource::Content(content) => &content.options,
+            CopyResource::AddGitRepo(repo) => &repo.options,
+            CopyResource::Add(add) => &add.options,
+        };
+        validate_chmod(
+            options.chmod.as_ref(),
+            path(&["copy", &position.to_string(), "chmod"]),
+            messages,
+        );
+        if let CopyResource::Add(add) = copy {
+            if let Some(Err(err)) = add.checksum.as_deref().map(checksum_algorithm) {
+                messages.push(error(
+                    path(&["copy", &position.to_string(), "checksum"]),
+                    err.to_string(),
+                ));
+            }
+        }
+    }
+    validate_run(&stage.run, path(&[]), messages);
+    if let Some(root) = &stage.root {
+        validate_run(root, path(&["root"]), messages);
+    }
+}
+
+fn validate_run(run: &Run, path: Vec<String>, messages: &mut Vec<LintMessage>) {
+    for (position, command) in run.run.iter().enumerate() {
+        if command.trim().is_empty() {
+            messages.push(error(
+                [path.clone(), vec!["run".into(), position.to_string()]].concat(),
+                "The command is empty".into(),
+            ));
+        }
+    }
+    for (position, cache) in run.cache.iter().enumerate() {
+        validate_chmod(
+            cache.chmod.as_ref(),
+            [
+                path.clone(),
+                vec!["cache".into(), position.to_string(), "chmod".into()],
+            ]
+            .concat(),
+            messages,
+        );
+    }
+}
+
+/// Checks an exec form command, whose first item is the executable
+fn validate_command(command: &[String], field: &str, messages: &mut Vec<LintMessage>) {
+    if let Some(executable) = command.first() {
+        if executable.trim().contains(char::is_whitespace) {
+            messages.push(error(
+                vec![field.into(), "0".into()],
+                format!(
+                    "The executable '{}' contains spaces: the {} is in the exec form and needs an item for each argument",
+                    executable, field
+                ),
+            ));
+        }
+    }
+    validate_arguments(command, field, messages);
+}
+
+fn validate_arguments(arguments: &[String], field: &str, messages: &mut Vec<LintMessage>) {
+    for (position, argument) in arguments.iter().enumerate() {
+        if argument.is_empty() {
+            messages.push(error(
+                vec![field.into(), position.to_string()],
+                format!("The {} contains an empty item", field),
+            ));
+        }
+    }
+}
+
+/// Checks that the permissions are octal, like `755`, or symbolic, like `u=rwx,go=rx`
+fn validate_chmod(chmod: Option<&String>, path: Vec<String>, messages: &mut Vec<LintMessage>) {
+    if let Some(chmod) = chmod {
+        let octal = Regex::new(r"^[0-7]{3,4}$").unwrap();
+        let symbolic = Regex::new(r"^[ugoa]*[-+=][rwxXst]*(,[ugoa]*[-+=][rwxXst]*)*$").unwrap();
+        if !octal.is_match(chmod) && !symbolic.is_match(chmod) {
+            messages.push(error(
+                path,
+                format!(
+                    "The permissions '{}' are neither octal, like 755, nor symbolic, like u=rwx,go=rx",
+                    chmod
+                ),
+            ));
+        }
+    }
+}
+
+/// Returns the name the closest to the given one when it looks like a typo
+fn closest_name<'a>(name: &str, names: impl Iterator<Item = &'a String>) -> Option<&'a String> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    names
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Add, Cache, Copy, Healthcheck, LintLevel, OutputUse, Port};
+    use pretty_assertions_sorted::assert_eq_sorted;
+    use std::collections::HashMap;
+
+    fn dofigen(builders: &[&str], stage: Stage) -> Dofigen {
+        Dofigen {
+            builders: builders
+                .iter()
+                .map(|name| (name.to_string(), Stage::default()))
+                .collect::<HashMap<_, _>>(),
+            stage,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn existing_builders() {
+        let dofigen = dofigen(
+            &["builder"],
+            Stage {
+                from: FromContext::FromBuilder("builder".into()),
+                copy: vec![CopyResource::Copy(Copy {
+                    from: FromContext::FromBuilder("builder".into()),
+                    ..Default::default()
+                })],
+                ..Default::default()
+            },
+        );
+
+        assert!(check_builder_references(&dofigen).is_ok());
+    }
+
+    #[test]
+    fn misspelled_builders() {
+        let dofigen = dofigen(
+            &["builder", "tests"],
+            Stage {
+                from: FromContext::FromBuilder("buidler".into()),
+                copy: vec![CopyResource::Copy(Copy {
+                    from: FromContext::FromBuilder("assets".into()),
+                    ..Default::default()
+                })],
+                uses: vec![OutputUse {
+                    builder: "test".into(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        );
+
+        assert_eq_sorted!(
+            check_builder_references(&dofigen).unwrap_err().to_string(),
+            "The builder 'buidler' referenced by fromBuilder doesn't exist, did you mean 'builder'?
+The builder 'assets' referenced by copy.0.fromBuilder doesn't exist
+The builder 'test' referenced by use.0.builder doesn't exist, did you mean 'tests'?"
+        );
+    }
+
+    #[test]
+    fn cyclic_builders() {
+        let mut dofigen = dofigen(&["a", "b", "c"], Stage::default());
+        for (name, dependency) in [("a", "b"), ("b", "c"), ("c", "a")] {
+            dofigen.builders.get_mut(name).unwrap().from =
+                FromContext::FromBuilder(dependency.into());
+        }
+
+        assert_eq_sorted!(
+            check_builder_references(&dofigen).unwrap_err().to_string(),
+            "Circular dependency detected between the builders: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn self_referencing_builder() {
+        let mut dofigen = dofigen(&["builder"], Stage::default());
+        dofigen.builders.get_mut("builder").unwrap().copy = vec![CopyResource::Copy(Copy {
+            from: FromContext::FromBuilder("builder".into()),
+            ..Default::default()
+        })];
+
+        assert_eq_sorted!(
+            check_builder_references(&dofigen).unwrap_err().to_string(),
+            "Circular dependency detected between the builders: builder -> builder"
+        );
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("builder", "builder"), 0);
+        assert_eq!(edit_distance("buidler", "builder"), 2);
+        assert_eq!(edit_distance("build", "builder"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn valid_dofigen() {
+        let dofigen = Dofigen {
+            stage: Stage {
+                copy: vec![CopyResource::Copy(Copy {
+                    options: CopyOptions {
+                        chmod: Some("u=rwx,go=rx".into()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })],
+                ..Default::default()
+            },
+            entrypoint: vec!["/bin/app".into()],
+            cmd: vec!["--port".into(), "8080".into()],
+            platforms: vec!["linux/amd64".into(), "linux/arm/v7".into()],
+            expose: vec![Port {
+                port: 8080,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        assert_eq_sorted!(dofigen.validate(), vec![]);
+    }
+
+    #[test]
+    fn all_problems() {
+        let dofigen = Dofigen {
+            builders: HashMap::from([(
+                "builder".into(),
+                Stage {
+                    run: Run {
+                        run: vec!["".into()],
+                        cache: vec![Cache {
+                            target: "/cache".into(),
+                            chmod: Some("0999".into()),
+                            ..Default::default()
+                        }],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )]),
+            stage: Stage {
+                copy: vec![
+                    CopyResource::Copy(Copy {
+                        options: CopyOptions {
+                            chmod: Some("rwx".into()),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }),
+                    CopyResource::Add(Add {
+                        checksum: Some("md5:abc".into()),
+                        ..Default::default()
+                    }),
+                ],
+                ..Default::default()
+            },
+            cmd: vec!["npm start".into(), "".into()],
+            healthcheck: Some(Healthcheck {
+                cmd: " ".into(),
+                ..Default::default()
+            }),
+            lint: HashMap::from([("latest-image".into(), LintLevel::Off)]),
+            platforms: vec!["arm64".into()],
+            expose: vec![
+                Port {
+                    port: 0,
+                    ..Default::default()
+                },
+                Port {
+                    port: 8010,
+                    end: Some(8000),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq_sorted!(
+            dofigen
+                .validate()
+                .into_iter()
+                .map(|message| format!("{}: {}", message.path.join("."), message.message))
+                .collect::<Vec<_>>(),
+            vec![
+                "builders.builder.run.0: The command is empty",
+                "builders.builder.cache.0.chmod: The permissions '0999' are neither octal, like 755, nor symbolic, like u=rwx,go=rx",
+                "copy.0.chmod: The permissions 'rwx' are neither octal, like 755, nor symbolic, like u=rwx,go=rx",
+                "copy.1.checksum: The checksum algorithm 'md5' isn't supported, the supported ones are: sha256, sha512, blake3",
+                "cmd.0: The executable 'npm start' contains spaces: the cmd is in the exec form and needs an item for each argument",
+                "cmd.1: The cmd contains an empty item",
+                "healthcheck.cmd: The healthcheck command is empty",
+                "lint.latest-image: The lint rule 'latest-image' doesn't exist, the rules are: unpinned-image, apt-get-cleanup, secret-in-env",
+                "platforms.0: The platform 'arm64' must be like os/arch or os/arch/variant, for example linux/arm64",
+                "expose.0: The port 0 can't be exposed, the ports range from 1 to 65535",
+                "expose.1.end: The port range 8010-8000 is invalid, its end must be greater than its start",
+            ]
+        );
+    }
+}
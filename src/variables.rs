@@ -0,0 +1,379 @@
+use crate::{Dofigen, DofigenPatch, Error, Result};
+use regex::{Captures, Regex};
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+
+/// Replaces the ${name} and ${function(...)} patterns of the string values with the variables of the Dofigen struct and the given overrides.
+/// The patterns of the undeclared variables, like the shell or ARG ones, are kept as is
+pub(crate) fn interpolate_variables(
+    mut dofigen: Dofigen,
+    overrides: &HashMap<String, String>,
+) -> Result<Dofigen> {
+    let mut variables = std::mem::take(&mut dofigen.variables);
+    variables.extend(overrides.clone());
+
+    let interpolator = Interpolator::new(
+        variables
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect(),
+    );
+    let value = serde_yaml::to_value(&dofigen).map_err(Error::display)?;
+    let patch: DofigenPatch =
+        serde_yaml::from_value(interpolator.value(value)).map_err(Error::display)?;
+    Ok(patch.into())
+}
+
+/// A combination of the values of the matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixCombination {
+    /// The values of the combination joined by '-', used to name its files
+    pub key: String,
+    pub variables: HashMap<String, String>,
+}
+
+impl Dofigen {
+    /// Returns the combinations of the matrix values, the variables being sorted by name.
+    /// Fails when two combinations have the same key, like for the `linux/amd64` and `linux-amd64` values
+    pub fn matrix_combinations(&self) -> Result<Vec<MatrixCombination>> {
+        if self.matrix.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut names = self.matrix.keys().collect::<Vec<_>>();
+        names.sort();
+        let mut combinations = vec![vec![]];
+        for name in names {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination: Vec<(&String, &String)>| {
+                    self.matrix[name].iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((name, value));
+                        combination
+                    })
+                })
+                .collect();
+        }
+
+        let combinations = combinations
+            .into_iter()
+            .map(|combination| MatrixCombination {
+                key: combination
+                    .iter()
+                    .map(|(_, value)| {
+                        value.replace(
+                            |c: char| !c.is_ascii_alphanumeric() && !"._-".contains(c),
+                            "-",
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("-"),
+                variables: combination
+                    .into_iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        let mut keys = HashSet::new();
+        if let Some(duplicate) = combinations
+            .iter()
+            .find(|combination| !keys.insert(&combination.key))
+        {
+            return Err(Error::Custom(format!(
+                "Several combinations of the matrix have the key '{}', use values that differ by their letters, digits, '.', '_' or '-'",
+                duplicate.key
+            )));
+        }
+        Ok(combinations)
+    }
+}
+
+/// Replaces the ${name} patterns of the values.
+/// A string value made of a single pattern is replaced by the typed value.
+///
+/// The patterns can also call a template function on a variable, or on an environment variable with `env:NAME`:
+/// - `${upper(name)}` gives the value in upper case
+/// - `${replace(name, from, to)}` replaces the `from` substrings of the value by `to`
+/// - `${default(name, value)}` gives the given value when the variable isn't declared
+///
+/// The other arguments are literals, optionally quoted to keep spaces or commas.
+/// A call that can't be resolved is kept as is, like an undeclared variable
+pub(crate) struct Interpolator {
+    regex: Regex,
+    variables: HashMap<String, Value>,
+}
+
+impl Interpolator {
+    pub(crate) fn new(variables: HashMap<String, Value>) -> Self {
+        Self {
+            regex: Regex::new(
+                r"\$?\$\{(?:([A-Za-z_][A-Za-z0-9_]*)|(upper|replace|default)\(([^()]*)\))\}",
+            )
+            .unwrap(),
+            variables,
+        }
+    }
+
+    pub(crate) fn value(&self, value: Value) -> Value {
+        match value {
+            Value::String(string) => match self.regex.captures(&string) {
+                Some(captures)
+                    if captures[0].len() == string.len() && !string.starts_with("$$") =>
+                {
+                    self.resolve(&captures)
+                        .unwrap_or_else(|| Value::String(self.string(&string)))
+                }
+                _ => Value::String(self.string(&string)),
+            },
+            Value::Sequence(sequence) => Value::Sequence(
+                sequence
+                    .into_iter()
+                    .map(|value| self.value(value))
+                    .collect(),
+            ),
+            Value::Mapping(mapping) => Value::Mapping(
+                mapping
+                    .into_iter()
+                    .map(|(key, value)| (self.value(key), self.value(value)))
+                    .collect::<Mapping>(),
+            ),
+            Value::Tagged(mut tagged) => {
+                tagged.value = self.value(tagged.value);
+                Value::Tagged(tagged)
+            }
+            value => value,
+        }
+    }
+
+    fn string(&self, string: &str) -> String {
+        self.regex
+            .replace_all(string, |captures: &Captures| {
+                let pattern = &captures[0];
+                match self.resolve(captures) {
+                    Some(_) if pattern.starts_with("$$") => pattern[1..].to_string(),
+                    Some(value) => scalar_string(&value),
+                    None => pattern.to_string(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// Resolves the variable or the function call of a pattern
+    fn resolve(&self, captures: &Captures) -> Option<Value> {
+        if let Some(name) = captures.get(1) {
+            return self.variables.get(name.as_str()).cloned();
+        }
+        let arguments = split_arguments(&captures[3]);
+        let subject = self.reference(arguments[0]);
+        match (&captures[2], subject, &arguments[1..]) {
+            ("upper", Some(value), []) => Some(Value::String(scalar_string(&value).to_uppercase())),
+            ("replace", Some(value), [from, to]) => Some(Value::String(
+                scalar_string(&value).replace(&unquote(from), &unquote(to)),
+            )),
+            ("default", Some(value), [_]) => Some(value),
+            ("default", None, [value]) => Some(literal(value)),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a variable, or of an environment variable with the `env:` prefix
+    fn reference(&self, name: &str) -> Option<Value> {
+        match name.strip_prefix("env:") {
+            Some(name) => std::env::var(name).ok().map(Value::String),
+            None => self.variables.get(name).cloned(),
+        }
+    }
+}
+
+/// Splits the arguments of a function call on the commas that aren't quoted
+fn split_arguments(arguments: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in arguments.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, ',') => {
+                parts.push(arguments[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(arguments[start..].trim());
+    parts
+}
+
+/// Removes the quotes around a literal argument
+fn unquote(argument: &str) -> String {
+    for quote in ['\'', '"'] {
+        if let Some(inner) = argument
+            .strip_prefix(quote)
+            .and_then(|argument| argument.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    argument.to_string()
+}
+
+/// Returns the typed value of a literal argument, a quoted one always being a string
+fn literal(argument: &str) -> Value {
+    let string = unquote(argument);
+    if string.len() != argument.len() {
+        return Value::String(string);
+    }
+    match serde_yaml::from_str::<Value>(argument) {
+        Ok(value @ (Value::Bool(_) | Value::Number(_))) => value,
+        _ => Value::String(string),
+    }
+}
+
+/// Returns the string representation of a scalar value
+pub(crate) fn scalar_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::new(),
+        value => serde_yaml::to_string(value)
+            .map(|string| string.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn interpolate_patterns() {
+        let interpolator = Interpolator::new(HashMap::from([(
+            "version".to_string(),
+            Value::String("1.2".into()),
+        )]));
+
+        assert_eq_sorted!(
+            interpolator.string("node:${version}-alpine"),
+            "node:1.2-alpine"
+        );
+        assert_eq_sorted!(interpolator.string("echo $${version}"), "echo ${version}");
+        assert_eq_sorted!(
+            interpolator.string("echo ${HOME} $HOME"),
+            "echo ${HOME} $HOME"
+        );
+        assert_eq_sorted!(interpolator.string("echo $${HOME}"), "echo $${HOME}");
+    }
+
+    #[test]
+    fn interpolate_functions() {
+        let interpolator = Interpolator::new(HashMap::from([
+            ("name".to_string(), Value::String("my-app".into())),
+            ("port".to_string(), Value::from(8080)),
+        ]));
+
+        assert_eq_sorted!(interpolator.string("${upper(name)}"), "MY-APP");
+        assert_eq_sorted!(
+            interpolator.string("/app/${replace(name, -, _)}"),
+            "/app/my_app"
+        );
+        assert_eq_sorted!(
+            interpolator.string("${replace(name, '-', ', ')}"),
+            "my, app"
+        );
+        assert_eq_sorted!(interpolator.string("${default(name, other)}"), "my-app");
+        assert_eq_sorted!(interpolator.string("${default(missing, other)}"), "other");
+        assert_eq_sorted!(
+            interpolator.string("${default(env:DOFIGEN_UNDEFINED_TEST_VAR, 3000)}"),
+            "3000"
+        );
+        assert_eq_sorted!(
+            interpolator.string("$${upper(name)} ${upper(missing)} ${upper(name, other)}"),
+            "${upper(name)} ${upper(missing)} ${upper(name, other)}"
+        );
+        assert_eq_sorted!(
+            interpolator.value(Value::String("${default(port, 3000)}".into())),
+            Value::from(8080)
+        );
+        assert_eq_sorted!(
+            interpolator.value(Value::String("${default(missing, 3000)}".into())),
+            Value::from(3000)
+        );
+        assert_eq_sorted!(
+            interpolator.value(Value::String("${default(missing, '3000')}".into())),
+            Value::String("3000".into())
+        );
+    }
+
+    #[test]
+    fn interpolate_typed_values() {
+        let interpolator =
+            Interpolator::new(HashMap::from([("port".to_string(), Value::from(8080))]));
+
+        assert_eq_sorted!(
+            interpolator.value(Value::String("${port}".into())),
+            Value::from(8080)
+        );
+        assert_eq_sorted!(
+            interpolator.value(Value::String("localhost:${port}".into())),
+            Value::String("localhost:8080".into())
+        );
+        assert_eq_sorted!(
+            interpolator.value(Value::String("$${port}".into())),
+            Value::String("${port}".into())
+        );
+    }
+
+    #[test]
+    fn matrix_combinations() {
+        let dofigen = Dofigen {
+            matrix: HashMap::from([
+                ("node".into(), vec!["20".into(), "22".into()]),
+                (
+                    "platform".into(),
+                    vec!["linux/amd64".into(), "linux/arm64".into()],
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq_sorted!(
+            dofigen
+                .matrix_combinations()
+                .unwrap()
+                .into_iter()
+                .map(|combination| combination.key)
+                .collect::<Vec<_>>(),
+            vec![
+                "20-linux-amd64",
+                "20-linux-arm64",
+                "22-linux-amd64",
+                "22-linux-arm64"
+            ]
+        );
+        assert_eq_sorted!(
+            dofigen.matrix_combinations().unwrap()[1].variables,
+            HashMap::from([
+                ("node".into(), "20".into()),
+                ("platform".into(), "linux/arm64".into()),
+            ])
+        );
+        assert_eq_sorted!(Dofigen::default().matrix_combinations().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn matrix_duplicate_keys() {
+        let dofigen = Dofigen {
+            matrix: HashMap::from([(
+                "platform".into(),
+                vec!["linux/amd64".into(), "linux-amd64".into()],
+            )]),
+            ..Default::default()
+        };
+
+        assert_eq_sorted!(
+            dofigen.matrix_combinations().unwrap_err().to_string(),
+            "Several combinations of the matrix have the key 'linux-amd64', use values that differ by their letters, digits, '.', '_' or '-'"
+        );
+    }
+}
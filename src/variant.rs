@@ -0,0 +1,126 @@
+use crate::{Dofigen, Error, FromContext, Result, Run, User};
+
+/// The overlay of the debug variant of the runtime stage
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugVariant {
+    /// The packages installed in the runtime stage with the package manager of its base image
+    pub packages: Vec<String>,
+    /// Run the image as root
+    pub root: bool,
+}
+
+impl Default for DebugVariant {
+    fn default() -> Self {
+        Self {
+            packages: vec!["curl".into(), "strace".into()],
+            root: false,
+        }
+    }
+}
+
+impl DebugVariant {
+    /// The shell command installing the packages with the first package manager found
+    fn install_command(&self) -> String {
+        let packages = self.packages.join(" ");
+        [
+            format!("apk add --no-cache {}", packages),
+            format!(
+                "apt-get update && apt-get install -y --no-install-recommends {} && rm -rf /var/lib/apt/lists/*",
+                packages
+            ),
+            format!("dnf install -y {}", packages),
+            format!("microdnf install -y {}", packages),
+        ]
+        .iter()
+        .zip(["apk", "apt-get", "dnf", "microdnf"])
+        .enumerate()
+        .map(|(index, (install, manager))| {
+            format!(
+                "{} command -v {} >/dev/null; then {};",
+                if index == 0 { "if" } else { "elif" },
+                manager,
+                install
+            )
+        })
+        .chain(std::iter::once(
+            "else echo 'No supported package manager found' >&2; exit 1; fi".to_string(),
+        ))
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+impl Dofigen {
+    /// Returns the debug variant of the image: the packages of the overlay are installed in the runtime stage.
+    /// The runtime stage needs a base with a shell and a package manager
+    pub fn debug_variant(&self, variant: &DebugVariant) -> Result<Dofigen> {
+        if self.stage.from == FromContext::FromContext(None) {
+            return Err(Error::Custom(
+                "The debug variant can't be generated from a scratch runtime stage".into(),
+            ));
+        }
+        let mut dofigen = self.clone();
+        if !variant.packages.is_empty() {
+            dofigen
+                .stage
+                .root
+                .get_or_insert_with(Run::default)
+                .run
+                .push(variant.install_command());
+        }
+        if variant.root {
+            dofigen.stage.user = Some(User {
+                user: "0".into(),
+                group: Some("0".into()),
+            });
+        }
+        Ok(dofigen)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ImageName, Stage};
+    use pretty_assertions_sorted::assert_eq_sorted;
+
+    #[test]
+    fn install_packages() {
+        let dofigen = Dofigen {
+            stage: Stage {
+                from: FromContext::FromImage(ImageName {
+                    path: "alpine".into(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let debug = dofigen
+            .debug_variant(&DebugVariant {
+                packages: vec!["curl".into()],
+                root: true,
+            })
+            .unwrap();
+
+        assert_eq_sorted!(
+            debug.stage.root.unwrap().run,
+            vec!["if command -v apk >/dev/null; then apk add --no-cache curl; elif command -v apt-get >/dev/null; then apt-get update && apt-get install -y --no-install-recommends curl && rm -rf /var/lib/apt/lists/*; elif command -v dnf >/dev/null; then dnf install -y curl; elif command -v microdnf >/dev/null; then microdnf install -y curl; else echo 'No supported package manager found' >&2; exit 1; fi"]
+        );
+        assert_eq_sorted!(
+            debug.stage.user,
+            Some(User {
+                user: "0".into(),
+                group: Some("0".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn scratch_runtime() {
+        assert!(Dofigen::default()
+            .debug_variant(&DebugVariant::default())
+            .is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    Add, AddGitRepo, Bind, Cache, Copy, CopyContent, CopyResource, Dofigen, FromContext,
+    Healthcheck, OutputUse, Port, Run, Stage,
+};
+
+/// The name given to the runtime stage when visiting it
+pub const RUNTIME_STAGE_NAME: &str = "runtime";
+
+/// A visitor over the Dofigen struct.
+///
+/// Each method visits the children of the element by default, calling the matching `walk_*` function.
+/// Override a method to handle an element, and call its `walk_*` function to keep visiting its children.
+///
+/// ```
+/// use dofigen_lib::*;
+///
+/// #[derive(Default)]
+/// struct RunLines(usize);
+///
+/// impl<'a> Visitor<'a> for RunLines {
+///     fn visit_run(&mut self, run: &'a Run) {
+///         self.0 += run.run.len();
+///         walk_run(self, run);
+///     }
+/// }
+///
+/// let dofigen = DofigenContext::new()
+///     .parse_from_string("run: [make, make test]")
+///     .unwrap();
+/// let mut lines = RunLines::default();
+/// lines.visit_dofigen(&dofigen);
+/// assert_eq!(lines.0, 2);
+/// ```
+pub trait Visitor<'a> {
+    /// Visits the builders sorted by name, then the runtime stage and the image metadata
+    fn visit_dofigen(&mut self, dofigen: &'a Dofigen) {
+        walk_dofigen(self, dofigen);
+    }
+
+    /// Visits a stage, the runtime one being named [`RUNTIME_STAGE_NAME`]
+    fn visit_stage(&mut self, name: &'a str, stage: &'a Stage) {
+        walk_stage(self, name, stage);
+    }
+
+    /// Visits an origin: the base of a stage, of a copy or of a mount
+    fn visit_from(&mut self, _from: &'a FromContext) {}
+
+    /// Visits a run, the root one of a stage or its main one
+    fn visit_run(&mut self, run: &'a Run) {
+        walk_run(self, run);
+    }
+
+    fn visit_cache(&mut self, cache: &'a Cache) {
+        walk_cache(self, cache);
+    }
+
+    fn visit_bind(&mut self, bind: &'a Bind) {
+        walk_bind(self, bind);
+    }
+
+    fn visit_copy_resource(&mut self, resource: &'a CopyResource) {
+        walk_copy_resource(self, resource);
+    }
+
+    fn visit_copy(&mut self, copy: &'a Copy) {
+        walk_copy(self, copy);
+    }
+
+    fn visit_copy_content(&mut self, _content: &'a CopyContent) {}
+
+    fn visit_add_git_repo(&mut self, _repo: &'a AddGitRepo) {}
+
+    fn visit_add(&mut self, _add: &'a Add) {}
+
+    fn visit_output_use(&mut self, _output: &'a OutputUse) {}
+
+    fn visit_port(&mut self, _port: &'a Port) {}
+
+    fn visit_healthcheck(&mut self, _healthcheck: &'a Healthcheck) {}
+}
+
+pub fn walk_dofigen<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, dofigen: &'a Dofigen) {
+    let mut builders = dofigen.builders.iter().collect::<Vec<_>>();
+    builders.sort_by_key(|(name, _)| *name);
+    for (name, builder) in builders {
+        visitor.visit_stage(name, builder);
+    }
+    visitor.visit_stage(RUNTIME_STAGE_NAME, &dofigen.stage);
+    for port in dofigen.expose.iter() {
+        visitor.visit_port(port);
+    }
+    if let Some(healthcheck) = &dofigen.healthcheck {
+        visitor.visit_healthcheck(healthcheck);
+    }
+}
+
+/// Visits the base of the stage, its copies, its used outputs, then its root run and its main run
+pub fn walk_stage<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, _name: &'a str, stage: &'a Stage) {
+    visitor.visit_from(&stage.from);
+    for resource in stage.copy.iter() {
+        visitor.visit_copy_resource(resource);
+    }
+    for output in stage.uses.iter() {
+        visitor.visit_output_use(output);
+    }
+    if let Some(root) = &stage.root {
+        visitor.visit_run(root);
+    }
+    visitor.visit_run(&stage.run);
+}
+
+pub fn walk_run<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, run: &'a Run) {
+    for cache in run.cache.iter() {
+        visitor.visit_cache(cache);
+    }
+    for bind in run.bind.iter() {
+        visitor.visit_bind(bind);
+    }
+}
+
+pub fn walk_cache<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, cache: &'a Cache) {
+    visitor.visit_from(&cache.from);
+}
+
+pub fn walk_bind<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, bind: &'a Bind) {
+    visitor.visit_from(&bind.from);
+}
+
+pub fn walk_copy_resource<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    resource: &'a CopyResource,
+) {
+    match resource {
+        CopyResource::Copy(copy) => visitor.visit_copy(copy),
+        CopyResource::Content(content) => visitor.visit_copy_content(content),
+        CopyResource::AddGitRepo(repo) => visitor.visit_add_git_repo(repo),
+        CopyResource::Add(add) => visitor.visit_add(add),
+    }
+}
+
+pub fn walk_copy<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, copy: &'a Copy) {
+    visitor.visit_from(&copy.from);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions_sorted::assert_eq_sorted;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl<'a> Visitor<'a> for Trace {
+        fn visit_stage(&mut self, name: &'a str, stage: &'a Stage) {
+            self.0.push(format!("stage {}", name));
+            walk_stage(self, name, stage);
+        }
+
+        fn visit_from(&mut self, from: &'a FromContext) {
+            self.0.push(format!("from {:?}", from));
+        }
+
+        fn visit_run(&mut self, run: &'a Run) {
+            self.0.push(format!("run {:?}", run.run));
+            walk_run(self, run);
+        }
+
+        fn visit_copy_content(&mut self, content: &'a CopyContent) {
+            self.0.push(format!("content {}", content.content));
+        }
+
+        fn visit_output_use(&mut self, output: &'a OutputUse) {
+            self.0.push(format!("use {}", output.builder));
+        }
+    }
+
+    #[test]
+    fn visit_order() {
+        let stage = |from: &str, run: &str| Stage {
+            from: FromContext::FromBuilder(from.into()),
+            run: Run {
+                run: vec![run.into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let dofigen = Dofigen {
+            builders: HashMap::from([("b".into(), stage("a", "b")), ("a".into(), stage("c", "a"))]),
+            stage: Stage {
+                copy: vec![CopyResource::Content(CopyContent {
+                    content: "text".into(),
+                    ..Default::default()
+                })],
+                uses: vec![OutputUse {
+                    builder: "b".into(),
+                    ..Default::default()
+                }],
+                root: Some(Run {
+                    run: vec!["root".into()],
+                    bind: vec![Bind {
+                        from: FromContext::FromBuilder("a".into()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut trace = Trace::default();
+        trace.visit_dofigen(&dofigen);
+
+        assert_eq_sorted!(
+            trace.0,
+            vec![
+                "stage a",
+                "from FromBuilder(\"c\")",
+                "run [\"a\"]",
+                "stage b",
+                "from FromBuilder(\"a\")",
+                "run [\"b\"]",
+                "stage runtime",
+                "from FromContext(None)",
+                "content text",
+                "use b",
+                "run [\"root\"]",
+                "from FromBuilder(\"a\")",
+                "run []",
+            ]
+        );
+    }
+}
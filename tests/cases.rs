@@ -119,3 +119,25 @@ fn test_load_url() {
             .unwrap()
     );
 }
+
+#[test]
+fn test_cases_generate_many() {
+    let names = [
+        "simple",
+        "springboot-maven.base",
+        "springboot-maven.extend",
+        "springboot-maven.override",
+    ];
+    let resources =
+        names.map(|name| Resource::File(PathBuf::from(format!("tests/cases/{}.yml", name))));
+
+    let mut context = DofigenContext::new();
+    let dockerfiles = context.generate_many(&resources);
+
+    for (name, dockerfile) in names.iter().zip(dockerfiles) {
+        assert_eq_sorted!(
+            dockerfile.unwrap(),
+            std::fs::read_to_string(format!("tests/cases/{}.result.Dockerfile", name)).unwrap()
+        );
+    }
+}
@@ -1,7 +1,10 @@
 #[cfg(feature = "cli")]
 mod cli {
     use core::str;
-    use std::fs::read_to_string;
+    use std::{
+        fs::read_to_string,
+        io::{BufRead, BufReader},
+    };
 
     use assert_cmd::output::OutputOkExt;
     use assert_fs::{
@@ -200,4 +203,1737 @@ Usage: dofigen <COMMAND>"#,
 
         temp.close().unwrap();
     }
+
+    #[test]
+    fn effective_keeps_image_tags() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("effective").arg("--offline").arg("--keep-tags");
+
+        let file = temp.child("dofigen.yml");
+        file.write_str(
+            r#"fromImage:
+  path: alpine
+  tag: "3.20"
+"#,
+        )
+        .unwrap();
+
+        let output = cmd.unwrap();
+
+        assert!(output.status.success());
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stdout).unwrap(),
+            r#"fromImage:
+  path: alpine
+  tag: '3.20'
+
+"#
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn effective_resolve_images_from_lockfile() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("effective")
+            .arg("--offline")
+            .arg("--resolve-images");
+
+        let file = temp.child("dofigen.yml");
+        file.write_str(
+            r#"fromImage:
+  path: alpine
+  tag: "3.20"
+"#,
+        )
+        .unwrap();
+
+        let lockfile = temp.child("dofigen.lock");
+        lockfile
+            .write_str(
+                r#"effective: ""
+images:
+  registry.hub.docker.com:443:
+    library:
+      alpine:
+        '3.20':
+          digest: sha256:0123456789abcdef
+resources: {}
+"#,
+            )
+            .unwrap();
+
+        let output = cmd.unwrap();
+
+        assert!(output.status.success());
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stdout).unwrap(),
+            r#"fromImage:
+  path: alpine
+  digest: sha256:0123456789abcdef
+
+"#
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn effective_keep_tags_with_locked() {
+        let mut cmd = BIN.command();
+        cmd.arg("effective").arg("--locked").arg("--keep-tags");
+
+        let output = cmd.output().unwrap();
+
+        assert!(!output.status.success());
+        output_starts_with(
+            &output.stderr,
+            "error: the argument '--locked' cannot be used with '--keep-tags'",
+        );
+    }
+
+    #[test]
+    fn effective_timings_json() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("effective")
+            .arg("--offline")
+            .arg("--keep-tags")
+            .arg("--timings")
+            .arg("json");
+
+        let file = temp.child("dofigen.yml");
+        file.write_str(
+            r#"fromImage:
+  path: alpine
+"#,
+        )
+        .unwrap();
+
+        let output = cmd.unwrap();
+
+        assert!(output.status.success());
+
+        let stderr = str::from_utf8(&output.stderr).unwrap();
+        let re = Regex::new(r#"^\{"parse":[0-9.e-]+,"extend":[0-9.e-]+,"lockIo":[0-9.e-]+\}\n$"#)
+            .unwrap();
+        assert!(re.is_match(stderr), "Unexpected timings output: {}", stderr);
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn lint_json() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("lint").arg("--offline").arg("--format").arg("json");
+
+        let file = temp.child("dofigen.yml");
+        file.write_str(
+            r#"fromImage:
+  path: alpine
+env:
+  DB_PASSWORD: changeme
+"#,
+        )
+        .unwrap();
+
+        let output = cmd.output().unwrap();
+
+        assert!(!output.status.success());
+
+        let messages: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq_sorted!(
+            messages,
+            serde_json::json!([
+                {
+                    "rule": "unpinned-image",
+                    "level": "warning",
+                    "path": "fromImage",
+                    "message": "The image 'alpine' uses the latest tag, pin it with a version tag or a digest",
+                },
+                {
+                    "rule": "secret-in-env",
+                    "level": "error",
+                    "path": "env.DB_PASSWORD",
+                    "message": "The env 'DB_PASSWORD' looks like a secret and is kept in the image, mount it with the secret field of the run instead",
+                },
+            ])
+        );
+        output_starts_with(&output.stderr, "error: The lint found 1 error");
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn lint_config_overrides_levels() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("lint").arg("--offline");
+
+        temp.child("dofigen.yml")
+            .write_str(
+                r#"lint:
+  unpinned-image: error
+  secret-in-env: off
+fromImage:
+  path: alpine
+env:
+  DB_PASSWORD: changeme
+"#,
+            )
+            .unwrap();
+        temp.child(".dofigenlint.yml")
+            .write_str("unpinned-image: warn\n")
+            .unwrap();
+
+        let output = cmd.output().unwrap();
+
+        assert!(output.status.success());
+        assert_eq_sorted!(
+            str::from_utf8(&output.stdout).unwrap(),
+            "warning[unpinned-image][path=fromImage]: The image 'alpine' uses the latest tag, pin it with a version tag or a digest\n"
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn diff_dofigen_files() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("diff")
+            .arg("--offline")
+            .arg("--exit-code")
+            .arg("base.yml");
+
+        temp.child("base.yml")
+            .write_str(
+                r#"fromImage:
+  path: alpine
+  digest: sha256:0000000000000000000000000000000000000000000000000000000000000000
+workdir: /app
+run:
+  - echo hello
+"#,
+            )
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str(
+                r#"fromImage:
+  path: alpine
+  digest: sha256:0000000000000000000000000000000000000000000000000000000000000000
+run:
+  - echo hello
+  - echo world
+"#,
+            )
+            .unwrap();
+
+        let output = cmd.output().unwrap();
+
+        assert!(!output.status.success());
+        assert_eq_sorted!(
+            str::from_utf8(&output.stdout).unwrap(),
+            r#"Effective configuration
++ run.1: echo world
+- workdir: /app
+
+--- base.yml
++++ dofigen.yml
+@@ -4,6 +4,8 @@
+ 
+ # runtime
+ FROM alpine@sha256:0000000000000000000000000000000000000000000000000000000000000000 AS runtime
+-WORKDIR /app
+ USER 1000:1000
+-RUN echo hello
++RUN <<EOF
++echo hello
++echo world
++EOF
+"#
+        );
+        output_starts_with(&output.stderr, "error: The files differ");
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn generate_watch_extended_file() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let base = temp.child("base.yml");
+        base.write_str("workdir: /app\n").unwrap();
+        temp.child("dofigen.yml")
+            .write_str(
+                r#"extend:
+  - base.yml
+fromImage:
+  path: alpine
+  digest: sha256:0000000000000000000000000000000000000000000000000000000000000000
+"#,
+            )
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("--offline").arg("--watch");
+        cmd.stdout(std::process::Stdio::null());
+        cmd.stderr(std::process::Stdio::piped());
+        let mut child = cmd.spawn().unwrap();
+
+        // Each generation ends with the watching message
+        let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+        let mut wait_for_generation = || {
+            stderr
+                .by_ref()
+                .map_while(|line| line.ok())
+                .any(|line| line.starts_with("Watching "))
+        };
+        let dockerfile = temp.path().join("Dockerfile");
+
+        let generated = wait_for_generation()
+            && read_to_string(&dockerfile).is_ok_and(|content| content.contains("WORKDIR /app"));
+        base.write_str("workdir: /srv\n").unwrap();
+        let regenerated = wait_for_generation()
+            && read_to_string(&dockerfile).is_ok_and(|content| content.contains("WORKDIR /srv"));
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert!(generated, "The Dockerfile wasn't generated");
+        assert!(regenerated, "The Dockerfile wasn't generated again");
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn sbom_static() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("sbom").arg("--offline").arg("--static");
+
+        temp.child("dofigen.yml")
+            .write_str(
+                r#"fromImage:
+  path: alpine
+  tag: "3.20"
+packages:
+  - name: tini
+    version: 0.19.0-r3
+    license: MIT
+run:
+  - apk add --no-cache curl
+"#,
+            )
+            .unwrap();
+
+        let output = cmd.output().unwrap();
+
+        assert!(output.status.success());
+        let sbom: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq_sorted!(sbom["bomFormat"], "CycloneDX");
+        assert_eq_sorted!(
+            sbom["components"],
+            serde_json::json!([
+                {
+                    "type": "container",
+                    "bom-ref": "pkg:docker/alpine@3.20",
+                    "name": "alpine",
+                    "version": "3.20",
+                    "purl": "pkg:docker/alpine@3.20",
+                    "properties": [{ "name": "dofigen:stage", "value": "runtime" }],
+                },
+                {
+                    "type": "library",
+                    "bom-ref": "tini@0.19.0-r3",
+                    "name": "tini",
+                    "version": "0.19.0-r3",
+                    "licenses": [{ "expression": "MIT" }],
+                    "properties": [
+                        { "name": "dofigen:stage", "value": "runtime" },
+                        { "name": "dofigen:source", "value": "declared" },
+                    ],
+                },
+                {
+                    "type": "library",
+                    "bom-ref": "curl",
+                    "name": "curl",
+                    "properties": [
+                        { "name": "dofigen:stage", "value": "runtime" },
+                        { "name": "dofigen:source", "value": "run" },
+                    ],
+                },
+            ])
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn refactor_extract_builder_to_new_file() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor")
+            .arg("extract-builder")
+            .arg("builder")
+            .arg("--to")
+            .arg("base.yml");
+
+        let file = temp.child("dofigen.yml");
+        file.write_str(
+            r#"builders:
+  builder:
+    fromImage:
+      path: rust
+    run:
+    - cargo build --release
+fromImage:
+  path: alpine
+copy:
+- fromBuilder: builder
+  paths:
+  - /app/target/release/app
+"#,
+        )
+        .unwrap();
+
+        cmd.unwrap();
+
+        assert_eq_sorted!(
+            read_to_string(file.path()).unwrap(),
+            r#"extend:
+- base.yml
+fromImage:
+  path: alpine
+copy:
+- fromBuilder: builder
+  paths:
+  - /app/target/release/app
+"#
+        );
+        assert_eq_sorted!(
+            read_to_string(temp.child("base.yml").path()).unwrap(),
+            r#"builders:
+  builder:
+    fromImage:
+      path: rust
+    run:
+    - cargo build --release
+"#
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn refactor_extract_builder_to_last_extended_file() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor")
+            .arg("extract-builder")
+            .arg("tools")
+            .arg("--to")
+            .arg("base.yml");
+
+        let file = temp.child("dofigen.yml");
+        file.write_str(
+            r#"extend:
+- common.yml
+- base.yml
+builders:
+  tools:
+    fromImage: busybox
+fromImage: alpine
+"#,
+        )
+        .unwrap();
+        temp.child("common.yml")
+            .write_str("workdir: /app\n")
+            .unwrap();
+        let base = temp.child("base.yml");
+        base.write_str(
+            r#"builders:
+  builder:
+    fromImage: rust
+"#,
+        )
+        .unwrap();
+
+        cmd.unwrap();
+
+        assert_eq_sorted!(
+            read_to_string(file.path()).unwrap(),
+            r#"extend:
+- common.yml
+- base.yml
+fromImage: alpine
+"#
+        );
+        assert_eq_sorted!(
+            read_to_string(base.path()).unwrap(),
+            r#"builders:
+  builder:
+    fromImage: rust
+  tools:
+    fromImage: busybox
+"#
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn refactor_extract_builder_not_last_extended_file() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor")
+            .arg("extract-builder")
+            .arg("tools")
+            .arg("--to")
+            .arg("base.yml");
+
+        let content = r#"extend:
+- base.yml
+- common.yml
+builders:
+  tools:
+    fromImage: busybox
+"#;
+        let file = temp.child("dofigen.yml");
+        file.write_str(content).unwrap();
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .contains("The file base.yml must be the last extended file"));
+        assert_eq_sorted!(read_to_string(file.path()).unwrap(), content);
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn refactor_extract_builder_with_comments() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let content = r#"builders:
+  builder:
+    fromImage:
+      path: rust
+    # Build the release binary
+    run:
+    - cargo build --release
+fromImage:
+  path: alpine
+"#;
+        let file = temp.child("dofigen.yml");
+        file.write_str(content).unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor")
+            .arg("extract-builder")
+            .arg("builder")
+            .arg("--to")
+            .arg("base.yml");
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .contains("has comments that the refactoring would remove, use --force"));
+        assert_eq_sorted!(read_to_string(file.path()).unwrap(), content);
+        assert!(!temp.child("base.yml").exists());
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor")
+            .arg("extract-builder")
+            .arg("builder")
+            .arg("--to")
+            .arg("base.yml")
+            .arg("--force");
+
+        cmd.unwrap();
+
+        assert_eq_sorted!(
+            read_to_string(temp.child("base.yml").path()).unwrap(),
+            r#"builders:
+  builder:
+    fromImage:
+      path: rust
+    run:
+    - cargo build --release
+"#
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn refactor_inline_last_extended_file() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor").arg("inline").arg("base.yml");
+
+        temp.child("common.yml")
+            .write_str("workdir: /app\n")
+            .unwrap();
+        temp.child("base.yml")
+            .write_str(
+                r#"fromImage:
+  path: alpine
+run:
+- echo base
+"#,
+            )
+            .unwrap();
+        let file = temp.child("dofigen.yml");
+        file.write_str(
+            r#"extend:
+- common.yml
+- base.yml
+run:
+  +: [echo local]
+context:
+  +: [/src]
+"#,
+        )
+        .unwrap();
+
+        cmd.unwrap();
+
+        assert_eq_sorted!(
+            read_to_string(file.path()).unwrap(),
+            r#"extend:
+- common.yml
+context:
+  +:
+  - /src
+fromImage:
+  path: alpine
+run:
+- echo base
+- echo local
+"#
+        );
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn refactor_inline_with_comments() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor").arg("inline").arg("base.yml");
+
+        temp.child("base.yml").write_str("workdir: /app\n").unwrap();
+        let content = r#"extend:
+- base.yml
+run:
+- echo local # Says hello
+"#;
+        let file = temp.child("dofigen.yml");
+        file.write_str(content).unwrap();
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .contains("has comments that the refactoring would remove, use --force"));
+        assert_eq_sorted!(read_to_string(file.path()).unwrap(), content);
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn refactor_inline_changing_effective_configuration() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("refactor").arg("inline").arg("base.yml");
+
+        temp.child("common.yml")
+            .write_str("workdir: /app\n")
+            .unwrap();
+        temp.child("base.yml")
+            .write_str("workdir: /base\n")
+            .unwrap();
+        let content = r#"extend:
+- base.yml
+- common.yml
+"#;
+        let file = temp.child("dofigen.yml");
+        file.write_str(content).unwrap();
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .contains("can't be inlined without changing the effective configuration"));
+        assert_eq_sorted!(read_to_string(file.path()).unwrap(), content);
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn generate_from_cargo_metadata() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("generate")
+            .arg("--from-metadata")
+            .arg("--offline")
+            .arg("-o")
+            .arg("-");
+
+        temp.child("Cargo.toml")
+            .write_str(
+                r#"[package]
+name = "app"
+version = "0.1.0"
+
+[package.metadata.dofigen]
+fromImage = { path = "alpine", digest = "sha256:0123456789abcdef" }
+run = ["echo hello"]
+"#,
+            )
+            .unwrap();
+
+        let output = cmd.unwrap();
+
+        assert!(str::from_utf8(&output.stdout).unwrap().ends_with(
+            r#"FROM alpine@sha256:0123456789abcdef AS runtime
+USER 1000:1000
+RUN echo hello
+"#
+        ));
+        temp.child("dofigen.lock")
+            .assert(predicates::path::is_file());
+        temp.child("Cargo.lock").assert(predicates::path::missing());
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn generate_from_package_json_metadata() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("generate")
+            .arg("--from-metadata")
+            .arg("--offline")
+            .arg("-o")
+            .arg("-");
+
+        temp.child("package.json")
+            .write_str(
+                r#"{
+  "name": "app",
+  "dofigen": {
+    "fromImage": { "path": "node", "digest": "sha256:0123456789abcdef" },
+    "run": ["npm ci"]
+  }
+}"#,
+            )
+            .unwrap();
+
+        let output = cmd.unwrap();
+
+        assert!(str::from_utf8(&output.stdout).unwrap().ends_with(
+            r#"FROM node@sha256:0123456789abcdef AS runtime
+USER 1000:1000
+RUN npm ci
+"#
+        ));
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn generate_from_metadata_without_metadata() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("generate").arg("--from-metadata").arg("--offline");
+
+        temp.child("Cargo.toml")
+            .write_str(
+                r#"[package]
+name = "app"
+version = "0.1.0"
+"#,
+            )
+            .unwrap();
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .contains("No Dofigen metadata found in Cargo.toml or package.json"));
+
+        temp.close().unwrap();
+    }
+
+    #[test]
+    fn inspect_invalid_image() {
+        let mut cmd = BIN.command();
+        cmd.arg("inspect").arg("not an image");
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stderr).unwrap(),
+            "error: Not matching image name pattern\n"
+        );
+    }
+
+    #[test]
+    fn tags_invalid_filter() {
+        let mut cmd = BIN.command();
+        cmd.arg("tags").arg("alpine").arg("--filter").arg("(");
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .starts_with("error: regex parse error"));
+    }
+
+    #[cfg(feature = "json_schema")]
+    #[test]
+    fn complete_builder_name() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let file = temp.child("dofigen.yml");
+        file.write_str("builders:\n  builder:\n    fromImage: rust\nfromBuilder: \n")
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.arg("complete")
+            .arg("--position")
+            .arg(format!("{}:4:14", file.path().display()));
+
+        let output = cmd.unwrap();
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stdout).unwrap(),
+            r#"{
+  "path": [
+    "fromBuilder"
+  ],
+  "kind": "value",
+  "prefix": "",
+  "items": [
+    {
+      "label": "builder",
+      "kind": "builder"
+    }
+  ]
+}
+"#
+        );
+    }
+
+    #[cfg(feature = "json_schema")]
+    #[test]
+    fn complete_invalid_position() {
+        let mut cmd = BIN.command();
+        cmd.arg("complete").arg("--position").arg("dofigen.yml:4");
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stderr).unwrap(),
+            "error: The position 'dofigen.yml:4' must be formatted as file:line:column\n"
+        );
+    }
+
+    #[test]
+    fn generate_with_fixtures() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n")
+            .unwrap();
+        temp.child("fixtures.yml")
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/ubuntu:latest:\n    digest: sha256:abc\n",
+            )
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("generate")
+            .arg("-f")
+            .arg("dofigen.yml")
+            .arg("--fixtures")
+            .arg("fixtures.yml");
+
+        let output = cmd.unwrap();
+
+        assert!(output.status.success());
+
+        let dockerfile = read_to_string(temp.child("Dockerfile").path()).unwrap();
+        assert!(dockerfile.contains("FROM ubuntu@sha256:abc AS runtime"));
+    }
+
+    #[test]
+    fn generate_with_missing_fixture() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n")
+            .unwrap();
+        temp.child("fixtures.yml").write_str("{}").unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("generate")
+            .arg("-f")
+            .arg("dofigen.yml")
+            .arg("--fixtures")
+            .arg("fixtures.yml");
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stderr).unwrap(),
+            "error: No image tag fixture recorded for 'registry.hub.docker.com:443/ubuntu:latest' in \"fixtures.yml\"\n"
+        );
+    }
+
+    #[test]
+    fn vendor_url_resources() {
+        use httptest::{matchers::request, responders::status_code, Expectation, ServerBuilder};
+
+        let server = ServerBuilder::new()
+            .bind_addr("127.0.0.1:0".parse().unwrap())
+            .run()
+            .unwrap();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/base.yml")).respond_with(
+                status_code(200).body("fromImage:\n  path: ubuntu\n  digest: sha256:abc\n"),
+            ),
+        );
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str(&format!(
+                "extend:\n  - {}\nuser: \"1001\"\n",
+                server.url_str("/base.yml")
+            ))
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("vendor").arg("-f").arg("dofigen.yml");
+        cmd.unwrap();
+
+        let vendored = temp.child(format!(
+            ".dofigen/vendor/127.0.0.1_{}/base.yml",
+            server.addr().port()
+        ));
+        vendored.assert(predicates::path::is_file());
+        drop(server);
+
+        // The vendored file is loaded instead of the URL
+        vendored
+            .write_str("fromImage:\n  path: debian\n  digest: sha256:def\n")
+            .unwrap();
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("update").arg("-f").arg("dofigen.yml");
+        cmd.unwrap();
+
+        let lockfile = read_to_string(temp.child("dofigen.lock").path()).unwrap();
+        assert!(lockfile.contains("path: debian"));
+        assert!(lockfile.contains(".dofigen/vendor/127.0.0.1_"));
+    }
+
+    #[test]
+    fn self_check_update() {
+        use httptest::{matchers::request, responders::status_code, Expectation, ServerBuilder};
+
+        let server = ServerBuilder::new()
+            .bind_addr("127.0.0.1:0".parse().unwrap())
+            .run()
+            .unwrap();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/releases/latest")).respond_with(
+                status_code(200).body(
+                    r#"{ "tag_name": "v999.0.0", "html_url": "https://github.com/lenra-io/dofigen/releases/tag/v999.0.0", "assets": [] }"#,
+                ),
+            ),
+        );
+
+        let mut cmd = BIN.command();
+        cmd.env("DOFIGEN_RELEASES_URL", server.url_str("/releases/latest"))
+            .env("NO_COLOR", "1")
+            .arg("self")
+            .arg("check-update");
+        let output = cmd.unwrap();
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stdout).unwrap(),
+            format!(
+                "A new version of Dofigen is available: {} -> 999.0.0\nRelease notes: https://github.com/lenra-io/dofigen/releases/tag/v999.0.0\nRun dofigen self update to install it\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn external_subcommand_plugin() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  digest: sha256:abc\n")
+            .unwrap();
+        let plugin = temp.child("bin/dofigen-hello");
+        plugin
+            .write_str("#!/bin/sh\necho \"args: $*\"\necho \"file: $DOFIGEN_FILE\"\ncat\n")
+            .unwrap();
+        std::fs::set_permissions(plugin.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        let path = format!(
+            "{}:{}",
+            temp.child("bin").path().display(),
+            std::env::var("PATH").unwrap_or_default()
+        );
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path())
+            .env("PATH", &path)
+            .arg("hello")
+            .arg("--name")
+            .arg("world");
+        let output = cmd.unwrap();
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.stdout).unwrap(),
+            "args: --name world\nfile: dofigen.yml\n{\"fromImage\":{\"path\":\"ubuntu\",\"digest\":\"sha256:abc\"}}"
+        );
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path())
+            .env("PATH", &path)
+            .arg("missing");
+        let output = cmd.unwrap_err();
+
+        assert_eq_sorted!(
+            str::from_utf8(&output.as_output().unwrap().stderr).unwrap(),
+            "error: The subcommand 'missing' doesn't exist and no dofigen-missing plugin is found in the PATH\n"
+        );
+    }
+
+    #[test]
+    fn compose_services() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let dofigen = "fromImage:\n  path: ubuntu\n  digest: sha256:abc\n";
+        temp.child("compose.yaml")
+            .write_str(
+                "services:\n  api:\n    build: ./api\n  worker:\n    build:\n      context: worker\n    x-dofigen: worker.dofigen.yml\n  db:\n    image: postgres\n",
+            )
+            .unwrap();
+        temp.child("api/dofigen.yml").write_str(dofigen).unwrap();
+        temp.child("worker/worker.dofigen.yml")
+            .write_str(dofigen)
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("compose").arg("--write");
+        cmd.unwrap();
+
+        temp.child("api/Dockerfile")
+            .assert(predicates::path::is_file());
+        temp.child("api/.dockerignore")
+            .assert(predicates::path::is_file());
+        temp.child("worker/worker.dofigen.Dockerfile")
+            .assert(predicates::path::is_file());
+        temp.child("worker/worker.dofigen.lock")
+            .assert(predicates::path::is_file());
+
+        let compose: serde_yaml::Value =
+            serde_yaml::from_str(&read_to_string(temp.child("compose.yaml").path()).unwrap())
+                .unwrap();
+        assert_eq_sorted!(
+            compose["services"]["api"]["build"],
+            serde_yaml::from_str::<serde_yaml::Value>("context: ./api\ndockerfile: Dockerfile")
+                .unwrap()
+        );
+        assert_eq_sorted!(
+            compose["services"]["worker"]["build"]["dockerfile"],
+            serde_yaml::Value::from("worker.dofigen.Dockerfile")
+        );
+        assert_eq_sorted!(
+            compose["services"]["db"],
+            serde_yaml::from_str::<serde_yaml::Value>("image: postgres").unwrap()
+        );
+    }
+
+    #[test]
+    fn update_sync_dockerfile() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let dofigen = temp.child("dofigen.yml");
+        dofigen
+            .write_str("fromImage:\n  path: ubuntu\n  digest: sha256:abc\n")
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("-o").arg("-");
+        cmd.unwrap();
+        temp.child("Dockerfile").assert(predicates::path::missing());
+
+        dofigen
+            .write_str(
+                "fromImage:\n  path: ubuntu\n  digest: sha256:abc\nuser:\n  user: \"1001\"\n",
+            )
+            .unwrap();
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("update").arg("--sync-dockerfile");
+        cmd.unwrap();
+
+        let dockerfile = read_to_string(temp.child("Dockerfile").path()).unwrap();
+        assert!(dockerfile.contains("USER 1001"));
+        temp.child(".dockerignore")
+            .assert(predicates::path::is_file());
+    }
+
+    #[test]
+    fn generate_oci_config() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str(
+                "fromImage:\n  path: ubuntu\n  digest: sha256:abc\nworkdir: /app\nenv:\n  MODE: prod\nexpose:\n  - port: 8080\ncmd: [./server]\n",
+            )
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("--oci-config").arg("config.json");
+        cmd.unwrap();
+
+        assert_eq_sorted!(
+            read_to_string(temp.child("config.json").path()).unwrap(),
+            r#"{
+  "config": {
+    "User": "1000:1000",
+    "ExposedPorts": {
+      "8080": {}
+    },
+    "Env": [
+      "MODE=prod"
+    ],
+    "Cmd": [
+      "./server"
+    ],
+    "WorkingDir": "/app"
+  }
+}"#
+        );
+    }
+
+    #[test]
+    fn update_image_tag_with_manifest() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("fixtures.yml")
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/ubuntu:24.04:\n    digest: sha256:abc\n  registry.hub.docker.com:443/ubuntu:25.04:\n    digest: sha256:def\n",
+            )
+            .unwrap();
+        temp.child("base.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  tag: \"24.04\"\n")
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str("extend: [base.yml]\nworkdir: /app\n")
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen")
+            .arg("-o")
+            .arg("-")
+            .arg("--fixtures")
+            .arg("fixtures.yml");
+        cmd.unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("update")
+            .arg("--fixtures")
+            .arg("fixtures.yml")
+            .arg("--image")
+            .arg("ubuntu=25.04")
+            .arg("--manifest")
+            .arg("dependencies.json");
+        cmd.unwrap();
+
+        assert_eq_sorted!(
+            read_to_string(temp.child("base.yml").path()).unwrap(),
+            "fromImage:\n  path: ubuntu\n  tag: '25.04'\n"
+        );
+        assert_eq_sorted!(
+            read_to_string(temp.child("dependencies.json").path()).unwrap(),
+            r#"[
+  {
+    "path": "fromImage",
+    "image": "ubuntu",
+    "tag": "25.04",
+    "digest": "sha256:def"
+  }
+]"#
+        );
+    }
+
+    #[test]
+    fn clean_generated_files() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  digest: sha256:abc\n")
+            .unwrap();
+        temp.child(".dofigen/vendor/example.com/base.yml")
+            .write_str("user:\n  user: \"1001\"\n")
+            .unwrap();
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen");
+        cmd.unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("clean").arg("--dry-run");
+        cmd.unwrap();
+        temp.child("Dockerfile").assert(predicates::path::is_file());
+        temp.child(".dofigen/vendor")
+            .assert(predicates::path::is_dir());
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("clean");
+        cmd.unwrap();
+        temp.child("Dockerfile").assert(predicates::path::missing());
+        temp.child(".dockerignore")
+            .assert(predicates::path::missing());
+        temp.child(".dofigen/vendor")
+            .assert(predicates::path::missing());
+        temp.child("dofigen.lock")
+            .assert(predicates::path::is_file());
+
+        // The files not generated by Dofigen are kept
+        temp.child("Dockerfile").write_str("FROM alpine\n").unwrap();
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("clean");
+        cmd.unwrap();
+        temp.child("Dockerfile").assert(predicates::path::is_file());
+    }
+
+    #[test]
+    fn doctor_checks() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  digest: sha256:abc\n")
+            .unwrap();
+        let doctor = || {
+            let mut cmd = BIN.command();
+            cmd.current_dir(temp.path());
+            cmd.arg("doctor").arg("--offline");
+            cmd.output().unwrap()
+        };
+
+        let output = doctor();
+        assert!(output.status.success());
+        let stdout = str::from_utf8(&output.stdout).unwrap().to_string();
+        assert!(stdout.contains("The Dofigen file dofigen.yml is valid"));
+        assert!(stdout.contains("There is no lock file"));
+        assert!(stdout.contains("The file Dockerfile isn't generated"));
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("--offline");
+        cmd.unwrap();
+
+        let output = doctor();
+        assert!(output.status.success());
+        let stdout = str::from_utf8(&output.stdout).unwrap().to_string();
+        assert!(stdout.contains("The lock file is up to date"));
+        assert!(stdout.contains("The file Dockerfile is generated by this Dofigen version"));
+
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  digest: sha256:abc\nworkdir: /app\n")
+            .unwrap();
+        let output = doctor();
+        assert!(output.status.success());
+        let stdout = str::from_utf8(&output.stdout).unwrap().to_string();
+        assert!(stdout.contains("The lock file doesn't match the Dofigen file"));
+
+        temp.child("dofigen.yml")
+            .write_str("fromImage: [ubuntu\n")
+            .unwrap();
+        let output = doctor();
+        assert!(!output.status.success());
+        let stdout = str::from_utf8(&output.stdout).unwrap().to_string();
+        assert!(stdout.contains("The Dofigen file dofigen.yml can't be loaded"));
+    }
+
+    #[test]
+    fn explain_extended_file() {
+        let mut cmd = BIN.command();
+        cmd.arg("explain")
+            .arg("--offline")
+            .arg("-f")
+            .arg("tests/cases/springboot-maven.override.yml");
+        let output = cmd.unwrap();
+        let stdout = str::from_utf8(&output.stdout).unwrap();
+
+        assert!(stdout.contains(
+            "\n  tag: 21-jre-alpine  # tests/cases/springboot-maven.override.yml (set)\n"
+        ));
+        assert!(stdout.contains(
+            "\n  path: eclipse-temurin  # tests/cases/springboot-maven.base.yml (set)\n"
+        ));
+        assert!(
+            stdout.contains("\nfromImage:  # tests/cases/springboot-maven.override.yml (merge)\n")
+        );
+
+        // The comments don't change the effective content
+        let mut cmd = BIN.command();
+        cmd.arg("effective")
+            .arg("--offline")
+            .arg("--keep-tags")
+            .arg("-f")
+            .arg("tests/cases/springboot-maven.override.yml");
+        let effective = cmd.unwrap();
+        assert_eq_sorted!(
+            serde_yaml::from_str::<serde_yaml::Value>(stdout).unwrap(),
+            serde_yaml::from_slice::<serde_yaml::Value>(&effective.stdout).unwrap()
+        );
+    }
+
+    #[test]
+    fn verify_image_lock_labels() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n")
+            .unwrap();
+        temp.child("fixtures.yml")
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/ubuntu:latest:\n    digest: sha256:abc\n",
+            )
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("generate")
+            .arg("--fixtures")
+            .arg("fixtures.yml")
+            .arg("--lock-labels");
+        cmd.unwrap();
+
+        let dockerfile = read_to_string(temp.child("Dockerfile").path()).unwrap();
+        let lock = Regex::new(r#"io\.lenra\.dofigen\.lock="([0-9a-f]{64})""#)
+            .unwrap()
+            .captures(&dockerfile)
+            .expect("The lock label is missing")[1]
+            .to_string();
+
+        // The locked generation gives the same labels
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("generate").arg("--locked").arg("--lock-labels");
+        cmd.unwrap();
+        assert_eq_sorted!(
+            read_to_string(temp.child("Dockerfile").path()).unwrap(),
+            dockerfile
+        );
+
+        let image_fixtures = |lock: &str| {
+            format!(
+                "resolvedImages:\n  ghcr.io:443/lenra/app:1.0:\n    image:\n      host: ghcr.io\n      path: lenra/app\n    digest: sha256:def\n    platforms: []\n    labels:\n      io.lenra.dofigen.lock: {}\n      io.lenra.dofigen.version: 0.0.0\n",
+                lock
+            )
+        };
+
+        temp.child("image.yml")
+            .write_str(&image_fixtures(&lock))
+            .unwrap();
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("verify-image")
+            .arg("ghcr.io/lenra/app:1.0")
+            .arg("--fixtures")
+            .arg("image.yml");
+        cmd.unwrap();
+
+        temp.child("image.yml")
+            .write_str(&image_fixtures("other"))
+            .unwrap();
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("verify-image")
+            .arg("ghcr.io/lenra/app:1.0")
+            .arg("--fixtures")
+            .arg("image.yml");
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+        assert_eq_sorted!(
+            str::from_utf8(&output.stderr).unwrap(),
+            format!(
+                "error: The image ghcr.io/lenra/app:1.0 is generated from another lock file: other instead of {}\n",
+                lock
+            )
+        );
+    }
+
+    #[test]
+    fn graph_mermaid() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("dofigen.yml")
+            .write_str(
+                "builders:\n  builder:\n    fromImage:\n      path: rust\nfromImage:\n  path: alpine\ncopy:\n- fromBuilder: builder\n  paths: [/app]\n",
+            )
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("graph")
+            .arg("--offline")
+            .arg("--format")
+            .arg("mermaid");
+        let output = cmd.unwrap();
+
+        assert_eq_sorted!(
+            String::from_utf8(output.stdout).unwrap(),
+            r#"flowchart LR
+  n0(["alpine"])
+  n1(["rust"])
+  n2["builder"]
+  n3[["runtime"]]
+  n0 -->|from| n3
+  n2 -->|copy| n3
+  n1 -->|from| n2
+"#
+        );
+    }
+
+    #[test]
+    fn update_markdown_report() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let fixtures = temp.child("fixtures.yml");
+        fixtures
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/ubuntu:24.04:\n    digest: sha256:abc\n",
+            )
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  tag: \"24.04\"\n")
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("--fixtures").arg("fixtures.yml");
+        cmd.unwrap();
+
+        fixtures
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/ubuntu:24.04:\n    digest: sha256:def\n",
+            )
+            .unwrap();
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("update")
+            .arg("--fixtures")
+            .arg("fixtures.yml")
+            .arg("--output")
+            .arg("report.md");
+        cmd.unwrap();
+
+        assert_eq_sorted!(
+            read_to_string(temp.child("report.md").path()).unwrap(),
+            r#"## Dofigen updates
+
+### Images
+
+#### `registry.hub.docker.com:443/ubuntu`
+
+| Tag | Change | Previous | Current |
+| --- | --- | --- | --- |
+| 24.04 | updated | `sha256:abc` | `sha256:def` |
+"#
+        );
+    }
+
+    #[test]
+    fn generate_matrix() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("fixtures.yml")
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/node:20-alpine:\n    digest: sha256:abc\n  registry.hub.docker.com:443/node:22-alpine:\n    digest: sha256:def\n",
+            )
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str(
+                "matrix:\n  node: [\"20\", \"22\"]\nfromImage:\n  path: node\n  tag: ${node}-alpine\n",
+            )
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen")
+            .arg("--fixtures")
+            .arg("fixtures.yml")
+            .arg("--matrix");
+        cmd.unwrap();
+
+        assert!(read_to_string(temp.child("Dockerfile.20").path())
+            .unwrap()
+            .contains("FROM node@sha256:abc AS runtime"));
+        assert!(read_to_string(temp.child("Dockerfile.22").path())
+            .unwrap()
+            .contains("FROM node@sha256:def AS runtime"));
+        temp.child("dofigen.20.lock")
+            .assert(predicates::path::exists());
+        temp.child("dofigen.22.lock")
+            .assert(predicates::path::exists());
+        temp.child("Dockerfile").assert(predicates::path::missing());
+    }
+
+    #[test]
+    fn generate_debug_variant() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("fixtures.yml")
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/alpine:3.20:\n    digest: sha256:abc\n",
+            )
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: alpine\n  tag: \"3.20\"\nentrypoint: [/bin/app]\n")
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen")
+            .arg("--fixtures")
+            .arg("fixtures.yml")
+            .arg("--variant")
+            .arg("debug")
+            .arg("--debug-package")
+            .arg("curl")
+            .arg("--debug-root");
+        cmd.unwrap();
+
+        assert!(!read_to_string(temp.child("Dockerfile").path())
+            .unwrap()
+            .contains("apk add"));
+        let debug = read_to_string(temp.child("Dockerfile.debug").path()).unwrap();
+        assert!(debug.contains("then apk add --no-cache curl;"));
+        assert!(debug.contains("USER 0:0"));
+        temp.child("Dockerfile.debug.dockerignore")
+            .assert(predicates::path::exists());
+    }
+
+    #[test]
+    fn generate_validation_errors() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("fixtures.yml")
+            .write_str(
+                "imageTags:\n  registry.hub.docker.com:443/alpine:3.20:\n    digest: sha256:abc\n",
+            )
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str(
+                "fromImage:\n  path: alpine\n  tag: \"3.20\"\ncmd: [npm start]\nexpose:\n  - port: 0\n",
+            )
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("--fixtures").arg("fixtures.yml");
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(!output.status.success());
+
+        let stderr = str::from_utf8(&output.stderr).unwrap();
+        assert!(stderr.contains("The executable 'npm start' contains spaces"));
+        assert!(stderr.contains("The port 0 can't be exposed"));
+        assert!(stderr.contains("Could not generate the Dockerfile due to 2 previous errors"));
+    }
+
+    #[test]
+    fn update_add_checksums() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let checksum = format!("sha256:{}", "a".repeat(64));
+        temp.child("fixtures.yml")
+            .write_str(&format!(
+                "checksums:\n  https://example.com/tool.tar.gz: {}\n",
+                checksum
+            ))
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  digest: sha256:abc\ncopy:\n  - files: [https://example.com/tool.tar.gz]\n    target: /opt/\n")
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("--fixtures").arg("fixtures.yml");
+        cmd.unwrap();
+        assert!(!read_to_string(temp.child("Dockerfile").path())
+            .unwrap()
+            .contains("--checksum"));
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("update")
+            .arg("--fixtures")
+            .arg("fixtures.yml")
+            .arg("--sync-dockerfile");
+        cmd.unwrap();
+
+        assert!(read_to_string(temp.child("dofigen.lock").path())
+            .unwrap()
+            .contains(&format!("https://example.com/tool.tar.gz: {}", checksum)));
+        assert!(read_to_string(temp.child("Dockerfile").path())
+            .unwrap()
+            .contains(&format!("--checksum={}", checksum)));
+    }
+
+    #[test]
+    fn generate_verify_checksums() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("tool.tar.gz").write_str("content").unwrap();
+        temp.child("dofigen.yml")
+            .write_str(&format!("fromImage:\n  path: ubuntu\n  digest: sha256:abc\ncopy:\n  - files: [tool.tar.gz]\n    target: /opt/\n    checksum: sha512:{}\n", "a".repeat(128)))
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.arg("gen").arg("-o").arg("-").arg("--verify-checksums");
+
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(!output.status.success());
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .contains("copy.0.checksum: The checksum of \"tool.tar.gz\" is sha512:"));
+    }
+
+    #[test]
+    fn config_set_and_get() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.env_remove("DOFIGEN_CONFIG_DIR")
+            .env("XDG_CONFIG_HOME", temp.child("xdg").path());
+        cmd.arg("config").arg("set").arg("profiles").arg("dev, ci");
+        cmd.unwrap();
+
+        assert_eq!(
+            read_to_string(temp.child("xdg/dofigen/config.yml").path()).unwrap(),
+            "profiles:\n- dev\n- ci\n"
+        );
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.env_remove("DOFIGEN_CONFIG_DIR")
+            .env("XDG_CONFIG_HOME", temp.child("xdg").path());
+        cmd.arg("config").arg("get").arg("profiles");
+        let output = cmd.unwrap();
+
+        assert_eq!(str::from_utf8(&output.stdout).unwrap(), "dev,ci\n");
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.env_remove("DOFIGEN_CONFIG_DIR")
+            .env("XDG_CONFIG_HOME", temp.child("xdg").path());
+        cmd.arg("config").arg("set").arg("offline").arg("maybe");
+        let output = cmd.unwrap_err();
+        let output = output.as_output().unwrap();
+
+        assert!(str::from_utf8(&output.stderr)
+            .unwrap()
+            .contains("The value of 'offline' must be true or false, not 'maybe'"));
+    }
+
+    #[test]
+    fn generate_user_config_defaults() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("config/config.yml")
+            .write_str("offline: true\ncolor: false\nprofiles: [dev, undeclared]\n")
+            .unwrap();
+        temp.child("dofigen.yml")
+            .write_str("fromImage:\n  path: ubuntu\n  digest: sha256:abc\nenv:\n  LOG_LEVEL: info\nprofiles:\n  dev:\n    env:\n      LOG_LEVEL: debug\n  prod:\n    env:\n      LOG_LEVEL: warn\n")
+            .unwrap();
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.env("DOFIGEN_CONFIG_DIR", temp.child("config").path());
+        cmd.arg("gen").arg("-o").arg("-");
+        let output = cmd.unwrap();
+
+        assert!(str::from_utf8(&output.stdout)
+            .unwrap()
+            .contains("LOG_LEVEL=\"debug\""));
+
+        let mut cmd = BIN.command();
+        cmd.current_dir(temp.path());
+        cmd.env("DOFIGEN_CONFIG_DIR", temp.child("config").path());
+        cmd.arg("gen")
+            .arg("-o")
+            .arg("-")
+            .arg("--profile")
+            .arg("prod");
+        let output = cmd.unwrap();
+
+        assert!(str::from_utf8(&output.stdout)
+            .unwrap()
+            .contains("LOG_LEVEL=\"warn\""));
+    }
 }
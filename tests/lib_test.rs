@@ -1,5 +1,6 @@
 use dofigen_lib::*;
 use pretty_assertions_sorted::assert_eq_sorted;
+use std::collections::HashMap;
 
 #[test]
 fn yaml_to_dockerfile_empty() {
@@ -454,7 +455,14 @@ RUN \
 "#
     );
 
-    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+    assert_eq_sorted!(
+        generation_context.get_lint_messages(),
+        vec![LintMessage {
+            level: MessageLevel::Warn,
+            path: vec!["builders".into(), "builder".into(), "copy".into(), "0".into()],
+            message: "The files are copied from the whole build context. Define the context or ignore fields to only send the used files to the build".into(),
+        }]
+    );
 }
 
 #[test]
@@ -587,6 +595,105 @@ user: wrong.user
     );
 }
 
+#[test]
+#[cfg(feature = "permissive")]
+fn legacy_fields_with_warnings() {
+    use std::collections::HashMap;
+
+    let yaml = r#"
+builders:
+  builder:
+    fromImage: rust
+    adds: "."
+    run: cargo build --release
+fromImage: alpine
+envs:
+  RUST_LOG: info
+artifacts:
+  - builder: builder
+    source: /target/release/app
+    destination: /app
+ports: 8080
+"#;
+
+    let mut context = DofigenContext::new();
+    let dofigen: Dofigen = context.parse_from_string(yaml).unwrap();
+
+    assert_eq_sorted!(
+        dofigen,
+        Dofigen {
+            builders: HashMap::from([(
+                "builder".into(),
+                Stage {
+                    from: ImageName {
+                        path: "rust".into(),
+                        ..Default::default()
+                    }
+                    .into(),
+                    copy: vec![CopyResource::Copy(Copy {
+                        paths: vec![".".into()],
+                        ..Default::default()
+                    })],
+                    run: Run {
+                        run: vec!["cargo build --release".into()],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }
+            )]),
+            stage: Stage {
+                from: ImageName {
+                    path: "alpine".into(),
+                    ..Default::default()
+                }
+                .into(),
+                env: HashMap::from([("RUST_LOG".into(), "info".into())]),
+                copy: vec![CopyResource::Copy(Copy {
+                    from: FromContext::FromBuilder("builder".into()),
+                    paths: vec!["/target/release/app".into()],
+                    options: CopyOptions {
+                        target: Some("/app".into()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })],
+                ..Default::default()
+            },
+            expose: vec![Port {
+                port: 8080,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    );
+
+    assert_eq_sorted!(
+        context.get_lint_messages(),
+        vec![
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["builders".into(), "builder".into(), "adds".into()],
+                message: "The 'adds' field is deprecated, use 'copy' instead".into(),
+            },
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["envs".into()],
+                message: "The 'envs' field is deprecated, use 'env' instead".into(),
+            },
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["artifacts".into()],
+                message: "The 'artifacts' field is deprecated, use 'copy' instead".into(),
+            },
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["ports".into()],
+                message: "The 'ports' field is deprecated, use 'expose' instead".into(),
+            },
+        ]
+    );
+}
+
 mod copy_file_content {
     use super::*;
 
@@ -717,3 +824,1297 @@ ENTRYPOINT ["/entrypoint.sh"]
         assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
     }
 }
+
+#[test]
+fn builders_outputs_use() {
+    let yaml = r#"
+builders:
+  web:
+    fromImage:
+      path: node
+    workdir: /app
+    run: [npm run build]
+    outputs:
+      - /app/dist
+      - /app/package.json
+fromImage:
+  path: nginx
+use:
+  - builder: web
+    output: 0
+    target: /srv
+  - builder: web
+    output: 1
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# web
+FROM node AS web
+WORKDIR /app
+RUN npm run build
+
+# runtime
+FROM nginx AS runtime
+COPY \
+    --from=web \
+    --chown=1000:1000 \
+    --link \
+    "/app/dist" "/srv"
+COPY \
+    --from=web \
+    --chown=1000:1000 \
+    --link \
+    "/app/package.json" "/app/package.json"
+USER 1000:1000
+"#
+    );
+    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+}
+
+#[test]
+fn external_builder() {
+    let yaml = r#"
+builders:
+  deps:
+    fromContext: deps-context
+    external: true
+    outputs:
+      - /app/vendor
+fromImage:
+  path: php
+use:
+  - builder: deps
+    output: 0
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# deps
+FROM deps-context AS deps
+
+# runtime
+FROM php AS runtime
+COPY \
+    --from=deps \
+    --chown=1000:1000 \
+    --link \
+    "/app/vendor" "/app/vendor"
+USER 1000:1000
+"#
+    );
+    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+}
+
+#[test]
+fn builder_compiler_cache() {
+    let yaml = r#"
+builders:
+  builder:
+    fromImage:
+      path: rust
+    compilerCache:
+      type: sccache
+      bucket: build-cache
+    run: [cargo build --release]
+fromImage:
+  path: alpine
+copy:
+  - fromBuilder: builder
+    paths: [/target/release/app]
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# builder
+FROM rust AS builder
+RUN cargo install --locked sccache
+ENV \
+    RUSTC_WRAPPER="sccache" \
+    SCCACHE_BUCKET="build-cache"
+RUN \
+    --mount=type=secret,id=AWS_ACCESS_KEY_ID,env=AWS_ACCESS_KEY_ID \
+    --mount=type=secret,id=AWS_SECRET_ACCESS_KEY,env=AWS_SECRET_ACCESS_KEY \
+    cargo build --release
+
+# runtime
+FROM alpine AS runtime
+COPY \
+    --from=builder \
+    --chown=1000:1000 \
+    --link \
+    "/target/release/app" "./"
+USER 1000:1000
+"#
+    );
+    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+}
+
+#[test]
+fn stop_signal() {
+    let yaml = r#"
+fromImage:
+  path: nginx
+stopSignal: SIGQUIT
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    assert_eq_sorted!(
+        generate_effective_content(&dofigen).unwrap(),
+        "fromImage:\n  path: nginx\nstopSignal: SIGQUIT\n"
+    );
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM nginx AS runtime
+USER 1000:1000
+STOPSIGNAL SIGQUIT
+"#
+    );
+}
+
+#[test]
+fn metadata_instruction_order() {
+    let yaml = r#"
+fromImage:
+  path: nginx
+expose:
+  - port: 80
+entrypoint: [nginx]
+cmd: [-g, daemon off;]
+stopSignal: SIGQUIT
+instructionOrder: [cmd, expose]
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM nginx AS runtime
+USER 1000:1000
+CMD ["-g", "daemon off;"]
+EXPOSE 80
+STOPSIGNAL SIGQUIT
+ENTRYPOINT ["nginx"]
+"#
+    );
+}
+
+#[test]
+fn cross_compilation_platform() {
+    let yaml = r#"
+builders:
+  builder:
+    fromImage:
+      path: golang
+    platform: $BUILDPLATFORM
+    run:
+      - GOOS=$TARGETOS GOARCH=$TARGETARCH go build -o /app
+fromImage:
+  path: alpine
+platform: $TARGETPLATFORM
+copy:
+  - fromBuilder: builder
+    paths: [/app]
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# builder
+FROM \
+    --platform=$BUILDPLATFORM \
+    golang AS builder
+ARG TARGETARCH
+ARG TARGETOS
+RUN GOOS=$TARGETOS GOARCH=$TARGETARCH go build -o /app
+
+# runtime
+FROM \
+    --platform=$TARGETPLATFORM \
+    alpine AS runtime
+COPY \
+    --from=builder \
+    --chown=1000:1000 \
+    --link \
+    "/app" "./"
+USER 1000:1000
+"#
+    );
+}
+
+#[test]
+fn multi_platform_args() {
+    let yaml = r#"
+platforms:
+  - linux/amd64
+  - linux/arm64
+fromImage:
+  path: debian
+run:
+  - curl -fsSLo /usr/local/bin/tool https://example.com/tool-$TARGETARCH
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+    assert_eq_sorted!(dofigen.validate(), vec![]);
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM debian AS runtime
+ARG TARGETARCH
+USER 1000:1000
+RUN curl -fsSLo /usr/local/bin/tool https://example.com/tool-$TARGETARCH
+"#
+    );
+}
+
+#[test]
+fn run_network() {
+    let yaml = r#"
+fromImage:
+  path: alpine
+root:
+  run:
+    - apk add --no-cache make
+run:
+  - make test
+network: none
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM alpine AS runtime
+USER 0:0
+RUN apk add --no-cache make
+USER 1000:1000
+RUN \
+    --network=none \
+    make test
+"#
+    );
+}
+
+#[cfg(feature = "permissive")]
+#[test]
+fn run_secrets() {
+    let yaml = r#"
+fromImage:
+  path: node
+run:
+  - npm ci
+secrets:
+  - npmrc /root/.npmrc
+  - id: pip_token
+    env: PIP_TOKEN
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM node AS runtime
+USER 1000:1000
+RUN \
+    --mount=type=secret,id=npmrc,target=/root/.npmrc \
+    --mount=type=secret,id=pip_token,env=PIP_TOKEN \
+    npm ci
+"#
+    );
+}
+
+#[test]
+fn from_arg() {
+    let yaml = r#"
+builders:
+  builder:
+    fromImage:
+      path: rust
+      tag: "1.85"
+    fromArg: BUILDER_IMAGE
+    run:
+      - cargo build --release
+fromArg: RUNTIME_IMAGE
+copy:
+  - fromBuilder: builder
+    paths: [/app]
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+ARG BUILDER_IMAGE=rust:1.85
+ARG RUNTIME_IMAGE
+
+# builder
+FROM ${BUILDER_IMAGE} AS builder
+RUN cargo build --release
+
+# runtime
+FROM ${RUNTIME_IMAGE} AS runtime
+COPY \
+    --from=builder \
+    --chown=1000:1000 \
+    --link \
+    "/app" "./"
+USER 1000:1000
+"#
+    );
+}
+
+#[test]
+fn additional_contexts() {
+    let yaml = r#"
+additionalContexts:
+  base: docker-image://alpine:3.21
+  assets: ../assets
+fromContext: base
+copy:
+  - fromContext: assets
+    paths: [/images]
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+# Build it with the additional contexts:
+#   --build-context assets=../assets
+#   --build-context base=docker-image://alpine:3.21
+
+# runtime
+FROM base AS runtime
+COPY \
+    --from=assets \
+    --chown=1000:1000 \
+    --link \
+    "/images" "./"
+USER 1000:1000
+"#
+    );
+    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+}
+
+#[test]
+fn extended_field_origins() {
+    let yaml = r#"
+extend:
+  - tests/cases/springboot-maven.base.yml
+builders:
+  unused:
+    fromImage:
+      path: alpine
+fromImage:
+  tag: 21-jre-alpine
+"#;
+
+    let mut context = DofigenContext::new();
+    let dofigen: Dofigen = context.parse_from_string(yaml).unwrap();
+    let base = Resource::File("tests/cases/springboot-maven.base.yml".into());
+
+    assert_eq_sorted!(
+        context.field_origin(&[
+            "builders".into(),
+            "maven-builder".into(),
+            "copy".into(),
+            "0".into()
+        ]),
+        Some(&base)
+    );
+    assert_eq_sorted!(
+        context.field_origin(&["fromImage".into(), "path".into()]),
+        Some(&base)
+    );
+    assert_eq_sorted!(
+        context.field_origin(&["fromImage".into(), "tag".into()]),
+        None
+    );
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        context.with_field_origins(vec![
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["builders".into(), "maven-builder".into(), "root".into()],
+                message: "A message on an extended field".into(),
+            },
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["builders".into(), "unused".into()],
+                message: "A message on a local field".into(),
+            }
+        ]),
+        vec![
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["builders".into(), "maven-builder".into(), "root".into()],
+                message: "A message on an extended field (introduced by tests/cases/springboot-maven.base.yml)".into(),
+            },
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["builders".into(), "unused".into()],
+                message: "A message on a local field".into(),
+            }
+        ]
+    );
+    assert_eq_sorted!(
+        context.with_field_origins(generation_context.get_lint_messages()),
+        vec![
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["builders".into(), "unused".into()],
+                message: "The builder 'unused' is empty and should be removed".into(),
+            },
+            LintMessage {
+                level: MessageLevel::Warn,
+                path: vec!["builders".into(), "unused".into()],
+                message: "The builder 'unused' is not used and should be removed".into(),
+            }
+        ]
+    );
+}
+
+#[test]
+fn named_users() {
+    let yaml = r#"
+users:
+  app:
+    uid: 1001
+    shell: /bin/sh
+builders:
+  builder:
+    fromImage:
+      path: alpine
+    user:
+      user: app
+    run:
+      - make
+    cache:
+      - target: /cache
+  tests:
+    fromBuilder: builder
+    user:
+      user: app
+    run:
+      - make test
+fromImage:
+  path: alpine
+user:
+  user: app
+copy:
+  - fromBuilder: builder
+    paths: [/app/bin]
+  - fromBuilder: tests
+    paths: [/app/report]
+    chown:
+      user: app
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# builder
+FROM alpine AS builder
+USER 0:0
+RUN <<EOF
+echo 'app:x:1001:1001::/home/app:/bin/sh' >> /etc/passwd
+echo 'app:x:1001:' >> /etc/group
+mkdir -p /home/app
+chown 1001:1001 /home/app
+EOF
+USER 1001:1001
+RUN \
+    --mount=type=cache,target=/cache,uid=1001,gid=1001,sharing=locked \
+    make
+
+# tests
+FROM builder AS tests
+USER 1001:1001
+RUN make test
+
+# runtime
+FROM alpine AS runtime
+COPY \
+    --from=builder \
+    --chown=1001:1001 \
+    --link \
+    "/app/bin" "./"
+COPY \
+    --from=tests \
+    --chown=1001:1001 \
+    --link \
+    "/app/report" "./"
+USER 0:0
+RUN <<EOF
+echo 'app:x:1001:1001::/home/app:/bin/sh' >> /etc/passwd
+echo 'app:x:1001:' >> /etc/group
+mkdir -p /home/app
+chown 1001:1001 /home/app
+EOF
+USER 1001:1001
+"#
+    );
+    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+}
+
+#[cfg(feature = "permissive")]
+#[test]
+fn reproducible_builders_order() {
+    let yaml = r#"
+builders:
+  zlib:
+    fromImage: alpine
+    run: make zlib
+  app:
+    fromBuilder: deps
+    env:
+      PROFILE: release
+      CC: clang
+      ARCH: x86_64
+    run: make app
+  deps:
+    fromBuilder: zlib
+    run: make deps
+  assets:
+    fromImage: node
+    run: npm run build
+fromImage: alpine
+copy:
+  - fromBuilder: app
+    paths: [/app/bin]
+  - fromBuilder: assets
+    paths: [/app/dist]
+"#;
+
+    let dockerfiles = (0..10)
+        .map(|_| {
+            let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+            GenerationContext::from(dofigen)
+                .generate_dockerfile()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let stages = dockerfiles[0]
+        .lines()
+        .filter(|line| line.starts_with("FROM "))
+        .collect::<Vec<_>>();
+    assert_eq_sorted!(
+        stages,
+        vec![
+            "FROM node AS assets",
+            "FROM alpine AS zlib",
+            "FROM zlib AS deps",
+            "FROM deps AS app",
+            "FROM alpine AS runtime",
+        ]
+    );
+    assert!(dockerfiles[0]
+        .contains("ENV \\\n    ARCH=\"x86_64\" \\\n    CC=\"clang\" \\\n    PROFILE=\"release\""));
+    assert!(dockerfiles
+        .iter()
+        .all(|dockerfile| *dockerfile == dockerfiles[0]));
+}
+
+#[test]
+fn backtick_escape() {
+    let yaml = r#"
+escape: "`"
+fromImage:
+  path: mcr.microsoft.com/windows/servercore
+  tag: ltsc2022
+workdir: C:\app
+env:
+  PATH: C:\app\bin
+  PROFILE: release
+run:
+  - build.cmd
+cache:
+  - target: C:\cache
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# escape=`
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM mcr.microsoft.com/windows/servercore:ltsc2022 AS runtime
+ENV `
+    PATH="C:\app\bin" `
+    PROFILE="release"
+WORKDIR C:\app
+USER 1000:1000
+RUN `
+    --mount=type=cache,target=C:\cache,uid=1000,gid=1000,sharing=locked `
+    build.cmd
+"#
+    );
+}
+
+#[test]
+fn build_checks_directive() {
+    let yaml = r#"
+checks:
+  skip:
+    - JSONArgsRecommended
+    - StageNameCasing
+  error: true
+fromImage:
+  path: alpine
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# check=skip=JSONArgsRecommended,StageNameCasing;error=true
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM alpine AS runtime
+USER 1000:1000
+"#
+    );
+}
+
+#[test]
+fn prune_unused_builders() {
+    let yaml = r#"
+builders:
+  deps:
+    fromImage:
+      path: rust
+    run: [cargo fetch]
+  tests:
+    fromBuilder: deps
+    run: [cargo test]
+  builder:
+    fromImage:
+      path: rust
+    run: [cargo build]
+fromImage:
+  path: alpine
+copy:
+  - fromBuilder: builder
+    paths: [/app/bin]
+"#;
+
+    let mut dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    assert_eq_sorted!(dofigen.prune_unused_builders(), vec!["deps", "tests"]);
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# builder
+FROM rust AS builder
+RUN cargo build
+
+# runtime
+FROM alpine AS runtime
+COPY \
+    --from=builder \
+    --chown=1000:1000 \
+    --link \
+    "/app/bin" "./"
+USER 1000:1000
+"#
+    );
+    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+}
+
+#[test]
+fn template_variables() {
+    let yaml = r#"
+variables:
+  version: "22"
+  target: /app
+fromImage:
+  path: node
+  tag: ${version}-alpine
+workdir: ${target}
+run:
+  - echo "${HOME} $${target}" > ${target}/version
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    assert!(dofigen.variables.is_empty());
+    let dockerfile = GenerationContext::from(dofigen)
+        .generate_dockerfile()
+        .unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM node:22-alpine AS runtime
+WORKDIR /app
+USER 1000:1000
+RUN echo "${HOME} ${target}" > /app/version
+"#
+    );
+
+    let mut context = DofigenContext::new();
+    context.variables = [("version".to_string(), "20".to_string())].into();
+    let dofigen: Dofigen = context.parse_from_string(yaml).unwrap();
+
+    assert_eq_sorted!(
+        dofigen.stage.from,
+        FromContext::FromImage(ImageName {
+            path: "node".into(),
+            version: Some(ImageVersion::Tag("20-alpine".into())),
+            ..Default::default()
+        })
+    );
+}
+
+#[test]
+fn activated_profiles() {
+    let yaml = r#"
+fromImage:
+  path: alpine
+run: [./start.sh]
+profiles:
+  dev:
+    root:
+      run: [apk add --no-cache curl]
+    env:
+      LOG_LEVEL: debug
+  prod:
+    env:
+      LOG_LEVEL: warn
+"#;
+
+    let mut context = DofigenContext::new();
+    context.profiles = vec!["dev".into()];
+    let dofigen: Dofigen = context.parse_from_string(yaml).unwrap();
+    let dockerfile = GenerationContext::from(dofigen)
+        .generate_dockerfile()
+        .unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# runtime
+FROM alpine AS runtime
+ENV LOG_LEVEL="debug"
+USER 0:0
+RUN apk add --no-cache curl
+USER 1000:1000
+RUN ./start.sh
+"#
+    );
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    assert_eq_sorted!(dofigen.stage.env, HashMap::new());
+    assert_eq_sorted!(dofigen.stage.root, None);
+
+    let mut context = DofigenContext::new();
+    context.profiles = vec!["staging".into()];
+    assert_eq_sorted!(
+        context.parse_from_string(yaml).unwrap_err().to_string(),
+        "The profile 'staging' isn't declared in the Dofigen files"
+    );
+}
+
+#[test]
+fn sanitized_stage_names() {
+    let yaml = r#"
+builders:
+  Build App:
+    fromImage:
+      path: rust
+    run: [cargo build]
+fromImage:
+  path: alpine
+copy:
+  - fromBuilder: Build App
+    paths: [/app/bin]
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# build-app
+FROM rust AS build-app
+RUN cargo build
+
+# runtime
+FROM alpine AS runtime
+COPY \
+    --from=build-app \
+    --chown=1000:1000 \
+    --link \
+    "/app/bin" "./"
+USER 1000:1000
+"#
+    );
+    assert_eq_sorted!(
+        generation_context.get_lint_messages(),
+        vec![LintMessage {
+            level: MessageLevel::Warn,
+            message: "The builder name 'Build App' isn't a valid stage name, the stage is named 'build-app'".into(),
+            path: vec!["builders".into(), "Build App".into()],
+        }]
+    );
+}
+
+#[test]
+fn embedded_preset() {
+    let yaml = r#"
+extend:
+  - preset://rust-musl
+variables:
+  binary: dofigen
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+    let dockerfile = GenerationContext::from(dofigen)
+        .generate_dockerfile()
+        .unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+# rust-musl
+FROM clux/muslrust:stable AS rust-musl
+WORKDIR /app
+RUN \
+    --mount=type=bind,target=/app/Cargo.toml,source=Cargo.toml \
+    --mount=type=bind,target=/app/Cargo.lock,source=Cargo.lock \
+    --mount=type=bind,target=/app/src,source=src \
+    --mount=type=cache,target=/root/.cargo/registry,sharing=locked \
+    --mount=type=cache,target=/app/target,sharing=locked \
+    <<EOF
+cargo build --release
+mv target/x86_64-unknown-linux-musl/release/dofigen /tmp/
+EOF
+
+# runtime
+FROM scratch AS runtime
+COPY \
+    --from=rust-musl \
+    --chown=1000:1000 \
+    --link \
+    "/tmp/dofigen" "/bin/"
+USER 1000:1000
+ENTRYPOINT ["/bin/dofigen"]
+"#
+    );
+}
+
+#[test]
+fn stream_dockerfile() {
+    let dofigen = Dofigen {
+        builders: HashMap::from([(
+            "builder".into(),
+            Stage {
+                from: FromContext::FromImage(ImageName {
+                    path: "rust".into(),
+                    ..Default::default()
+                }),
+                run: Run {
+                    run: vec!["cargo build".into(), "cargo test".into()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    };
+    let mut writer = vec![];
+    GenerationContext::from(dofigen.clone())
+        .generate_dockerfile_to(&mut writer)
+        .unwrap();
+    let dockerfile = GenerationContext::from(dofigen.clone())
+        .generate_dockerfile()
+        .unwrap();
+
+    assert_eq_sorted!(String::from_utf8(writer).unwrap(), dockerfile);
+    assert_eq_sorted!(
+        GenerationContext::from(dofigen)
+            .dockerfile_lines()
+            .unwrap()
+            .filter(|line| line.starts_with("FROM"))
+            .collect::<Vec<_>>(),
+        vec!["FROM rust AS builder", "FROM scratch AS runtime"]
+    );
+}
+
+#[test]
+fn expose_port_range() {
+    let yaml = r#"
+fromImage:
+  path: nginx
+expose:
+  - port: 80
+  - port: 8000
+    end: 8010
+    protocol: udp
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    assert_eq_sorted!(
+        GenerationContext::from(dofigen.clone())
+            .dockerfile_lines()
+            .unwrap()
+            .filter(|line| line.starts_with("EXPOSE"))
+            .collect::<Vec<_>>(),
+        vec!["EXPOSE 80", "EXPOSE 8000-8010/udp"]
+    );
+
+    let reparsed: Dofigen = DofigenContext::new()
+        .parse_from_string(&serde_yaml::to_string(&dofigen).unwrap())
+        .unwrap();
+    assert_eq_sorted!(reparsed, dofigen);
+}
+
+#[test]
+fn forward_global_args() {
+    let yaml = r#"
+globalArg:
+  VERSION: "1.0"
+  GIT_SHA: ""
+builders:
+  builder:
+    fromImage:
+      path: rust
+    forwardArgs: [VERSION, GIT_SHA]
+    run:
+      - cargo build --release
+fromImage:
+  path: alpine
+fromArg: BASE_IMAGE
+forwardArgs: [BASE_IMAGE]
+arg:
+  VERSION: dev
+env:
+  BASE: $BASE_IMAGE
+copy:
+  - fromBuilder: builder
+    paths: [/app]
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    let mut generation_context = GenerationContext::from(dofigen);
+    let dockerfile: String = generation_context.generate_dockerfile().unwrap();
+
+    assert_eq_sorted!(
+        dockerfile,
+        r#"# syntax=docker/dockerfile:1.11
+# This file is generated by Dofigen v0.0.0
+# See https://github.com/lenra-io/dofigen
+
+ARG BASE_IMAGE=alpine
+ARG GIT_SHA
+ARG VERSION=1.0
+
+# builder
+FROM rust AS builder
+ARG VERSION
+ARG GIT_SHA
+RUN cargo build --release
+
+# runtime
+FROM ${BASE_IMAGE} AS runtime
+ARG BASE_IMAGE
+ARG VERSION=dev
+ENV BASE="$BASE_IMAGE"
+COPY \
+    --from=builder \
+    --chown=1000:1000 \
+    --link \
+    "/app" "./"
+USER 1000:1000
+"#
+    );
+    assert_eq_sorted!(generation_context.get_lint_messages(), vec![]);
+}
+
+#[test]
+fn healthcheck_start_interval() {
+    let yaml = r#"
+fromImage:
+  path: nginx
+healthcheck:
+  cmd: curl -f http://localhost/
+  interval: 30s
+  start: 10s
+  startInterval: 1s
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new()
+        .parse_from_string(yaml)
+        .map_err(Error::from)
+        .unwrap();
+
+    assert_eq_sorted!(
+        GenerationContext::from(dofigen)
+            .generate_dockerfile()
+            .unwrap()
+            .split("\n")
+            .skip_while(|line| !line.starts_with("HEALTHCHECK"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        r#"HEALTHCHECK \
+    --interval=30s \
+    --start-period=10s \
+    --start-interval=1s \
+    CMD curl -f http://localhost/
+"#
+    );
+}
+
+#[test]
+fn stage_templates() {
+    let yaml = r#"
+templates:
+  go-build:
+    params:
+      pkg:
+        required: true
+      flags:
+        default: -trimpath
+    fromImage:
+      path: golang
+      tag: "1.23"
+    workdir: /src
+    run:
+      - go build ${flags} -o /out/app ${pkg}
+builders:
+  api:
+    uses:
+      template: go-build
+      with:
+        pkg: ./cmd/api
+  worker:
+    uses:
+      template: go-build
+      with:
+        pkg: ./cmd/worker
+    workdir: /worker
+fromImage:
+  path: alpine
+"#;
+
+    let dofigen: Dofigen = DofigenContext::new().parse_from_string(yaml).unwrap();
+
+    assert_eq_sorted!(
+        dofigen.builders["api"],
+        Stage {
+            from: ImageName {
+                path: "golang".into(),
+                version: Some(ImageVersion::Tag("1.23".into())),
+                ..Default::default()
+            }
+            .into(),
+            workdir: Some("/src".into()),
+            run: Run {
+                run: vec!["go build -trimpath -o /out/app ./cmd/api".into()],
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    );
+    assert_eq_sorted!(dofigen.builders["worker"].workdir, Some("/worker".into()));
+    assert_eq_sorted!(
+        dofigen.builders["worker"].run.run,
+        vec!["go build -trimpath -o /out/app ./cmd/worker".to_string()]
+    );
+
+    let yaml = r#"
+templates:
+  go-build:
+    params:
+      pkg:
+        required: true
+    fromImage:
+      path: golang
+builders:
+  api:
+    uses:
+      template: go-build
+fromImage:
+  path: alpine
+"#;
+    assert_eq_sorted!(
+        DofigenContext::new()
+            .parse_from_string(yaml)
+            .unwrap_err()
+            .to_string(),
+        "The parameter 'pkg' of the template 'go-build' is required"
+    );
+}
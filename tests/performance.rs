@@ -0,0 +1,107 @@
+//! Checks the performance budget described in docs/performance.md.
+//! The tests measure the wall-clock time and are ignored by default, run them with `cargo test --release --test performance -- --ignored`
+
+use assert_fs::TempDir;
+use dofigen_lib::{Dofigen, DofigenContext, DofigenPatch, GenerationContext};
+use std::time::{Duration, Instant};
+use struct_patch::Merge;
+
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use common::{extend_chain, large_dofigen, layer, LAYERS};
+
+const RUNS: usize = 5;
+
+/// Returns the best duration of several runs of the operation
+fn best_duration<F: FnMut()>(mut operation: F) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            operation();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn assert_budget(operation: &str, duration: Duration, budget_millis: u64) {
+    let budget = if cfg!(debug_assertions) {
+        Duration::from_millis(budget_millis * 20)
+    } else {
+        Duration::from_millis(budget_millis)
+    };
+    assert!(
+        duration <= budget,
+        "{} took {:?}, over the {:?} budget",
+        operation,
+        duration,
+        budget
+    );
+}
+
+fn offline_context() -> DofigenContext {
+    let mut context = DofigenContext::new();
+    context.offline = true;
+    context
+}
+
+#[test]
+#[ignore = "wall-clock budget, run on a dedicated CI job"]
+fn parse_budget() {
+    let yaml = large_dofigen();
+
+    let duration = best_duration(|| {
+        offline_context().parse_from_string(&yaml).unwrap();
+    });
+
+    assert_budget("Parsing 100 builders", duration, 50);
+}
+
+#[test]
+#[ignore = "wall-clock budget, run on a dedicated CI job"]
+fn extend_budget() {
+    let temp = TempDir::new().unwrap();
+    let resource = extend_chain(&temp);
+
+    let duration = best_duration(|| {
+        offline_context()
+            .parse_from_resource(resource.clone())
+            .unwrap();
+    });
+
+    assert_budget("Extending a chain of 10 files", duration, 50);
+}
+
+#[test]
+#[ignore = "wall-clock budget, run on a dedicated CI job"]
+fn generate_budget() {
+    let dofigen = offline_context()
+        .parse_from_string(&large_dofigen())
+        .unwrap();
+
+    let duration = best_duration(|| {
+        GenerationContext::from(dofigen.clone())
+            .generate_dockerfile()
+            .unwrap();
+    });
+
+    assert_budget("Generating 100 builders", duration, 20);
+}
+
+#[test]
+#[ignore = "wall-clock budget, run on a dedicated CI job"]
+fn merge_budget() {
+    let layers: Vec<DofigenPatch> = (0..LAYERS).map(layer).collect();
+
+    let duration = best_duration(|| {
+        let merged = layers
+            .clone()
+            .into_iter()
+            .reduce(|a, b| a.merge(b))
+            .unwrap();
+        let _ = Dofigen::from(merged);
+    });
+
+    assert_budget("Merging 10 layers of 100 builders", duration, 20);
+}